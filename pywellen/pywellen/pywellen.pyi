@@ -1,4 +1,7 @@
-from typing import Optional, Tuple, Union, List, Literal
+from typing import Callable, Optional, Tuple, Union, List, Literal
+
+import numpy as np
+import pandas as pd
 
 
 
@@ -66,10 +69,30 @@ class Waveform:
     ) -> None: ...
     def get_signal(self, var: Var) -> Signal: ...
     def get_signal_from_path(self, abs_hierarchy_path: str) -> Signal: ...
+    def to_dataframe(self, vars: List[Var]) -> pd.DataFrame: ...
 
 class Signal:
-    def value_at_time(self, time: int) -> Union[int, str]: ...
-    def value_at_idx(self, idx: int) -> Union[int, str]: ...
+    def value_at_time(self, time: int) -> Optional[Union[int, float, str]]: ...
+    def value_at_idx(self, idx: int) -> Optional[Union[int, float, str]]: ...
+    def find_change(
+        self, time: int, backward: bool = False
+    ) -> Optional[Tuple[int, Union[int, float, str]]]: ...
+    def find_edge(
+        self,
+        time: int,
+        edge: Literal["rising", "falling", "any"] = "rising",
+        backward: bool = False,
+    ) -> Optional[Tuple[int, Union[int, float, str]]]: ...
+    def find_value(
+        self, time: int, value: Union[int, float, str], backward: bool = False
+    ) -> Optional[Tuple[int, Union[int, float, str]]]: ...
+    def find_matching(
+        self,
+        time: int,
+        predicate: Callable[[Union[int, float, str]], bool],
+        backward: bool = False,
+    ) -> Optional[Tuple[int, Union[int, float, str]]]: ...
+    def to_numpy(self) -> Tuple[np.ndarray, np.ndarray, np.ndarray]: ...
     def all_changes(self) -> SignalChangeIter: ...
 
 class SignalChangeIter:
@@ -0,0 +1,170 @@
+use num_bigint::BigUint;
+use numpy::{IntoPyArray, PyArray1};
+use pyo3::prelude::*;
+use pyo3::IntoPyObjectExt;
+use std::borrow::Cow;
+use wellen::SignalValue;
+
+/// Values of a signal, converted into a representation that can be moved into a NumPy array
+/// without copying.
+pub enum Values {
+    /// bit-vectors with up to 64 bits
+    UInt64(Vec<u64>),
+    /// wider bit-vectors, converted into Python integers
+    Wide(Vec<BigUint>),
+    Real(Vec<f64>),
+    String(Vec<String>),
+}
+
+/// Values together with a mask that marks entries that are missing or that contain
+/// states other than `0` and `1`, e.g., `x` or `z`. In that case, the value only contains
+/// the bits that are `1`.
+pub struct Column {
+    pub values: Values,
+    pub mask: Vec<bool>,
+}
+
+impl Column {
+    /// Creates an empty column that can hold values like `example`.
+    pub fn like(example: Option<SignalValue>, capacity: usize) -> Self {
+        let values = match example {
+            Some(SignalValue::Real(_)) => Values::Real(Vec::with_capacity(capacity)),
+            Some(SignalValue::String(_)) => Values::String(Vec::with_capacity(capacity)),
+            Some(value) if value.bits().unwrap() > 64 => Values::Wide(Vec::with_capacity(capacity)),
+            _ => Values::UInt64(Vec::with_capacity(capacity)),
+        };
+        Self {
+            values,
+            mask: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn push(&mut self, value: Option<SignalValue>) {
+        let Some(value) = value else {
+            match &mut self.values {
+                Values::UInt64(values) => values.push(0),
+                Values::Wide(values) => values.push(BigUint::default()),
+                Values::Real(values) => values.push(f64::NAN),
+                Values::String(values) => values.push(String::new()),
+            }
+            self.mask.push(true);
+            return;
+        };
+        let masked = match (&mut self.values, value) {
+            (Values::Real(values), SignalValue::Real(v)) => {
+                values.push(v);
+                false
+            }
+            (Values::String(values), SignalValue::String(v)) => {
+                values.push(v.to_string());
+                false
+            }
+            (Values::UInt64(values), _) => {
+                let (bytes, masked) = two_state_bytes(value);
+                values.push(bytes.iter().fold(0, |acc, b| (acc << 8) | *b as u64));
+                masked
+            }
+            (Values::Wide(values), _) => {
+                let (bytes, masked) = two_state_bytes(value);
+                values.push(BigUint::from_bytes_be(&bytes));
+                masked
+            }
+            _ => unreachable!("all values of a signal have the same type"),
+        };
+        self.mask.push(masked);
+    }
+
+    /// Moves the values and the mask into NumPy arrays. If `none_if_masked` is set, masked
+    /// strings and wide values are replaced with `None`.
+    pub fn into_numpy(
+        self,
+        py: Python<'_>,
+        none_if_masked: bool,
+    ) -> PyResult<(Bound<'_, PyAny>, Bound<'_, PyAny>)> {
+        let values = match self.values {
+            Values::UInt64(values) => values.into_pyarray(py).into_any(),
+            Values::Real(values) => values.into_pyarray(py).into_any(),
+            Values::Wide(values) => {
+                let objects = values
+                    .into_iter()
+                    .zip(self.mask.iter())
+                    .map(|(v, masked)| object_or_none(py, v, none_if_masked && *masked))
+                    .collect::<PyResult<Vec<_>>>()?;
+                PyArray1::from_vec(py, objects).into_any()
+            }
+            Values::String(values) => {
+                let objects = values
+                    .into_iter()
+                    .zip(self.mask.iter())
+                    .map(|(v, masked)| object_or_none(py, v, none_if_masked && *masked))
+                    .collect::<PyResult<Vec<_>>>()?;
+                PyArray1::from_vec(py, objects).into_any()
+            }
+        };
+        Ok((values, self.mask.into_pyarray(py).into_any()))
+    }
+}
+
+fn object_or_none<'py, T: IntoPyObject<'py>>(
+    py: Python<'py>,
+    value: T,
+    none: bool,
+) -> PyResult<Py<PyAny>> {
+    if none {
+        Ok(py.None())
+    } else {
+        value.into_py_any(py)
+    }
+}
+
+/// Big-endian bytes that contain all bits that are `1` and whether there are any bits that are
+/// neither `0` nor `1`.
+fn two_state_bytes(value: SignalValue<'_>) -> (Cow<'_, [u8]>, bool) {
+    let (data, bits, bits_per_state) = match value {
+        SignalValue::Binary(data, _) => return (Cow::Borrowed(data), false),
+        SignalValue::FourValue(data, bits) => (data, bits as usize, 2),
+        SignalValue::NineValue(data, bits) => (data, bits as usize, 4),
+        SignalValue::String(_) | SignalValue::Real(_) => unreachable!("not a bit-vector"),
+    };
+    // states are right aligned, i.e., the least significant bit is stored in the last byte
+    let mut out = vec![0u8; bits.div_ceil(8)];
+    let mut masked = false;
+    let state_mask = (1u8 << bits_per_state) - 1;
+    for ii in 0..bits {
+        let position = ii * bits_per_state;
+        let byte = data[data.len() - 1 - position / 8];
+        match (byte >> (position % 8)) & state_mask {
+            0 => {}
+            1 => {
+                let len = out.len();
+                out[len - 1 - ii / 8] |= 1 << (ii % 8);
+            }
+            _ => masked = true,
+        }
+    }
+    (Cow::Owned(out), masked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_state_bytes() {
+        let data = [0b1, 0b10];
+        assert_eq!(
+            two_state_bytes(SignalValue::Binary(&data, 9)),
+            (Cow::Borrowed(data.as_slice()), false)
+        );
+        // 4-state `1x01`
+        let four = [0b0110_0001];
+        let (bytes, masked) = two_state_bytes(SignalValue::FourValue(&four, 4));
+        assert_eq!(bytes.as_ref(), [0b1001]);
+        assert!(masked);
+        // 9-state `h1`
+        let nine = [0b0100_0001];
+        let (bytes, masked) = two_state_bytes(SignalValue::NineValue(&nine, 2));
+        assert_eq!(bytes.as_ref(), [0b01]);
+        assert!(masked);
+    }
+}
@@ -12,9 +12,16 @@ macro_rules! impl_mappable_basic {
             fn try_from_signal(signal_value: SignalValue<'_>) -> Option<Self> {
                 match signal_value {
                     SignalValue::Binary(val, bits) => {
-                        if bits <= std::mem::size_of::<Self>() as u32 {
-                            let val = val.try_into().ok().map(|val| <$t>::from_be_bytes(val));
-                            val
+                        const BYTES: usize = std::mem::size_of::<$t>();
+                        if bits as usize <= BYTES * 8 {
+                            // values are right aligned, thus we might need to skip leading zeros or pad
+                            let start = val.len().saturating_sub(BYTES);
+                            if val[..start].iter().any(|b| *b != 0) {
+                                return None;
+                            }
+                            let mut buf = [0u8; BYTES];
+                            buf[BYTES - (val.len() - start)..].copy_from_slice(&val[start..]);
+                            Some(<$t>::from_be_bytes(buf))
                         } else {
                             None
                         }
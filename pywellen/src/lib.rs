@@ -1,14 +1,22 @@
+mod arrays;
 mod convert;
+use std::collections::HashMap;
 use std::sync::Arc;
 
+use arrays::{Column, Values};
 use convert::Mappable;
 use num_bigint::BigUint;
-use pyo3::types::PyInt;
-use pyo3::{exceptions::PyRuntimeError, prelude::*};
+use numpy::IntoPyArray;
+use pyo3::types::{PyDict, PyInt};
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    prelude::*,
+};
 
 use wellen::{
     viewers::{self},
-    LoadOptions, ScopeType, SignalValue, TimeTableIdx,
+    Edge, LoadOptions, ScopeType, SearchDirection, SignalChange, SignalRef, SignalValue,
+    TimeTableIdx,
 };
 
 pub trait PyErrExt<T> {
@@ -355,6 +363,7 @@ impl Waveform {
         let opts = LoadOptions {
             multi_thread: multi_threaded,
             remove_scopes_with_empty_name,
+            ..Default::default()
         };
         let header_result = viewers::read_header_from_file(path.as_str(), &opts).toerr()?;
         let hier = Hierarchy(Arc::new(header_result.hierarchy));
@@ -369,9 +378,10 @@ impl Waveform {
         })
     }
     fn get_signal<'py>(&mut self, var: &Var, py: Python<'py>) -> PyResult<Bound<'py, Signal>> {
-        let mut signal =
-            self.wave_source
-                .load_signals(&[var.0.signal_ref()], &self.hierarchy.0, true);
+        let mut signal = self
+            .wave_source
+            .load_signals(&[var.0.signal_ref()], &self.hierarchy.0, true)
+            .toerr()?;
         let (_sr, sig) = signal.swap_remove(0);
         Bound::new(
             py,
@@ -405,6 +415,72 @@ impl Waveform {
         let var = &self.hierarchy.0[maybe_var];
         self.get_signal(&Var(var.clone()), py)
     }
+
+    /// Returns a pandas DataFrame with one column per variable, indexed by all times at which
+    /// at least one of the variables changes. Values before the first change or with `x`, `z`
+    /// or other non-binary states are missing. Bit-vectors with up to 64 bits use the nullable
+    /// `UInt64` type.
+    #[pyo3(name = "to_dataframe")]
+    fn dataframe<'py>(
+        &mut self,
+        vars: Vec<PyRef<'py, Var>>,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let pandas = py.import("pandas")?;
+        let hierarchy = &self.hierarchy.0;
+        let ids: Vec<_> = vars.iter().map(|v| v.0.signal_ref()).collect();
+        let mut unique = ids.clone();
+        unique.sort();
+        unique.dedup();
+        let signals: HashMap<SignalRef, wellen::Signal> = self
+            .wave_source
+            .load_signals(&unique, hierarchy, true)
+            .toerr()?
+            .into_iter()
+            .collect();
+        let mut indices: Vec<TimeTableIdx> = signals
+            .values()
+            .flat_map(|s| s.time_indices().iter().copied())
+            .collect();
+        indices.sort();
+        indices.dedup();
+
+        let columns = PyDict::new(py);
+        for (var, id) in vars.iter().zip(ids) {
+            let signal = &signals[&id];
+            let first = signal.iter_changes().next().map(|(_, v)| v);
+            let mut column = Column::like(first, indices.len());
+            for idx in indices.iter() {
+                // only the last delta cycle of a time step counts
+                let value = signal
+                    .get_offset(*idx)
+                    .map(|offset| signal.get_value_at(&offset, offset.elements - 1));
+                column.push(value);
+            }
+            let is_integer = matches!(column.values, Values::UInt64(_));
+            let (values, mask) = column.into_numpy(py, true)?;
+            let values = if is_integer {
+                pandas
+                    .getattr("arrays")?
+                    .getattr("IntegerArray")?
+                    .call1((values, mask))?
+            } else {
+                values
+            };
+            columns.set_item(var.0.full_name(hierarchy), values)?;
+        }
+
+        let times: Vec<wellen::Time> = indices
+            .iter()
+            .map(|idx| self.time_table.0[*idx as usize])
+            .collect();
+        let index_args = PyDict::new(py);
+        index_args.set_item("name", "time")?;
+        let index = pandas.call_method("Index", (times.into_pyarray(py),), Some(&index_args))?;
+        let frame_args = PyDict::new(py);
+        frame_args.set_item("index", index)?;
+        pandas.call_method("DataFrame", (columns,), Some(&frame_args))
+    }
 }
 
 #[pyclass]
@@ -414,6 +490,62 @@ struct Signal {
     all_times: TimeTable,
 }
 
+/// A value that we can search for.
+#[derive(FromPyObject)]
+enum SearchValue {
+    Int(BigUint),
+    Float(f64),
+    /// string signal or bit string, e.g., `"01xz"`
+    Str(String),
+}
+
+impl SearchValue {
+    fn matches(&self, value: SignalValue) -> bool {
+        match (self, value) {
+            (SearchValue::Int(expected), SignalValue::Binary(..)) => {
+                BigUint::try_from_signal(value).as_ref() == Some(expected)
+            }
+            (SearchValue::Float(expected), SignalValue::Real(actual)) => *expected == actual,
+            (SearchValue::Str(expected), SignalValue::String(actual)) => expected == actual,
+            (SearchValue::Str(_), SignalValue::Real(_)) => false,
+            (SearchValue::Str(expected), _) => value.to_bit_string().as_ref() == Some(expected),
+            _ => false,
+        }
+    }
+}
+
+fn search_direction(backward: bool) -> SearchDirection {
+    if backward {
+        SearchDirection::Backward
+    } else {
+        SearchDirection::Forward
+    }
+}
+
+fn value_to_py<'a>(value: SignalValue, py: Python<'a>) -> Option<Bound<'a, PyAny>> {
+    match value {
+        SignalValue::Real(inner) => Some(inner.into_pyobject(py).unwrap().into_any()),
+        SignalValue::String(str) => Some(str.into_pyobject(py).unwrap().into_any()),
+        _ => match BigUint::try_from_signal(value) {
+            // If this signal is 2bits, this function will return an int
+            Some(number) => Some(number.into_pyobject(py).unwrap().into_any()),
+            // if this signal is not 2bits (e.g. it contains z,x, etc) then this function
+            // will return a string
+            None => value
+                .to_bit_string()
+                .map(|val| val.into_pyobject(py).unwrap().into_any()),
+        },
+    }
+}
+
+fn change_to_py<'a>(
+    change: Option<SignalChange>,
+    py: Python<'a>,
+) -> Option<(wellen::Time, Bound<'a, PyAny>)> {
+    let change = change?;
+    value_to_py(change.value, py).map(|value| (change.time, value))
+}
+
 #[pymethods]
 impl Signal {
     pub fn value_at_time<'a>(
@@ -421,38 +553,126 @@ impl Signal {
         time: wellen::Time,
         py: Python<'a>,
     ) -> Option<Bound<'a, PyAny>> {
-        let val = self
-            .all_times
-            .0
-            .as_ref()
-            .binary_search(&time)
-            .unwrap_or_else(|val| val);
-        self.value_at_idx(val as TimeTableIdx, py)
+        let value = self.signal.value_at_time(&self.all_times.0, time)?;
+        value_to_py(value, py)
     }
 
     pub fn value_at_idx<'a>(&self, idx: TimeTableIdx, py: Python<'a>) -> Option<Bound<'a, PyAny>> {
-        let maybe_signal = self
+        let value = self.signal.value_at_idx(idx)?;
+        value_to_py(value, py)
+    }
+
+    /// Returns the time and value of the first change after `time`
+    /// or the last change before `time` if `backward` is set.
+    #[pyo3(signature = (time, backward = false))]
+    pub fn find_change<'a>(
+        &self,
+        time: wellen::Time,
+        backward: bool,
+        py: Python<'a>,
+    ) -> Option<(wellen::Time, Bound<'a, PyAny>)> {
+        let change = self
             .signal
-            .get_offset(idx)
-            .map(|data_offset| self.signal.get_value_at(&data_offset, 0));
-        if let Some(signal) = maybe_signal {
-            let output = match signal {
-                SignalValue::Real(inner) => Some(inner.into_pyobject(py).unwrap().into_any()),
-                SignalValue::String(str) => Some(str.into_pyobject(py).unwrap().into_any()),
-                _ => match BigUint::try_from_signal(signal) {
-                    // If this signal is 2bits, this function will return an int
-                    Some(number) => Some(number.into_pyobject(py).unwrap().into_any()),
-                    // if this signal is not 2bits (e.g. it contains z,x, etc) then this function
-                    // will return a string
-                    None => signal
-                        .to_bit_string()
-                        .map(|val| val.into_pyobject(py).unwrap().into_any()),
-                },
-            };
-            output
-        } else {
-            None
+            .find_change(&self.all_times.0, time, search_direction(backward));
+        change_to_py(change, py)
+    }
+
+    /// `edge` is one of `"rising"`, `"falling"` or `"any"`.
+    #[pyo3(signature = (time, edge = "rising", backward = false))]
+    pub fn find_edge<'a>(
+        &self,
+        time: wellen::Time,
+        edge: &str,
+        backward: bool,
+        py: Python<'a>,
+    ) -> PyResult<Option<(wellen::Time, Bound<'a, PyAny>)>> {
+        let edge = match edge {
+            "rising" => Edge::Rising,
+            "falling" => Edge::Falling,
+            "any" => Edge::Any,
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "unknown edge `{other}`, expected `rising`, `falling` or `any`"
+                )))
+            }
+        };
+        let change =
+            self.signal
+                .find_edge(&self.all_times.0, time, search_direction(backward), edge);
+        Ok(change_to_py(change, py))
+    }
+
+    /// Searches for a change to `value` which can be an int, a float, a string or a bit string.
+    #[pyo3(signature = (time, value, backward = false))]
+    pub fn find_value<'a>(
+        &self,
+        time: wellen::Time,
+        value: SearchValue,
+        backward: bool,
+        py: Python<'a>,
+    ) -> Option<(wellen::Time, Bound<'a, PyAny>)> {
+        let change =
+            self.signal
+                .find_matching(&self.all_times.0, time, search_direction(backward), |v| {
+                    value.matches(v)
+                });
+        change_to_py(change, py)
+    }
+
+    /// Searches for a change to a value for which `predicate` returns `True`.
+    #[pyo3(signature = (time, predicate, backward = false))]
+    pub fn find_matching<'a>(
+        &self,
+        time: wellen::Time,
+        predicate: Bound<'a, PyAny>,
+        backward: bool,
+        py: Python<'a>,
+    ) -> PyResult<Option<(wellen::Time, Bound<'a, PyAny>)>> {
+        let mut error = None;
+        let change =
+            self.signal
+                .find_matching(&self.all_times.0, time, search_direction(backward), |v| {
+                    if error.is_some() {
+                        return false;
+                    }
+                    let Some(value) = value_to_py(v, py) else {
+                        return false;
+                    };
+                    match predicate.call1((value,)).and_then(|r| r.is_truthy()) {
+                        Ok(result) => result,
+                        Err(err) => {
+                            error = Some(err);
+                            false
+                        }
+                    }
+                });
+        match error {
+            Some(err) => Err(err),
+            None => Ok(change_to_py(change, py)),
+        }
+    }
+
+    /// Returns all changes as NumPy arrays `(times, values, mask)`. Times are `uint64`.
+    /// Values are `uint64` for bit-vectors with up to 64 bits, `float64` for reals and Python
+    /// objects for wider bit-vectors and strings. The boolean mask marks values with `x`, `z`
+    /// or other non-binary states, which only contain the bits that are `1`.
+    pub fn to_numpy<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<(Bound<'py, PyAny>, Bound<'py, PyAny>, Bound<'py, PyAny>)> {
+        let signal = &self.signal;
+        let times: Vec<wellen::Time> = signal
+            .time_indices()
+            .iter()
+            .map(|idx| self.all_times.0[*idx as usize])
+            .collect();
+        let first = signal.iter_changes().next().map(|(_, v)| v);
+        let mut column = Column::like(first, times.len());
+        for (_, value) in signal.iter_changes() {
+            column.push(Some(value));
         }
+        let (values, mask) = column.into_numpy(py, false)?;
+        Ok((times.into_pyarray(py).into_any(), values, mask))
     }
 
     pub fn all_changes(&self) -> SignalChangeIter {
@@ -480,7 +700,12 @@ impl SignalChangeIter {
         python: Python<'a>,
     ) -> Option<(wellen::Time, Bound<'a, PyAny>)> {
         if let Some(time_idx) = slf.signal.signal.time_indices().get(slf.offset) {
-            let data = slf.signal.value_at_idx(*time_idx, python);
+            // report every delta cycle
+            let signal = &slf.signal.signal;
+            let data = signal.get_offset(*time_idx).and_then(|offset| {
+                let element = (slf.offset - offset.start) as u16;
+                value_to_py(signal.get_value_at(&offset, element), python)
+            });
             let time = slf.signal.all_times.0.get(*time_idx as usize).cloned()?;
             slf.offset += 1;
             data.map(|val| (time, val))
@@ -64,6 +64,40 @@ def test_vcd_not_starting_at_zero():
     assert sp_sig.value_at_idx(1) is not None
     assert sp_sig.value_at_idx(0) is None
 
+def test_value_at_time_between_changes():
+    filename = _git_root_rel("wellen/inputs/gameroy/trace_prefix.vcd")
+    waves = Waveform(path=filename)
+    pc = waves.get_signal_from_path("gameroy.cpu.pc")
+
+    # before the first time step, there is no value
+    assert pc.value_at_time(0) is None
+    assert pc.value_at_time(4) == 1
+    # in between time steps, the previous value is still valid
+    assert pc.value_at_time(15) == 1
+    assert pc.value_at_time(16) == 4
+    assert pc.value_at_time(19) == 4
+
+
+def test_search():
+    filename = _git_root_rel("wellen/inputs/gameroy/trace_prefix.vcd")
+    waves = Waveform(path=filename)
+    pc = waves.get_signal_from_path("gameroy.cpu.pc")
+
+    assert pc.find_change(4) == (16, 4)
+    assert pc.find_change(16) == (20, 5)
+    assert pc.find_change(20, backward=True) == (16, 4)
+    assert pc.find_change(4, backward=True) is None
+
+    assert pc.find_value(0, 5) == (20, 5)
+    assert pc.find_value(0, "0000000000000101") == (20, 5)
+    assert pc.find_value(100, 1, backward=True) == (4, 1)
+    assert pc.find_matching(0, lambda v: v > 4) == (20, 5)
+
+    # pc goes from 4 to 5, thus the least significant bit rises
+    assert pc.find_edge(4, "rising") == (20, 5)
+    assert pc.find_edge(4, "falling") == (16, 4)
+
+
 def test_vcd_var_types_types():
     filename = _git_root_rel("wellen/inputs/gtkwave-analyzer/vcd_extensions.vcd")
     waves = Waveform(path=filename)
@@ -292,3 +326,45 @@ def test_scope_types():
             expected_full_name = f"main.{scope_name}"
             actual_full_name = scope.full_name(h)
             assert actual_full_name == expected_full_name, f"Expected full name '{expected_full_name}', got '{actual_full_name}'"
+
+
+def test_to_numpy():
+    import numpy as np
+
+    filename = _git_root_rel("wellen/inputs/gameroy/trace_prefix.vcd")
+    waves = Waveform(path=filename)
+    pc = waves.get_signal_from_path("gameroy.cpu.pc")
+    times, values, mask = pc.to_numpy()
+    assert times.dtype == np.uint64
+    assert values.dtype == np.uint64
+    assert mask.dtype == np.bool_
+    assert not mask.any()
+    assert [(int(t), int(v)) for t, v in zip(times, values)] == list(pc.all_changes())
+
+
+def test_to_numpy_with_x():
+    filename = _git_root_rel("wellen/inputs/ghdl/idea.vcd")
+    waves = Waveform(path=filename)
+    y_1 = waves.get_signal_from_path("tb_idea.y_1")
+    times, values, mask = y_1.to_numpy()
+    assert list(times) == [0, 100000000, 200000000, 300000000]
+    # the first value is all `x`
+    assert list(mask) == [True, False, False, False]
+    assert values[0] == 0
+    assert values[1] == 0b1000101010101001
+
+
+def test_to_dataframe():
+    filename = _git_root_rel("wellen/inputs/ghdl/idea.vcd")
+    waves = Waveform(path=filename)
+    h = waves.hierarchy
+    tb = next(s for s in h.top_scopes() if s.name(h) == "tb_idea")
+    vars = [v for v in tb.vars(h) if v.name(h) in ("x_1", "y_1")]
+    df = waves.to_dataframe(vars)
+    assert list(df.columns) == ["tb_idea.x_1", "tb_idea.y_1"]
+    assert list(df.index) == [0, 100000000, 200000000, 300000000]
+    assert str(df["tb_idea.x_1"].dtype) == "UInt64"
+    # `u` and `x` values are missing
+    assert df["tb_idea.x_1"].isna().tolist() == [True, False, False, False]
+    assert df["tb_idea.y_1"].isna().tolist() == [True, False, False, False]
+    assert df["tb_idea.x_1"][100000000] == 0b0001000100010001
@@ -0,0 +1,748 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// Command-line tool to inspect, convert and export waveforms.
+
+use clap::{Parser, Subcommand, ValueEnum};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use wellen::search::{Pattern, SearchFilter};
+use wellen::simple::Waveform;
+use wellen::*;
+
+#[derive(Parser, Debug)]
+#[command(name = "wellen")]
+#[command(version)]
+#[command(about = "Inspects and converts VCD, FST and GHW waveforms.", long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Prints meta data and statistics.
+    Info { file: PathBuf },
+    /// Prints the hierarchy together with the type and width of every variable.
+    Tree {
+        file: PathBuf,
+        /// Only prints scopes up to this depth.
+        #[arg(long)]
+        max_depth: Option<usize>,
+    },
+    /// Prints the value changes of all variables that match one of the glob patterns,
+    /// e.g., `top.cpu.*`.
+    Dump {
+        file: PathBuf,
+        #[arg(required = true)]
+        patterns: Vec<String>,
+        /// Skips changes before this time. Times without a unit, like `100`, are in the
+        /// timescale of the file, otherwise a unit can be appended, like `10ns`.
+        #[arg(long)]
+        from: Option<String>,
+        /// Skips changes after this time.
+        #[arg(long)]
+        to: Option<String>,
+        #[arg(long, value_enum, default_value_t = DumpFormat::Text)]
+        format: DumpFormat,
+    },
+    /// Prints the values of all variables that match one of the glob patterns at `time`.
+    /// Prints all variables if no pattern is given.
+    ValuesAt {
+        file: PathBuf,
+        time: String,
+        patterns: Vec<String>,
+    },
+    /// Converts a waveform into a VCD or FST.
+    Convert {
+        input: PathBuf,
+        output: PathBuf,
+        /// Derived from the extension of the output file by default.
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+    /// Exports the values of all variables that match one of the glob patterns into a Parquet
+    /// file. Exports all variables if no pattern is given.
+    #[cfg(feature = "parquet")]
+    Export {
+        input: PathBuf,
+        output: PathBuf,
+        patterns: Vec<String>,
+        #[arg(long, value_enum, default_value_t = ExportLayout::Changes)]
+        layout: ExportLayout,
+    },
+    /// Prints the toggle coverage of all variables in a scope, e.g., `top.cpu`, or in the whole
+    /// design if no scope is given.
+    Coverage {
+        file: PathBuf,
+        scope: Option<String>,
+        /// Skips transitions before this time.
+        #[arg(long)]
+        from: Option<String>,
+        /// Skips transitions after this time.
+        #[arg(long)]
+        to: Option<String>,
+        #[arg(long, value_enum, default_value_t = XzPolicy::Strict)]
+        xz: XzPolicy,
+        /// Only prints scopes up to this depth below the root.
+        #[arg(long)]
+        max_depth: Option<u32>,
+        /// Writes the full report with the coverage of every bit to a JSON file.
+        #[arg(long)]
+        json: Option<PathBuf>,
+    },
+    /// Writes the switching activity of all nets in a scope, e.g., `top.cpu`, or in the whole
+    /// design if no scope is given, to a SAIF file for power analysis.
+    Saif {
+        input: PathBuf,
+        output: PathBuf,
+        scope: Option<String>,
+        /// Start of the analyzed interval, the first time step by default.
+        #[arg(long)]
+        from: Option<String>,
+        /// End of the analyzed interval, the last time step by default.
+        #[arg(long)]
+        to: Option<String>,
+    },
+    /// Checks temporal properties, e.g., `@(posedge top.clk) top.req |-> ##[1:8] top.ack`,
+    /// and prints every failed attempt. Fails if any property does not hold.
+    Check {
+        file: PathBuf,
+        #[arg(required = true)]
+        properties: Vec<String>,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum DumpFormat {
+    Text,
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Vcd,
+    Fst,
+}
+
+#[cfg(feature = "parquet")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum ExportLayout {
+    /// one row per value change
+    Changes,
+    /// one column per variable and one row per time step
+    Sampled,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum XzPolicy {
+    /// only transitions between 0 and 1 count
+    Strict,
+    /// x and z states are skipped, 0 -> x -> 1 is a rise
+    Bridge,
+    /// transitions from x and z count, x -> 1 is a rise
+    Optimistic,
+}
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let stdout = std::io::stdout().lock();
+    let mut out = BufWriter::new(stdout);
+    let result = match args.command {
+        Command::Info { file } => info(&mut out, &file),
+        Command::Tree { file, max_depth } => tree(&mut out, &file, max_depth),
+        Command::Dump {
+            file,
+            patterns,
+            from,
+            to,
+            format,
+        } => dump(&mut out, &file, &patterns, from, to, format),
+        Command::ValuesAt {
+            file,
+            time,
+            patterns,
+        } => values_at(&mut out, &file, &time, &patterns),
+        Command::Convert {
+            input,
+            output,
+            format,
+        } => convert(&input, &output, format),
+        #[cfg(feature = "parquet")]
+        Command::Export {
+            input,
+            output,
+            patterns,
+            layout,
+        } => export(&input, &output, &patterns, layout),
+        Command::Coverage {
+            file,
+            scope,
+            from,
+            to,
+            xz,
+            max_depth,
+            json,
+        } => coverage(&mut out, &file, scope, from, to, xz, max_depth, json),
+        Command::Saif {
+            input,
+            output,
+            scope,
+            from,
+            to,
+        } => saif(&input, &output, scope, from, to),
+        Command::Check { file, properties } => check(&mut out, &file, &properties),
+    };
+    match result.and_then(|_| Ok(out.flush()?)) {
+        Ok(()) => ExitCode::SUCCESS,
+        // a closed pipe, e.g., when piping into `head`, is not an error
+        Err(e) if is_broken_pipe(e.as_ref()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn is_broken_pipe(e: &(dyn std::error::Error + 'static)) -> bool {
+    e.downcast_ref::<std::io::Error>()
+        .is_some_and(|e| e.kind() == std::io::ErrorKind::BrokenPipe)
+}
+
+fn load(file: &Path) -> Result<Waveform> {
+    simple::read(file).map_err(|e| format!("failed to load {}: {e}", file.display()).into())
+}
+
+/// Like `load`, but keeps the signal source in order to load signals for a time window only.
+fn load_source(file: &Path) -> Result<(Hierarchy, TimeTable, SignalSource)> {
+    let read = || -> wellen::Result<_> {
+        let header = viewers::read_header_from_file(file, &LoadOptions::default())?;
+        let body = viewers::read_body(header.body, &header.hierarchy, None)?;
+        Ok((header.hierarchy, body.time_table, body.source))
+    };
+    read().map_err(|e| format!("failed to load {}: {e}", file.display()).into())
+}
+
+fn info(out: &mut impl Write, file: &Path) -> Result<()> {
+    let wave = load(file)?;
+    let h = wave.hierarchy();
+    writeln!(out, "file:       {}", file.display())?;
+    writeln!(out, "format:     {:?}", h.file_format())?;
+    match h.timescale() {
+        Some(timescale) => writeln!(out, "timescale:  {}", format_timescale(timescale))?,
+        None => writeln!(out, "timescale:  unknown")?,
+    }
+    writeln!(out, "date:       {}", h.date())?;
+    writeln!(out, "version:    {}", h.version())?;
+    let time_table = wave.time_table();
+    match (time_table.first(), time_table.last()) {
+        (Some(first), Some(last)) => writeln!(
+            out,
+            "time:       {first} .. {last} ({} time steps)",
+            time_table.len()
+        )?,
+        _ => writeln!(out, "time:       no time steps")?,
+    }
+    writeln!(out, "scopes:     {}", h.iter_scopes().len())?;
+    writeln!(out, "variables:  {}", h.iter_vars().len())?;
+    let signals = h.get_unique_signals_vars().iter().flatten().count();
+    writeln!(out, "signals:    {signals}")?;
+    writeln!(
+        out,
+        "file size:  {}",
+        format_bytes(std::fs::metadata(file)?.len())
+    )?;
+    writeln!(
+        out,
+        "hierarchy:  {} in memory",
+        format_bytes(h.size_in_memory() as u64)
+    )?;
+    Ok(())
+}
+
+fn tree(out: &mut impl Write, file: &Path, max_depth: Option<usize>) -> Result<()> {
+    let wave = load(file)?;
+    let h = wave.hierarchy();
+    for item in h.items() {
+        print_item(out, h, item, 0, max_depth)?;
+    }
+    Ok(())
+}
+
+fn print_item(
+    out: &mut impl Write,
+    h: &Hierarchy,
+    item: ScopeOrVarRef,
+    depth: usize,
+    max_depth: Option<usize>,
+) -> std::io::Result<()> {
+    let indent = "  ".repeat(depth);
+    match item.deref(h) {
+        ScopeOrVar::Scope(scope) => {
+            if max_depth.is_some_and(|max| depth >= max) {
+                return Ok(());
+            }
+            write!(out, "{indent}{} ({:?}", scope.name(h), scope.scope_type())?;
+            if let Some(component) = scope.component(h) {
+                write!(out, " {component}")?;
+            }
+            writeln!(out, ")")?;
+            for child in scope.items(h) {
+                print_item(out, h, child, depth + 1, max_depth)?;
+            }
+        }
+        ScopeOrVar::Var(var) => {
+            let width = match var.signal_encoding() {
+                SignalEncoding::String => "string".to_string(),
+                SignalEncoding::Real => "real".to_string(),
+                SignalEncoding::BitVector(len) if len.get() == 1 => "1 bit".to_string(),
+                SignalEncoding::BitVector(len) => format!("{len} bits"),
+            };
+            writeln!(
+                out,
+                "{indent}{}{} : {:?}, {width}, {:?}",
+                var.name(h),
+                format_index(var),
+                var.var_type(),
+                var.direction()
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn dump(
+    out: &mut impl Write,
+    file: &Path,
+    patterns: &[String],
+    from: Option<String>,
+    to: Option<String>,
+    format: DumpFormat,
+) -> Result<()> {
+    let (h, time_table, mut source) = load_source(file)?;
+    let vars = find_vars(&h, patterns)?;
+    let timescale = h.timescale();
+    let from = from.map(|t| parse_time(&t, timescale)).transpose()?;
+    let to = to.map(|t| parse_time(&t, timescale)).transpose()?;
+    let ids: Vec<_> = vars.iter().map(|v| h[*v].signal_ref()).collect();
+    // with a time window, only the data inside of it needs to be decoded
+    let loaded = match (from, to) {
+        (None, None) => source.load_signals(&ids, &h, true)?,
+        (from, to) => source.load_signals_in_range(
+            &ids,
+            &h,
+            from.unwrap_or(0),
+            to.unwrap_or(Time::MAX),
+            true,
+        )?,
+    };
+    let signals: HashMap<_, _> = loaded.into_iter().collect();
+    let names: Vec<_> = vars.iter().map(|v| full_name(&h, &h[*v])).collect();
+
+    // merge the changes of all variables by time, the order of variables and delta cycles is kept
+    let before = |idx: TimeTableIdx| from.is_some_and(|from| time_table[idx as usize] < from);
+    let after = |idx: TimeTableIdx| to.is_some_and(|to| time_table[idx as usize] > to);
+    let mut changes: Vec<_> = ids
+        .iter()
+        .map(|id| {
+            signals[id]
+                .iter_changes()
+                .skip_while(move |(idx, _)| before(*idx))
+                .take_while(move |(idx, _)| !after(*idx))
+                .peekable()
+        })
+        .collect();
+    let mut queue: BinaryHeap<Reverse<(TimeTableIdx, usize)>> = changes
+        .iter_mut()
+        .enumerate()
+        .filter_map(|(ii, c)| c.peek().map(|(t, _)| Reverse((*t, ii))))
+        .collect();
+
+    match format {
+        DumpFormat::Text => {}
+        DumpFormat::Csv => writeln!(out, "time,name,value")?,
+        DumpFormat::Json => write!(out, "[")?,
+    }
+    let mut first = true;
+    while let Some(Reverse((idx, ii))) = queue.pop() {
+        let (_, value) = changes[ii].next().unwrap();
+        let time = time_table[idx as usize];
+        let value = format_value(&h, &h[vars[ii]], value);
+        match format {
+            DumpFormat::Text => writeln!(out, "{time} {} {value}", names[ii])?,
+            DumpFormat::Csv => writeln!(
+                out,
+                "{time},{},{}",
+                csv_escape(&names[ii]),
+                csv_escape(&value)
+            )?,
+            DumpFormat::Json => {
+                let separator = if first { "" } else { "," };
+                write!(
+                    out,
+                    "{separator}\n  {{\"time\": {time}, \"name\": {}, \"value\": {}}}",
+                    json_string(&names[ii]),
+                    json_string(&value)
+                )?;
+            }
+        }
+        first = false;
+        if let Some((t, _)) = changes[ii].peek() {
+            queue.push(Reverse((*t, ii)));
+        }
+    }
+    if let DumpFormat::Json = format {
+        writeln!(out, "\n]")?;
+    }
+    Ok(())
+}
+
+fn values_at(out: &mut impl Write, file: &Path, time: &str, patterns: &[String]) -> Result<()> {
+    let mut wave = load(file)?;
+    let time = parse_time(time, wave.hierarchy().timescale())?;
+    let vars = find_vars_or_all(wave.hierarchy(), patterns)?;
+    load_vars(&mut wave, &vars)?;
+    let h = wave.hierarchy();
+    for var in vars.iter() {
+        let signal = wave.get_signal(h[*var].signal_ref()).unwrap();
+        let value = match signal.value_at_time(wave.time_table(), time) {
+            Some(value) => format_value(h, &h[*var], value),
+            None => "-".to_string(),
+        };
+        writeln!(out, "{} {value}", full_name(h, &h[*var]))?;
+    }
+    Ok(())
+}
+
+fn convert(input: &Path, output: &Path, format: Option<OutputFormat>) -> Result<()> {
+    let format = match format {
+        Some(format) => format,
+        None => match output.extension().and_then(|e| e.to_str()) {
+            Some("vcd") => OutputFormat::Vcd,
+            Some("fst") => OutputFormat::Fst,
+            _ => {
+                return Err(format!(
+                    "cannot derive the output format from {}, please use --format",
+                    output.display()
+                )
+                .into());
+            }
+        },
+    };
+    let mut wave = load(input)?;
+    let ids: Vec<_> = wave
+        .hierarchy()
+        .get_unique_signals_vars()
+        .iter()
+        .flatten()
+        .map(|v| v.signal_ref())
+        .collect();
+    wave.load_signals_multi_threaded(&ids)?;
+    let signals = ids.iter().map(|id| wave.get_signal(*id).unwrap());
+    let mut out = BufWriter::new(std::fs::File::create(output)?);
+    match format {
+        OutputFormat::Vcd => vcd::write(&mut out, wave.hierarchy(), wave.time_table(), signals)?,
+        OutputFormat::Fst => fst::write(&mut out, wave.hierarchy(), wave.time_table(), signals)?,
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(feature = "parquet")]
+fn export(input: &Path, output: &Path, patterns: &[String], layout: ExportLayout) -> Result<()> {
+    let mut wave = load(input)?;
+    let vars = find_vars_or_all(wave.hierarchy(), patterns)?;
+    let layout = match layout {
+        ExportLayout::Changes => arrow::Layout::Changes,
+        ExportLayout::Sampled => arrow::Layout::Sampled,
+    };
+    let out = BufWriter::new(std::fs::File::create(output)?);
+    arrow::write_parquet(&mut wave, &vars, layout, out)?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn coverage(
+    out: &mut impl Write,
+    file: &Path,
+    scope: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    xz: XzPolicy,
+    max_depth: Option<u32>,
+    json: Option<PathBuf>,
+) -> Result<()> {
+    let mut wave = load(file)?;
+    let root = find_scope(wave.hierarchy(), scope)?;
+    let timescale = wave.hierarchy().timescale();
+    let from = from.map(|t| parse_time(&t, timescale)).transpose()?;
+    let to = to.map(|t| parse_time(&t, timescale)).transpose()?;
+    let time_window = match (from, to) {
+        (None, None) => None,
+        (from, to) => Some((from.unwrap_or(0), to.unwrap_or(Time::MAX))),
+    };
+    let xz_policy = match xz {
+        XzPolicy::Strict => coverage::XzPolicy::Strict,
+        XzPolicy::Bridge => coverage::XzPolicy::Bridge,
+        XzPolicy::Optimistic => coverage::XzPolicy::Optimistic,
+    };
+    let options = coverage::CoverageOptions {
+        time_window,
+        xz_policy,
+    };
+    let report = coverage::toggle_coverage(&mut wave, root, &options)?;
+    report.write_summary(wave.hierarchy(), max_depth, out)?;
+    if let Some(json) = json {
+        let mut file = BufWriter::new(std::fs::File::create(json)?);
+        report.write_json(wave.hierarchy(), &mut file)?;
+        file.flush()?;
+    }
+    Ok(())
+}
+
+fn saif(
+    input: &Path,
+    output: &Path,
+    scope: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<()> {
+    let mut wave = load(input)?;
+    let root = find_scope(wave.hierarchy(), scope)?;
+    let timescale = wave.hierarchy().timescale();
+    let from = from.map(|t| parse_time(&t, timescale)).transpose()?;
+    let to = to.map(|t| parse_time(&t, timescale)).transpose()?;
+    let time_window = match (from, to) {
+        (None, None) => None,
+        (from, to) => {
+            let tt = wave.time_table();
+            let first = tt.first().copied().unwrap_or(0);
+            let last = tt.last().copied().unwrap_or(first);
+            Some((from.unwrap_or(first), to.unwrap_or(last)))
+        }
+    };
+    let design = root
+        .map(|r| wave.hierarchy()[r].full_name(wave.hierarchy()))
+        .unwrap_or_default();
+    let options = saif::SaifOptions {
+        time_window,
+        design,
+    };
+    let mut out = BufWriter::new(std::fs::File::create(output)?);
+    saif::write_saif(&mut wave, root, &options, &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+fn check(out: &mut impl Write, file: &Path, properties: &[String]) -> Result<()> {
+    let mut wave = load(file)?;
+    let properties = properties
+        .iter()
+        .map(|p| Ok((p, checker::Property::parse(p, wave.hierarchy())?)))
+        .collect::<Result<Vec<_>>>()?;
+    let mut failed = 0;
+    for (text, property) in properties.iter() {
+        let report = checker::check_property(&mut wave, property)?;
+        writeln!(out, "{text}")?;
+        writeln!(
+            out,
+            "  {} attempts: {} passed, {} vacuous, {} disabled, {} unfinished, {} failed",
+            report.attempts,
+            report.passed,
+            report.vacuous,
+            report.disabled,
+            report.unfinished,
+            report.failures.len()
+        )?;
+        for failure in report.failures.iter() {
+            writeln!(
+                out,
+                "  failed: started at {}, failed at {}",
+                failure.start, failure.end
+            )?;
+        }
+        if !report.failures.is_empty() {
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        out.flush()?;
+        return Err(format!("{failed} of {} properties failed", properties.len()).into());
+    }
+    Ok(())
+}
+
+/// Looks up a scope by its dot separated name, e.g., `top.cpu`.
+fn find_scope(h: &Hierarchy, name: Option<String>) -> Result<Option<ScopeRef>> {
+    let Some(name) = name else {
+        return Ok(None);
+    };
+    let path: Vec<_> = name.split('.').collect();
+    let scope = h.lookup_scope(&path);
+    Ok(Some(
+        scope.ok_or_else(|| format!("no scope named `{name}`"))?,
+    ))
+}
+
+/// Returns all variables if there are no patterns.
+fn find_vars_or_all(h: &Hierarchy, patterns: &[String]) -> Result<Vec<VarRef>> {
+    if patterns.is_empty() {
+        Ok((0..h.iter_vars().len())
+            .map(|ii| VarRef::from_index(ii).unwrap())
+            .collect())
+    } else {
+        find_vars(h, patterns)
+    }
+}
+
+/// Returns all variables that match any of the patterns, in the order of the patterns.
+fn find_vars(h: &Hierarchy, patterns: &[String]) -> Result<Vec<VarRef>> {
+    let mut out: Vec<VarRef> = vec![];
+    for pattern in patterns.iter() {
+        let found = h.search(&Pattern::glob(pattern), &SearchFilter::default());
+        if found.is_empty() {
+            return Err(format!("no variable matches `{pattern}`").into());
+        }
+        for var in found {
+            if !out.contains(&var) {
+                out.push(var);
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn load_vars(wave: &mut Waveform, vars: &[VarRef]) -> Result<()> {
+    let ids: Vec<_> = vars
+        .iter()
+        .map(|v| wave.hierarchy()[*v].signal_ref())
+        .collect();
+    wave.load_signals_multi_threaded(&ids)?;
+    Ok(())
+}
+
+fn full_name(h: &Hierarchy, var: &Var) -> String {
+    format!("{}{}", var.full_name(h), format_index(var))
+}
+
+fn format_index(var: &Var) -> String {
+    match var.index() {
+        None => String::new(),
+        Some(index) if index.msb() == index.lsb() => format!("[{}]", index.lsb()),
+        Some(index) => format!("[{}:{}]", index.msb(), index.lsb()),
+    }
+}
+
+/// Enum values are printed by their name.
+fn format_value(h: &Hierarchy, var: &Var, value: SignalValue) -> String {
+    let value = value.to_string();
+    if let Some((_, mapping)) = var.enum_type(h) {
+        if let Some((_, name)) = mapping.iter().find(|(bits, _)| **bits == value) {
+            return name.to_string();
+        }
+    }
+    value
+}
+
+fn format_timescale(timescale: Timescale) -> String {
+    format!("{} {}", timescale.factor, unit_name(timescale.unit))
+}
+
+fn unit_name(unit: TimescaleUnit) -> &'static str {
+    match unit {
+        TimescaleUnit::ZeptoSeconds => "zs",
+        TimescaleUnit::AttoSeconds => "as",
+        TimescaleUnit::FemtoSeconds => "fs",
+        TimescaleUnit::PicoSeconds => "ps",
+        TimescaleUnit::NanoSeconds => "ns",
+        TimescaleUnit::MicroSeconds => "us",
+        TimescaleUnit::MilliSeconds => "ms",
+        TimescaleUnit::Seconds => "s",
+        TimescaleUnit::Unknown => "(unknown unit)",
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+/// Parses a time like `100` in the timescale of the file, or with a unit like `10ns`.
+/// Times that fall between two ticks of the timescale are rounded down.
+fn parse_time(value: &str, timescale: Option<Timescale>) -> Result<Time> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid time `{value}`"))?;
+    let unit = unit.trim();
+    if unit.is_empty() {
+        return Ok(number);
+    }
+    let unit_exponent = [
+        TimescaleUnit::ZeptoSeconds,
+        TimescaleUnit::AttoSeconds,
+        TimescaleUnit::FemtoSeconds,
+        TimescaleUnit::PicoSeconds,
+        TimescaleUnit::NanoSeconds,
+        TimescaleUnit::MicroSeconds,
+        TimescaleUnit::MilliSeconds,
+        TimescaleUnit::Seconds,
+    ]
+    .into_iter()
+    .find(|u| unit_name(*u) == unit)
+    .and_then(|u| u.to_exponent())
+    .ok_or_else(|| format!("unknown time unit `{unit}`"))?;
+    let (factor, exponent) = timescale
+        .and_then(|t| Some((t.factor as u128, t.unit.to_exponent()?)))
+        .ok_or("the file has no timescale, please specify times without a unit")?;
+    let shift = 10u128.pow(unit_exponent.abs_diff(exponent) as u32);
+    let ticks = if unit_exponent >= exponent {
+        number as u128 * shift / factor
+    } else {
+        number as u128 / shift / factor
+    };
+    Ok(Time::try_from(ticks).map_err(|_| format!("time `{value}` is too large"))?)
+}
+
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
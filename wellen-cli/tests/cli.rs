@@ -0,0 +1,168 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// runs the `wellen` binary on waveforms from the library tests
+
+use std::process::Command;
+
+const IDEA: &str = "../wellen/inputs/ghdl/idea.vcd";
+
+fn run(args: &[&str]) -> String {
+    let out = Command::new(env!("CARGO_BIN_EXE_wellen"))
+        .args(args)
+        .output()
+        .expect("failed to run wellen");
+    assert!(
+        out.status.success(),
+        "wellen {args:?} failed:\n{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    String::from_utf8(out.stdout).unwrap()
+}
+
+fn run_err(args: &[&str]) -> String {
+    let out = Command::new(env!("CARGO_BIN_EXE_wellen"))
+        .args(args)
+        .output()
+        .expect("failed to run wellen");
+    assert!(!out.status.success(), "wellen {args:?} should have failed");
+    String::from_utf8(out.stderr).unwrap()
+}
+
+#[test]
+fn info() {
+    let out = run(&["info", IDEA]);
+    assert!(out.contains("format:     Vcd"), "{out}");
+    assert!(out.contains("timescale:  1 fs"), "{out}");
+    assert!(
+        out.contains("time:       0 .. 1400000000 (6 time steps)"),
+        "{out}"
+    );
+    assert!(out.contains("variables:  706"), "{out}");
+}
+
+#[test]
+fn tree() {
+    let out = run(&["tree", IDEA, "--max-depth", "1"]);
+    assert!(out.contains("tb_idea (Module)\n  x_1[15:0] : Reg, 16 bits, Unknown\n"));
+    // scopes below the maximum depth are skipped
+    assert!(!out.contains("uut"));
+}
+
+#[test]
+fn dump() {
+    let out = run(&[
+        "dump",
+        IDEA,
+        "tb_idea.x_1",
+        "--from",
+        "100ns",
+        "--to",
+        "200000000",
+    ]);
+    assert_eq!(
+        out,
+        "100000000 tb_idea.x_1[15:0] 0001000100010001\n\
+         200000000 tb_idea.x_1[15:0] 0000000000000000\n"
+    );
+    let csv = run(&["dump", IDEA, "tb_idea.y_?", "--to", "0", "--format", "csv"]);
+    assert_eq!(csv.lines().count(), 5);
+    assert_eq!(
+        csv.lines().nth(1),
+        Some("0,tb_idea.y_1[15:0],xxxxxxxxxxxxxxxx")
+    );
+    let json = run(&["dump", IDEA, "tb_idea.x_1", "--to", "0", "--format", "json"]);
+    assert_eq!(
+        json,
+        "[\n  {\"time\": 0, \"name\": \"tb_idea.x_1[15:0]\", \"value\": \"uuuuuuuuuuuuuuuu\"}\n]\n"
+    );
+}
+
+#[test]
+fn values_at() {
+    let out = run(&["values-at", IDEA, "250ns", "tb_idea.x_1", "tb_idea.x_2"]);
+    assert_eq!(
+        out,
+        "tb_idea.x_1[15:0] 0000000000000000\ntb_idea.x_2[15:0] 0000000000000000\n"
+    );
+}
+
+#[test]
+fn convert_to_fst_and_back() {
+    let dir = std::env::temp_dir().join(format!("wellen-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let fst = dir.join("idea.fst");
+    let vcd = dir.join("idea.vcd");
+    run(&["convert", IDEA, fst.to_str().unwrap()]);
+    run(&["convert", fst.to_str().unwrap(), vcd.to_str().unwrap()]);
+    for file in [&fst, &vcd] {
+        let file = file.to_str().unwrap();
+        assert_eq!(
+            run(&["values-at", file, "1400000000"]),
+            run(&["values-at", IDEA, "1400000000"])
+        );
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn errors() {
+    assert!(run_err(&["dump", IDEA, "tb_idea.nope"]).contains("no variable matches"));
+    assert!(run_err(&["dump", IDEA, "tb_idea.x_1", "--from", "1xs"]).contains("unknown time unit"));
+    assert!(run_err(&["convert", IDEA, "out.txt"]).contains("--format"));
+    assert!(run_err(&["info", "does-not-exist.vcd"]).contains("failed to load"));
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn export_parquet() {
+    let filename = std::env::temp_dir().join(format!("wellen-cli-{}.parquet", std::process::id()));
+    let out = filename.to_str().unwrap();
+    run(&["export", IDEA, out, "tb_idea.x_*", "--layout", "sampled"]);
+    let content = std::fs::read(&filename).unwrap();
+    std::fs::remove_file(&filename).unwrap();
+    assert!(content.starts_with(b"PAR1") && content.ends_with(b"PAR1"));
+}
+
+#[test]
+fn coverage() {
+    let summary = run(&["coverage", IDEA, "tb_idea", "--max-depth", "0"]);
+    let lines: Vec<_> = summary.lines().collect();
+    assert_eq!(lines.len(), 2, "{summary}");
+    assert!(lines[0].starts_with("toggle coverage: "), "{summary}");
+    assert!(lines[1].starts_with("  tb_idea: "), "{summary}");
+    let filename = std::env::temp_dir().join(format!("wellen-cli-{}.json", std::process::id()));
+    run(&["coverage", IDEA, "--json", filename.to_str().unwrap()]);
+    let json = std::fs::read_to_string(&filename).unwrap();
+    std::fs::remove_file(&filename).unwrap();
+    assert!(json.contains("\"name\": \"tb_idea.x_1\""), "{json}");
+    assert!(run_err(&["coverage", IDEA, "tb_idea.nope"]).contains("no scope named"));
+}
+
+#[test]
+fn saif() {
+    let filename = std::env::temp_dir().join(format!("wellen-cli-{}.saif", std::process::id()));
+    let out = filename.to_str().unwrap();
+    run(&["saif", IDEA, out, "tb_idea", "--from", "100ns"]);
+    let saif = std::fs::read_to_string(&filename).unwrap();
+    std::fs::remove_file(&filename).unwrap();
+    assert!(saif.starts_with("(SAIFILE\n"), "{saif}");
+    assert!(saif.contains("(DESIGN \"tb_idea\")\n"), "{saif}");
+    assert!(saif.contains("(INSTANCE tb_idea\n"), "{saif}");
+    assert!(run_err(&["saif", IDEA, out, "tb_idea.nope"]).contains("no scope named"));
+}
+
+#[test]
+fn check() {
+    let out = run(&[
+        "check",
+        IDEA,
+        "@(tb_idea.x_1) $stable(tb_idea.key) |-> tb_idea.y_1 == 0",
+    ]);
+    assert!(out.contains("  2 attempts: 0 passed, 2 vacuous,"), "{out}");
+    // the first sample is unknown
+    let err = run_err(&["check", IDEA, "@(tb_idea.x_1) tb_idea.x_2 == tb_idea.x_2"]);
+    assert!(err.contains("1 of 1 properties failed"), "{err}");
+    let err = run_err(&["check", IDEA, "tb_idea.x_2 == 0"]);
+    assert!(err.contains("clocking event"), "{err}");
+}
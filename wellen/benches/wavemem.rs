@@ -18,3 +18,6 @@ fn criterion_benchmark(c: &mut Criterion) {
 criterion_group!(benches, criterion_benchmark);
 #[cfg(feature = "benchmark")]
 criterion_main!(benches);
+
+#[cfg(not(feature = "benchmark"))]
+fn main() {}
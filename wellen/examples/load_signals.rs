@@ -71,6 +71,7 @@ fn main() {
     let load_opts = LoadOptions {
         multi_thread: !args.single_thread,
         remove_scopes_with_empty_name: false,
+        ..Default::default()
     };
 
     // load header
@@ -161,7 +162,9 @@ fn main() {
         let _signal_name: String = var.full_name(&hierarchy);
         let ids = [var.signal_ref(); 1];
         let start = std::time::Instant::now();
-        let loaded = wave_source.load_signals(&ids, &hierarchy, load_opts.multi_thread);
+        let loaded = wave_source
+            .load_signals(&ids, &hierarchy, load_opts.multi_thread)
+            .unwrap();
         let load_time = start.elapsed();
         assert_eq!(loaded.len(), ids.len());
         let (loaded_id, loaded_signal) = loaded.into_iter().next().unwrap();
@@ -0,0 +1,37 @@
+-- Multi-dimensional arrays and scalar subtypes, used by the GHW tests.
+-- wellen_multi_dim.ghw is generated with:
+--   ghdl -a --std=08 wellen_multi_dim.vhdl
+--   ghdl -e --std=08 wellen_multi_dim
+--   ghdl -r --std=08 wellen_multi_dim --wave=wellen_multi_dim.ghw --stop-time=20ns
+library IEEE;
+use IEEE.STD_LOGIC_1164.ALL;
+
+entity wellen_multi_dim is
+end entity wellen_multi_dim;
+
+architecture Behavioral of wellen_multi_dim is
+    type mem_t is array (0 to 1, 3 downto 0) of std_ulogic;
+    type grid_t is array (0 to 1, 0 to 2) of integer;
+    type cube_t is array (0 to 1, 0 to 1, 1 downto 0) of std_ulogic;
+    type state_t is (idle, busy, done, error);
+    subtype active_t is state_t range busy to done;
+    subtype x01_t is std_ulogic range 'X' to '1';
+    type int64_t is range -1099511627776 to 1099511627776;
+
+    signal mem : mem_t := ("1010", "0110");
+    signal grid : grid_t := ((1, 2, 3), (4, 5, 6));
+    signal cube : cube_t := (("00", "01"), ("10", "11"));
+    signal active : active_t := done;
+    signal b : x01_t := 'X';
+    signal big : int64_t := 1099511627776;
+begin
+    process
+    begin
+        wait for 10 ns;
+        mem(1, 0) <= '1';
+        cube(1, 0, 1) <= '0';
+        active <= busy;
+        big <= -1;
+        wait;
+    end process;
+end architecture Behavioral;
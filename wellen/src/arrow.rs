@@ -0,0 +1,311 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// Exports signals to Apache Arrow record batches and Parquet files for data analysis.
+
+use crate::simple::Waveform;
+use crate::{Hierarchy, Signal, SignalEncoding, SignalValue, Time, VarRef};
+use arrow_array::builder::{FixedSizeBinaryBuilder, Float64Builder, StringBuilder, UInt64Builder};
+use arrow_array::types::UInt32Type;
+use arrow_array::{ArrayRef, DictionaryArray, RecordBatch, StringArray, UInt32Array, UInt64Array};
+use arrow_schema::{DataType, Field, Schema};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("[arrow] {0}")]
+    Arrow(#[from] arrow_schema::ArrowError),
+    #[cfg(feature = "parquet")]
+    #[error("[arrow] failed to write parquet file: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+    #[error("[arrow] failed to load signals: {0}")]
+    Load(#[from] crate::WellenError),
+}
+
+pub type Result<T> = std::result::Result<T, ExportError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    /// One row per value change with the columns `time`, `signal` and `value`, sorted by time.
+    /// All values share a single column, thus the column type is the common type of all
+    /// signals, or a string if their types differ.
+    #[default]
+    Changes,
+    /// One row per entry of the time table. Besides the `time` column, every signal gets a
+    /// column that contains its value at that time or null before its first change.
+    Sampled,
+}
+
+/// Name of the schema metadata entry that contains the duration of one time unit in seconds,
+/// e.g., `1e-9` or `10e-12`. Missing if the waveform does not have a timescale.
+pub const TIMESCALE_METADATA: &str = "timescale";
+
+/// Converts the values of `vars` into a record batch. Signals that need to be loaded for the
+/// export are unloaded again afterward.
+///
+/// Bit-vectors that only ever contain `0` and `1` are exported as `UInt64` if they are up to
+/// 64 bits wide and as big-endian `FixedSizeBinary` otherwise. Bit-vectors with four or nine
+/// states and strings are exported as strings, reals as `Float64`.
+pub fn to_record_batch(
+    wave: &mut Waveform,
+    vars: &[VarRef],
+    layout: Layout,
+) -> Result<RecordBatch> {
+    let ids: Vec<_> = vars
+        .iter()
+        .map(|v| wave.hierarchy()[*v].signal_ref())
+        .collect();
+    let loaded = wave.load_missing_signals(&ids)?;
+    let columns: Vec<_> = vars
+        .iter()
+        .zip(ids.iter())
+        .map(|(var, id)| Column::new(wave.hierarchy(), *var, wave.get_signal(*id).unwrap()))
+        .collect();
+    let batch = match layout {
+        Layout::Changes => changes(wave.time_table(), &columns),
+        Layout::Sampled => sampled(wave.time_table(), &columns),
+    };
+    let batch = batch.and_then(|b| add_metadata(b, wave.hierarchy()));
+    wave.unload_signals(&loaded);
+    batch
+}
+
+/// Exports the values of `vars` into a Parquet file with Snappy compression.
+/// See [`to_record_batch`] for details.
+#[cfg(feature = "parquet")]
+pub fn write_parquet(
+    wave: &mut Waveform,
+    vars: &[VarRef],
+    layout: Layout,
+    out: impl std::io::Write + Send,
+) -> Result<()> {
+    use parquet::basic::Compression;
+    use parquet::file::properties::WriterProperties;
+    let batch = to_record_batch(wave, vars, layout)?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer = parquet::arrow::ArrowWriter::try_new(out, batch.schema(), Some(properties))?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+/// How values are represented in Arrow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueType {
+    UInt64,
+    /// Number of bytes.
+    FixedSizeBinary(i32),
+    Float64,
+    Utf8,
+}
+
+impl ValueType {
+    fn data_type(self) -> DataType {
+        match self {
+            ValueType::UInt64 => DataType::UInt64,
+            ValueType::FixedSizeBinary(bytes) => DataType::FixedSizeBinary(bytes),
+            ValueType::Float64 => DataType::Float64,
+            ValueType::Utf8 => DataType::Utf8,
+        }
+    }
+}
+
+struct Column<'a> {
+    name: String,
+    signal: &'a Signal,
+    tpe: ValueType,
+}
+
+impl<'a> Column<'a> {
+    fn new(h: &Hierarchy, var: VarRef, signal: &'a Signal) -> Self {
+        let var = &h[var];
+        let mut name = var.full_name(h);
+        // bit-blasted variables would not be unique otherwise
+        if let Some(index) = var.index().filter(|i| i.msb() == i.lsb()) {
+            name.push_str(&format!("[{}]", index.lsb()));
+        }
+        let tpe = match var.signal_encoding() {
+            SignalEncoding::String => ValueType::Utf8,
+            SignalEncoding::Real => ValueType::Float64,
+            SignalEncoding::BitVector(_)
+                if !signal.iter_changes().all(|(_, v)| is_two_state(&v)) =>
+            {
+                ValueType::Utf8
+            }
+            SignalEncoding::BitVector(bits) if bits.get() <= 64 => ValueType::UInt64,
+            SignalEncoding::BitVector(bits) => {
+                ValueType::FixedSizeBinary(bits.get().div_ceil(8) as i32)
+            }
+        };
+        Self { name, signal, tpe }
+    }
+}
+
+fn changes(time_table: &[Time], columns: &[Column]) -> Result<RecordBatch> {
+    let mut rows = vec![];
+    for (ii, column) in columns.iter().enumerate() {
+        for (idx, value) in column.signal.iter_changes() {
+            rows.push((time_table[idx as usize], ii as u32, value));
+        }
+    }
+    // stable sort keeps the order of signals and delta cycles
+    rows.sort_by_key(|(time, _, _)| *time);
+
+    let tpe = match columns.split_first() {
+        Some((first, rest)) if rest.iter().all(|c| c.tpe == first.tpe) => first.tpe,
+        _ => ValueType::Utf8,
+    };
+    let times = UInt64Array::from_iter_values(rows.iter().map(|(time, _, _)| *time));
+    let keys = UInt32Array::from_iter_values(rows.iter().map(|(_, ii, _)| *ii));
+    let names = StringArray::from_iter_values(columns.iter().map(|c| c.name.as_str()));
+    let signals = DictionaryArray::<UInt32Type>::try_new(keys, Arc::new(names))?;
+    let values = to_array(tpe, rows.iter().map(|(_, _, v)| Some(*v)), rows.len())?;
+
+    let schema = Schema::new(vec![
+        Field::new("time", DataType::UInt64, false),
+        Field::new(
+            "signal",
+            DataType::Dictionary(Box::new(DataType::UInt32), Box::new(DataType::Utf8)),
+            false,
+        ),
+        Field::new("value", tpe.data_type(), false),
+    ]);
+    let columns: Vec<ArrayRef> = vec![Arc::new(times), Arc::new(signals), values];
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+fn sampled(time_table: &[Time], columns: &[Column]) -> Result<RecordBatch> {
+    let mut fields = vec![Field::new("time", DataType::UInt64, false)];
+    let mut arrays: Vec<ArrayRef> = vec![Arc::new(UInt64Array::from(time_table.to_vec()))];
+    for column in columns.iter() {
+        let values = sample(column.signal, time_table.len());
+        fields.push(Field::new(&column.name, column.tpe.data_type(), true));
+        arrays.push(to_array(column.tpe, values.into_iter(), time_table.len())?);
+    }
+    Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?)
+}
+
+/// Returns the value of the signal at every index of the time table. If there are several
+/// changes at the same time, only the last one counts.
+fn sample(signal: &Signal, len: usize) -> Vec<Option<SignalValue<'_>>> {
+    let mut out = Vec::with_capacity(len);
+    let mut current = None;
+    for (idx, value) in signal.iter_changes() {
+        out.resize(idx as usize, current);
+        current = Some(value);
+    }
+    out.resize(len, current);
+    out
+}
+
+fn to_array<'a>(
+    tpe: ValueType,
+    values: impl Iterator<Item = Option<SignalValue<'a>>>,
+    len: usize,
+) -> Result<ArrayRef> {
+    Ok(match tpe {
+        ValueType::UInt64 => {
+            let mut builder = UInt64Builder::with_capacity(len);
+            values.for_each(|v| builder.append_option(v.map(|v| to_u64(&v))));
+            Arc::new(builder.finish())
+        }
+        ValueType::FixedSizeBinary(bytes) => {
+            let mut builder = FixedSizeBinaryBuilder::with_capacity(len, bytes);
+            for value in values {
+                match value {
+                    Some(v) => builder.append_value(to_bytes(&v, bytes as usize))?,
+                    None => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        ValueType::Float64 => {
+            let mut builder = Float64Builder::with_capacity(len);
+            values.for_each(|v| {
+                builder.append_option(v.and_then(|v| match v {
+                    SignalValue::Real(r) => Some(r),
+                    _ => None,
+                }))
+            });
+            Arc::new(builder.finish())
+        }
+        ValueType::Utf8 => {
+            let mut builder = StringBuilder::with_capacity(len, len * 8);
+            values.for_each(|v| builder.append_option(v.map(|v| v.to_string())));
+            Arc::new(builder.finish())
+        }
+    })
+}
+
+/// Four and nine state values can still only consist of `0` and `1`.
+fn is_two_state(value: &SignalValue) -> bool {
+    match value {
+        SignalValue::Binary(_, _) => true,
+        SignalValue::FourValue(_, _) | SignalValue::NineValue(_, _) => value
+            .to_bit_string()
+            .unwrap()
+            .bytes()
+            .all(|c| c == b'0' || c == b'1'),
+        SignalValue::String(_) | SignalValue::Real(_) => false,
+    }
+}
+
+fn to_u64(value: &SignalValue) -> u64 {
+    match value {
+        SignalValue::Binary(data, _) => data.iter().fold(0, |acc, b| (acc << 8) | *b as u64),
+        other => to_bytes(other, 8)
+            .iter()
+            .fold(0, |acc, b| (acc << 8) | *b as u64),
+    }
+}
+
+/// Big-endian bytes of a value that only contains `0` and `1`.
+fn to_bytes(value: &SignalValue, len: usize) -> Vec<u8> {
+    let mut out = vec![0u8; len];
+    match value {
+        SignalValue::Binary(data, _) => out[len - data.len()..].copy_from_slice(data),
+        other => {
+            let bits = other.to_bit_string().unwrap();
+            for (ii, c) in bits.bytes().rev().enumerate() {
+                if c == b'1' {
+                    out[len - 1 - ii / 8] |= 1 << (ii % 8);
+                }
+            }
+        }
+    }
+    out
+}
+
+fn add_metadata(batch: RecordBatch, h: &Hierarchy) -> Result<RecordBatch> {
+    let Some((factor, exponent)) = h
+        .timescale()
+        .and_then(|t| Some((t.factor, t.unit.to_exponent()?)))
+    else {
+        return Ok(batch);
+    };
+    let metadata = HashMap::from([(
+        TIMESCALE_METADATA.to_string(),
+        format!("{factor}e{exponent}"),
+    )]);
+    let schema = Schema::new(batch.schema().fields().clone()).with_metadata(metadata);
+    Ok(batch.with_schema(Arc::new(schema))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_bytes() {
+        let data = [0b1u8, 0b0000_0010];
+        assert_eq!(to_bytes(&SignalValue::Binary(&data, 9), 4), [0, 0, 1, 2]);
+        assert_eq!(to_u64(&SignalValue::Binary(&data, 9)), 258);
+        // 4-state value `0100000010`
+        let four = [0b0001u8, 0b0000_0000, 0b0000_0100];
+        assert_eq!(to_bytes(&SignalValue::FourValue(&four, 10), 2), [1, 2]);
+        assert_eq!(to_u64(&SignalValue::FourValue(&four, 10)), 258);
+    }
+}
@@ -0,0 +1,307 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// On-disk cache of parsed VCD and GHW bodies.
+
+use crate::wavemem::Reader;
+use crate::{FileFormat, Hierarchy, LoadOptions, ScopeFilter, TimeTable};
+use num_enum::TryFromPrimitive;
+use std::hash::Hasher;
+use std::io::{BufWriter, Read, Write};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const MAGIC: &[u8; 8] = b"wellen\xca\xc4";
+/// Needs to be incremented every time the encoding of the cache file changes.
+const FORMAT_VERSION: u64 = 2;
+/// Name of the environment variable that can be used to specify a cache directory.
+const CACHE_DIR_VAR: &str = "WELLEN_CACHE_DIR";
+const EXTENSION: &str = "wellen";
+
+/// Returns the path of the cache file for `source`. By default, the cache is placed next to the
+/// source file. If a cache directory is configured, the cache file name contains a hash of the
+/// absolute source path in order to avoid collisions.
+pub(crate) fn cache_path(source: &Path) -> PathBuf {
+    match std::env::var_os(CACHE_DIR_VAR) {
+        Some(dir) if !dir.is_empty() => {
+            let absolute = std::fs::canonicalize(source).unwrap_or_else(|_| source.to_path_buf());
+            let mut hasher = rustc_hash::FxHasher::default();
+            hasher.write(absolute.as_os_str().as_encoded_bytes());
+            let name = source.file_name().unwrap_or_default().to_string_lossy();
+            PathBuf::from(dir).join(format!("{name}.{:016x}.{EXTENSION}", hasher.finish()))
+        }
+        _ => {
+            let mut name = source.as_os_str().to_os_string();
+            name.push(".");
+            name.push(EXTENSION);
+            PathBuf::from(name)
+        }
+    }
+}
+
+/// Everything that needs to stay the same in order for a cache file to be valid.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CacheKey {
+    source_len: u64,
+    source_modified_secs: u64,
+    source_modified_nanos: u32,
+    remove_scopes_with_empty_name: bool,
+    scope_filter: ScopeFilter,
+}
+
+impl CacheKey {
+    pub(crate) fn new(source: &Path, options: &LoadOptions) -> std::io::Result<Self> {
+        let meta = std::fs::metadata(source)?;
+        let modified = meta
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|_| invalid_data("modification time before unix epoch"))?;
+        Ok(Self {
+            source_len: meta.len(),
+            source_modified_secs: modified.as_secs(),
+            source_modified_nanos: modified.subsec_nanos(),
+            remove_scopes_with_empty_name: options.remove_scopes_with_empty_name,
+            scope_filter: options.scope_filter.clone().unwrap_or_default(),
+        })
+    }
+
+    fn write(&self, out: &mut impl Write) -> std::io::Result<()> {
+        write_uint(out, self.source_len)?;
+        write_uint(out, self.source_modified_secs)?;
+        write_uint(out, self.source_modified_nanos as u64)?;
+        write_bool(out, self.remove_scopes_with_empty_name)?;
+        write_strings(out, &self.scope_filter.include_scopes)?;
+        write_strings(out, &self.scope_filter.exclude_scopes)?;
+        // zero encodes an unlimited depth
+        write_uint(out, self.scope_filter.max_depth.map_or(0, |d| d as u64 + 1))
+    }
+
+    fn read(input: &mut impl Read) -> std::io::Result<Self> {
+        Ok(Self {
+            source_len: read_uint(input)?,
+            source_modified_secs: read_uint(input)?,
+            source_modified_nanos: read_uint(input)? as u32,
+            remove_scopes_with_empty_name: read_bool(input)?,
+            scope_filter: ScopeFilter {
+                include_scopes: read_strings(input)?,
+                exclude_scopes: read_strings(input)?,
+                max_depth: read_uint(input)?.checked_sub(1).map(|d| d as usize),
+            },
+        })
+    }
+}
+
+fn write_strings(out: &mut impl Write, values: &[String]) -> std::io::Result<()> {
+    write_uint(out, values.len() as u64)?;
+    for value in values.iter() {
+        write_string(out, value)?;
+    }
+    Ok(())
+}
+
+fn read_strings(input: &mut impl Read) -> std::io::Result<Vec<String>> {
+    let len = read_uint(input)? as usize;
+    let mut out = Vec::with_capacity(std::cmp::min(len, 1 << 10));
+    for _ in 0..len {
+        out.push(read_string(input)?);
+    }
+    Ok(out)
+}
+
+/// Information needed in order to write a cache file once the body has been parsed.
+pub(crate) struct CacheTarget {
+    pub(crate) path: PathBuf,
+    pub(crate) key: CacheKey,
+    pub(crate) file_format: FileFormat,
+}
+
+/// A signal source and time table that were loaded from a cache file.
+pub(crate) struct CachedBody {
+    pub(crate) reader: Reader,
+    pub(crate) time_table: TimeTable,
+}
+
+/// Loads the hierarchy and body from the cache file at `path`. Returns `None` if there is
+/// no cache file, or if it is outdated or invalid.
+pub(crate) fn load(path: &Path, key: &CacheKey) -> Option<(Hierarchy, FileFormat, CachedBody)> {
+    let file = std::fs::File::open(path).ok()?;
+    let mmap = Arc::new(unsafe { memmap2::Mmap::map(&file).ok()? });
+    load_from_mmap(mmap, key).ok()
+}
+
+fn load_from_mmap(
+    mmap: Arc<memmap2::Mmap>,
+    key: &CacheKey,
+) -> std::io::Result<(Hierarchy, FileFormat, CachedBody)> {
+    let mut input = std::io::Cursor::new(&mmap[..]);
+    let mut magic = [0u8; MAGIC.len()];
+    input.read_exact(&mut magic)?;
+    if &magic != MAGIC
+        || read_uint(&mut input)? != FORMAT_VERSION
+        || read_string(&mut input)? != crate::VERSION
+        || CacheKey::read(&mut input)? != *key
+    {
+        return Err(invalid_data("outdated cache"));
+    }
+    let file_format = read_enum(&mut input)?;
+    let hierarchy = Hierarchy::read_from_cache(&mut input)?;
+    let (reader, time_table) =
+        Reader::read_from_cache(&mut input, &mmap, hierarchy.num_unique_signals())?;
+    let body = CachedBody { reader, time_table };
+    Ok((hierarchy, file_format, body))
+}
+
+/// Writes a new cache file. The data is first written to a temporary file which is then moved
+/// in place, thus a concurrent reader never observes a partially written cache.
+pub(crate) fn store(
+    target: &CacheTarget,
+    hierarchy: &Hierarchy,
+    reader: &Reader,
+    time_table: &[crate::Time],
+) -> std::io::Result<()> {
+    if let Some(dir) = target.path.parent() {
+        if !dir.as_os_str().is_empty() {
+            std::fs::create_dir_all(dir)?;
+        }
+    }
+    let mut tmp = target.path.clone().into_os_string();
+    tmp.push(format!(".{}.tmp", std::process::id()));
+    let tmp = PathBuf::from(tmp);
+    let result = write_file(&tmp, target, hierarchy, reader, time_table)
+        .and_then(|_| std::fs::rename(&tmp, &target.path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
+
+fn write_file(
+    path: &Path,
+    target: &CacheTarget,
+    hierarchy: &Hierarchy,
+    reader: &Reader,
+    time_table: &[crate::Time],
+) -> std::io::Result<()> {
+    let mut out = BufWriter::new(std::fs::File::create(path)?);
+    out.write_all(MAGIC)?;
+    write_uint(&mut out, FORMAT_VERSION)?;
+    write_string(&mut out, crate::VERSION)?;
+    target.key.write(&mut out)?;
+    write_uint(&mut out, target.file_format as u64)?;
+    hierarchy.write_to_cache(&mut out)?;
+    reader.write_to_cache(&mut out, time_table)?;
+    out.into_inner().map_err(|e| e.into_error())?.sync_all()
+}
+
+pub(crate) fn invalid_data(msg: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, format!("[cache] {msg}"))
+}
+
+pub(crate) fn write_uint(out: &mut impl Write, value: u64) -> std::io::Result<()> {
+    leb128::write::unsigned(out, value)?;
+    Ok(())
+}
+
+pub(crate) fn read_uint(input: &mut impl Read) -> std::io::Result<u64> {
+    leb128::read::unsigned(input).map_err(leb128_to_io)
+}
+
+pub(crate) fn write_int(out: &mut impl Write, value: i64) -> std::io::Result<()> {
+    leb128::write::signed(out, value)?;
+    Ok(())
+}
+
+pub(crate) fn read_int(input: &mut impl Read) -> std::io::Result<i64> {
+    leb128::read::signed(input).map_err(leb128_to_io)
+}
+
+fn leb128_to_io(e: leb128::read::Error) -> std::io::Error {
+    match e {
+        leb128::read::Error::IoError(e) => e,
+        leb128::read::Error::Overflow => invalid_data("integer overflow"),
+    }
+}
+
+pub(crate) fn write_bool(out: &mut impl Write, value: bool) -> std::io::Result<()> {
+    out.write_all(&[value as u8])
+}
+
+pub(crate) fn read_bool(input: &mut impl Read) -> std::io::Result<bool> {
+    let mut buf = [0u8; 1];
+    input.read_exact(&mut buf)?;
+    Ok(buf[0] != 0)
+}
+
+pub(crate) fn write_string(out: &mut impl Write, value: &str) -> std::io::Result<()> {
+    write_uint(out, value.len() as u64)?;
+    out.write_all(value.as_bytes())
+}
+
+pub(crate) fn read_string(input: &mut impl Read) -> std::io::Result<String> {
+    let len = read_uint(input)? as usize;
+    let mut buf = Vec::with_capacity(std::cmp::min(len, 4096));
+    input.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    String::from_utf8(buf).map_err(|_| invalid_data("invalid utf-8 string"))
+}
+
+pub(crate) fn read_enum<T: TryFromPrimitive<Primitive = u8>>(
+    input: &mut impl Read,
+) -> std::io::Result<T> {
+    let value = read_uint(input)?;
+    u8::try_from(value)
+        .ok()
+        .and_then(|v| T::try_from_primitive(v).ok())
+        .ok_or_else(|| invalid_data("invalid enum value"))
+}
+
+/// Ids are encoded such that `None` becomes zero.
+pub(crate) fn write_id(out: &mut impl Write, id: Option<NonZeroU32>) -> std::io::Result<()> {
+    write_uint(out, id.map(|i| i.get() as u64).unwrap_or(0))
+}
+
+pub(crate) fn read_id(input: &mut impl Read) -> std::io::Result<Option<NonZeroU32>> {
+    let value = read_uint(input)?;
+    let value = u32::try_from(value).map_err(|_| invalid_data("id out of range"))?;
+    Ok(NonZeroU32::new(value))
+}
+
+pub(crate) fn read_required_id(input: &mut impl Read) -> std::io::Result<NonZeroU32> {
+    read_id(input)?.ok_or_else(|| invalid_data("missing id"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_primitives_round_trip() {
+        let mut buf = vec![];
+        write_uint(&mut buf, 1234567).unwrap();
+        write_int(&mut buf, -77).unwrap();
+        write_bool(&mut buf, true).unwrap();
+        write_string(&mut buf, "hello ⚡").unwrap();
+        write_id(&mut buf, None).unwrap();
+        write_id(&mut buf, NonZeroU32::new(42)).unwrap();
+        write_uint(&mut buf, FileFormat::Ghw as u64).unwrap();
+        write_uint(&mut buf, 200).unwrap();
+
+        let mut input = std::io::Cursor::new(&buf[..]);
+        assert_eq!(read_uint(&mut input).unwrap(), 1234567);
+        assert_eq!(read_int(&mut input).unwrap(), -77);
+        assert!(read_bool(&mut input).unwrap());
+        assert_eq!(read_string(&mut input).unwrap(), "hello ⚡");
+        assert_eq!(read_id(&mut input).unwrap(), None);
+        assert_eq!(read_required_id(&mut input).unwrap().get(), 42);
+        assert_eq!(
+            read_enum::<FileFormat>(&mut input).unwrap(),
+            FileFormat::Ghw
+        );
+        assert!(read_enum::<FileFormat>(&mut input).is_err());
+        // reading past the end should fail
+        assert!(read_string(&mut input).is_err());
+    }
+}
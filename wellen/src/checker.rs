@@ -0,0 +1,477 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// Checks temporal properties, written in a subset of SystemVerilog assertions, on waveforms.
+
+use crate::cycles::CycleTrace;
+use crate::expr::{Expr, ExprError, Parser, Token};
+use crate::simple::Waveform;
+use crate::{Edge, Hierarchy, Signal, SignalRef, Time};
+
+#[derive(Debug, thiserror::Error)]
+pub enum CheckError {
+    #[error("{0}")]
+    Parse(#[from] ExprError),
+    #[error("[checker] properties need a clocking event, e.g., `@(posedge top.clk)`")]
+    MissingClock,
+    #[error("[checker] invalid delay ##[{0}:{1}], the upper bound is smaller than the lower bound")]
+    InvalidDelay(u32, u32),
+    #[error("[checker] signal {0:?} is used by the property but was not provided")]
+    MissingSignal(SignalRef),
+    #[error("[checker] failed to load signals: {0}")]
+    Load(#[from] crate::WellenError),
+}
+
+pub type Result<T> = std::result::Result<T, CheckError>;
+
+/// A property that is checked at every edge of a clock, written in a subset of SystemVerilog
+/// assertion syntax, e.g.:
+///
+/// ```text
+/// @(posedge top.clk) disable iff (top.rst) top.req |-> ##[1:8] top.ack
+/// ```
+///
+/// - the clocking event `@(posedge clk)`, `@(negedge clk)` or `@(clk)` is required
+/// - `disable iff (e)` cancels every attempt during which `e` is true
+/// - sequences are boolean expressions separated by delays: `##2` waits exactly two cycles,
+///   `##[1:3]` one to three cycles and `##[1:$]` at least one cycle. Sequences can be grouped
+///   with parentheses and may start with a delay.
+/// - `s1 |-> s2` checks `s2` starting in the cycle in which `s1` matched,
+///   `s1 |=> s2` starting in the following cycle
+/// - boolean expressions use the syntax of [`Expr`] and may in addition contain
+///   `$rose(e)`, `$fell(e)`, `$stable(e)`, `$changed(e)` and `$past(e, n)`
+///
+/// Like in a simulator, values are sampled just before each clock edge and a new attempt starts
+/// at every edge. Unlike in a simulator, `disable iff` is evaluated on sampled values as well.
+#[derive(Debug, Clone)]
+pub struct Property {
+    clock: SignalRef,
+    edge: Edge,
+    /// boolean expressions, which sequences refer to by index
+    conditions: Vec<Expr>,
+    disable: Option<usize>,
+    antecedent: Option<Sequence>,
+    /// whether the consequent starts in the cycle in which the antecedent matched (`|->`)
+    overlapping: bool,
+    consequent: Sequence,
+}
+
+/// Boolean expressions separated by delays.
+#[derive(Debug, Clone)]
+struct Sequence {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Step {
+    /// Range of cycles after the previous step, or after the start of the sequence.
+    /// The maximum is `None` for an unbounded delay.
+    min: u32,
+    max: Option<u32>,
+    condition: usize,
+}
+
+/// Summary of all attempts to check a property, one per clock edge.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CheckReport {
+    pub attempts: u64,
+    pub passed: u64,
+    /// attempts in which the antecedent of an implication never matched
+    pub vacuous: u64,
+    pub disabled: u64,
+    /// attempts that had not passed or failed yet at the end of the waveform
+    pub unfinished: u64,
+    pub failures: Vec<Failure>,
+}
+
+/// A failed attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Failure {
+    /// Time of the clock edge at which the attempt started.
+    pub start: Time,
+    /// Time of the clock edge at which the attempt failed.
+    pub end: Time,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Outcome {
+    Passed(usize),
+    Vacuous(usize),
+    Failed(usize),
+    Unfinished,
+}
+
+impl Property {
+    /// Parses a property and resolves all signal names in the hierarchy.
+    pub fn parse(property: &str, hierarchy: &Hierarchy) -> Result<Self> {
+        let mut parser = Parser::new(property, hierarchy, true)?;
+        if !parser.eat("@") {
+            return Err(CheckError::MissingClock);
+        }
+        parser.expect("(")?;
+        let edge = if parser.eat_keyword("posedge") {
+            Edge::Rising
+        } else if parser.eat_keyword("negedge") {
+            Edge::Falling
+        } else {
+            parser.eat_keyword("edge");
+            Edge::Any
+        };
+        let clock = parser.parse_var()?.1.signal_ref();
+        parser.expect(")")?;
+
+        let mut conditions = vec![];
+        let disable = if parser.eat_keyword("disable") {
+            if !parser.eat_keyword("iff") {
+                return Err(parser.unexpected("`iff`").into());
+            }
+            parser.expect("(")?;
+            conditions.push(Expr::parse_sampled(&mut parser)?);
+            parser.expect(")")?;
+            Some(conditions.len() - 1)
+        } else {
+            None
+        };
+
+        let first = parse_sequence(&mut parser, &mut conditions)?;
+        let (antecedent, overlapping, consequent) = if parser.eat("|->") {
+            (
+                Some(first),
+                true,
+                parse_sequence(&mut parser, &mut conditions)?,
+            )
+        } else if parser.eat("|=>") {
+            (
+                Some(first),
+                false,
+                parse_sequence(&mut parser, &mut conditions)?,
+            )
+        } else {
+            (None, true, first)
+        };
+        if !parser.is_done() {
+            return Err(parser.unexpected("end of property").into());
+        }
+        Ok(Self {
+            clock,
+            edge,
+            conditions,
+            disable,
+            antecedent,
+            overlapping,
+            consequent,
+        })
+    }
+
+    /// Signals that need to be loaded in order to check the property, including the clock.
+    pub fn signals(&self) -> Vec<SignalRef> {
+        let mut out = self.inputs();
+        out.push(self.clock);
+        out.sort();
+        out.dedup();
+        out
+    }
+
+    fn inputs(&self) -> Vec<SignalRef> {
+        let mut out: Vec<_> = self.conditions.iter().flat_map(|c| c.signals()).collect();
+        out.sort();
+        out.dedup();
+        out
+    }
+
+    /// Checks the property. `signals` needs to contain all signals returned by
+    /// [`Property::signals`].
+    pub fn check(&self, time_table: &[Time], signals: &[&Signal]) -> Result<CheckReport> {
+        let find = |signal: SignalRef| -> Result<&Signal> {
+            signals
+                .iter()
+                .find(|s| s.signal_ref() == signal)
+                .copied()
+                .ok_or(CheckError::MissingSignal(signal))
+        };
+        let inputs = self.inputs();
+        let columns = inputs
+            .iter()
+            .map(|id| find(*id))
+            .collect::<Result<Vec<_>>>()?;
+        let trace = CycleTrace::sample(find(self.clock)?, time_table, self.edge, &columns, false);
+        let num_cycles = trace.len();
+
+        // all conditions are evaluated once for every cycle
+        let truth: Vec<Vec<bool>> = self
+            .conditions
+            .iter()
+            .map(|condition| {
+                (0..num_cycles)
+                    .map(|cycle| {
+                        condition.holds(|signal, delay| {
+                            let cycle = cycle.checked_sub(delay as usize)?;
+                            trace.value(cycle, inputs.binary_search(&signal).unwrap())
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+        // number of cycles in which the property is disabled before each cycle
+        let mut disabled_before = vec![0usize; num_cycles + 1];
+        if let Some(disable) = self.disable {
+            for (cycle, value) in truth[disable].iter().enumerate() {
+                disabled_before[cycle + 1] = disabled_before[cycle] + *value as usize;
+            }
+        }
+
+        let time = |cycle: usize| time_table[trace.edges()[cycle] as usize];
+        let mut report = CheckReport::default();
+        for start in 0..num_cycles {
+            report.attempts += 1;
+            let outcome = self.attempt(&truth, num_cycles, start);
+            let end = match outcome {
+                Outcome::Passed(end) | Outcome::Vacuous(end) | Outcome::Failed(end) => end,
+                Outcome::Unfinished => num_cycles - 1,
+            };
+            if disabled_before[end + 1] > disabled_before[start] {
+                report.disabled += 1;
+                continue;
+            }
+            match outcome {
+                Outcome::Passed(_) => report.passed += 1,
+                Outcome::Vacuous(_) => report.vacuous += 1,
+                Outcome::Failed(end) => report.failures.push(Failure {
+                    start: time(start),
+                    end: time(end),
+                }),
+                Outcome::Unfinished => report.unfinished += 1,
+            }
+        }
+        Ok(report)
+    }
+
+    fn attempt(&self, truth: &[Vec<bool>], num_cycles: usize, start: usize) -> Outcome {
+        let Some(antecedent) = &self.antecedent else {
+            let m = find_matches(&self.consequent, truth, num_cycles, start, true);
+            return match (m.ends.first(), m.done) {
+                (Some(end), _) => Outcome::Passed(*end),
+                (None, Some(end)) => Outcome::Failed(end),
+                (None, None) => Outcome::Unfinished,
+            };
+        };
+        let a = find_matches(antecedent, truth, num_cycles, start, false);
+        let mut unfinished = a.done.is_none();
+        let mut passed: Option<usize> = None;
+        let mut failed: Option<usize> = None;
+        for end in a.ends {
+            let next = if self.overlapping { end } else { end + 1 };
+            let m = find_matches(&self.consequent, truth, num_cycles, next, true);
+            match (m.ends.first(), m.done) {
+                (Some(end), _) => passed = passed.max(Some(*end)),
+                (None, Some(end)) => failed = Some(failed.map_or(end, |f| f.min(end))),
+                (None, None) => unfinished = true,
+            }
+        }
+        match (failed, passed) {
+            (Some(end), _) => Outcome::Failed(end),
+            _ if unfinished => Outcome::Unfinished,
+            (None, Some(end)) => Outcome::Passed(end),
+            (None, None) => Outcome::Vacuous(a.done.unwrap()),
+        }
+    }
+}
+
+/// Checks a property on a waveform. Signals that need to be loaded are unloaded again afterward.
+pub fn check_property(wave: &mut Waveform, property: &Property) -> Result<CheckReport> {
+    let ids = property.signals();
+    let missing = wave.load_missing_signals(&ids)?;
+    let signals: Vec<_> = ids.iter().map(|id| wave.get_signal(*id).unwrap()).collect();
+    let report = property.check(wave.time_table(), &signals);
+    wave.unload_signals(&missing);
+    report
+}
+
+fn parse_sequence(parser: &mut Parser, conditions: &mut Vec<Expr>) -> Result<Sequence> {
+    let mut steps: Vec<Step> = vec![];
+    loop {
+        let (min, max) = if parser.eat("##") {
+            parse_delay(parser)?
+        } else if steps.is_empty() {
+            (0, Some(0))
+        } else {
+            break;
+        };
+        let first = steps.len();
+        if is_parenthesized_sequence(parser) {
+            parser.expect("(")?;
+            steps.extend(parse_sequence(parser, conditions)?.steps);
+            parser.expect(")")?;
+        } else {
+            conditions.push(Expr::parse_sampled(parser)?);
+            steps.push(Step {
+                min: 0,
+                max: Some(0),
+                condition: conditions.len() - 1,
+            });
+        }
+        // the delay in front of a group is added to the delay of its first step
+        let step = &mut steps[first];
+        step.min += min;
+        step.max = step.max.zip(max).map(|(a, b)| a + b);
+    }
+    Ok(Sequence { steps })
+}
+
+fn parse_delay(parser: &mut Parser) -> Result<(u32, Option<u32>)> {
+    if !parser.eat("[") {
+        let cycles = parser.parse_index()?;
+        return Ok((cycles, Some(cycles)));
+    }
+    let min = parser.parse_index()?;
+    parser.expect(":")?;
+    let max = if parser.eat_keyword("$") {
+        None
+    } else {
+        Some(parser.parse_index()?)
+    };
+    parser.expect("]")?;
+    match max {
+        Some(max) if max < min => Err(CheckError::InvalidDelay(min, max)),
+        _ => Ok((min, max)),
+    }
+}
+
+/// Boolean expressions cannot contain delays. Thus, parentheses around a delay group a sequence.
+fn is_parenthesized_sequence(parser: &Parser) -> bool {
+    if parser.peek_punct() != Some("(") {
+        return false;
+    }
+    let mut depth = 0;
+    for token in (0..).map_while(|n| parser.peek_nth(n)) {
+        match token {
+            Token::Punct("(") => depth += 1,
+            Token::Punct(")") if depth == 1 => return false,
+            Token::Punct(")") => depth -= 1,
+            Token::Punct("##") => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// All ways in which a sequence matched, starting from a cycle.
+struct Matches {
+    /// cycles in which the sequence matched, in ascending order
+    ends: Vec<usize>,
+    /// The cycle in which the last possible match was ruled out. `None` if the sequence could
+    /// still match after the last cycle.
+    done: Option<usize>,
+}
+
+/// Follows all ways in which `seq` can match, cycle by cycle. With `first_only`, the search
+/// stops after the first match.
+fn find_matches(
+    seq: &Sequence,
+    truth: &[Vec<bool>],
+    num_cycles: usize,
+    start: usize,
+    first_only: bool,
+) -> Matches {
+    // each thread waits for a step to match within a range of cycles
+    let window = |step: usize, after: usize| {
+        let Step { min, max, .. } = seq.steps[step];
+        (
+            step,
+            after + min as usize,
+            max.map(|max| after + max as usize),
+        )
+    };
+    let mut threads = vec![window(0, start)];
+    let mut ends = vec![];
+    let mut cycle = start;
+    while !threads.is_empty() {
+        if cycle >= num_cycles {
+            return Matches { ends, done: None };
+        }
+        // threads that are added while iterating start in the same cycle if they have no delay
+        let mut ii = 0;
+        while ii < threads.len() {
+            let (step, from, to) = threads[ii];
+            ii += 1;
+            let active = from <= cycle && to.is_none_or(|to| cycle <= to);
+            if !active || !truth[seq.steps[step].condition][cycle] {
+                continue;
+            }
+            if step + 1 == seq.steps.len() {
+                if ends.last() != Some(&cycle) {
+                    ends.push(cycle);
+                }
+                if first_only {
+                    return Matches {
+                        ends,
+                        done: Some(cycle),
+                    };
+                }
+            } else {
+                let next = window(step + 1, cycle);
+                if !threads.contains(&next) {
+                    threads.push(next);
+                }
+            }
+        }
+        threads.retain(|(_, _, to)| to.is_none_or(|to| to > cycle));
+        // of two unbounded threads for the same step, the earlier one covers the later one
+        threads.sort_unstable();
+        threads.dedup_by(|b, a| a.0 == b.0 && a.2.is_none() && b.2.is_none());
+        cycle += 1;
+    }
+    Matches {
+        ends,
+        done: Some(cycle - 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seq(steps: &[(u32, Option<u32>)]) -> Sequence {
+        let steps = steps
+            .iter()
+            .enumerate()
+            .map(|(condition, (min, max))| Step {
+                min: *min,
+                max: *max,
+                condition,
+            })
+            .collect();
+        Sequence { steps }
+    }
+
+    fn truth(rows: &[&str]) -> Vec<Vec<bool>> {
+        rows.iter()
+            .map(|r| r.chars().map(|c| c == '1').collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_find_matches() {
+        // a ##[1:2] b
+        let s = seq(&[(0, Some(0)), (1, Some(2))]);
+        let t = truth(&["100100", "011010"]);
+        let m = find_matches(&s, &t, 6, 0, false);
+        assert_eq!((m.ends, m.done), (vec![1, 2], Some(2)));
+        let m = find_matches(&s, &t, 6, 3, true);
+        assert_eq!((m.ends, m.done), (vec![4], Some(4)));
+        // no match, ruled out in the cycle in which `a` is false
+        let m = find_matches(&s, &t, 6, 1, false);
+        assert_eq!((m.ends, m.done), (vec![], Some(1)));
+        // `b` might still follow after the end of the trace
+        let m = find_matches(&s, &truth(&["100001", "000000"]), 6, 5, false);
+        assert_eq!((m.ends, m.done), (vec![], None));
+
+        // a ##[1:$] b ##1 c, only the second `b` is followed by a `c`
+        let s = seq(&[(0, Some(0)), (1, None), (1, Some(1))]);
+        let t = truth(&["10000000", "01010000", "00001000"]);
+        let m = find_matches(&s, &t, 8, 0, true);
+        assert_eq!((m.ends, m.done), (vec![4], Some(4)));
+        let m = find_matches(&s, &t, 8, 0, false);
+        assert_eq!((m.ends, m.done), (vec![4], None));
+    }
+}
@@ -60,6 +60,10 @@ impl CompressedSignal {
         };
         load_compressed_signal(meta, self.idx, self.tpe)
     }
+
+    pub fn size_in_memory(&self) -> usize {
+        std::mem::size_of::<Self>() + self.data.len()
+    }
 }
 
 /// Compressed form of the timetable. Uses delta compression with variable length integers and gzip.
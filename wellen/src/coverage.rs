@@ -0,0 +1,459 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// Toggle coverage of all bits in a hierarchy.
+
+use crate::signals::{BitState, bit_state};
+use crate::simple::Waveform;
+use crate::{Hierarchy, Result, ScopeRef, Signal, SignalEncoding, SignalRef, Time, VarRef};
+use rayon::prelude::*;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::io::Write;
+
+/// How transitions from or to `x`, `z` and other undefined states are counted.
+/// The `h` and `l` states of nine-value signals are treated like `1` and `0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum XzPolicy {
+    /// Only direct transitions between `0` and `1` count, `0 -> x -> 1` is not a toggle.
+    #[default]
+    Strict,
+    /// Undefined states are skipped, `0 -> x -> 1` counts as a rise.
+    Bridge,
+    /// Transitions from an undefined state count, `x -> 1` is a rise and `x -> 0` a fall.
+    Optimistic,
+}
+
+impl XzPolicy {
+    fn name(&self) -> &'static str {
+        match self {
+            XzPolicy::Strict => "strict",
+            XzPolicy::Bridge => "bridge",
+            XzPolicy::Optimistic => "optimistic",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CoverageOptions {
+    /// Only transitions at times from `start` to `end`, both inclusive, are counted.
+    /// The value before the window is taken into account, i.e., a change at `start` can be
+    /// a toggle.
+    pub time_window: Option<(Time, Time)>,
+    pub xz_policy: XzPolicy,
+}
+
+/// Rising and falling transitions of every bit of a signal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignalToggles {
+    width: u32,
+    rose: Vec<u64>,
+    fell: Vec<u64>,
+}
+
+impl SignalToggles {
+    fn new(width: u32) -> Self {
+        let words = (width as usize).div_ceil(64);
+        Self {
+            width,
+            rose: vec![0; words],
+            fell: vec![0; words],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Bit 0 is the least significant bit.
+    pub fn rose(&self, bit: u32) -> bool {
+        get_bit(&self.rose, bit)
+    }
+
+    pub fn fell(&self, bit: u32) -> bool {
+        get_bit(&self.fell, bit)
+    }
+
+    /// A bit is covered if it went both from `0` to `1` and from `1` to `0`.
+    pub fn toggled(&self, bit: u32) -> bool {
+        self.rose(bit) && self.fell(bit)
+    }
+
+    pub fn num_rose(&self) -> u32 {
+        self.rose.iter().map(|w| w.count_ones()).sum()
+    }
+
+    pub fn num_fell(&self) -> u32 {
+        self.fell.iter().map(|w| w.count_ones()).sum()
+    }
+
+    pub fn num_toggled(&self) -> u32 {
+        self.rose
+            .iter()
+            .zip(self.fell.iter())
+            .map(|(r, f)| (r & f).count_ones())
+            .sum()
+    }
+
+    /// Bit string, most significant bit first, that contains `r` for bits that only rose,
+    /// `f` for bits that only fell, `1` for toggled bits and `0` for bits that never changed.
+    pub fn to_bit_string(&self) -> String {
+        (0..self.width)
+            .rev()
+            .map(|bit| match (self.rose(bit), self.fell(bit)) {
+                (true, true) => '1',
+                (true, false) => 'r',
+                (false, true) => 'f',
+                (false, false) => '0',
+            })
+            .collect()
+    }
+}
+
+fn get_bit(words: &[u64], bit: u32) -> bool {
+    (words[bit as usize / 64] >> (bit % 64)) & 1 == 1
+}
+
+fn set_bit(words: &mut [u64], bit: u32) {
+    words[bit as usize / 64] |= 1 << (bit % 64);
+}
+
+/// Number of bits that changed. Aliased variables are only counted once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Totals {
+    pub bits: u64,
+    pub rose: u64,
+    pub fell: u64,
+    pub toggled: u64,
+}
+
+impl Totals {
+    fn add(&mut self, toggles: &SignalToggles) {
+        self.bits += toggles.width() as u64;
+        self.rose += toggles.num_rose() as u64;
+        self.fell += toggles.num_fell() as u64;
+        self.toggled += toggles.num_toggled() as u64;
+    }
+
+    /// Percentage of bits that toggled. 100 if there are no bits.
+    pub fn percent(&self) -> f64 {
+        if self.bits == 0 {
+            100.0
+        } else {
+            self.toggled as f64 * 100.0 / self.bits as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeCoverage {
+    pub scope: ScopeRef,
+    /// Number of levels below the root of the report, the root has depth 0.
+    pub depth: u32,
+    /// Bits of all variables in the scope and its children.
+    pub totals: Totals,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VarCoverage {
+    pub var: VarRef,
+    pub signal: SignalRef,
+    pub width: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoverageReport {
+    pub options: CoverageOptions,
+    /// Bits of all variables that were analyzed.
+    pub totals: Totals,
+    /// All scopes under the root, parents before their children.
+    pub scopes: Vec<ScopeCoverage>,
+    /// All bit-vector variables under the root, in hierarchy order. Strings and reals are left
+    /// out.
+    pub vars: Vec<VarCoverage>,
+    signals: FxHashMap<SignalRef, SignalToggles>,
+}
+
+impl CoverageReport {
+    /// Toggles of a signal, shared by all variables that alias it.
+    pub fn toggles(&self, signal: SignalRef) -> Option<&SignalToggles> {
+        self.signals.get(&signal)
+    }
+
+    /// Writes the report as JSON, with one entry per scope and variable. The toggles of each
+    /// variable are encoded as described in [`SignalToggles::to_bit_string`].
+    pub fn write_json(&self, hierarchy: &Hierarchy, out: &mut impl Write) -> std::io::Result<()> {
+        let window = match self.options.time_window {
+            Some((start, end)) => format!("[{start}, {end}]"),
+            None => "null".to_string(),
+        };
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"time_window\": {window},")?;
+        writeln!(
+            out,
+            "  \"xz_policy\": \"{}\",",
+            self.options.xz_policy.name()
+        )?;
+        writeln!(out, "  \"totals\": {},", totals_json(&self.totals))?;
+        writeln!(out, "  \"scopes\": [")?;
+        for (ii, scope) in self.scopes.iter().enumerate() {
+            let comma = if ii + 1 < self.scopes.len() { "," } else { "" };
+            let name = hierarchy[scope.scope].full_name(hierarchy);
+            writeln!(
+                out,
+                "    {{\"name\": {}, \"totals\": {}}}{comma}",
+                json_string(&name),
+                totals_json(&scope.totals)
+            )?;
+        }
+        writeln!(out, "  ],")?;
+        writeln!(out, "  \"vars\": [")?;
+        for (ii, var) in self.vars.iter().enumerate() {
+            let comma = if ii + 1 < self.vars.len() { "," } else { "" };
+            let name = hierarchy[var.var].full_name(hierarchy);
+            let toggles = &self.signals[&var.signal];
+            writeln!(
+                out,
+                "    {{\"name\": {}, \"width\": {}, \"toggled\": {}, \"bits\": \"{}\"}}{comma}",
+                json_string(&name),
+                toggles.width(),
+                toggles.num_toggled(),
+                toggles.to_bit_string()
+            )?;
+        }
+        writeln!(out, "  ]")?;
+        writeln!(out, "}}")
+    }
+
+    /// Writes one line per scope, indented by its depth, up to `max_depth` levels below the root.
+    pub fn write_summary(
+        &self,
+        hierarchy: &Hierarchy,
+        max_depth: Option<u32>,
+        out: &mut impl Write,
+    ) -> std::io::Result<()> {
+        writeln!(out, "toggle coverage: {}", summary_line(&self.totals))?;
+        for scope in self.scopes.iter() {
+            if max_depth.is_some_and(|max| scope.depth > max) {
+                continue;
+            }
+            let indent = "  ".repeat(scope.depth as usize + 1);
+            let name = hierarchy[scope.scope].name(hierarchy);
+            writeln!(out, "{indent}{name}: {}", summary_line(&scope.totals))?;
+        }
+        Ok(())
+    }
+}
+
+fn summary_line(totals: &Totals) -> String {
+    format!(
+        "{}/{} bits toggled ({:.1}%), {} rose, {} fell",
+        totals.toggled,
+        totals.bits,
+        totals.percent(),
+        totals.rose,
+        totals.fell
+    )
+}
+
+fn totals_json(totals: &Totals) -> String {
+    format!(
+        "{{\"bits\": {}, \"rose\": {}, \"fell\": {}, \"toggled\": {}}}",
+        totals.bits, totals.rose, totals.fell, totals.toggled
+    )
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Signals are loaded and analyzed in batches in order to limit memory usage.
+const BATCH_SIZE: usize = 1024;
+
+/// Computes the toggle coverage of all bit-vector variables in `root` and its children,
+/// or in the whole hierarchy if `root` is `None`. Signals that need to be loaded are unloaded
+/// again afterward.
+pub fn toggle_coverage(
+    wave: &mut Waveform,
+    root: Option<ScopeRef>,
+    options: &CoverageOptions,
+) -> Result<CoverageReport> {
+    let h = wave.hierarchy();
+    let mut vars = vec![];
+    let mut scopes = vec![];
+    match root {
+        Some(root) => collect(h, root, 0, &mut scopes, &mut vars),
+        None => {
+            vars.extend(h.vars().filter_map(|v| bit_vector(h, v)));
+            for scope in h.scopes() {
+                collect(h, scope, 0, &mut scopes, &mut vars);
+            }
+        }
+    }
+    let mut unique: Vec<_> = vars.iter().map(|v| (v.signal, v.width)).collect();
+    unique.sort();
+    unique.dedup_by_key(|(signal, _)| *signal);
+
+    let mut signals = FxHashMap::default();
+    for batch in unique.chunks(BATCH_SIZE) {
+        let ids: Vec<_> = batch.iter().map(|(id, _)| *id).collect();
+        let missing = wave.load_missing_signals(&ids)?;
+        let wave_ref = &*wave;
+        let counted: Vec<_> = batch
+            .par_iter()
+            .map(|&(id, width)| {
+                let signal = wave_ref.get_signal(id).unwrap();
+                let toggles = count_toggles(signal, width, wave_ref.time_table(), options);
+                (id, toggles)
+            })
+            .collect();
+        signals.extend(counted);
+        wave.unload_signals(&missing);
+    }
+
+    let mut report = CoverageReport {
+        options: options.clone(),
+        totals: Totals::default(),
+        scopes,
+        vars,
+        signals,
+    };
+    for (signal, _) in unique.iter() {
+        report.totals.add(&report.signals[signal]);
+    }
+    roll_up(wave.hierarchy(), &mut report);
+    Ok(report)
+}
+
+fn bit_vector(h: &Hierarchy, var: VarRef) -> Option<VarCoverage> {
+    match h[var].signal_encoding() {
+        SignalEncoding::BitVector(width) => Some(VarCoverage {
+            var,
+            signal: h[var].signal_ref(),
+            width: width.get(),
+        }),
+        _ => None,
+    }
+}
+
+/// Lists scopes before their children and the variables of each scope before those of
+/// its children.
+fn collect(
+    h: &Hierarchy,
+    scope: ScopeRef,
+    depth: u32,
+    scopes: &mut Vec<ScopeCoverage>,
+    vars: &mut Vec<VarCoverage>,
+) {
+    scopes.push(ScopeCoverage {
+        scope,
+        depth,
+        totals: Totals::default(),
+    });
+    vars.extend(h[scope].vars(h).filter_map(|v| bit_vector(h, v)));
+    for child in h[scope].scopes(h) {
+        collect(h, child, depth + 1, scopes, vars);
+    }
+}
+
+/// Computes the totals of every scope from the unique signals in it and its children.
+fn roll_up(h: &Hierarchy, report: &mut CoverageReport) {
+    let index: FxHashMap<ScopeRef, usize> = report
+        .scopes
+        .iter()
+        .enumerate()
+        .map(|(ii, s)| (s.scope, ii))
+        .collect();
+    // children come after their parents, thus we can go backwards and merge the signals of
+    // each scope into its parent
+    let mut signals: Vec<FxHashSet<SignalRef>> = vec![FxHashSet::default(); report.scopes.len()];
+    for (ii, scope) in report.scopes.iter().enumerate() {
+        signals[ii].extend(
+            h[scope.scope]
+                .vars(h)
+                .filter_map(|v| bit_vector(h, v))
+                .map(|v| v.signal),
+        );
+    }
+    for ii in (0..report.scopes.len()).rev() {
+        let mut totals = Totals::default();
+        for signal in signals[ii].iter() {
+            totals.add(&report.signals[signal]);
+        }
+        report.scopes[ii].totals = totals;
+        let parent = h[report.scopes[ii].scope]
+            .parent()
+            .and_then(|p| index.get(&p).copied());
+        if let Some(parent) = parent {
+            // merge the smaller set into the larger one
+            let mut child = std::mem::take(&mut signals[ii]);
+            if child.len() > signals[parent].len() {
+                std::mem::swap(&mut child, &mut signals[parent]);
+            }
+            signals[parent].extend(child);
+        }
+    }
+}
+
+/// Counts the transitions of every bit, including those in delta cycles.
+fn count_toggles(
+    signal: &Signal,
+    width: u32,
+    time_table: &[Time],
+    options: &CoverageOptions,
+) -> SignalToggles {
+    let mut out = SignalToggles::new(width);
+    // `None` before the first value
+    let mut prev: Vec<Option<BitState>> = vec![None; width as usize];
+    let policy = options.xz_policy;
+    for (idx, value) in signal.iter_changes() {
+        let time = time_table[idx as usize];
+        if options.time_window.is_some_and(|(_, end)| time > end) {
+            break;
+        }
+        let count = options.time_window.is_none_or(|(start, _)| time >= start);
+        for bit in 0..width {
+            let state = bit_state(&value, bit);
+            let before = prev[bit as usize];
+            let (rise, fall, next) = match policy {
+                XzPolicy::Strict => (
+                    before == Some(BitState::Zero) && state == BitState::One,
+                    before == Some(BitState::One) && state == BitState::Zero,
+                    Some(state),
+                ),
+                // `z` is undefined as well
+                XzPolicy::Bridge if matches!(state, BitState::X | BitState::Z) => {
+                    (false, false, before)
+                }
+                XzPolicy::Bridge => (
+                    before == Some(BitState::Zero) && state == BitState::One,
+                    before == Some(BitState::One) && state == BitState::Zero,
+                    Some(state),
+                ),
+                XzPolicy::Optimistic => (
+                    before.is_some_and(|b| b != BitState::One) && state == BitState::One,
+                    before.is_some_and(|b| b != BitState::Zero) && state == BitState::Zero,
+                    Some(state),
+                ),
+            };
+            if count && rise {
+                set_bit(&mut out.rose, bit);
+            }
+            if count && fall {
+                set_bit(&mut out.fell, bit);
+            }
+            prev[bit as usize] = next;
+        }
+    }
+    out
+}
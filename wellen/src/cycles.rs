@@ -0,0 +1,206 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// Turns an event based waveform into a cycle based trace by sampling signals at clock edges.
+
+use crate::{Edge, SearchDirection, Signal, SignalValue, Time, TimeTableIdx};
+
+/// Values of a list of signals, sampled at the edges of a clock.
+/// The data is stored in columns, one per signal, which only contain references into the
+/// original signals.
+#[derive(Debug)]
+pub struct CycleTrace<'a> {
+    signals: Vec<&'a Signal>,
+    /// time table index of each clock edge
+    edges: Vec<TimeTableIdx>,
+    /// For each signal and cycle: the index of the change that is sampled.
+    columns: Vec<Vec<Option<u32>>>,
+}
+
+impl<'a> CycleTrace<'a> {
+    /// Samples `signals` at every `edge` of the `clock`. Values are taken from just before the
+    /// edge, i.e., any change that happens in the same time step as the edge is only visible in the
+    /// next cycle. If `skip_unchanged` is set, cycles in which none of the signals changed
+    /// are dropped.
+    pub fn sample(
+        clock: &Signal,
+        time_table: &[Time],
+        edge: Edge,
+        signals: &[&'a Signal],
+        skip_unchanged: bool,
+    ) -> Self {
+        let mut trace = Self {
+            signals: signals.to_vec(),
+            edges: vec![],
+            columns: vec![vec![]; signals.len()],
+        };
+        let Some(first_time) = time_table.first() else {
+            return trace;
+        };
+        let mut time = *first_time;
+        let mut sample = Vec::with_capacity(signals.len());
+        while let Some(change) = clock.find_edge(time_table, time, SearchDirection::Forward, edge) {
+            time = change.time;
+            sample.clear();
+            sample.extend(signals.iter().map(|s| sample_before(s, change.time_idx)));
+            if skip_unchanged && !trace.edges.is_empty() && trace.is_same_as_last(&sample) {
+                continue;
+            }
+            trace.edges.push(change.time_idx);
+            for (column, value) in trace.columns.iter_mut().zip(sample.iter()) {
+                column.push(*value);
+            }
+        }
+        trace
+    }
+
+    fn is_same_as_last(&self, sample: &[Option<u32>]) -> bool {
+        self.columns
+            .iter()
+            .zip(sample.iter())
+            .zip(self.signals.iter())
+            .all(|((column, new), signal)| {
+                let old = column.last().unwrap();
+                old == new
+                    || match (old, new) {
+                        (Some(old), Some(new)) => {
+                            signal.get_value_at_change(*old as usize)
+                                == signal.get_value_at_change(*new as usize)
+                        }
+                        _ => false,
+                    }
+            })
+    }
+
+    /// Number of cycles.
+    pub fn len(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty()
+    }
+
+    pub fn num_signals(&self) -> usize {
+        self.signals.len()
+    }
+
+    /// Time table indices of the clock edges.
+    pub fn edges(&self) -> &[TimeTableIdx] {
+        &self.edges
+    }
+
+    /// Value of the `signal`-th signal in `cycle`. Returns `None` if the signal did not have a
+    /// value yet.
+    pub fn value(&self, cycle: usize, signal: usize) -> Option<SignalValue<'a>> {
+        let change = self.columns[signal][cycle]?;
+        Some(self.signals[signal].get_value_at_change(change as usize))
+    }
+
+    /// All values of the `signal`-th signal, one per cycle.
+    pub fn column(&self, signal: usize) -> impl Iterator<Item = Option<SignalValue<'a>>> + '_ {
+        let s = self.signals[signal];
+        self.columns[signal]
+            .iter()
+            .map(move |c| c.map(|c| s.get_value_at_change(c as usize)))
+    }
+
+    /// Iterates over all cycles. Each item contains the time table index of the clock edge
+    /// and the values of all signals.
+    pub fn iter(&self) -> impl Iterator<Item = (TimeTableIdx, Vec<Option<SignalValue<'a>>>)> + '_ {
+        self.edges.iter().enumerate().map(|(cycle, edge)| {
+            let values = (0..self.signals.len())
+                .map(|signal| self.value(cycle, signal))
+                .collect();
+            (*edge, values)
+        })
+    }
+}
+
+/// Returns the index of the last change before the time step `idx`.
+fn sample_before(signal: &Signal, idx: TimeTableIdx) -> Option<u32> {
+    let offset = signal.get_offset(idx.checked_sub(1)?)?;
+    Some((offset.start + offset.elements as usize - 1) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SignalRef;
+    use crate::signals::FixedWidthEncoding;
+    use crate::wavemem::States;
+
+    fn two_state(id: usize, bits: u32, changes: &[(TimeTableIdx, u8)]) -> Signal {
+        let encoding = FixedWidthEncoding::BitVector {
+            max_states: States::Two,
+            bits,
+            meta_byte: false,
+        };
+        Signal::new_fixed_len(
+            SignalRef::from_index(id).unwrap(),
+            changes.iter().map(|(t, _)| *t).collect(),
+            encoding,
+            1,
+            changes.iter().map(|(_, v)| *v).collect(),
+        )
+    }
+
+    fn to_strings(trace: &CycleTrace) -> Vec<(TimeTableIdx, Vec<String>)> {
+        trace
+            .iter()
+            .map(|(edge, values)| {
+                let values = values
+                    .iter()
+                    .map(|v| v.map(|v| v.to_string()).unwrap_or("?".to_string()))
+                    .collect();
+                (edge, values)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sample() {
+        let time_table: Vec<Time> = (0..10).map(|t| t * 5).collect();
+        let clock = two_state(
+            0,
+            1,
+            &[
+                (0, 0),
+                (1, 1),
+                (2, 0),
+                (3, 1),
+                (4, 0),
+                (5, 1),
+                (6, 0),
+                (7, 1),
+            ],
+        );
+        // changes together with the first rising edge
+        let data = two_state(1, 4, &[(1, 3), (4, 7)]);
+        let trace = CycleTrace::sample(&clock, &time_table, Edge::Rising, &[&data], false);
+        assert_eq!(
+            to_strings(&trace),
+            [
+                (1, vec!["?".to_string()]),
+                (3, vec!["0011".to_string()]),
+                (5, vec!["0111".to_string()]),
+                (7, vec!["0111".to_string()]),
+            ]
+        );
+        assert_eq!(trace.len(), 4);
+        assert_eq!(trace.edges(), [1, 3, 5, 7]);
+
+        let trace = CycleTrace::sample(&clock, &time_table, Edge::Rising, &[&data], true);
+        assert_eq!(trace.edges(), [1, 3, 5]);
+
+        let trace = CycleTrace::sample(&clock, &time_table, Edge::Falling, &[&data], false);
+        assert_eq!(trace.edges(), [2, 4, 6]);
+        let column: Vec<_> = trace.column(0).map(|v| v.unwrap().to_string()).collect();
+        assert_eq!(column, ["0011", "0011", "0111"]);
+
+        let trace = CycleTrace::sample(&clock, &time_table, Edge::Any, &[&data, &clock], true);
+        assert_eq!(trace.edges(), [1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(trace.value(0, 1).unwrap().to_string(), "0");
+        assert_eq!(trace.value(1, 1).unwrap().to_string(), "1");
+    }
+}
@@ -0,0 +1,344 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// Compares two waveforms variable by variable, e.g., an RTL against a gate-level simulation.
+
+use crate::simple::Waveform;
+use crate::{
+    Result, Signal, SignalEncoding, SignalRef, Time, Timescale, TimescaleUnit, VarRef, WellenError,
+};
+use rayon::prelude::*;
+use rustc_hash::FxHashMap;
+
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    /// Replaces prefixes of full variable names in the first waveform before they are matched,
+    /// e.g., `("tb.dut", "top")` turns `tb.dut.alu.result` into `top.alu.result`.
+    /// Prefixes only match complete levels of the hierarchy and the first matching entry wins.
+    pub remap_a: Vec<(String, String)>,
+    /// Replaces prefixes of full variable names in the second waveform.
+    pub remap_b: Vec<(String, String)>,
+    /// Converts the times of both waveforms into a common unit before comparing them. Otherwise,
+    /// the raw time values are compared. Only possible if both waveforms have a known timescale.
+    pub normalize_timescale: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffReport {
+    /// Unit of all times in this report. `None` if the raw time values were compared.
+    pub timescale: Option<Timescale>,
+    /// Variables that exist in both waveforms, but whose values differ.
+    pub mismatches: Vec<SignalMismatch>,
+    /// Variables that exist in both waveforms, but differ in width or type. Their values are
+    /// not compared.
+    pub type_mismatches: Vec<TypeMismatch>,
+    pub only_in_a: Vec<VarRef>,
+    pub only_in_b: Vec<VarRef>,
+}
+
+impl DiffReport {
+    /// Returns true if both waveforms are the same.
+    pub fn is_empty(&self) -> bool {
+        self.mismatches.is_empty()
+            && self.type_mismatches.is_empty()
+            && self.only_in_a.is_empty()
+            && self.only_in_b.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalMismatch {
+    /// Full name after remapping.
+    pub name: String,
+    pub a: VarRef,
+    pub b: VarRef,
+    /// Never empty.
+    pub intervals: Vec<MismatchInterval>,
+}
+
+impl SignalMismatch {
+    /// Time at which the values diverge for the first time.
+    pub fn first_mismatch(&self) -> Time {
+        self.intervals[0].start
+    }
+}
+
+/// The values differ from `start` until right before `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MismatchInterval {
+    pub start: Time,
+    /// `None` if the values differ until the end of the waveform.
+    pub end: Option<Time>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeMismatch {
+    /// Full name after remapping.
+    pub name: String,
+    pub a: VarRef,
+    pub b: VarRef,
+    pub a_encoding: SignalEncoding,
+    pub b_encoding: SignalEncoding,
+}
+
+/// Signals are loaded and compared in batches in order to limit memory usage.
+const BATCH_SIZE: usize = 1024;
+
+/// Matches the variables of both waveforms by their full name and compares their values.
+/// Signals that need to be loaded for the comparison are unloaded again afterward.
+pub fn diff(a: &mut Waveform, b: &mut Waveform, options: &DiffOptions) -> Result<DiffReport> {
+    let (timescale, scale_a, scale_b) = time_scales(a, b, options.normalize_timescale)?;
+    let mut report = DiffReport {
+        timescale,
+        mismatches: vec![],
+        type_mismatches: vec![],
+        only_in_a: vec![],
+        only_in_b: vec![],
+    };
+
+    let mut names_b: FxHashMap<String, VarRef> = FxHashMap::default();
+    for (var, name) in names(b, &options.remap_b) {
+        names_b.entry(name).or_insert(var);
+    }
+    // pairs of variables with the same encoding, in the order in which they appear in `a`
+    let mut pairs = vec![];
+    for (var_a, name) in names(a, &options.remap_a) {
+        let Some(var_b) = names_b.remove(&name) else {
+            report.only_in_a.push(var_a);
+            continue;
+        };
+        let a_encoding = a.hierarchy()[var_a].signal_encoding();
+        let b_encoding = b.hierarchy()[var_b].signal_encoding();
+        if a_encoding == b_encoding {
+            pairs.push((name, var_a, var_b));
+        } else {
+            report.type_mismatches.push(TypeMismatch {
+                name,
+                a: var_a,
+                b: var_b,
+                a_encoding,
+                b_encoding,
+            });
+        }
+    }
+    report.only_in_b = names_b.into_values().collect();
+    report.only_in_b.sort_by_key(|v| v.index());
+
+    // aliased variables only need to be compared once
+    let mut results: FxHashMap<(SignalRef, SignalRef), Vec<MismatchInterval>> =
+        FxHashMap::default();
+    for batch in pairs.chunks(BATCH_SIZE) {
+        let signals: Vec<_> = batch
+            .iter()
+            .map(|(_, var_a, var_b)| {
+                (
+                    a.hierarchy()[*var_a].signal_ref(),
+                    b.hierarchy()[*var_b].signal_ref(),
+                )
+            })
+            .filter(|key| !results.contains_key(key))
+            .collect();
+        let ids_a: Vec<_> = signals.iter().map(|(s, _)| *s).collect();
+        let ids_b: Vec<_> = signals.iter().map(|(_, s)| *s).collect();
+        let loaded_a = a.load_missing_signals(&ids_a)?;
+        let loaded_b = b.load_missing_signals(&ids_b)?;
+        let (wave_a, wave_b) = (&*a, &*b);
+        let compared: Vec<_> = signals
+            .par_iter()
+            .map(|&(signal_a, signal_b)| {
+                let intervals = compare(
+                    wave_a.get_signal(signal_a).unwrap(),
+                    wave_a.time_table(),
+                    scale_a,
+                    wave_b.get_signal(signal_b).unwrap(),
+                    wave_b.time_table(),
+                    scale_b,
+                );
+                ((signal_a, signal_b), intervals)
+            })
+            .collect();
+        results.extend(compared);
+        a.unload_signals(&loaded_a);
+        b.unload_signals(&loaded_b);
+
+        for (name, var_a, var_b) in batch.iter() {
+            let key = (
+                a.hierarchy()[*var_a].signal_ref(),
+                b.hierarchy()[*var_b].signal_ref(),
+            );
+            let intervals = &results[&key];
+            if !intervals.is_empty() {
+                report.mismatches.push(SignalMismatch {
+                    name: name.clone(),
+                    a: *var_a,
+                    b: *var_b,
+                    intervals: intervals.clone(),
+                });
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Full names of all variables after remapping. Bit-blasted variables, i.e., variables with a
+/// single bit index, include their index since they would not be unique otherwise.
+fn names<'a>(
+    wave: &'a Waveform,
+    rules: &'a [(String, String)],
+) -> impl Iterator<Item = (VarRef, String)> + 'a {
+    let h = wave.hierarchy();
+    h.iter_vars().enumerate().map(move |(ii, var)| {
+        let mut name = var.full_name(h);
+        if let Some(index) = var.index().filter(|i| i.msb() == i.lsb()) {
+            name.push_str(&format!("[{}]", index.lsb()));
+        }
+        (VarRef::from_index(ii).unwrap(), remap(name, rules))
+    })
+}
+
+/// Replaces the first prefix from `rules` that matches complete levels of the hierarchy.
+fn remap(name: String, rules: &[(String, String)]) -> String {
+    for (from, to) in rules.iter() {
+        let rest = if from.is_empty() {
+            name.as_str()
+        } else {
+            match name.strip_prefix(from.as_str()) {
+                Some("") => "",
+                Some(rest) if rest.starts_with('.') => &rest[1..],
+                _ => continue,
+            }
+        };
+        return match (to.is_empty(), rest.is_empty()) {
+            (true, _) => rest.to_string(),
+            (false, true) => to.clone(),
+            (false, false) => format!("{to}.{rest}"),
+        };
+    }
+    name
+}
+
+/// Returns the common timescale and the factors by which the times of each waveform need to be
+/// multiplied in order to convert them.
+fn time_scales(
+    a: &Waveform,
+    b: &Waveform,
+    normalize: bool,
+) -> Result<(Option<Timescale>, u64, u64)> {
+    let exponent = |w: &Waveform| {
+        let timescale = w.hierarchy().timescale()?;
+        Some((timescale.factor as u64, timescale.unit.to_exponent()?))
+    };
+    let (Some((factor_a, exp_a)), Some((factor_b, exp_b)), true) =
+        (exponent(a), exponent(b), normalize)
+    else {
+        return Ok((None, 1, 1));
+    };
+    let common = std::cmp::min(exp_a, exp_b);
+    let unit = [
+        TimescaleUnit::ZeptoSeconds,
+        TimescaleUnit::AttoSeconds,
+        TimescaleUnit::FemtoSeconds,
+        TimescaleUnit::PicoSeconds,
+        TimescaleUnit::NanoSeconds,
+        TimescaleUnit::MicroSeconds,
+        TimescaleUnit::MilliSeconds,
+        TimescaleUnit::Seconds,
+    ]
+    .into_iter()
+    .find(|u| u.to_exponent() == Some(common))
+    .unwrap();
+    // the largest time needs to be representable as well, so that all times can be scaled
+    let scale = |w: &Waveform, factor: u64, exp: i8| {
+        let last = w.time_table().last().copied().unwrap_or(0);
+        10u64
+            .checked_pow((exp - common) as u32)
+            .and_then(|p| p.checked_mul(factor))
+            .filter(|s| last.checked_mul(*s).is_some())
+            .ok_or_else(|| {
+                WellenError::malformed(
+                    w.hierarchy().file_format(),
+                    "timescale",
+                    None,
+                    format!(
+                        "{last} x {factor}e{exp} s cannot be converted to {unit:?} without overflow"
+                    ),
+                )
+            })
+    };
+    Ok((
+        Some(Timescale::new(1, unit)),
+        scale(a, factor_a, exp_a)?,
+        scale(b, factor_b, exp_b)?,
+    ))
+}
+
+/// Walks over the changes of both signals in chronological order. If there are several changes
+/// at the same time, only the last one counts.
+fn compare(
+    a: &Signal,
+    time_table_a: &[Time],
+    scale_a: u64,
+    b: &Signal,
+    time_table_b: &[Time],
+    scale_b: u64,
+) -> Vec<MismatchInterval> {
+    let mut changes_a = a
+        .iter_changes()
+        .map(|(idx, value)| (time_table_a[idx as usize] * scale_a, value))
+        .peekable();
+    let mut changes_b = b
+        .iter_changes()
+        .map(|(idx, value)| (time_table_b[idx as usize] * scale_b, value))
+        .peekable();
+    let (mut value_a, mut value_b) = (None, None);
+    let mut mismatch_start = None;
+    let mut out = vec![];
+    loop {
+        let time = match (changes_a.peek(), changes_b.peek()) {
+            (None, None) => break,
+            (Some((t, _)), None) | (None, Some((t, _))) => *t,
+            (Some((ta, _)), Some((tb, _))) => std::cmp::min(*ta, *tb),
+        };
+        while let Some((_, value)) = changes_a.next_if(|(t, _)| *t == time) {
+            value_a = Some(value);
+        }
+        while let Some((_, value)) = changes_b.next_if(|(t, _)| *t == time) {
+            value_b = Some(value);
+        }
+        match (mismatch_start, value_a == value_b) {
+            (None, false) => mismatch_start = Some(time),
+            (Some(start), true) => {
+                out.push(MismatchInterval {
+                    start,
+                    end: Some(time),
+                });
+                mismatch_start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = mismatch_start {
+        out.push(MismatchInterval { start, end: None });
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remap() {
+        let rules = vec![
+            ("tb.dut".to_string(), "top".to_string()),
+            ("".to_string(), "root".to_string()),
+        ];
+        assert_eq!(remap("tb.dut.alu.x".to_string(), &rules), "top.alu.x");
+        assert_eq!(remap("tb.dut".to_string(), &rules), "top");
+        // only complete levels of the hierarchy match
+        assert_eq!(remap("tb.dut2.x".to_string(), &rules), "root.tb.dut2.x");
+        assert_eq!(remap("tb.dut2.x".to_string(), &rules[..1]), "tb.dut2.x");
+        let remove = vec![("tb".to_string(), "".to_string())];
+        assert_eq!(remap("tb.dut.x".to_string(), &remove), "dut.x");
+    }
+}
@@ -0,0 +1,1229 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// Derived signals that are computed from a small, Verilog-like expression language.
+
+use crate::signals::{BitVectorBuilder, slice_signal};
+use crate::wavemem::States;
+use crate::{Hierarchy, Signal, SignalEncoding, SignalRef, SignalValue, TimeTableIdx, Var};
+use std::cmp::Ordering;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+#[non_exhaustive]
+pub enum ExprError {
+    #[error("[expr] unexpected character `{0}` at position {1}")]
+    UnexpectedChar(char, usize),
+    #[error("[expr] unexpected `{0}` at position {1}, expected {2}")]
+    UnexpectedToken(String, usize, &'static str),
+    #[error("[expr] unexpected end of expression, expected {0}")]
+    UnexpectedEnd(&'static str),
+    #[error("[expr] failed to parse literal `{0}`")]
+    InvalidLiteral(String),
+    #[error("[expr] unknown signal `{0}`")]
+    UnknownSignal(String),
+    #[error("[expr] `{0}` is not a bit-vector signal")]
+    NotABitVector(String),
+    #[error("[expr] slice [{1}:{2}] is out of range for `{0}` which has {3} bits")]
+    InvalidSlice(String, u32, u32, u32),
+    #[error("[expr] unknown system function `{0}`")]
+    UnknownFunction(String),
+    #[error("[expr] `{0}` refers to earlier clock cycles and is only available in properties")]
+    SampledFunction(String),
+    #[error("[expr] signal {0:?} is used by the expression but was not provided")]
+    MissingSignal(SignalRef),
+}
+
+pub type Result<T> = std::result::Result<T, ExprError>;
+
+/// An expression over signals in a hierarchy.
+///
+/// The syntax follows Verilog:
+/// - signals are referenced by their hierarchical path, e.g., `top.cpu.valid`
+/// - bits are selected with `a[3]` or `a[31:12]`, counting from the least significant bit
+/// - literals can be plain decimals like `12` or sized, e.g., `4'b10x1` or `8'hff`
+/// - `{a, b}` concatenates, `c ? a : b` selects
+/// - bitwise and reduction operators: `~ & | ^`
+/// - logical operators: `! && ||`
+/// - comparisons: `== != < <= > >=`
+/// - arithmetic: `+ - * / % << >>`
+///
+/// All values are unsigned. The result of an arithmetic or bitwise operation is as wide as its
+/// widest operand and unsized literals only use as many bits as they need.
+/// `x` and `z` states propagate like they do in a Verilog simulator.
+#[derive(Debug, Clone)]
+pub struct Expr {
+    inputs: Vec<Input>,
+    root: Node,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Input {
+    signal: SignalRef,
+    slice: Option<(u32, u32)>,
+    width: u32,
+    /// Number of clock cycles by which the value is delayed. Always zero, unless the
+    /// expression is part of a property, see [`crate::checker`].
+    delay: u32,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    /// index into the inputs
+    Input(usize),
+    Const(Bits),
+    Unary(UnaryOp, Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    Ternary(Box<Node>, Box<Node>, Box<Node>),
+    Concat(Vec<Node>),
+    /// compares the current value to the one from the previous clock cycle
+    Sampled(SampledFn, Box<Node>, Box<Node>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SampledFn {
+    Rose,
+    Fell,
+    Stable,
+    Changed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum UnaryOp {
+    Not,
+    LogicNot,
+    Neg,
+    ReduceAnd,
+    ReduceOr,
+    ReduceXor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOp {
+    And,
+    Or,
+    Xor,
+    LogicAnd,
+    LogicOr,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Shl,
+    Shr,
+}
+
+impl BinaryOp {
+    fn from_token(token: &str) -> Option<(u8, Self)> {
+        let res = match token {
+            "||" => (0, BinaryOp::LogicOr),
+            "&&" => (1, BinaryOp::LogicAnd),
+            "|" => (2, BinaryOp::Or),
+            "^" => (3, BinaryOp::Xor),
+            "&" => (4, BinaryOp::And),
+            "==" => (5, BinaryOp::Eq),
+            "!=" => (5, BinaryOp::Ne),
+            "<" => (6, BinaryOp::Lt),
+            "<=" => (6, BinaryOp::Le),
+            ">" => (6, BinaryOp::Gt),
+            ">=" => (6, BinaryOp::Ge),
+            "<<" => (7, BinaryOp::Shl),
+            ">>" => (7, BinaryOp::Shr),
+            "+" => (8, BinaryOp::Add),
+            "-" => (8, BinaryOp::Sub),
+            "*" => (9, BinaryOp::Mul),
+            "/" => (9, BinaryOp::Div),
+            "%" => (9, BinaryOp::Rem),
+            _ => return None,
+        };
+        Some(res)
+    }
+}
+
+impl Expr {
+    /// Parses an expression and resolves all signal names in the hierarchy.
+    pub fn parse(expression: &str, hierarchy: &Hierarchy) -> Result<Self> {
+        let mut parser = Parser::new(expression, hierarchy, false)?;
+        let root = parser.parse_expr()?;
+        if let Some((token, pos)) = parser.tokens.get(parser.pos) {
+            return Err(ExprError::UnexpectedToken(
+                token.to_string(),
+                *pos,
+                "end of expression",
+            ));
+        }
+        Ok(Self {
+            inputs: parser.inputs,
+            root,
+        })
+    }
+
+    /// Parses an expression that is part of a property. Unlike [`Expr::parse`], system
+    /// functions like `$rose` that refer to earlier clock cycles are allowed.
+    pub(crate) fn parse_sampled(parser: &mut Parser) -> Result<Self> {
+        debug_assert!(parser.sampled && parser.inputs.is_empty());
+        let root = parser.parse_expr()?;
+        Ok(Self {
+            inputs: std::mem::take(&mut parser.inputs),
+            root,
+        })
+    }
+
+    /// Evaluates an expression on values that were sampled at clock edges. `sample` returns the
+    /// value of a signal a number of cycles ago, or `None` if there is no such value.
+    /// Only `1` counts as true, `x` and `z` are false like in SystemVerilog assertions.
+    pub(crate) fn holds<'a>(
+        &self,
+        sample: impl Fn(SignalRef, u32) -> Option<SignalValue<'a>>,
+    ) -> bool {
+        let values: Vec<Bits> = self
+            .inputs
+            .iter()
+            .map(|i| match sample(i.signal, i.delay) {
+                None => vec![X; i.width as usize],
+                Some(value) => {
+                    let mut bits = vec![];
+                    unpack(value, &mut bits);
+                    match i.slice {
+                        Some((msb, lsb)) => bits[lsb as usize..=msb as usize].to_vec(),
+                        None => bits,
+                    }
+                }
+            })
+            .collect();
+        to_bool(&self.eval(&self.root, &values)) == ONE
+    }
+
+    /// Number of bits in the result.
+    pub fn width(&self) -> u32 {
+        self.node_width(&self.root)
+    }
+
+    /// Signals that need to be loaded in order to evaluate the expression.
+    pub fn signals(&self) -> Vec<SignalRef> {
+        let mut out: Vec<_> = self.inputs.iter().map(|i| i.signal).collect();
+        out.sort();
+        out.dedup();
+        out
+    }
+
+    /// Computes a new signal with the provided `id`. `signals` needs to contain all signals
+    /// returned by [`Expr::signals`]. The result shares the time table of its inputs.
+    pub fn evaluate(&self, id: SignalRef, signals: &[&Signal]) -> Result<Signal> {
+        let find = |signal: SignalRef| -> Result<&Signal> {
+            signals
+                .iter()
+                .find(|s| s.signal_ref() == signal)
+                .copied()
+                .ok_or(ExprError::MissingSignal(signal))
+        };
+        let sliced = self
+            .inputs
+            .iter()
+            .map(|i| {
+                i.slice
+                    .map(|(msb, lsb)| Ok(slice_signal(i.signal, find(i.signal)?, msb, lsb)))
+                    .transpose()
+            })
+            .collect::<Result<Vec<Option<Signal>>>>()?;
+        let inputs = self
+            .inputs
+            .iter()
+            .zip(sliced.iter())
+            .map(|(i, s)| s.as_ref().map_or_else(|| find(i.signal), Ok))
+            .collect::<Result<Vec<&Signal>>>()?;
+
+        // the result can only change when one of the inputs changes
+        let mut time_indices: Vec<TimeTableIdx> = inputs
+            .iter()
+            .flat_map(|s| s.time_indices().iter().cloned())
+            .collect();
+        time_indices.sort_unstable();
+        time_indices.dedup();
+        if time_indices.is_empty() {
+            // constant expression
+            time_indices.push(0);
+        }
+
+        let width = self.width() as usize;
+        let mut values: Vec<Bits> = self
+            .inputs
+            .iter()
+            .map(|i| vec![X; i.width as usize])
+            .collect();
+        let mut changes: Vec<TimeTableIdx> = vec![];
+        let mut results: Vec<u8> = vec![];
+        let mut max_state = 0u8;
+        for time_idx in time_indices {
+            for (value, signal) in values.iter_mut().zip(inputs.iter()) {
+                if let Some(offset) = signal.get_offset(time_idx) {
+                    // we only care about the final value after all delta cycles
+                    unpack(signal.get_value_at(&offset, offset.elements - 1), value);
+                }
+            }
+            let result = self.eval(&self.root, &values);
+            debug_assert_eq!(result.len(), width);
+            if changes.is_empty() || results[results.len() - width..] != result[..] {
+                max_state = result.iter().fold(max_state, |a, b| std::cmp::max(a, *b));
+                changes.push(time_idx);
+                results.extend_from_slice(&result);
+            }
+        }
+
+        let states = if max_state <= ONE {
+            States::Two
+        } else if max_state <= Z {
+            States::Four
+        } else {
+            States::Nine
+        };
+        let mut builder = BitVectorBuilder::new(states, width as u32);
+        let mut buf = vec![];
+        for (time_idx, value) in changes.into_iter().zip(results.chunks_exact(width)) {
+            pack(states, value, &mut buf);
+            let value = match states {
+                States::Two => SignalValue::Binary(&buf, width as u32),
+                States::Four => SignalValue::FourValue(&buf, width as u32),
+                States::Nine => SignalValue::NineValue(&buf, width as u32),
+            };
+            builder.add_change(time_idx, value);
+        }
+        Ok(builder.finish(id))
+    }
+
+    fn node_width(&self, node: &Node) -> u32 {
+        match node {
+            Node::Input(ii) => self.inputs[*ii].width,
+            Node::Const(bits) => bits.len() as u32,
+            Node::Unary(op, e) => match op {
+                UnaryOp::Not | UnaryOp::Neg => self.node_width(e),
+                _ => 1,
+            },
+            Node::Binary(op, a, b) => match op {
+                BinaryOp::Shl | BinaryOp::Shr => self.node_width(a),
+                BinaryOp::And
+                | BinaryOp::Or
+                | BinaryOp::Xor
+                | BinaryOp::Add
+                | BinaryOp::Sub
+                | BinaryOp::Mul
+                | BinaryOp::Div
+                | BinaryOp::Rem => std::cmp::max(self.node_width(a), self.node_width(b)),
+                _ => 1,
+            },
+            Node::Ternary(_, a, b) => std::cmp::max(self.node_width(a), self.node_width(b)),
+            Node::Concat(parts) => parts.iter().map(|p| self.node_width(p)).sum(),
+            Node::Sampled(..) => 1,
+        }
+    }
+
+    fn eval(&self, node: &Node, inputs: &[Bits]) -> Bits {
+        match node {
+            Node::Input(ii) => inputs[*ii].clone(),
+            Node::Const(bits) => bits.clone(),
+            Node::Unary(op, e) => eval_unary(*op, self.eval(e, inputs)),
+            Node::Binary(op, a, b) => eval_binary(*op, self.eval(a, inputs), self.eval(b, inputs)),
+            Node::Ternary(cond, a, b) => {
+                let width = self.node_width(node) as usize;
+                match to_bool(&self.eval(cond, inputs)) {
+                    ONE => resize(self.eval(a, inputs), width),
+                    ZERO => resize(self.eval(b, inputs), width),
+                    _ => {
+                        // bits on which both sides agree are known
+                        let a = resize(self.eval(a, inputs), width);
+                        let b = resize(self.eval(b, inputs), width);
+                        a.into_iter()
+                            .zip(b)
+                            .map(|(a, b)| if a == b { a } else { X })
+                            .collect()
+                    }
+                }
+            }
+            Node::Concat(parts) => {
+                // the first part ends up in the most significant bits
+                parts
+                    .iter()
+                    .rev()
+                    .flat_map(|p| self.eval(p, inputs))
+                    .collect()
+            }
+            Node::Sampled(f, now, before) => {
+                let now = self.eval(now, inputs);
+                let before = self.eval(before, inputs);
+                // like in SystemVerilog, only the least significant bit matters for edges
+                let result = match f {
+                    SampledFn::Rose => logic(now[0]) == ONE && logic(before[0]) != ONE,
+                    SampledFn::Fell => logic(now[0]) == ZERO && logic(before[0]) != ZERO,
+                    SampledFn::Stable => now == before,
+                    SampledFn::Changed => now != before,
+                };
+                vec![result as u8]
+            }
+        }
+    }
+}
+
+/// One state per bit, least significant bit first. The state values follow the order of
+/// nine-value states `0 1 x z h u w l -` that is used throughout the library.
+type Bits = Vec<u8>;
+
+const ZERO: u8 = 0;
+const ONE: u8 = 1;
+const X: u8 = 2;
+const Z: u8 = 3;
+const H: u8 = 4;
+const L: u8 = 7;
+
+/// Maps a state to `0`, `1` or `x`. Weak values are treated as their strong counterparts.
+#[inline]
+fn logic(state: u8) -> u8 {
+    match state {
+        ZERO | L => ZERO,
+        ONE | H => ONE,
+        _ => X,
+    }
+}
+
+fn is_known(bits: &[u8]) -> bool {
+    bits.iter().all(|b| logic(*b) != X)
+}
+
+fn resize(mut bits: Bits, width: usize) -> Bits {
+    bits.resize(width, ZERO);
+    bits
+}
+
+fn to_bool(bits: &[u8]) -> u8 {
+    let mut res = ZERO;
+    for b in bits.iter() {
+        match logic(*b) {
+            ONE => return ONE,
+            X => res = X,
+            _ => {}
+        }
+    }
+    res
+}
+
+fn eval_unary(op: UnaryOp, value: Bits) -> Bits {
+    match op {
+        UnaryOp::Not => value
+            .into_iter()
+            .map(|b| match logic(b) {
+                ZERO => ONE,
+                ONE => ZERO,
+                _ => X,
+            })
+            .collect(),
+        UnaryOp::LogicNot => eval_unary(UnaryOp::Not, vec![to_bool(&value)]),
+        UnaryOp::Neg => {
+            let width = value.len();
+            eval_binary(BinaryOp::Sub, vec![ZERO; width], value)
+        }
+        UnaryOp::ReduceAnd => vec![value.iter().fold(ONE, |a, b| bit_and(a, *b))],
+        UnaryOp::ReduceOr => vec![value.iter().fold(ZERO, |a, b| bit_or(a, *b))],
+        UnaryOp::ReduceXor => vec![value.iter().fold(ZERO, |a, b| bit_xor(a, *b))],
+    }
+}
+
+#[inline]
+fn bit_and(a: u8, b: u8) -> u8 {
+    match (logic(a), logic(b)) {
+        (ZERO, _) | (_, ZERO) => ZERO,
+        (ONE, ONE) => ONE,
+        _ => X,
+    }
+}
+
+#[inline]
+fn bit_or(a: u8, b: u8) -> u8 {
+    match (logic(a), logic(b)) {
+        (ONE, _) | (_, ONE) => ONE,
+        (ZERO, ZERO) => ZERO,
+        _ => X,
+    }
+}
+
+#[inline]
+fn bit_xor(a: u8, b: u8) -> u8 {
+    match (logic(a), logic(b)) {
+        (X, _) | (_, X) => X,
+        (a, b) => a ^ b,
+    }
+}
+
+fn eval_binary(op: BinaryOp, a: Bits, b: Bits) -> Bits {
+    let width = std::cmp::max(a.len(), b.len());
+    let bitwise = |f: fn(u8, u8) -> u8| -> Bits {
+        resize(a.clone(), width)
+            .into_iter()
+            .zip(resize(b.clone(), width))
+            .map(|(a, b)| f(a, b))
+            .collect()
+    };
+    match op {
+        BinaryOp::And => bitwise(bit_and),
+        BinaryOp::Or => bitwise(bit_or),
+        BinaryOp::Xor => bitwise(bit_xor),
+        BinaryOp::LogicAnd => vec![bit_and(to_bool(&a), to_bool(&b))],
+        BinaryOp::LogicOr => vec![bit_or(to_bool(&a), to_bool(&b))],
+        BinaryOp::Shl | BinaryOp::Shr => {
+            if !is_known(&b) {
+                return vec![X; a.len()];
+            }
+            let limbs = to_limbs(&b, b.len().div_ceil(64));
+            let amount = if limbs.iter().skip(1).any(|l| *l != 0) {
+                usize::MAX
+            } else {
+                limbs.first().cloned().unwrap_or(0) as usize
+            };
+            let width = a.len();
+            let amount = std::cmp::min(amount, width);
+            let mut out = vec![ZERO; width];
+            if op == BinaryOp::Shl {
+                out[amount..].copy_from_slice(&a[..width - amount]);
+            } else {
+                out[..width - amount].copy_from_slice(&a[amount..]);
+            }
+            out
+        }
+        _ => {
+            let is_comparison = matches!(
+                op,
+                BinaryOp::Eq
+                    | BinaryOp::Ne
+                    | BinaryOp::Lt
+                    | BinaryOp::Le
+                    | BinaryOp::Gt
+                    | BinaryOp::Ge
+            );
+            let out_width = if is_comparison { 1 } else { width };
+            if !is_known(&a) || !is_known(&b) {
+                return vec![X; out_width];
+            }
+            // one extra limb, so that the division can shift without overflowing
+            let num_limbs = width.div_ceil(64) + 1;
+            let a = to_limbs(&a, num_limbs);
+            let b = to_limbs(&b, num_limbs);
+            let from_bool = |value: bool| vec![if value { ONE } else { ZERO }];
+            match op {
+                BinaryOp::Eq => from_bool(a == b),
+                BinaryOp::Ne => from_bool(a != b),
+                BinaryOp::Lt => from_bool(cmp_limbs(&a, &b) == Ordering::Less),
+                BinaryOp::Le => from_bool(cmp_limbs(&a, &b) != Ordering::Greater),
+                BinaryOp::Gt => from_bool(cmp_limbs(&a, &b) == Ordering::Greater),
+                BinaryOp::Ge => from_bool(cmp_limbs(&a, &b) != Ordering::Less),
+                BinaryOp::Add => from_limbs(&add_limbs(&a, &b), width),
+                BinaryOp::Sub => from_limbs(&sub_limbs(&a, &b), width),
+                BinaryOp::Mul => from_limbs(&mul_limbs(&a, &b), width),
+                BinaryOp::Div | BinaryOp::Rem => {
+                    if b.iter().all(|l| *l == 0) {
+                        // division by zero results in all `x`, just like in Verilog
+                        vec![X; width]
+                    } else {
+                        let (quotient, remainder) = div_rem_limbs(&a, &b, width);
+                        if op == BinaryOp::Div {
+                            from_limbs(&quotient, width)
+                        } else {
+                            from_limbs(&remainder, width)
+                        }
+                    }
+                }
+                _ => unreachable!("{op:?} is handled above"),
+            }
+        }
+    }
+}
+
+fn to_limbs(bits: &[u8], num_limbs: usize) -> Vec<u64> {
+    let mut limbs = vec![0u64; num_limbs];
+    for (ii, b) in bits.iter().enumerate() {
+        if logic(*b) == ONE {
+            limbs[ii / 64] |= 1 << (ii % 64);
+        }
+    }
+    limbs
+}
+
+fn from_limbs(limbs: &[u64], width: usize) -> Bits {
+    (0..width)
+        .map(|ii| ((limbs[ii / 64] >> (ii % 64)) & 1) as u8)
+        .collect()
+}
+
+fn cmp_limbs(a: &[u64], b: &[u64]) -> Ordering {
+    a.iter().rev().cmp(b.iter().rev())
+}
+
+fn add_limbs(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let mut carry = false;
+    a.iter()
+        .zip(b.iter())
+        .map(|(a, b)| {
+            let (sum, c0) = a.overflowing_add(*b);
+            let (sum, c1) = sum.overflowing_add(carry as u64);
+            carry = c0 || c1;
+            sum
+        })
+        .collect()
+}
+
+fn sub_limbs(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let mut borrow = false;
+    a.iter()
+        .zip(b.iter())
+        .map(|(a, b)| {
+            let (diff, b0) = a.overflowing_sub(*b);
+            let (diff, b1) = diff.overflowing_sub(borrow as u64);
+            borrow = b0 || b1;
+            diff
+        })
+        .collect()
+}
+
+fn mul_limbs(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let n = a.len();
+    let mut out = vec![0u64; n];
+    for (ii, a) in a.iter().enumerate() {
+        let mut carry = 0u128;
+        for (jj, b) in b.iter().enumerate().take(n - ii) {
+            let value = (*a as u128) * (*b as u128) + out[ii + jj] as u128 + carry;
+            out[ii + jj] = value as u64;
+            carry = value >> 64;
+        }
+    }
+    out
+}
+
+/// Long division, one bit at a time.
+fn div_rem_limbs(a: &[u64], b: &[u64], width: usize) -> (Vec<u64>, Vec<u64>) {
+    let mut quotient = vec![0u64; a.len()];
+    let mut remainder = vec![0u64; a.len()];
+    for ii in (0..width).rev() {
+        // remainder = (remainder << 1) | a[ii]
+        let mut carry = (a[ii / 64] >> (ii % 64)) & 1;
+        for limb in remainder.iter_mut() {
+            let next = *limb >> 63;
+            *limb = (*limb << 1) | carry;
+            carry = next;
+        }
+        if cmp_limbs(&remainder, b) != Ordering::Less {
+            remainder = sub_limbs(&remainder, b);
+            quotient[ii / 64] |= 1 << (ii % 64);
+        }
+    }
+    (quotient, remainder)
+}
+
+fn unpack(value: SignalValue, out: &mut Bits) {
+    let (states, data, bits) = match value {
+        SignalValue::Binary(data, bits) => (States::Two, data, bits),
+        SignalValue::FourValue(data, bits) => (States::Four, data, bits),
+        SignalValue::NineValue(data, bits) => (States::Nine, data, bits),
+        other => unreachable!("expected a bit vector, not {other:?}"),
+    };
+    let per_byte = states.bits_in_a_byte();
+    out.clear();
+    for ii in 0..bits as usize {
+        let byte = data[data.len() - 1 - ii / per_byte];
+        out.push((byte >> ((ii % per_byte) * states.bits())) & states.mask());
+    }
+}
+
+fn pack(states: States, value: &[u8], out: &mut Vec<u8>) {
+    let per_byte = states.bits_in_a_byte();
+    out.clear();
+    out.resize(states.bytes_required(value.len()), 0);
+    let len = out.len();
+    for (ii, state) in value.iter().enumerate() {
+        out[len - 1 - ii / per_byte] |= state << ((ii % per_byte) * states.bits());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token {
+    Ident(String),
+    Literal(String),
+    Punct(&'static str),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Ident(s) | Token::Literal(s) => write!(f, "{s}"),
+            Token::Punct(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+/// Multi-character operators need to come first. Delays, implications and clocking events are
+/// only used by properties.
+const PUNCTUATION: [&str; 34] = [
+    "|->", "|=>", "##", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "(", ")", "[", "]", "{",
+    "}", ",", ":", "?", ".", "~", "!", "&", "|", "^", "+", "-", "*", "/", "%", "<", ">", "@",
+];
+
+fn tokenize(expression: &str) -> Result<Vec<(Token, usize)>> {
+    let mut out = vec![];
+    let mut pos = 0;
+    while let Some(c) = expression[pos..].chars().next() {
+        let rest = &expression[pos..];
+        if c.is_whitespace() {
+            pos += c.len_utf8();
+        } else if c.is_ascii_alphabetic() || c == '_' || c == '$' {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '$'))
+                .unwrap_or(rest.len());
+            out.push((Token::Ident(rest[..len].to_string()), pos));
+            pos += len;
+        } else if c.is_ascii_digit() || c == '\'' {
+            let mut len = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '_'))
+                .unwrap_or(rest.len());
+            if rest[len..].starts_with('\'') {
+                // quote, base and digits
+                len += 1;
+                len += rest[len..]
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len() - len);
+            }
+            out.push((Token::Literal(rest[..len].to_string()), pos));
+            pos += len;
+        } else if let Some(p) = PUNCTUATION.iter().find(|p| rest.starts_with(**p)) {
+            out.push((Token::Punct(p), pos));
+            pos += p.len();
+        } else {
+            return Err(ExprError::UnexpectedChar(c, pos));
+        }
+    }
+    Ok(out)
+}
+
+/// Parses a Verilog style literal into bits.
+fn parse_literal(text: &str) -> Result<Bits> {
+    let err = || ExprError::InvalidLiteral(text.to_string());
+    let clean = text.replace('_', "");
+    let Some((size, rest)) = clean.split_once('\'') else {
+        let value: u128 = clean.parse().map_err(|_| err())?;
+        return Ok(bits_from_u128(value, None));
+    };
+    let size: Option<usize> = if size.is_empty() {
+        None
+    } else {
+        Some(size.parse().map_err(|_| err())?)
+    };
+    let mut chars = rest.chars();
+    let base = chars.next().ok_or_else(err)?.to_ascii_lowercase();
+    let digits = chars.as_str().to_ascii_lowercase();
+    if digits.is_empty() || size == Some(0) {
+        return Err(err());
+    }
+    let mut bits = if base == 'd' {
+        match digits.as_str() {
+            "x" => vec![X],
+            "z" => vec![Z],
+            _ => bits_from_u128(digits.parse().map_err(|_| err())?, None),
+        }
+    } else {
+        let bits_per_digit = match base {
+            'b' => 1,
+            'o' => 3,
+            'h' => 4,
+            _ => return Err(err()),
+        };
+        let mut bits = vec![];
+        for digit in digits.chars().rev() {
+            match digit {
+                'x' => bits.extend(std::iter::repeat_n(X, bits_per_digit)),
+                'z' => bits.extend(std::iter::repeat_n(Z, bits_per_digit)),
+                _ => {
+                    let value = digit.to_digit(1 << bits_per_digit).ok_or_else(err)?;
+                    bits.extend((0..bits_per_digit).map(|ii| ((value >> ii) & 1) as u8));
+                }
+            }
+        }
+        bits
+    };
+    if let Some(size) = size {
+        // unknown values are extended with unknown values
+        let fill = match bits.last() {
+            Some(&X) => X,
+            Some(&Z) => Z,
+            _ => ZERO,
+        };
+        bits.resize(size, fill);
+    }
+    Ok(bits)
+}
+
+fn bits_from_u128(value: u128, width: Option<usize>) -> Bits {
+    let width = width.unwrap_or(std::cmp::max(1, 128 - value.leading_zeros() as usize));
+    (0..width)
+        .map(|ii| {
+            if ii < 128 {
+                ((value >> ii) & 1) as u8
+            } else {
+                ZERO
+            }
+        })
+        .collect()
+}
+
+pub(crate) struct Parser<'a> {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    hierarchy: &'a Hierarchy,
+    inputs: Vec<Input>,
+    /// whether system functions that refer to earlier clock cycles are allowed
+    sampled: bool,
+}
+
+impl<'a> Parser<'a> {
+    pub(crate) fn new(text: &str, hierarchy: &'a Hierarchy, sampled: bool) -> Result<Self> {
+        Ok(Self {
+            tokens: tokenize(text)?,
+            pos: 0,
+            hierarchy,
+            inputs: vec![],
+            sampled,
+        })
+    }
+
+    pub(crate) fn peek(&self) -> Option<&Token> {
+        self.peek_nth(0)
+    }
+
+    /// Looks `n` tokens ahead.
+    pub(crate) fn peek_nth(&self, n: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + n).map(|(token, _)| token)
+    }
+
+    pub(crate) fn peek_punct(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some((Token::Punct(p), _)) => Some(p),
+            _ => None,
+        }
+    }
+
+    /// Consumes an identifier that is used as a keyword, like `posedge`.
+    pub(crate) fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(name)) if name == keyword) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(crate) fn is_done(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    pub(crate) fn eat(&mut self, punct: &str) -> bool {
+        if self.peek_punct() == Some(punct) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(crate) fn expect(&mut self, punct: &'static str) -> Result<()> {
+        if self.eat(punct) {
+            Ok(())
+        } else {
+            Err(self.unexpected(punct))
+        }
+    }
+
+    pub(crate) fn unexpected(&self, expected: &'static str) -> ExprError {
+        match self.tokens.get(self.pos) {
+            Some((token, pos)) => ExprError::UnexpectedToken(token.to_string(), *pos, expected),
+            None => ExprError::UnexpectedEnd(expected),
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Node> {
+        let cond = self.parse_binary(0)?;
+        if self.eat("?") {
+            let a = self.parse_expr()?;
+            self.expect(":")?;
+            let b = self.parse_expr()?;
+            Ok(Node::Ternary(Box::new(cond), Box::new(a), Box::new(b)))
+        } else {
+            Ok(cond)
+        }
+    }
+
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Node> {
+        let mut lhs = self.parse_unary()?;
+        while let Some((precedence, op)) = self.peek_punct().and_then(BinaryOp::from_token) {
+            if precedence < min_precedence {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.parse_binary(precedence + 1)?;
+            lhs = Node::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Node> {
+        let op = match self.peek_punct() {
+            Some("~") => UnaryOp::Not,
+            Some("!") => UnaryOp::LogicNot,
+            Some("-") => UnaryOp::Neg,
+            Some("&") => UnaryOp::ReduceAnd,
+            Some("|") => UnaryOp::ReduceOr,
+            Some("^") => UnaryOp::ReduceXor,
+            _ => return self.parse_primary(),
+        };
+        self.pos += 1;
+        Ok(Node::Unary(op, Box::new(self.parse_unary()?)))
+    }
+
+    fn parse_primary(&mut self) -> Result<Node> {
+        const EXPECTED: &str = "a signal, literal, `(` or `{`";
+        let Some((token, _)) = self.tokens.get(self.pos).cloned() else {
+            return Err(self.unexpected(EXPECTED));
+        };
+        match token {
+            Token::Punct("(") => {
+                self.pos += 1;
+                let e = self.parse_expr()?;
+                self.expect(")")?;
+                Ok(e)
+            }
+            Token::Punct("{") => {
+                self.pos += 1;
+                let mut parts = vec![self.parse_expr()?];
+                while self.eat(",") {
+                    parts.push(self.parse_expr()?);
+                }
+                self.expect("}")?;
+                Ok(Node::Concat(parts))
+            }
+            Token::Literal(text) => {
+                self.pos += 1;
+                Ok(Node::Const(parse_literal(&text)?))
+            }
+            Token::Ident(name) if name.starts_with('$') => {
+                self.pos += 1;
+                self.parse_system_function(name)
+            }
+            Token::Ident(_) => self.parse_signal(),
+            _ => Err(self.unexpected(EXPECTED)),
+        }
+    }
+
+    fn parse_system_function(&mut self, name: String) -> Result<Node> {
+        let f = match name.as_str() {
+            "$rose" => Some(SampledFn::Rose),
+            "$fell" => Some(SampledFn::Fell),
+            "$stable" => Some(SampledFn::Stable),
+            "$changed" => Some(SampledFn::Changed),
+            "$past" => None,
+            _ => return Err(ExprError::UnknownFunction(name)),
+        };
+        if !self.sampled {
+            return Err(ExprError::SampledFunction(name));
+        }
+        self.expect("(")?;
+        let e = self.parse_expr()?;
+        let cycles = if f.is_none() && self.eat(",") {
+            self.parse_index()?
+        } else {
+            1
+        };
+        self.expect(")")?;
+        let before = self.delayed(&e, cycles);
+        Ok(match f {
+            Some(f) => Node::Sampled(f, Box::new(e), Box::new(before)),
+            None => before,
+        })
+    }
+
+    /// Returns a copy of `node` in which all inputs are delayed by `cycles`.
+    fn delayed(&mut self, node: &Node, cycles: u32) -> Node {
+        match node {
+            Node::Input(ii) => {
+                let input = Input {
+                    delay: self.inputs[*ii].delay + cycles,
+                    ..self.inputs[*ii]
+                };
+                Node::Input(self.add_input(input))
+            }
+            Node::Const(bits) => Node::Const(bits.clone()),
+            Node::Unary(op, e) => Node::Unary(*op, Box::new(self.delayed(e, cycles))),
+            Node::Binary(op, a, b) => Node::Binary(
+                *op,
+                Box::new(self.delayed(a, cycles)),
+                Box::new(self.delayed(b, cycles)),
+            ),
+            Node::Ternary(c, a, b) => Node::Ternary(
+                Box::new(self.delayed(c, cycles)),
+                Box::new(self.delayed(a, cycles)),
+                Box::new(self.delayed(b, cycles)),
+            ),
+            Node::Concat(parts) => {
+                Node::Concat(parts.iter().map(|p| self.delayed(p, cycles)).collect())
+            }
+            Node::Sampled(f, now, before) => Node::Sampled(
+                *f,
+                Box::new(self.delayed(now, cycles)),
+                Box::new(self.delayed(before, cycles)),
+            ),
+        }
+    }
+
+    fn add_input(&mut self, input: Input) -> usize {
+        match self.inputs.iter().position(|i| *i == input) {
+            Some(index) => index,
+            None => {
+                self.inputs.push(input);
+                self.inputs.len() - 1
+            }
+        }
+    }
+
+    fn parse_ident(&mut self) -> Result<String> {
+        match self.tokens.get(self.pos) {
+            Some((Token::Ident(name), _)) => {
+                self.pos += 1;
+                Ok(name.clone())
+            }
+            _ => Err(self.unexpected("an identifier")),
+        }
+    }
+
+    pub(crate) fn parse_index(&mut self) -> Result<u32> {
+        match self.tokens.get(self.pos) {
+            Some((Token::Literal(text), _)) => {
+                self.pos += 1;
+                text.parse()
+                    .map_err(|_| ExprError::InvalidLiteral(text.clone()))
+            }
+            _ => Err(self.unexpected("a bit index")),
+        }
+    }
+
+    /// Parses a hierarchical path like `top.cpu.valid` and looks up the variable.
+    pub(crate) fn parse_var(&mut self) -> Result<(String, &'a Var)> {
+        let mut names = vec![self.parse_ident()?];
+        while self.eat(".") {
+            names.push(self.parse_ident()?);
+        }
+        let full_name = names.join(".");
+        let (name, path) = names.split_last().unwrap();
+        let var = self
+            .hierarchy
+            .lookup_var(path, name)
+            .map(|v| &self.hierarchy[v])
+            .ok_or_else(|| ExprError::UnknownSignal(full_name.clone()))?;
+        Ok((full_name, var))
+    }
+
+    fn parse_signal(&mut self) -> Result<Node> {
+        let (full_name, var) = self.parse_var()?;
+        let SignalEncoding::BitVector(width) = var.signal_encoding() else {
+            return Err(ExprError::NotABitVector(full_name));
+        };
+        let width = width.get();
+
+        let slice = if self.eat("[") {
+            let msb = self.parse_index()?;
+            let lsb = if self.eat(":") {
+                self.parse_index()?
+            } else {
+                msb
+            };
+            self.expect("]")?;
+            if msb < lsb || msb >= width {
+                return Err(ExprError::InvalidSlice(full_name, msb, lsb, width));
+            }
+            Some((msb, lsb))
+        } else {
+            None
+        };
+
+        let input = match slice {
+            // selecting all bits is the same as not slicing at all
+            Some((msb, lsb)) if msb - lsb + 1 < width => Input {
+                signal: var.signal_ref(),
+                slice: Some((msb, lsb)),
+                width: msb - lsb + 1,
+                delay: 0,
+            },
+            _ => Input {
+                signal: var.signal_ref(),
+                slice: None,
+                width,
+                delay: 0,
+            },
+        };
+        Ok(Node::Input(self.add_input(input)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bits(value: &str) -> Bits {
+        value
+            .chars()
+            .rev()
+            .map(|c| match c {
+                '0' => ZERO,
+                '1' => ONE,
+                'x' => X,
+                'z' => Z,
+                'h' => H,
+                'l' => L,
+                other => panic!("unexpected {other}"),
+            })
+            .collect()
+    }
+
+    fn eval_const(expression: &str) -> String {
+        let hierarchy = crate::hierarchy::HierarchyBuilder::new(crate::FileFormat::Vcd).finish();
+        let expr = Expr::parse(expression, &hierarchy).unwrap();
+        let result = expr.eval(&expr.root, &[]);
+        assert_eq!(result.len() as u32, expr.width(), "{expression}");
+        result
+            .iter()
+            .rev()
+            .map(|b| ['0', '1', 'x', 'z', 'h', 'u', 'w', 'l', '-'][*b as usize])
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_literal() {
+        assert_eq!(parse_literal("0").unwrap(), bits("0"));
+        assert_eq!(parse_literal("12").unwrap(), bits("1100"));
+        assert_eq!(parse_literal("4'b10x1").unwrap(), bits("10x1"));
+        assert_eq!(parse_literal("8'hf").unwrap(), bits("00001111"));
+        assert_eq!(parse_literal("8'hx").unwrap(), bits("xxxxxxxx"));
+        assert_eq!(parse_literal("6'o7_1").unwrap(), bits("111001"));
+        assert_eq!(parse_literal("3'd9").unwrap(), bits("001"));
+        assert_eq!(parse_literal("'hff").unwrap(), bits("11111111"));
+        assert!(parse_literal("4'b2").is_err());
+        assert!(parse_literal("4'q1").is_err());
+    }
+
+    #[test]
+    fn test_eval_constants() {
+        assert_eq!(eval_const("4'b1100 & 4'b1010"), "1000");
+        assert_eq!(eval_const("4'b1100 | 4'b10x0"), "11x0");
+        assert_eq!(eval_const("4'b1100 ^ 4'b1010"), "0110");
+        assert_eq!(eval_const("~4'b10zx"), "01xx");
+        assert_eq!(eval_const("4'b0 & 4'bxxxx"), "0000");
+        assert_eq!(eval_const("4'd3 + 4'd14"), "0001");
+        assert_eq!(eval_const("4'd3 - 4'd4"), "1111");
+        assert_eq!(eval_const("-4'd1"), "1111");
+        assert_eq!(eval_const("8'd12 * 8'd11"), "10000100");
+        assert_eq!(eval_const("8'd100 / 8'd7"), "00001110");
+        assert_eq!(eval_const("8'd100 % 8'd7"), "00000010");
+        assert_eq!(eval_const("8'd100 / 8'd0"), "xxxxxxxx");
+        assert_eq!(eval_const("4'd3 + 4'b000x"), "xxxx");
+        assert_eq!(eval_const("4'b0011 << 2"), "1100");
+        assert_eq!(eval_const("4'b1x11 >> 1"), "01x1");
+        assert_eq!(eval_const("4'b1011 << 1'bx"), "xxxx");
+        assert_eq!(eval_const("{2'b10, 3'b0z1}"), "100z1");
+        assert_eq!(eval_const("1 ? 4'b1010 : 4'b0110"), "1010");
+        assert_eq!(eval_const("0 ? 4'b1010 : 4'b0110"), "0110");
+        assert_eq!(eval_const("1'bx ? 4'b1010 : 4'b0110"), "xx10");
+        assert_eq!(eval_const("3 == 4'b0011"), "1");
+        assert_eq!(eval_const("3 != 4'b0011"), "0");
+        assert_eq!(eval_const("3 == 4'b00x1"), "x");
+        assert_eq!(eval_const("3 < 4 && 5 >= 5"), "1");
+        assert_eq!(eval_const("3 > 4 || !(5 <= 4)"), "1");
+        assert_eq!(eval_const("1'b0 && 1'bx"), "0");
+        assert_eq!(eval_const("1'b1 || 1'bz"), "1");
+        assert_eq!(eval_const("&4'b1111"), "1");
+        assert_eq!(eval_const("|4'b0x00"), "x");
+        assert_eq!(eval_const("^4'b1011"), "1");
+        // precedence
+        // unsized literals only use as many bits as they need
+        assert_eq!(eval_const("1 + 2 * 3"), "11");
+        assert_eq!(eval_const("(1 + 2) * 3"), "01");
+        assert_eq!(eval_const("4'd1 + 4'd2 * 4'd3"), "0111");
+    }
+
+    #[test]
+    fn test_eval_wide_arithmetic() {
+        assert_eq!(
+            eval_const("72'hff_ffff_ffff_ffff_ffff + 1"),
+            "000000000000000000000000000000000000000000000000000000000000000000000000"
+        );
+        assert_eq!(
+            eval_const("72'h80_0000_0000_0000_0000 / 72'd2"),
+            format!("01{}", "0".repeat(70))
+        );
+        assert_eq!(
+            eval_const("72'h1_0000_0000_0000_0000 * 72'h80"),
+            format!("1{}", "0".repeat(71))
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let hierarchy = crate::hierarchy::HierarchyBuilder::new(crate::FileFormat::Vcd).finish();
+        assert_eq!(
+            Expr::parse("1 +", &hierarchy).unwrap_err(),
+            ExprError::UnexpectedEnd("a signal, literal, `(` or `{`")
+        );
+        assert_eq!(
+            Expr::parse("(1 + 2", &hierarchy).unwrap_err(),
+            ExprError::UnexpectedEnd(")")
+        );
+        assert_eq!(
+            Expr::parse("1 2", &hierarchy).unwrap_err(),
+            ExprError::UnexpectedToken("2".to_string(), 2, "end of expression")
+        );
+        assert_eq!(
+            Expr::parse("1 # 2", &hierarchy).unwrap_err(),
+            ExprError::UnexpectedChar('#', 2)
+        );
+        assert_eq!(
+            Expr::parse("top.a", &hierarchy).unwrap_err(),
+            ExprError::UnknownSignal("top.a".to_string())
+        );
+        // functions that refer to earlier cycles need a clock
+        assert_eq!(
+            Expr::parse("$rose(1)", &hierarchy).unwrap_err(),
+            ExprError::SampledFunction("$rose".to_string())
+        );
+        assert_eq!(
+            Expr::parse("$clog2(4)", &hierarchy).unwrap_err(),
+            ExprError::UnknownFunction("$clog2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_pack_unpack() {
+        for (states, value) in [
+            (States::Two, "1011001"),
+            (States::Four, "10zx0"),
+            (States::Nine, "1hlxz0"),
+        ] {
+            let value = bits(value);
+            let mut buf = vec![];
+            pack(states, &value, &mut buf);
+            let signal_value = match states {
+                States::Two => SignalValue::Binary(&buf, value.len() as u32),
+                States::Four => SignalValue::FourValue(&buf, value.len() as u32),
+                States::Nine => SignalValue::NineValue(&buf, value.len() as u32),
+            };
+            let mut out = vec![];
+            unpack(signal_value, &mut out);
+            assert_eq!(out, value);
+        }
+    }
+}
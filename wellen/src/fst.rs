@@ -5,121 +5,352 @@
 
 use crate::hierarchy::*;
 use crate::signals::{
-    FixedWidthEncoding, Signal, SignalSource, SignalSourceImplementation, TimeTableIdx,
+    FixedWidthEncoding, Signal, SignalSource, SignalSourceImplementation, SignalValue, Time,
+    TimeTableIdx, time_window_to_indices,
 };
-use crate::vcd::parse_name;
+use crate::vcd::{
+    AttributeHandles, FST_SUP_VAR_DATA_TYPE_BITS, can_fold_array_scope, default_vhdl_type_name,
+    parse_name,
+};
+use crate::viewers::ProgressCount;
 use crate::wavemem::{States, check_if_changed_and_truncate, check_states, write_n_state};
 use crate::{FileFormat, LoadOptions, TimeTable, WellenError};
 use fst_reader::*;
+use rayon::prelude::*;
 use rustc_hash::FxHashMap;
-use std::io::{BufRead, Seek};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::{BufRead, Seek, Write};
+use std::sync::atomic::Ordering;
+
+pub(crate) type Result<T> = std::result::Result<T, WellenError>;
 
-pub type Result<T> = std::result::Result<T, WellenError>;
+fn malformed(section: &'static str, error: impl std::fmt::Display) -> WellenError {
+    WellenError::malformed(FileFormat::Fst, section, None, error)
+}
 
-pub fn read_header<R: BufRead + Seek>(
+/// Uses the block that the reader failed on, if it reports one, to locate the error.
+fn reader_error(section: &'static str, error: ReaderError) -> WellenError {
+    match error {
+        ReaderError::Block(tpe, offset, error) => {
+            let section = match tpe {
+                BlockType::VcData
+                | BlockType::VcDataDynamicAlias
+                | BlockType::VcDataDynamicAlias2 => "value changes",
+                BlockType::Hierarchy | BlockType::HierarchyLZ4 | BlockType::HierarchyLZ4Duo => {
+                    "hierarchy"
+                }
+                _ => "header",
+            };
+            WellenError::malformed(FileFormat::Fst, section, Some(offset), error)
+        }
+        other => malformed(section, other),
+    }
+}
+
+/// Checks whether we are dealing with an FST file.
+pub(crate) fn is_fst_file(input: &mut (impl BufRead + Seek)) -> bool {
+    fst_reader::is_fst_file(input)
+}
+
+pub(crate) fn read_header<R: BufRead + Seek>(
     input: R,
-    _options: &LoadOptions,
+    options: &LoadOptions,
 ) -> Result<(Hierarchy, ReadBodyContinuation<R>)> {
-    let mut reader = FstReader::open_and_read_time_table(input)?;
-    let hierarchy = read_hierarchy(&mut reader)?;
-    let cont = ReadBodyContinuation(reader);
+    let mut reader =
+        FstReader::open_and_read_time_table(input).map_err(|e| reader_error("header", e))?;
+    let hierarchy = read_hierarchy(&mut reader, options)?;
+    let cont = ReadBodyContinuation {
+        reader,
+        filename: None,
+    };
     Ok((hierarchy, cont))
 }
-pub fn read_header_from_file<P: AsRef<std::path::Path>>(
+pub(crate) fn read_header_from_file<P: AsRef<std::path::Path>>(
     filename: P,
-    _options: &LoadOptions,
+    options: &LoadOptions,
 ) -> Result<(
     Hierarchy,
     ReadBodyContinuation<std::io::BufReader<std::fs::File>>,
 )> {
-    let input = std::io::BufReader::new(std::fs::File::open(filename.as_ref())?);
-    let mut reader = match FstReader::open_and_read_time_table(input) {
-        Ok(header) => header,
+    let filename = filename.as_ref();
+    let mut reader = open_file(filename)?;
+    let hierarchy = read_hierarchy(&mut reader, options)?;
+    let cont = ReadBodyContinuation {
+        reader,
+        filename: Some(filename.to_path_buf()),
+    };
+    Ok((hierarchy, cont))
+}
+
+fn open_file(filename: &std::path::Path) -> Result<FstReader<std::io::BufReader<std::fs::File>>> {
+    let input = std::io::BufReader::new(std::fs::File::open(filename)?);
+    match FstReader::open_and_read_time_table(input) {
+        Ok(reader) => Ok(reader),
         Err(ReaderError::MissingGeometry() | ReaderError::MissingHierarchy()) => {
             // Geometric block or hierarchy block missing.
             // This generally indicates that the FST writing process was interrupted.
             // Thus, we try to load an external hierarchy file.
-            let input = std::io::BufReader::new(std::fs::File::open(filename.as_ref())?);
-            let mut hierarchy_filename = filename.as_ref().to_path_buf();
+            let input = std::io::BufReader::new(std::fs::File::open(filename)?);
+            let mut hierarchy_filename = filename.to_path_buf();
             hierarchy_filename.set_extension("fst.hier");
             let hierarchy = std::io::BufReader::new(std::fs::File::open(hierarchy_filename)?);
-            FstReader::open_incomplete_and_read_time_table(input, hierarchy)?
+            FstReader::open_incomplete_and_read_time_table(input, hierarchy)
+                .map_err(|e| reader_error("header", e))
         }
-        Err(e) => return Err(e.into()),
-    };
-    let hierarchy = read_hierarchy(&mut reader)?;
-    let cont = ReadBodyContinuation(reader);
-    Ok((hierarchy, cont))
+        Err(e) => Err(reader_error("header", e)),
+    }
 }
-pub fn read_body<R: BufRead + Seek + Sync + Send + 'static>(
+
+pub(crate) fn read_body<R: BufRead + Seek + Sync + Send + 'static>(
     data: ReadBodyContinuation<R>,
 ) -> Result<(SignalSource, TimeTable)> {
-    let time_table = data.0.get_time_table().unwrap().to_vec();
-    let reader = data.0;
-    let db = FstWaveDatabase::new(reader);
+    let time_table = data.reader.get_time_table().unwrap().to_vec();
+    let db = FstWaveDatabase::new(data.reader, data.filename);
     let boxed_db = Box::new(db);
     let source = SignalSource::new(boxed_db);
     Ok((source, time_table))
 }
 
-pub struct ReadBodyContinuation<R: BufRead + Seek>(FstReader<R>);
+pub(crate) struct ReadBodyContinuation<R: BufRead + Seek> {
+    reader: FstReader<R>,
+    filename: Option<std::path::PathBuf>,
+}
+
+/// Every thread needs to decode the value change blocks for its signals,
+/// thus we only start a new thread for at least this many signals.
+const MIN_SIGNALS_PER_THREAD: usize = 16;
 
 struct FstWaveDatabase<R: BufRead + Seek> {
     reader: FstReader<R>,
+    /// Used to open additional readers for multi-threaded loading.
+    filename: Option<std::path::PathBuf>,
+    /// Readers used by the other threads, opened on demand.
+    thread_readers: Vec<FstReader<std::io::BufReader<std::fs::File>>>,
 }
 
 impl<R: BufRead + Seek> FstWaveDatabase<R> {
-    fn new(reader: FstReader<R>) -> Self {
-        FstWaveDatabase { reader }
+    fn new(reader: FstReader<R>, filename: Option<std::path::PathBuf>) -> Self {
+        FstWaveDatabase {
+            reader,
+            filename,
+            thread_readers: vec![],
+        }
+    }
+}
+
+impl<R: BufRead + Seek + Sync + Send> FstWaveDatabase<R> {
+    /// Loads all changes that the reader reports for signals in `time_window`. With
+    /// `multi_threaded`, the signals are split between several readers of the same file.
+    fn load_signals_in_window(
+        &mut self,
+        ids: &[SignalRef],
+        types: &[SignalEncoding],
+        time_window: Option<(Time, Time)>,
+        multi_threaded: bool,
+        progress: Option<&ProgressCount>,
+    ) -> Result<Vec<Signal>> {
+        let num_threads = match &self.filename {
+            Some(_) if multi_threaded => std::cmp::min(
+                rayon::current_num_threads(),
+                ids.len() / MIN_SIGNALS_PER_THREAD,
+            ),
+            // we can only open more readers if we know the file name
+            _ => 1,
+        };
+        if num_threads <= 1 {
+            return load_signals_from_reader(&mut self.reader, ids, types, time_window, progress);
+        }
+
+        let filename = self.filename.as_ref().unwrap();
+        while self.thread_readers.len() < num_threads - 1 {
+            let reader = open_file(filename)?;
+            self.thread_readers.push(reader);
+        }
+
+        let chunk_size = ids.len().div_ceil(num_threads);
+        let mut chunks = ids.chunks(chunk_size).zip(types.chunks(chunk_size));
+        let (first_ids, first_types) = chunks.next().unwrap();
+        let chunks: Vec<_> = chunks.collect();
+        let (first, others) = rayon::join(
+            || {
+                load_signals_from_reader(
+                    &mut self.reader,
+                    first_ids,
+                    first_types,
+                    time_window,
+                    progress,
+                )
+            },
+            || {
+                self.thread_readers
+                    .par_iter_mut()
+                    .zip(chunks)
+                    .map(|(reader, (ids, types))| {
+                        load_signals_from_reader(reader, ids, types, time_window, progress)
+                    })
+                    .collect::<Result<Vec<_>>>()
+            },
+        );
+        let mut signals = first?;
+        for other in others? {
+            signals.extend(other);
+        }
+        Ok(signals)
     }
 }
 
+/// Loads all changes that the reader reports for signals in `time_window`.
+fn load_signals_from_reader(
+    reader: &mut FstReader<impl BufRead + Seek>,
+    ids: &[SignalRef],
+    types: &[SignalEncoding],
+    time_window: Option<(Time, Time)>,
+    progress: Option<&ProgressCount>,
+) -> Result<Vec<Signal>> {
+    // create a FST filter
+    let fst_ids = ids
+        .iter()
+        .map(|ii| FstSignalHandle::from_index(ii.index()))
+        .collect::<Vec<_>>();
+    let filter = match time_window {
+        None => FstFilter::filter_signals(fst_ids),
+        Some((start, end)) => FstFilter::new(start, end, fst_ids),
+    };
+
+    // lookup data structure for time table indices
+    let tt = reader.get_time_table().unwrap().to_vec();
+    let mut time_table = tt.iter().enumerate();
+    let mut index_and_time = time_table.next();
+
+    // store signals
+    let mut signals = ids
+        .iter()
+        .zip(types.iter())
+        .map(|(id, tpe)| SignalWriter::new(*id, *tpe))
+        .collect::<Vec<_>>();
+    let idx_to_pos: FxHashMap<usize, usize> = FxHashMap::from_iter(
+        ids.iter()
+            .zip(types.iter())
+            .map(|(r, _)| r.index())
+            .enumerate()
+            .map(|(pos, idx)| (idx, pos)),
+    );
+    let mut add_change = |time: u64, handle: FstSignalHandle, value: FstSignalValue| {
+        // determine time index
+        let time_idx = loop {
+            match index_and_time {
+                Some((idx, t)) if *t >= time => break idx as TimeTableIdx,
+                Some(_) => index_and_time = time_table.next(),
+                None => return Err(format!("time {time} is not part of the time table")),
+            }
+        };
+        let signal_pos = idx_to_pos
+            .get(&handle.get_index())
+            .ok_or_else(|| format!("unexpected signal handle: {handle:?}"))?;
+        signals[*signal_pos].add_change(time_idx, handle, value)
+    };
+
+    // the reader does not allow us to abort, thus we only remember the first error
+    let mut result = Ok(());
+    reader
+        .read_signals(&filter, |time, handle, value| {
+            if result.is_ok() {
+                result = add_change(time, handle, value);
+            }
+        })
+        .map_err(|e| reader_error("value changes", e))?;
+    result.map_err(|e| malformed("value changes", e))?;
+    if let Some(progress) = progress {
+        progress.fetch_add(ids.len() as u64, Ordering::SeqCst);
+    }
+    Ok(signals.into_iter().map(|w| w.finish()).collect())
+}
+
 impl<R: BufRead + Seek + Sync + Send> SignalSourceImplementation for FstWaveDatabase<R> {
     fn load_signals(
         &mut self,
         ids: &[SignalRef],
         types: &[SignalEncoding],
-        _multi_threaded: bool,
-    ) -> Vec<Signal> {
-        // create a FST filter
-        let fst_ids = ids
-            .iter()
-            .map(|ii| FstSignalHandle::from_index(ii.index()))
-            .collect::<Vec<_>>();
-        let filter = FstFilter::filter_signals(fst_ids);
+        multi_threaded: bool,
+    ) -> Result<Vec<Signal>> {
+        self.load_signals_in_window(ids, types, None, multi_threaded, None)
+    }
 
-        // lookup data structure for time table indices
-        let tt = self.reader.get_time_table().unwrap().to_vec();
-        let mut time_table = tt.iter().enumerate();
-        let mut index_and_time = time_table.next().unwrap();
+    fn load_signals_with_progress(
+        &mut self,
+        ids: &[SignalRef],
+        types: &[SignalEncoding],
+        multi_threaded: bool,
+        progress: &ProgressCount,
+    ) -> Result<Vec<Signal>> {
+        self.load_signals_in_window(ids, types, None, multi_threaded, Some(progress))
+    }
 
-        // store signals
-        let mut signals = ids
-            .iter()
-            .zip(types.iter())
-            .map(|(id, tpe)| SignalWriter::new(*id, *tpe))
-            .collect::<Vec<_>>();
-        let idx_to_pos: FxHashMap<usize, usize> = FxHashMap::from_iter(
-            ids.iter()
+    fn load_signals_in_range(
+        &mut self,
+        ids: &[SignalRef],
+        types: &[SignalEncoding],
+        start: Time,
+        end: Time,
+        multi_threaded: bool,
+    ) -> Result<Vec<Signal>> {
+        let time_table = self.reader.get_time_table().unwrap();
+        let window = time_window_to_indices(time_table, start, end);
+        let Some((start_idx, end_idx)) = window else {
+            return Ok(ids
+                .iter()
                 .zip(types.iter())
-                .map(|(r, _)| r.index())
-                .enumerate()
-                .map(|(pos, idx)| (idx, pos)),
-        );
-        let callback = |time: u64, handle: FstSignalHandle, value: FstSignalValue| {
-            // determine time index
-            while *(index_and_time.1) < time {
-                index_and_time = time_table.next().unwrap();
-            }
-            let time_idx = index_and_time.0 as TimeTableIdx;
-            debug_assert_eq!(*index_and_time.1, time);
-            let signal_pos = idx_to_pos[&handle.get_index()];
-            signals[signal_pos].add_change(time_idx, handle, value);
+                .map(|(id, tpe)| SignalWriter::new(*id, *tpe).finish())
+                .collect());
         };
+        let end_time = time_table[end_idx as usize];
+        let start_time = time_table[start_idx as usize];
+        let mut signals = self.load_signals_in_window(
+            ids,
+            types,
+            Some((start_time, end_time)),
+            multi_threaded,
+            None,
+        )?;
+
+        // The reader only decodes blocks that overlap with the window. For signals that did not
+        // change in any of them, we search backwards with an exponentially growing window.
+        let mut search_start = start_idx as usize;
+        let mut search_len = (end_idx - start_idx) as usize + 1;
+        loop {
+            let missing: Vec<usize> = signals
+                .iter()
+                .enumerate()
+                .filter(|(_, s)| s.get_offset(start_idx).is_none())
+                .map(|(ii, _)| ii)
+                .collect();
+            if missing.is_empty() || search_start == 0 {
+                break;
+            }
+            search_start = search_start.saturating_sub(search_len);
+            search_len *= 2;
+            let search_start_time = self.reader.get_time_table().unwrap()[search_start];
+            let missing_ids: Vec<_> = missing.iter().map(|ii| ids[*ii]).collect();
+            let missing_types: Vec<_> = missing.iter().map(|ii| types[*ii]).collect();
+            let reloaded = self.load_signals_in_window(
+                &missing_ids,
+                &missing_types,
+                Some((search_start_time, end_time)),
+                multi_threaded,
+                None,
+            )?;
+            for (ii, signal) in missing.into_iter().zip(reloaded) {
+                signals[ii] = signal;
+            }
+        }
 
-        self.reader.read_signals(&filter, callback).unwrap();
-        signals.into_iter().map(|w| w.finish()).collect()
+        for signal in signals.iter_mut() {
+            signal.retain_window(window);
+        }
+        Ok(signals)
     }
+
     fn print_statistics(&self) {
         println!("FST backend currently has not statistics to print.");
     }
@@ -154,7 +385,7 @@ impl SignalWriter {
         time_idx: TimeTableIdx,
         handle: FstSignalHandle,
         value: FstSignalValue,
-    ) {
+    ) -> std::result::Result<(), String> {
         debug_assert_eq!(handle, self.handle);
         if let Some(prev_idx) = self.time_indices.last() {
             debug_assert!(*prev_idx <= time_idx);
@@ -177,18 +408,18 @@ impl SignalWriter {
                 SignalEncoding::BitVector(len) => {
                     let bits = len.get();
 
-                    debug_assert_eq!(
-                        value.len(),
-                        bits as usize,
-                        "{}",
-                        String::from_utf8_lossy(value)
-                    );
-                    let local_encoding = check_states(value).unwrap_or_else(|| {
-                        panic!(
-                            "Unexpected signal value: {}",
+                    if value.len() != bits as usize {
+                        return Err(format!(
+                            "expected a value of {bits} bits, not: {}",
+                            String::from_utf8_lossy(value)
+                        ));
+                    }
+                    let local_encoding = check_states(value).ok_or_else(|| {
+                        format!(
+                            "unexpected signal value: {}",
                             String::from_utf8_lossy(value)
                         )
-                    });
+                    })?;
 
                     let signal_states = States::join(self.max_states, local_encoding);
                     if signal_states != self.max_states {
@@ -239,19 +470,24 @@ impl SignalWriter {
                         self.time_indices.push(time_idx);
                     }
                 }
-                SignalEncoding::Real => panic!(
-                    "Expecting reals, but got: {}",
-                    String::from_utf8_lossy(value)
-                ),
+                SignalEncoding::Real => {
+                    return Err(format!(
+                        "expected a real, not: {}",
+                        String::from_utf8_lossy(value)
+                    ));
+                }
             },
             FstSignalValue::Real(value) => {
-                debug_assert_eq!(self.tpe, SignalEncoding::Real);
+                if self.tpe != SignalEncoding::Real {
+                    return Err(format!("expected a {:?}, not the real {value}", self.tpe));
+                }
                 self.data_bytes.extend_from_slice(&value.to_le_bytes());
                 if check_if_changed_and_truncate(8, &mut self.data_bytes) {
                     self.time_indices.push(time_idx);
                 }
             }
         }
+        Ok(())
     }
 
     fn finish(self) -> Signal {
@@ -291,14 +527,14 @@ impl SignalWriter {
 }
 
 #[inline]
-pub fn get_len_and_meta(states: States, bits: u32) -> (usize, bool) {
+pub(crate) fn get_len_and_meta(states: States, bits: u32) -> (usize, bool) {
     let len = states.bytes_required(bits as usize);
     let has_meta = (states != States::Two) && ((bits as usize) % states.bits_in_a_byte() == 0);
     (len, has_meta)
 }
 
 #[inline]
-pub fn get_bytes_per_entry(len: usize, has_meta: bool) -> usize {
+pub(crate) fn get_bytes_per_entry(len: usize, has_meta: bool) -> usize {
     if has_meta { len + 1 } else { len }
 }
 
@@ -346,14 +582,14 @@ fn expand_entries(from: States, to: States, old: &[u8], entries: usize, bits: u3
     data
 }
 
-pub fn push_zeros(vec: &mut Vec<u8>, len: usize) {
+pub(crate) fn push_zeros(vec: &mut Vec<u8>, len: usize) {
     for _ in 0..len {
         vec.push(0);
     }
 }
 
-fn convert_scope_tpe(tpe: FstScopeType) -> ScopeType {
-    match tpe {
+fn convert_scope_tpe(tpe: FstScopeType) -> Option<ScopeType> {
+    let tpe = match tpe {
         FstScopeType::Module => ScopeType::Module,
         FstScopeType::Task => ScopeType::Task,
         FstScopeType::Function => ScopeType::Function,
@@ -379,8 +615,9 @@ fn convert_scope_tpe(tpe: FstScopeType) -> ScopeType {
         FstScopeType::AttributeBegin
         | FstScopeType::AttributeEnd
         | FstScopeType::VcdScope
-        | FstScopeType::VcdUpScope => unreachable!("unexpected scope type!"),
-    }
+        | FstScopeType::VcdUpScope => return None,
+    };
+    Some(tpe)
 }
 
 fn convert_var_tpe(tpe: FstVarType) -> VarType {
@@ -431,7 +668,7 @@ fn convert_var_direction(tpe: FstVarDirection) -> VarDirection {
 
 /// GHDL does not seem to encode any actual information in the VHDL variable type.
 /// Variables are always Signal or None.
-pub fn deal_with_vhdl_var_type(tpe: FstVhdlVarType, var_name: &str) {
+pub(crate) fn deal_with_vhdl_var_type(tpe: FstVhdlVarType, var_name: &str) {
     if !matches!(tpe, FstVhdlVarType::None | FstVhdlVarType::Signal) {
         println!("INFO: detected a VHDL Var Type that is not Signal!: {tpe:?} for {var_name}");
     }
@@ -439,7 +676,7 @@ pub fn deal_with_vhdl_var_type(tpe: FstVhdlVarType, var_name: &str) {
 
 /// GHDL only uses a small combination of VCD variable and VHDL data types.
 /// Here we merge them together into a single VarType.
-pub fn merge_vhdl_data_and_var_type(vcd: VarType, vhdl: FstVhdlDataType) -> VarType {
+pub(crate) fn merge_vhdl_data_and_var_type(vcd: VarType, vhdl: FstVhdlDataType) -> VarType {
     match vhdl {
         FstVhdlDataType::None => vcd,
         FstVhdlDataType::Boolean => VarType::Boolean,
@@ -476,49 +713,34 @@ pub fn merge_vhdl_data_and_var_type(vcd: VarType, vhdl: FstVhdlDataType) -> VarT
     }
 }
 
-fn convert_timescale(exponent: i8) -> Timescale {
-    if exponent >= 0 {
-        Timescale::new(10u32.pow(exponent as u32), TimescaleUnit::Seconds)
+/// Returns `None` if the exponent is outside of the range that we can represent.
+fn convert_timescale(exponent: i8) -> Option<Timescale> {
+    let (offset, unit) = if exponent >= 0 {
+        (0, TimescaleUnit::Seconds)
     } else if exponent >= -3 {
-        Timescale::new(
-            10u32.pow((exponent + 3) as u32),
-            TimescaleUnit::MilliSeconds,
-        )
+        (3, TimescaleUnit::MilliSeconds)
     } else if exponent >= -6 {
-        Timescale::new(
-            10u32.pow((exponent + 6) as u32),
-            TimescaleUnit::MicroSeconds,
-        )
+        (6, TimescaleUnit::MicroSeconds)
     } else if exponent >= -9 {
-        Timescale::new(10u32.pow((exponent + 9) as u32), TimescaleUnit::NanoSeconds)
+        (9, TimescaleUnit::NanoSeconds)
     } else if exponent >= -12 {
-        Timescale::new(
-            10u32.pow((exponent + 12) as u32),
-            TimescaleUnit::PicoSeconds,
-        )
+        (12, TimescaleUnit::PicoSeconds)
     } else if exponent >= -15 {
-        Timescale::new(
-            10u32.pow((exponent + 15) as u32),
-            TimescaleUnit::FemtoSeconds,
-        )
+        (15, TimescaleUnit::FemtoSeconds)
     } else if exponent >= -18 {
-        Timescale::new(
-            10u32.pow((exponent + 18) as u32),
-            TimescaleUnit::AttoSeconds,
-        )
+        (18, TimescaleUnit::AttoSeconds)
     } else if exponent >= -21 {
-        Timescale::new(
-            10u32.pow((exponent + 21) as u32),
-            TimescaleUnit::ZeptoSeconds,
-        )
+        (21, TimescaleUnit::ZeptoSeconds)
     } else {
-        panic!("Unexpected timescale exponent: {exponent}");
-    }
+        return None;
+    };
+    let factor = 10u32.checked_pow((exponent as i32 + offset) as u32)?;
+    Some(Timescale::new(factor, unit))
 }
 
 #[derive(Debug)]
 /// Represents an attribute which can from a FST or a VCD with extensions as generated by GTKWave or nvc.
-pub enum Attribute {
+pub(crate) enum Attribute {
     /// nvc: `misc 03 /home/oscar/test.vhdl 1`
     SourceLoc(HierarchyStringId, u64, bool),
     /// nvc: `misc 02 STD_LOGIC_VECTOR 1031`
@@ -526,7 +748,7 @@ pub enum Attribute {
     Enum(EnumTypeId),
 }
 
-pub fn parse_var_attributes(
+pub(crate) fn parse_var_attributes(
     attributes: &mut Vec<Attribute>,
     mut var_type: VarType,
     var_name: &str,
@@ -553,7 +775,7 @@ pub fn parse_var_attributes(
     Ok((type_name, var_type, enum_type))
 }
 
-pub fn parse_scope_attributes(
+pub(crate) fn parse_scope_attributes(
     attributes: &mut Vec<Attribute>,
     h: &mut HierarchyBuilder,
 ) -> crate::vcd::Result<(Option<SourceLocId>, Option<SourceLocId>)> {
@@ -576,19 +798,31 @@ pub fn parse_scope_attributes(
     Ok((declaration_source, instance_source))
 }
 
-fn read_hierarchy<F: BufRead + Seek>(reader: &mut FstReader<F>) -> Result<Hierarchy> {
+fn read_hierarchy<F: BufRead + Seek>(
+    reader: &mut FstReader<F>,
+    options: &LoadOptions,
+) -> Result<Hierarchy> {
     let mut h = HierarchyBuilder::new(FileFormat::Fst);
+    h.set_scope_filter(options);
     // load meta-data
     let fst_header = reader.get_header();
     h.set_version(fst_header.version.trim().to_string());
     h.set_date(fst_header.date.trim().to_string());
-    h.set_timescale(convert_timescale(fst_header.timescale_exponent));
+    let exponent = fst_header.timescale_exponent;
+    let timescale = convert_timescale(exponent).ok_or_else(|| {
+        malformed(
+            "header",
+            format!("unexpected timescale exponent: {exponent}"),
+        )
+    })?;
+    h.set_timescale(timescale);
 
     let mut path_names = FxHashMap::default();
     let mut enums = FxHashMap::default();
     let mut attributes = Vec::new();
 
-    let cb = |entry: FstHierarchyEntry| {
+    let mut add_entry = |entry: FstHierarchyEntry| -> Result<()> {
+        let error = |e: String| malformed("hierarchy", e);
         match entry {
             FstHierarchyEntry::Scope {
                 tpe,
@@ -596,13 +830,16 @@ fn read_hierarchy<F: BufRead + Seek>(reader: &mut FstReader<F>) -> Result<Hierar
                 component,
             } => {
                 let (declaration_source, instance_source) =
-                    parse_scope_attributes(&mut attributes, &mut h).unwrap();
+                    parse_scope_attributes(&mut attributes, &mut h)
+                        .map_err(|e| error(e.to_string()))?;
+                let tpe = convert_scope_tpe(tpe)
+                    .ok_or_else(|| error(format!("unexpected scope type: {tpe:?}")))?;
                 let name_id = h.add_string(name.into());
                 let component_id = h.add_string(component.into());
                 h.add_scope(
                     name_id,
                     Some(component_id),
-                    convert_scope_tpe(tpe),
+                    tpe,
                     declaration_source,
                     instance_source,
                     false,
@@ -618,9 +855,13 @@ fn read_hierarchy<F: BufRead + Seek>(reader: &mut FstReader<F>) -> Result<Hierar
                 ..
             } => {
                 // the fst name often contains the variable name + the index
-                let (var_name, index, scopes) = parse_name(name.as_bytes(), length).unwrap();
+                let (var_name, index, scopes) =
+                    parse_name(name.as_bytes(), length).map_err(|e| error(e.to_string()))?;
                 let (type_name, var_type, enum_type) =
-                    parse_var_attributes(&mut attributes, convert_var_tpe(tpe), &var_name).unwrap();
+                    parse_var_attributes(&mut attributes, convert_var_tpe(tpe), &var_name)
+                        .map_err(|e| error(e.to_string()))?;
+                let signal_ref = SignalRef::from_index(handle.get_index())
+                    .ok_or_else(|| error(format!("invalid signal handle: {handle:?}")))?;
                 let name_id = h.add_string(var_name);
                 let type_name = type_name.map(|s| h.add_string(s.into()));
                 let num_scopes = scopes.len();
@@ -640,7 +881,7 @@ fn read_hierarchy<F: BufRead + Seek>(reader: &mut FstReader<F>) -> Result<Hierar
                     signal_tpe,
                     convert_var_direction(direction),
                     index,
-                    SignalRef::from_index(handle.get_index()).unwrap(),
+                    signal_ref,
                     enum_type,
                     type_name,
                 );
@@ -655,7 +896,9 @@ fn read_hierarchy<F: BufRead + Seek>(reader: &mut FstReader<F>) -> Result<Hierar
                 path_id,
                 line,
             } => {
-                let path = path_names[&path_id];
+                let path = *path_names
+                    .get(&path_id)
+                    .ok_or_else(|| error(format!("unknown path id: {path_id}")))?;
                 attributes.push(Attribute::SourceLoc(path, line, is_instantiation));
             }
             FstHierarchyEntry::Comment { string } => {
@@ -676,7 +919,10 @@ fn read_hierarchy<F: BufRead + Seek>(reader: &mut FstReader<F>) -> Result<Hierar
                 enums.insert(handle, enum_ref);
             }
             FstHierarchyEntry::EnumTableRef { handle } => {
-                attributes.push(Attribute::Enum(enums[&handle]));
+                let enum_ref = *enums
+                    .get(&handle)
+                    .ok_or_else(|| error(format!("unknown enum table: {handle}")))?;
+                attributes.push(Attribute::Enum(enum_ref));
             }
             FstHierarchyEntry::VhdlVarInfo {
                 type_name,
@@ -693,7 +939,840 @@ fn read_hierarchy<F: BufRead + Seek>(reader: &mut FstReader<F>) -> Result<Hierar
                 // deduce array entries from the variable names.
             }
         };
+        Ok(())
     };
-    reader.read_hierarchy(cb)?;
+    // the reader does not allow us to abort, thus we only remember the first error
+    let mut result = Ok(());
+    reader
+        .read_hierarchy(|entry| {
+            if result.is_ok() {
+                result = add_entry(entry);
+            }
+        })
+        .map_err(|e| reader_error("hierarchy", e))?;
+    result?;
     Ok(h.finish())
 }
+
+/// Writes a FST containing every variable in `hierarchy` whose signal is part of `signals`.
+/// Variables without a matching signal are omitted, which allows for writing out a subset of a
+/// waveform. Scope source locations, enum tables, VHDL type names and variable directions
+/// are preserved.
+///
+/// `time_table` needs to be the time table that the signals were loaded against.
+pub fn write<'a, W: Write>(
+    out: &mut W,
+    hierarchy: &Hierarchy,
+    time_table: &[Time],
+    signals: impl IntoIterator<Item = &'a Signal>,
+) -> std::io::Result<()> {
+    write_with_block_size(out, hierarchy, time_table, signals, DEFAULT_BLOCK_SIZE)
+}
+
+/// Loads all signals in `hierarchy` from `source` and writes them to a FST.
+/// See [`write`] for details.
+pub fn write_from_source<W: Write>(
+    out: &mut W,
+    hierarchy: &Hierarchy,
+    time_table: &[Time],
+    source: &mut SignalSource,
+    multi_threaded: bool,
+) -> crate::Result<()> {
+    let loaded = source.load_all_signals(hierarchy, multi_threaded)?;
+    write(out, hierarchy, time_table, loaded.iter().map(|(_, s)| s))?;
+    Ok(())
+}
+
+/// Once the uncompressed value changes of a block exceed this size, we start a new block.
+const DEFAULT_BLOCK_SIZE: usize = 16 * 1024 * 1024;
+/// Ensures that time deltas always fit into the `u32` that the reader expects.
+const MAX_TIME_STEPS_PER_BLOCK: usize = 1 << 24;
+const ZLIB_LEVEL: u8 = 4;
+
+const BLOCK_TPE_HEADER: u8 = 0;
+const BLOCK_TPE_VC_DATA: u8 = 1;
+const BLOCK_TPE_GEOMETRY: u8 = 3;
+const BLOCK_TPE_HIERARCHY_LZ4: u8 = 6;
+
+const HEADER_LENGTH: u64 = 329;
+const HEADER_VERSION_MAX_LEN: usize = 128;
+const HEADER_DATE_MAX_LEN: usize = 119;
+const FILE_TYPE_VERILOG: u8 = 0;
+const FILE_TYPE_VHDL: u8 = 1;
+
+const HIERARCHY_TPE_SCOPE: u8 = 254;
+const HIERARCHY_TPE_UP_SCOPE: u8 = 255;
+const HIERARCHY_TPE_ATTRIBUTE_BEGIN: u8 = 252;
+const ATTRIBUTE_TPE_MISC: u8 = 0;
+const MISC_TPE_SUP_VAR: u8 = 2;
+const MISC_TPE_PATH_NAME: u8 = 3;
+const MISC_TPE_SOURCE_STEM: u8 = 4;
+const MISC_TPE_SOURCE_INSTANTIATION_STEM: u8 = 5;
+const MISC_TPE_ENUM_TABLE: u8 = 7;
+
+/// Single bit values other than `0` and `1` are encoded as an index into this table.
+const ONE_BIT_STATES: [u8; 8] = *b"xzhuwl-?";
+
+fn write_with_block_size<'a, W: Write>(
+    out: &mut W,
+    hierarchy: &Hierarchy,
+    time_table: &[Time],
+    signals: impl IntoIterator<Item = &'a Signal>,
+    block_size: usize,
+) -> std::io::Result<()> {
+    let signals: FxHashMap<SignalRef, &Signal> =
+        signals.into_iter().map(|s| (s.signal_ref(), s)).collect();
+
+    // the hierarchy determines the handle of every signal
+    let mut h = HierarchyWriter::new(hierarchy, &signals);
+    h.write_items(hierarchy.items(), "");
+    let handle_signals: Vec<_> = h.handles.iter().map(|(r, _)| signals[r]).collect();
+    let encodings: Vec<_> = h.handles.iter().map(|(_, e)| *e).collect();
+
+    let (exponent, time_factor) = timescale_to_exponent(hierarchy.timescale());
+    let times = time_table
+        .iter()
+        .map(|t| {
+            t.checked_mul(time_factor).ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("[fst] time {t} times the timescale factor {time_factor} overflows"),
+                )
+            })
+        })
+        .collect::<std::io::Result<Vec<Time>>>()?;
+    let blocks = write_value_change_blocks(&times, &handle_signals, &encodings, block_size)?;
+
+    // header
+    let mut header = Vec::with_capacity(HEADER_LENGTH as usize + 1);
+    header.push(BLOCK_TPE_HEADER);
+    write_u64(&mut header, HEADER_LENGTH);
+    write_u64(&mut header, times.first().cloned().unwrap_or_default());
+    write_u64(&mut header, times.last().cloned().unwrap_or_default());
+    header.extend_from_slice(&std::f64::consts::E.to_le_bytes());
+    write_u64(&mut header, block_size as u64);
+    write_u64(&mut header, h.scope_count);
+    write_u64(&mut header, h.var_count);
+    write_u64(&mut header, encodings.len() as u64);
+    write_u64(&mut header, blocks.len() as u64);
+    header.push(exponent as u8);
+    let version = if hierarchy.version().is_empty() {
+        format!("wellen {}", crate::VERSION)
+    } else {
+        hierarchy.version().to_string()
+    };
+    write_c_str_fixed_length(&mut header, &version, HEADER_VERSION_MAX_LEN);
+    write_c_str_fixed_length(&mut header, hierarchy.date(), HEADER_DATE_MAX_LEN);
+    header.push(if hierarchy.file_format() == FileFormat::Ghw {
+        FILE_TYPE_VHDL
+    } else {
+        FILE_TYPE_VERILOG
+    });
+    write_u64(&mut header, 0); // time zero
+    debug_assert_eq!(header.len(), HEADER_LENGTH as usize + 1);
+    out.write_all(&header)?;
+
+    for block in blocks.iter() {
+        out.write_all(block)?;
+    }
+
+    // geometry
+    let mut geometry = Vec::with_capacity(encodings.len() * 2);
+    for encoding in encodings.iter() {
+        let value = match encoding {
+            SignalEncoding::String => u32::MAX,
+            SignalEncoding::Real => 0,
+            SignalEncoding::BitVector(len) => len.get(),
+        };
+        write_varint(&mut geometry, value as u64);
+    }
+    let compressed = zlib_compress_if_smaller(&geometry);
+    out.write_all(&[BLOCK_TPE_GEOMETRY])?;
+    out.write_all(&(compressed.len() as u64 + 3 * 8).to_be_bytes())?;
+    out.write_all(&(geometry.len() as u64).to_be_bytes())?;
+    out.write_all(&(encodings.len() as u64).to_be_bytes())?;
+    out.write_all(&compressed)?;
+
+    // hierarchy
+    let compressed = lz4_flex::compress(&h.bytes);
+    out.write_all(&[BLOCK_TPE_HIERARCHY_LZ4])?;
+    out.write_all(&(compressed.len() as u64 + 2 * 8).to_be_bytes())?;
+    out.write_all(&(h.bytes.len() as u64).to_be_bytes())?;
+    out.write_all(&compressed)?;
+    Ok(())
+}
+
+/// Serializes the hierarchy and assigns a handle to every signal in order of declaration.
+struct HierarchyWriter<'a> {
+    h: &'a Hierarchy,
+    signals: &'a FxHashMap<SignalRef, &'a Signal>,
+    bytes: Vec<u8>,
+    /// signal and encoding for every handle
+    handles: Vec<(SignalRef, SignalEncoding)>,
+    handle_lookup: FxHashMap<SignalRef, u32>,
+    attributes: AttributeHandles,
+    scope_count: u64,
+    var_count: u64,
+}
+
+impl<'a> HierarchyWriter<'a> {
+    fn new(h: &'a Hierarchy, signals: &'a FxHashMap<SignalRef, &'a Signal>) -> Self {
+        Self {
+            h,
+            signals,
+            bytes: Vec::with_capacity(128 * 1024),
+            handles: Vec::new(),
+            handle_lookup: FxHashMap::default(),
+            attributes: AttributeHandles::default(),
+            scope_count: 0,
+            var_count: 0,
+        }
+    }
+
+    fn write_items(&mut self, items: impl Iterator<Item = ScopeOrVarRef>, name_prefix: &str) {
+        let h = self.h;
+        for item in items {
+            match item.deref(h) {
+                ScopeOrVar::Scope(scope) => {
+                    // array scopes are folded back into the variable name, the reader will split them off again
+                    if scope.scope_type() == ScopeType::VhdlArray && can_fold_array_scope(h, scope)
+                    {
+                        let prefix = format!("{name_prefix}{}", scope.name(h));
+                        self.write_items(scope.items(h), &prefix);
+                    } else {
+                        self.write_scope(scope);
+                        self.write_items(scope.items(h), "");
+                        self.bytes.push(HIERARCHY_TPE_UP_SCOPE);
+                    }
+                }
+                ScopeOrVar::Var(var) => {
+                    if self.signals.contains_key(&var.signal_ref()) {
+                        self.write_var(var, name_prefix);
+                    }
+                }
+            }
+        }
+    }
+
+    fn write_scope(&mut self, scope: &Scope) {
+        let h = self.h;
+        if let Some((path, line)) = scope.source_loc(h) {
+            self.write_source_stem(MISC_TPE_SOURCE_STEM, path, line);
+        }
+        if let Some((path, line)) = scope.instantiation_source_loc(h) {
+            self.write_source_stem(MISC_TPE_SOURCE_INSTANTIATION_STEM, path, line);
+        }
+        self.bytes.push(HIERARCHY_TPE_SCOPE);
+        self.bytes.push(scope_tpe_to_fst(scope.scope_type()) as u8);
+        write_c_str(&mut self.bytes, scope.name(h));
+        write_c_str(&mut self.bytes, scope.component(h).unwrap_or(""));
+        self.scope_count += 1;
+    }
+
+    fn write_source_stem(&mut self, tpe: u8, path: &str, line: u64) {
+        let path_id = match self.attributes.paths.get(path) {
+            Some(id) => *id,
+            None => {
+                let id = self.attributes.paths.len() as u64 + 1;
+                write_misc_attribute(&mut self.bytes, MISC_TPE_PATH_NAME, path.as_bytes(), id);
+                self.attributes.paths.insert(path.to_string(), id);
+                id
+            }
+        };
+        // the path id is encoded in place of the attribute name
+        let mut name = Vec::with_capacity(4);
+        write_varint(&mut name, path_id);
+        write_misc_attribute(&mut self.bytes, tpe, &name, line);
+    }
+
+    fn write_var(&mut self, var: &Var, name_prefix: &str) {
+        let h = self.h;
+        let (tpe, vhdl_data_type) = var_tpe_to_fst(var.var_type(), var.signal_encoding());
+
+        // VHDL type information
+        if vhdl_data_type.is_some() || var.vhdl_type_name(h).is_some() {
+            let type_name = var
+                .vhdl_type_name(h)
+                .unwrap_or_else(|| default_vhdl_type_name(var.var_type()));
+            let data_type = vhdl_data_type.unwrap_or(FstVhdlDataType::None);
+            let arg =
+                ((FstVhdlVarType::Signal as u64) << FST_SUP_VAR_DATA_TYPE_BITS) | data_type as u64;
+            write_misc_attribute(&mut self.bytes, MISC_TPE_SUP_VAR, type_name.as_bytes(), arg);
+        }
+
+        // enum table
+        if let (Some(enum_id), Some((name, mapping))) = (var.enum_type_id(), var.enum_type(h)) {
+            let handle = match self.attributes.enums.get(&enum_id) {
+                Some(handle) => *handle,
+                None => {
+                    let handle = self.attributes.enums.len() as u64 + 1;
+                    let mut table = format!("{name} {}", mapping.len());
+                    for (_, value) in mapping.iter() {
+                        table.push(' ');
+                        table.push_str(value);
+                    }
+                    for (key, _) in mapping.iter() {
+                        table.push(' ');
+                        table.push_str(key);
+                    }
+                    write_misc_attribute(
+                        &mut self.bytes,
+                        MISC_TPE_ENUM_TABLE,
+                        table.as_bytes(),
+                        handle,
+                    );
+                    self.attributes.enums.insert(enum_id, handle);
+                    handle
+                }
+            };
+            write_misc_attribute(&mut self.bytes, MISC_TPE_ENUM_TABLE, &[], handle);
+        }
+
+        let length = match var.signal_encoding() {
+            SignalEncoding::String => 0,
+            SignalEncoding::Real => 8,
+            SignalEncoding::BitVector(len) => len.get(),
+        };
+        let mut name = format!("{name_prefix}{}", var.name(h));
+        // an index that does not match the length would be interpreted as an array index
+        if let Some(index) = var.index().filter(|i| i.length() == length) {
+            if index.msb() == index.lsb() {
+                name.push_str(&format!(" [{}]", index.lsb()));
+            } else {
+                name.push_str(&format!(" [{}:{}]", index.msb(), index.lsb()));
+            }
+        }
+        let raw_length = if tpe == FstVarType::Port {
+            3 * length + 2
+        } else {
+            length
+        };
+
+        // the first variable of a signal gets a new handle, all others become aliases
+        let alias = match self.handle_lookup.get(&var.signal_ref()) {
+            Some(handle) => *handle + 1,
+            None => {
+                let handle = self.handles.len() as u32;
+                self.handles.push((var.signal_ref(), var.signal_encoding()));
+                self.handle_lookup.insert(var.signal_ref(), handle);
+                0
+            }
+        };
+
+        self.bytes.push(tpe as u8);
+        self.bytes.push(var_direction_to_fst(var.direction()) as u8);
+        write_c_str(&mut self.bytes, &name);
+        write_varint(&mut self.bytes, raw_length as u64);
+        write_varint(&mut self.bytes, alias as u64);
+        self.var_count += 1;
+    }
+}
+
+/// Merges the changes of all signals by time index and splits them into blocks.
+fn write_value_change_blocks(
+    times: &[Time],
+    signals: &[&Signal],
+    encodings: &[SignalEncoding],
+    block_size: usize,
+) -> std::io::Result<Vec<Vec<u8>>> {
+    let mut changes: Vec<_> = signals
+        .iter()
+        .map(|s| s.iter_changes().peekable())
+        .collect();
+    let mut queue: BinaryHeap<Reverse<(TimeTableIdx, usize)>> = changes
+        .iter_mut()
+        .enumerate()
+        .filter_map(|(handle, c)| c.peek().map(|(t, _)| Reverse((*t, handle))))
+        .collect();
+
+    let mut blocks = Vec::new();
+    let mut block = BlockWriter::new(encodings);
+    let mut block_start = 0;
+    for time_idx in 0..times.len() {
+        while let Some(Reverse((next_idx, handle))) = queue.peek().cloned() {
+            if next_idx as usize != time_idx {
+                break;
+            }
+            queue.pop();
+            let (_, value) = changes[handle].next().unwrap();
+            block.add_change(handle, time_idx - block_start, value)?;
+            if let Some((t, _)) = changes[handle].peek() {
+                queue.push(Reverse((*t, handle)));
+            }
+        }
+        let block_end = time_idx + 1;
+        if block_end == times.len()
+            || block.data_size >= block_size
+            || block_end - block_start >= MAX_TIME_STEPS_PER_BLOCK
+        {
+            blocks.push(block.finish(&times[block_start..block_end]));
+            block_start = block_end;
+        }
+    }
+    Ok(blocks)
+}
+
+/// Encodes the value changes of a single block.
+struct BlockWriter<'a> {
+    encodings: &'a [SignalEncoding],
+    /// value changes of the current block, one entry per handle
+    data: Vec<Vec<u8>>,
+    data_size: usize,
+    /// block relative time index of the previous change of every signal
+    prev_change: Vec<Option<usize>>,
+    /// values at the start of the current block
+    frame: Vec<u8>,
+    /// values after the last change
+    current: Vec<u8>,
+    frame_offsets: Vec<usize>,
+}
+
+impl<'a> BlockWriter<'a> {
+    fn new(encodings: &'a [SignalEncoding]) -> Self {
+        let mut frame_offsets = Vec::with_capacity(encodings.len());
+        let mut frame = Vec::new();
+        for encoding in encodings.iter() {
+            frame_offsets.push(frame.len());
+            match encoding {
+                SignalEncoding::String => {}
+                SignalEncoding::Real => frame.extend_from_slice(&0f64.to_le_bytes()),
+                SignalEncoding::BitVector(len) => {
+                    frame.resize(frame.len() + len.get() as usize, b'x')
+                }
+            }
+        }
+        Self {
+            encodings,
+            data: vec![Vec::new(); encodings.len()],
+            data_size: 0,
+            prev_change: vec![None; encodings.len()],
+            current: frame.clone(),
+            frame,
+            frame_offsets,
+        }
+    }
+
+    fn add_change(
+        &mut self,
+        handle: usize,
+        time_idx: usize,
+        value: SignalValue,
+    ) -> std::io::Result<()> {
+        let time_delta = (time_idx - self.prev_change[handle].unwrap_or(0)) as u64;
+        self.prev_change[handle] = Some(time_idx);
+        let data = &mut self.data[handle];
+        let prev_len = data.len();
+        let frame_offset = self.frame_offsets[handle];
+        match (self.encodings[handle], value) {
+            (SignalEncoding::String, SignalValue::String(value)) => {
+                write_varint(data, time_delta << 1);
+                write_varint(data, value.len() as u64);
+                data.extend_from_slice(value.as_bytes());
+            }
+            (SignalEncoding::Real, SignalValue::Real(value)) => {
+                write_varint(data, (time_delta << 1) | 1);
+                data.extend_from_slice(&value.to_le_bytes());
+                self.current[frame_offset..frame_offset + 8].copy_from_slice(&value.to_le_bytes());
+            }
+            (SignalEncoding::BitVector(len), value) if value.bits() == Some(len.get()) => {
+                let bits = value.to_bit_string().unwrap();
+                let bits = bits.as_bytes();
+                if len.get() == 1 {
+                    let vli = match bits[0] {
+                        b'0' | b'1' => (time_delta << 2) | (((bits[0] - b'0') as u64) << 1),
+                        other => {
+                            let state = ONE_BIT_STATES.iter().position(|s| *s == other).unwrap();
+                            (time_delta << 4) | ((state as u64) << 1) | 1
+                        }
+                    };
+                    write_varint(data, vli);
+                } else if value.states() == Some(States::Two) {
+                    write_varint(data, time_delta << 1);
+                    let start = data.len();
+                    data.resize(start + bits.len().div_ceil(8), 0);
+                    for (ii, bit) in bits.iter().enumerate() {
+                        data[start + ii / 8] |= (*bit - b'0') << (7 - (ii % 8));
+                    }
+                } else {
+                    write_varint(data, (time_delta << 1) | 1);
+                    data.extend_from_slice(bits);
+                }
+                self.current[frame_offset..frame_offset + bits.len()].copy_from_slice(bits);
+            }
+            (encoding, value) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "[fst] cannot write value {value:?} of signal with encoding {encoding:?}"
+                    ),
+                ));
+            }
+        }
+        self.data_size += data.len() - prev_len;
+        Ok(())
+    }
+
+    /// Returns the complete block, including the block type and section length.
+    fn finish(&mut self, times: &[Time]) -> Vec<u8> {
+        let max_handle = self.encodings.len() as u64;
+        let mut block = Vec::with_capacity(self.data_size + 1024);
+        block.push(BLOCK_TPE_VC_DATA);
+        write_u64(&mut block, 0); // section length is filled in at the end
+        write_u64(&mut block, times[0]);
+        write_u64(&mut block, *times.last().unwrap());
+        write_u64(&mut block, self.data_size as u64);
+
+        // frame
+        let compressed = zlib_compress_if_smaller(&self.frame);
+        write_varint(&mut block, self.frame.len() as u64);
+        write_varint(&mut block, compressed.len() as u64);
+        write_varint(&mut block, max_handle);
+        block.extend_from_slice(&compressed);
+        self.frame.copy_from_slice(&self.current);
+
+        // value changes, with offsets relative to the pack type
+        write_varint(&mut block, max_handle);
+        let vc_start = block.len();
+        block.push(b'4'); // lz4
+        let mut chain = Vec::with_capacity(self.data.len() * 2);
+        let mut prev_offset = 0;
+        let mut empty_run = 0u64;
+        for data in self.data.iter_mut() {
+            if data.is_empty() {
+                empty_run += 1;
+                continue;
+            }
+            if empty_run > 0 {
+                write_varint(&mut chain, empty_run << 1);
+                empty_run = 0;
+            }
+            let offset = block.len() - vc_start;
+            write_varint(&mut chain, (((offset - prev_offset) as u64) << 1) | 1);
+            prev_offset = offset;
+            let compressed = lz4_flex::compress(data);
+            if compressed.len() < data.len() {
+                write_varint(&mut block, data.len() as u64);
+                block.extend_from_slice(&compressed);
+            } else {
+                write_varint(&mut block, 0);
+                block.extend_from_slice(data);
+            }
+            data.clear();
+        }
+        if empty_run > 0 {
+            write_varint(&mut chain, empty_run << 1);
+        }
+        block.extend_from_slice(&chain);
+        write_u64(&mut block, chain.len() as u64);
+
+        // time table
+        let mut time_deltas = Vec::with_capacity(times.len());
+        let mut prev_time = 0;
+        for time in times.iter() {
+            write_varint(&mut time_deltas, time - prev_time);
+            prev_time = *time;
+        }
+        let compressed = zlib_compress_if_smaller(&time_deltas);
+        block.extend_from_slice(&compressed);
+        write_u64(&mut block, time_deltas.len() as u64);
+        write_u64(&mut block, compressed.len() as u64);
+        write_u64(&mut block, times.len() as u64);
+
+        let section_length = (block.len() - 1) as u64;
+        block[1..9].copy_from_slice(&section_length.to_be_bytes());
+        self.prev_change.fill(None);
+        self.data_size = 0;
+        block
+    }
+}
+
+/// Returns the FST timescale exponent and the factor that all times need to be multiplied with,
+/// since FST only supports timescales that are a power of ten.
+fn timescale_to_exponent(timescale: Option<Timescale>) -> (i8, u64) {
+    match timescale.and_then(|t| t.unit.to_exponent().map(|e| (t.factor, e))) {
+        None => (-9, 1),
+        Some((factor, exponent)) => match factor.checked_ilog10() {
+            Some(log) if 10u32.pow(log) == factor => (exponent + log as i8, 1),
+            _ => (exponent, factor as u64),
+        },
+    }
+}
+
+#[inline]
+fn write_u64(out: &mut Vec<u8>, value: u64) {
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+#[inline]
+fn write_varint(out: &mut Vec<u8>, value: u64) {
+    leb128::write::unsigned(out, value).unwrap();
+}
+
+fn write_c_str(out: &mut Vec<u8>, value: &str) {
+    out.extend_from_slice(value.as_bytes());
+    out.push(0);
+}
+
+fn write_c_str_fixed_length(out: &mut Vec<u8>, value: &str, max_len: usize) {
+    // leave room for the zero terminator without splitting a character
+    let mut len = std::cmp::min(value.len(), max_len - 1);
+    while !value.is_char_boundary(len) {
+        len -= 1;
+    }
+    out.extend_from_slice(&value.as_bytes()[..len]);
+    out.resize(out.len() + max_len - len, 0);
+}
+
+fn write_misc_attribute(out: &mut Vec<u8>, tpe: u8, name: &[u8], arg: u64) {
+    out.push(HIERARCHY_TPE_ATTRIBUTE_BEGIN);
+    out.push(ATTRIBUTE_TPE_MISC);
+    out.push(tpe);
+    out.extend_from_slice(name);
+    out.push(0);
+    write_varint(out, arg);
+}
+
+fn zlib_compress_if_smaller(bytes: &[u8]) -> Vec<u8> {
+    let compressed = miniz_oxide::deflate::compress_to_vec_zlib(bytes, ZLIB_LEVEL);
+    if compressed.len() < bytes.len() {
+        compressed
+    } else {
+        bytes.to_vec()
+    }
+}
+
+fn scope_tpe_to_fst(tpe: ScopeType) -> FstScopeType {
+    match tpe {
+        ScopeType::Module => FstScopeType::Module,
+        ScopeType::Task => FstScopeType::Task,
+        ScopeType::Function => FstScopeType::Function,
+        ScopeType::Begin => FstScopeType::Begin,
+        ScopeType::Fork => FstScopeType::Fork,
+        ScopeType::Generate => FstScopeType::Generate,
+        ScopeType::Struct => FstScopeType::Struct,
+        ScopeType::Union => FstScopeType::Union,
+        ScopeType::Class => FstScopeType::Class,
+        ScopeType::Interface => FstScopeType::Interface,
+        ScopeType::Package => FstScopeType::Package,
+        ScopeType::Program => FstScopeType::Program,
+        ScopeType::VhdlArchitecture => FstScopeType::VhdlArchitecture,
+        ScopeType::VhdlProcedure => FstScopeType::VhdlProcedure,
+        ScopeType::VhdlFunction => FstScopeType::VhdlFunction,
+        ScopeType::VhdlRecord => FstScopeType::VhdlRecord,
+        ScopeType::VhdlProcess => FstScopeType::VhdlProcess,
+        ScopeType::VhdlBlock => FstScopeType::VhdlBlock,
+        ScopeType::VhdlForGenerate => FstScopeType::VhdlForGenerate,
+        ScopeType::VhdlIfGenerate => FstScopeType::VhdlIfGenerate,
+        ScopeType::VhdlGenerate => FstScopeType::VhdlGenerate,
+        ScopeType::VhdlPackage => FstScopeType::VhdlPackage,
+        // there is no FST equivalent for these
+        ScopeType::GhwGeneric | ScopeType::VhdlArray | ScopeType::Unknown => FstScopeType::Module,
+    }
+}
+
+/// Returns the FST var type and an optional VHDL data type which is required in order to
+/// recreate the `VarType` through `merge_vhdl_data_and_var_type`.
+fn var_tpe_to_fst(tpe: VarType, encoding: SignalEncoding) -> (FstVarType, Option<FstVhdlDataType>) {
+    // the reader derives the signal encoding from the var type
+    match encoding {
+        SignalEncoding::String => return (FstVarType::GenericString, None),
+        SignalEncoding::Real => {
+            let fst_tpe = match tpe {
+                VarType::Parameter => FstVarType::RealParameter,
+                VarType::RealTime => FstVarType::RealTime,
+                VarType::ShortReal => FstVarType::ShortReal,
+                _ => FstVarType::Real,
+            };
+            return (fst_tpe, None);
+        }
+        SignalEncoding::BitVector(_) => {}
+    }
+    match tpe {
+        VarType::Event => (FstVarType::Event, None),
+        VarType::Integer => (FstVarType::Integer, None),
+        VarType::Parameter => (FstVarType::Parameter, None),
+        VarType::Reg => (FstVarType::Reg, None),
+        VarType::Supply0 => (FstVarType::Supply0, None),
+        VarType::Supply1 => (FstVarType::Supply1, None),
+        VarType::Time => (FstVarType::Time, None),
+        VarType::Tri => (FstVarType::Tri, None),
+        VarType::TriAnd => (FstVarType::TriAnd, None),
+        VarType::TriOr => (FstVarType::TriOr, None),
+        VarType::TriReg => (FstVarType::TriReg, None),
+        VarType::Tri0 => (FstVarType::Tri0, None),
+        VarType::Tri1 => (FstVarType::Tri1, None),
+        VarType::WAnd => (FstVarType::Wand, None),
+        VarType::Wire => (FstVarType::Wire, None),
+        VarType::WOr => (FstVarType::Wor, None),
+        VarType::Port => (FstVarType::Port, None),
+        VarType::SparseArray => (FstVarType::SparseArray, None),
+        VarType::Bit => (FstVarType::Bit, None),
+        VarType::Logic => (FstVarType::Logic, None),
+        VarType::Int => (FstVarType::Int, None),
+        VarType::ShortInt => (FstVarType::ShortInt, None),
+        VarType::LongInt => (FstVarType::LongInt, None),
+        VarType::Byte => (FstVarType::Byte, None),
+        VarType::Enum => (FstVarType::Enum, None),
+        // a bit-vector encoded real or string cannot be expressed through the var type
+        VarType::Real | VarType::RealTime | VarType::ShortReal | VarType::String => {
+            (FstVarType::Wire, None)
+        }
+        VarType::Boolean => (FstVarType::Bit, Some(FstVhdlDataType::Boolean)),
+        VarType::BitVector => (FstVarType::Bit, Some(FstVhdlDataType::Vector)),
+        VarType::StdLogic => (FstVarType::Logic, Some(FstVhdlDataType::Logic)),
+        VarType::StdLogicVector => (FstVarType::Logic, Some(FstVhdlDataType::LogicVector)),
+        VarType::StdULogic => (FstVarType::Logic, Some(FstVhdlDataType::ULogic)),
+        VarType::StdULogicVector => (FstVarType::Logic, Some(FstVhdlDataType::ULogicVector)),
+    }
+}
+
+fn var_direction_to_fst(direction: VarDirection) -> FstVarDirection {
+    match direction {
+        VarDirection::Unknown | VarDirection::Implicit => FstVarDirection::Implicit,
+        VarDirection::Input => FstVarDirection::Input,
+        VarDirection::Output => FstVarDirection::Output,
+        VarDirection::InOut => FstVarDirection::InOut,
+        VarDirection::Buffer => FstVarDirection::Buffer,
+        VarDirection::Linkage => FstVarDirection::Linkage,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timescale_to_exponent() {
+        let ns = |factor| Some(Timescale::new(factor, TimescaleUnit::NanoSeconds));
+        assert_eq!(timescale_to_exponent(ns(1)), (-9, 1));
+        assert_eq!(timescale_to_exponent(ns(100)), (-7, 1));
+        assert_eq!(timescale_to_exponent(ns(5)), (-9, 5));
+        assert_eq!(timescale_to_exponent(None), (-9, 1));
+    }
+
+    #[test]
+    fn test_write_reports_time_overflow() {
+        let vcd = format!(
+            "$timescale 5ns $end\n$var wire 1 ! a $end\n$enddefinitions $end\n#0\n0!\n#{}\n1!\n",
+            u64::MAX / 2
+        );
+        let mut wave =
+            crate::simple::read_from_reader(std::io::Cursor::new(vcd.into_bytes())).unwrap();
+        let ids: Vec<_> = wave
+            .hierarchy()
+            .iter_vars()
+            .map(|v| v.signal_ref())
+            .collect();
+        wave.load_signals(&ids).unwrap();
+        let signals = ids.iter().map(|r| wave.get_signal(*r).unwrap());
+        let err = write(
+            &mut Vec::new(),
+            wave.hierarchy(),
+            wave.time_table(),
+            signals,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    /// Writes `filename` as a FST with very small blocks.
+    fn write_small_blocks(filename: &str) -> (crate::simple::Waveform, Vec<SignalRef>, Vec<u8>) {
+        let mut original = crate::simple::read(filename).unwrap();
+        let ids: Vec<_> = original
+            .hierarchy()
+            .iter_vars()
+            .map(|v| v.signal_ref())
+            .collect();
+        original.load_signals(&ids).unwrap();
+        let signals: Vec<_> = ids
+            .iter()
+            .map(|r| original.get_signal(*r).unwrap())
+            .collect();
+        let mut bytes = Vec::new();
+        write_with_block_size(
+            &mut bytes,
+            original.hierarchy(),
+            original.time_table(),
+            signals,
+            256,
+        )
+        .unwrap();
+        (original, ids, bytes)
+    }
+
+    /// Small blocks make sure that signal values are carried across block boundaries.
+    #[test]
+    fn test_write_multiple_blocks() {
+        let (original, ids, bytes) = write_small_blocks("inputs/icarus/CPU.vcd");
+
+        // the section count directly follows the max handle in the header
+        let vc_section_count = u64::from_be_bytes(bytes[65..73].try_into().unwrap());
+        assert!(vc_section_count > 1, "{vc_section_count}");
+
+        let mut written = crate::simple::read_from_reader(std::io::Cursor::new(bytes)).unwrap();
+        assert_eq!(original.time_table(), written.time_table());
+        let written_ids: Vec<_> = written
+            .hierarchy()
+            .iter_vars()
+            .map(|v| v.signal_ref())
+            .collect();
+        written.load_signals(&written_ids).unwrap();
+        for (o, w) in ids.iter().zip(written_ids.iter()) {
+            let o_changes: Vec<_> = original
+                .get_signal(*o)
+                .unwrap()
+                .iter_changes()
+                .map(|(t, v)| (t, v.to_string()))
+                .collect();
+            let w_changes: Vec<_> = written
+                .get_signal(*w)
+                .unwrap()
+                .iter_changes()
+                .map(|(t, v)| (t, v.to_string()))
+                .collect();
+            assert_eq!(o_changes, w_changes);
+        }
+    }
+
+    #[test]
+    fn test_write_encoding_mismatch_is_an_error() {
+        let encodings = [SignalEncoding::BitVector(
+            std::num::NonZeroU32::new(4).unwrap(),
+        )];
+        let mut block = BlockWriter::new(&encodings);
+        assert!(block.add_change(0, 0, SignalValue::Real(1.0)).is_err());
+        assert!(
+            block
+                .add_change(0, 0, SignalValue::Binary(&[0b1], 1))
+                .is_err()
+        );
+        assert!(
+            block
+                .add_change(0, 0, SignalValue::Binary(&[0b1010], 4))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_load_signals_in_range_from_multiple_blocks() {
+        let (_, _, bytes) = write_small_blocks("inputs/icarus/CPU.vcd");
+        let (hierarchy, cont) =
+            read_header(std::io::Cursor::new(bytes), &LoadOptions::default()).unwrap();
+        let (mut source, time_table) = read_body(cont).unwrap();
+        let ids: Vec<_> = hierarchy.iter_vars().map(|v| v.signal_ref()).collect();
+        let full = source.load_signals(&ids, &hierarchy, false).unwrap();
+        let (start, end) = (
+            time_table[time_table.len() / 2],
+            time_table[time_table.len() / 2 + 3],
+        );
+        let window = time_window_to_indices(&time_table, start, end);
+        let windowed = source
+            .load_signals_in_range(&ids, &hierarchy, start, end, false)
+            .unwrap();
+        for ((_, full), (_, windowed)) in full.into_iter().zip(windowed) {
+            let mut expected = full;
+            expected.retain_window(window);
+            assert_eq!(expected, windowed);
+        }
+    }
+}
@@ -23,6 +23,10 @@ pub enum GhwParseError {
     UnexpectedType(String, &'static str),
     #[error("[ghw] failed to parse a {0} section: {1}")]
     FailedToParseSection(&'static str, String),
+    #[error("[ghw] unexpected value: {0} {1}")]
+    UnexpectedValue(i64, &'static str),
+    #[error("[ghw] unsupported: {0}")]
+    Unsupported(String),
     #[error("[ghw] expected positive integer, not: {0}")]
     ExpectedPositiveInteger(i64),
     #[error("[ghw] float range has no length: {0} .. {1}")]
@@ -150,9 +154,23 @@ pub struct HeaderData {
     pub word_len: u8,
     #[allow(dead_code)]
     pub word_offset: u8,
+    /// Size of the whole file in bytes, if known.
+    pub file_size: Option<u64>,
 }
 
 impl HeaderData {
+    /// Every entry takes up at least one byte, thus a count larger than the file has to be
+    /// malformed. We check this before allocating any tables.
+    pub fn check_count(&self, section: &'static str, count: u64) -> Result<usize> {
+        match self.file_size {
+            Some(size) if count > size => Err(GhwParseError::FailedToParseSection(
+                section,
+                format!("{count} entries cannot fit into a {size} byte file"),
+            )),
+            _ => Ok(count as usize),
+        }
+    }
+
     #[inline]
     pub fn read_i32(&self, input: &mut impl BufRead) -> Result<i32> {
         let mut b = [0u8; 4];
@@ -243,8 +261,13 @@ impl GhwSignals {
     pub fn new(signals: Vec<GhwSignalInfo>) -> Self {
         Self { signals }
     }
-    pub fn get_info(&self, signal_id: GhwSignalId) -> &GhwSignalInfo {
-        &self.signals[signal_id.index()]
+    pub fn get_info(&self, signal_id: GhwSignalId) -> Result<&GhwSignalInfo> {
+        self.signals.get(signal_id.index()).ok_or_else(|| {
+            GhwParseError::FailedToParseSection(
+                "signal",
+                format!("unknown signal id {}", signal_id.index() + 1),
+            )
+        })
     }
     pub fn signal_len(&self) -> usize {
         self.signals.len()
@@ -413,6 +436,26 @@ pub const STD_LOGIC_VALUES: [u8; 9] = [b'u', b'x', b'0', b'1', b'z', b'w', b'l',
 /// Mapping from STD_LOGIC value to the `wellen` nine state encoding: ['0', '1', 'x', 'z', 'h', 'u', 'w', 'l', '-']
 pub const STD_LOGIC_LUT: [u8; 9] = [5, 2, 0, 1, 3, 6, 7, 4, 8];
 
+/// Converts a GHDL std_logic value into our nine-state encoding.
+pub fn std_logic_value(ghdl_value: u8) -> Result<u8> {
+    STD_LOGIC_LUT
+        .get(ghdl_value as usize)
+        .copied()
+        .ok_or(GhwParseError::UnexpectedValue(
+            ghdl_value as i64,
+            "for a std_logic",
+        ))
+}
+
+/// Checks that a bit value is either 0 or 1.
+pub fn bit_value(value: u8) -> Result<u8> {
+    if value <= 1 {
+        Ok(value)
+    } else {
+        Err(GhwParseError::UnexpectedValue(value as i64, "for a bit"))
+    }
+}
+
 /// The order in which the two values appear in the BIT enum.
 pub const VHDL_BIT_VALUES: [u8; 2] = [b'0', b'1'];
 
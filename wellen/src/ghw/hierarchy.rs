@@ -6,8 +6,8 @@
 use crate::ghw::common::*;
 use crate::hierarchy::{EnumTypeId, HierarchyBuilder, HierarchyStringId};
 use crate::{
-    FileFormat, Hierarchy, ScopeType, SignalRef, Timescale, TimescaleUnit, VarDirection, VarIndex,
-    VarType,
+    FileFormat, Hierarchy, LoadOptions, ScopeType, SignalRef, Timescale, TimescaleUnit,
+    VarDirection, VarIndex, VarType,
 };
 use num_enum::TryFromPrimitive;
 use rustc_hash::FxHashMap;
@@ -48,6 +48,7 @@ pub fn read_ghw_header(input: &mut impl BufRead) -> Result<HeaderData> {
         big_endian: h[3] == 2,
         word_len: h[4],
         word_offset: h[5],
+        file_size: None,
     };
 
     if h[0] != 16 || h[1] != 0 {
@@ -108,11 +109,13 @@ pub fn try_read_directory(
 pub fn read_hierarchy(
     header: &HeaderData,
     input: &mut impl BufRead,
+    options: &LoadOptions,
 ) -> Result<(GhwDecodeInfo, Hierarchy)> {
     let mut tables = GhwTables::default();
     let mut strings = Vec::new();
     let mut decode: Option<GhwDecodeInfo> = None;
     let mut hb = HierarchyBuilder::new(FileFormat::Ghw);
+    hb.set_scope_filter(options);
 
     // GHW seems to always uses fs
     hb.set_timescale(Timescale::new(1, TimescaleUnit::FemtoSeconds));
@@ -123,14 +126,10 @@ pub fn read_hierarchy(
 
         match &mark {
             GHW_STRING_SECTION => {
-                let table = read_string_section(header, input)?;
-                debug_assert!(
-                    strings.is_empty(),
-                    "unexpected second string table:\n{:?}\n{:?}",
-                    &strings,
-                    &table
-                );
-                strings = table;
+                if !strings.is_empty() {
+                    return Err(duplicate_section("string"));
+                }
+                strings = read_string_section(header, input)?;
                 // add all strings to the hierarchy
                 tables.strings = strings
                     .iter()
@@ -139,7 +138,9 @@ pub fn read_hierarchy(
                     .collect();
             }
             GHW_TYPE_SECTION => {
-                debug_assert!(tables.types.is_empty(), "unexpected second type table");
+                if !tables.types.is_empty() {
+                    return Err(duplicate_section("type"));
+                }
                 let types = read_type_section(header, &strings, input)?;
                 tables.types = types;
 
@@ -147,44 +148,32 @@ pub fn read_hierarchy(
                 tables.enums = add_enums_to_wellen_hierarchy(&tables, &mut hb)?;
             }
             GHW_WK_TYPE_SECTION => {
-                let wkts = read_well_known_types_section(input)?;
-                debug_assert!(wkts.is_empty() || !tables.types.is_empty());
+                let wkts = read_well_known_types_section(input, tables.types.len())?;
 
                 // we should have already inferred the correct well know types, so we just check
                 // that we did so correctly
                 for (type_id, wkt) in wkts.into_iter() {
                     let tpe = &tables.types[type_id.index()];
-                    match wkt {
-                        GhwWellKnownType::Unknown => {} // does not matter
-                        GhwWellKnownType::Boolean => {
-                            // we expect the bool to be represented as an enum
-                            debug_assert!(
-                                matches!(tpe, VhdlType::Enum(_, _, _)),
-                                "{tpe:?} not recognized a VHDL bool!"
-                            );
-                        }
-                        GhwWellKnownType::Bit => {
-                            debug_assert!(
-                                matches!(tpe, VhdlType::Bit(_)),
-                                "{tpe:?} not recognized a VHDL bit!"
-                            );
-                        }
-                        GhwWellKnownType::StdULogic => {
-                            debug_assert!(
-                                matches!(tpe, VhdlType::NineValueBit(_)),
-                                "{tpe:?} not recognized a std_ulogic!"
-                            );
-                        }
+                    let recognized = match wkt {
+                        GhwWellKnownType::Unknown => true, // does not matter
+                        // we expect the bool to be represented as an enum
+                        GhwWellKnownType::Boolean => matches!(tpe, VhdlType::Enum(_, _, _)),
+                        GhwWellKnownType::Bit => matches!(tpe, VhdlType::Bit(_)),
+                        GhwWellKnownType::StdULogic => matches!(tpe, VhdlType::NineValueBit(_)),
+                    };
+                    if !recognized {
+                        return Err(GhwParseError::UnexpectedType(
+                            format!("{tpe:?}"),
+                            "does not match the well known type",
+                        ));
                     }
                 }
             }
             GHW_HIERARCHY_SECTION => {
-                let dec = read_hierarchy_section(header, &mut tables, input, &mut hb)?;
-                debug_assert!(
-                    decode.is_none(),
-                    "unexpected second hierarchy section:\n{decode:?}\n{dec:?}"
-                );
-                decode = Some(dec);
+                if decode.is_some() {
+                    return Err(duplicate_section("hierarchy"));
+                }
+                decode = Some(read_hierarchy_section(header, &mut tables, input, &mut hb)?);
             }
             GHW_END_OF_HEADER_SECTION => {
                 break; // done
@@ -197,7 +186,14 @@ pub fn read_hierarchy(
         }
     }
     let hierarchy = hb.finish();
-    Ok((decode.unwrap(), hierarchy))
+    let decode = decode.ok_or_else(|| {
+        GhwParseError::FailedToParseSection("hierarchy", "missing hierarchy section".to_string())
+    })?;
+    Ok((decode, hierarchy))
+}
+
+fn duplicate_section(section: &'static str) -> GhwParseError {
+    GhwParseError::FailedToParseSection(section, "unexpected second section".to_string())
 }
 
 /// adds all enums to the hierarchy and
@@ -237,7 +233,7 @@ fn add_enums_to_wellen_hierarchy(
 }
 
 fn get_enum_bits(literals: &[StringId]) -> u32 {
-    let max_value = literals.len() as u64 - 1;
+    let max_value = (literals.len() as u64).saturating_sub(1);
 
     u64::BITS - max_value.leading_zeros()
 }
@@ -250,7 +246,7 @@ fn read_string_section(header: &HeaderData, input: &mut impl BufRead) -> Result<
     let string_num = header.read_u32(&mut &h[4..8])? + 1;
     let _string_size = header.read_i32(&mut &h[8..12])? as u32;
 
-    let mut string_table = Vec::with_capacity(string_num as usize);
+    let mut string_table = Vec::with_capacity(header.check_count("string", string_num as u64)?);
     string_table.push("<anon>".to_string());
 
     let mut buf = Vec::with_capacity(64);
@@ -284,14 +280,29 @@ fn read_string_section(header: &HeaderData, input: &mut impl BufRead) -> Result<
     Ok(string_table)
 }
 
-fn read_string_id(input: &mut impl BufRead) -> Result<StringId> {
+/// Reads a pointer into a string table with `num_strings` entries.
+fn read_string_id(input: &mut impl BufRead, num_strings: usize) -> Result<StringId> {
     let value = leb128::read::unsigned(input)?;
-    Ok(StringId(value as usize))
+    if value as usize >= num_strings {
+        Err(GhwParseError::FailedToParseSection(
+            "string",
+            format!("StringId too large {value} >= {num_strings}"),
+        ))
+    } else {
+        Ok(StringId(value as usize))
+    }
 }
 
-fn read_type_id(input: &mut impl BufRead) -> Result<TypeId> {
+/// Reads a pointer to one of the first `num_types` types.
+fn read_type_id(input: &mut impl BufRead, num_types: usize) -> Result<TypeId> {
     let value = leb128::read::unsigned(input)?;
-    Ok(TypeId(NonZeroU32::new(value as u32).unwrap()))
+    match NonZeroU32::new(value as u32) {
+        Some(id) if value <= num_types as u64 => Ok(TypeId(id)),
+        _ => Err(GhwParseError::FailedToParseSection(
+            "type",
+            format!("TypeId {value} does not refer to one of {num_types} known types"),
+        )),
+    }
 }
 
 fn read_range(input: &mut impl BufRead) -> Result<Range> {
@@ -329,11 +340,15 @@ fn read_range(input: &mut impl BufRead) -> Result<Range> {
     Ok(range)
 }
 
-fn read_unit_vec(input: &mut impl BufRead) -> Result<Vec<(StringId, i64)>> {
+fn read_unit_vec(
+    header: &HeaderData,
+    num_strings: usize,
+    input: &mut impl BufRead,
+) -> Result<Vec<(StringId, i64)>> {
     let num_units = leb128::read::unsigned(input)?;
-    let mut units = Vec::with_capacity(num_units as usize);
+    let mut units = Vec::with_capacity(header.check_count("type", num_units)?);
     for _ in 0..num_units {
-        let name = read_string_id(input)?;
+        let name = read_string_id(input, num_strings)?;
         let val = leb128::read::signed(input)?; // Read an lsleb, not sure relation to sleb
         units.push((name, val));
     }
@@ -350,21 +365,24 @@ fn read_type_section(
     check_header_zeros("type", &h)?;
 
     let type_num = header.read_u32(&mut &h[4..8])?;
-    let mut types = vec![VhdlType::Missing; type_num as usize];
+    let mut types = vec![VhdlType::Missing; header.check_count("type", type_num as u64)?];
     let mut enum_count = 0;
+    let num_strings = strings.len();
 
     for new_tpe_id in 0..type_num {
+        // types may only refer to types that were defined before them
+        let num_types = new_tpe_id as usize;
         let t = read_u8(input)?;
         let kind = GhwRtik::try_from_primitive(t)?;
-        let name = read_string_id(input)?;
+        let name = read_string_id(input, num_strings)?;
         let tpe: VhdlType = match kind {
             GhwRtik::TypeE8 | GhwRtik::TypeB2 => {
                 let num_literals = leb128::read::unsigned(input)?;
-                let mut literals = Vec::with_capacity(num_literals as usize);
+                let mut literals = Vec::with_capacity(header.check_count("type", num_literals)?);
                 for _ in 0..num_literals {
-                    literals.push(read_string_id(input)?);
+                    literals.push(read_string_id(input, num_strings)?);
                 }
-                VhdlType::from_enum(strings, &mut enum_count, name, literals)
+                VhdlType::from_enum(strings, &mut enum_count, name, literals)?
             }
             GhwRtik::TypeI32 => VhdlType::I32(name, None),
             GhwRtik::TypeI64 => VhdlType::I64(name, None),
@@ -372,45 +390,59 @@ fn read_type_section(
             GhwRtik::TypeP64 => {
                 let units = match header.version {
                     0 => vec![],
-                    _ => read_unit_vec(input)?,
+                    _ => read_unit_vec(header, num_strings, input)?,
                 };
                 VhdlType::P64(name, None, units)
             }
             GhwRtik::SubtypeScalar => {
-                let base = read_type_id(input)?;
+                let base = read_type_id(input, num_types)?;
                 let range = read_range(input)?;
-                VhdlType::from_subtype_scalar(name, &types, base, range)
+                VhdlType::from_subtype_scalar(name, &types, base, range)?
             }
             GhwRtik::TypeArray => {
-                let element_tpe = read_type_id(input)?;
+                let element_tpe = read_type_id(input, num_types)?;
                 let num_dims = leb128::read::unsigned(input)?;
-                let mut dims = Vec::with_capacity(num_dims as usize);
+                let mut dims = Vec::with_capacity(header.check_count("type", num_dims)?);
                 for _ in 0..num_dims {
-                    dims.push(read_type_id(input)?);
+                    dims.push(read_type_id(input, num_types)?);
                 }
-                debug_assert!(!dims.is_empty());
-                if dims.len() > 1 {
-                    todo!("support multi-dimensional arrays!")
+                let Some((&index, inner_dims)) = dims.split_first() else {
+                    return Err(GhwParseError::FailedToParseSection(
+                        "type",
+                        "array without dimensions".to_string(),
+                    ));
+                };
+                // multi-dimensional arrays are represented as arrays of arrays
+                let mut element_tpe = element_tpe;
+                for _ in inner_dims.iter() {
+                    let inner =
+                        VhdlType::from_unbounded_array(StringId::none(), &types, element_tpe);
+                    element_tpe = type_to_id(&mut types, inner);
                 }
-                VhdlType::from_array(name, &types, element_tpe, dims[0])
+                VhdlType::from_array(name, &types, element_tpe, index)
             }
             GhwRtik::SubtypeArray => {
-                let base = read_type_id(input)?;
-                debug_assert!(types[base.index()].is_array());
+                let base = read_type_id(input, num_types)?;
+                if !types[base.index()].is_array() {
+                    return Err(GhwParseError::UnexpectedType(
+                        format!("{:?}", types[base.index()]),
+                        "expected array",
+                    ));
+                }
                 // construct a finite version of the base type by recursively reading bounds
                 read_subtype_bounds(input, &mut types, name, base)?
             }
             GhwRtik::SubtypeUnboundedArray => {
-                let base = read_type_id(input)?;
-                VhdlType::from_subtype_unbounded_array(name, &types, base)
+                let base = read_type_id(input, num_types)?;
+                VhdlType::from_subtype_unbounded_array(name, &types, base)?
             }
             GhwRtik::TypeRecord => {
                 let num_fields = leb128::read::unsigned(input)?;
-                let mut fields = Vec::with_capacity(num_fields as usize);
+                let mut fields = Vec::with_capacity(header.check_count("type", num_fields)?);
                 for _ in 0..num_fields {
-                    let field_name = read_string_id(input)?;
+                    let field_name = read_string_id(input, num_strings)?;
                     // important: we do not want to resolve aliases here!
-                    let field_tpe = read_type_id(input)?;
+                    let field_tpe = read_type_id(input, num_types)?;
                     fields.push((field_name, field_tpe));
                 }
                 VhdlType::from_record(name, fields)
@@ -418,12 +450,17 @@ fn read_type_section(
             GhwRtik::SubtypeRecord => {
                 // a subtype is a constraint version of the original type
                 // like: https://stackoverflow.com/questions/61895716/what-is-the-point-of-a-subtype-when-a-type-can-be-constrained
-                let base = read_type_id(input)?;
-                debug_assert!(types[base.index()].is_record());
+                let base = read_type_id(input, num_types)?;
+                if !types[base.index()].is_record() {
+                    return Err(GhwParseError::UnexpectedType(
+                        format!("{:?}", types[base.index()]),
+                        "expected record",
+                    ));
+                }
                 // construct a finite version of the base type by recursively reading bounds
                 read_subtype_bounds(input, &mut types, name, base)?
             }
-            other => todo!("Support: {other:?}"),
+            other => return Err(GhwParseError::Unsupported(format!("{other:?}"))),
         };
         debug_assert!(matches!(types[new_tpe_id as usize], VhdlType::Missing));
         types[new_tpe_id as usize] = tpe;
@@ -463,7 +500,7 @@ fn read_subtype_bounds(
             // restrict array size along dimension zero (we only support 1D arrays at the moment)
             let range = read_range(input)?;
             // create new type with restricted range
-            Ok(VhdlType::from_subtype_array(name, types, base, range, None))
+            VhdlType::from_subtype_array(name, types, base, range, None)
         }
         VhdlType::Array(_, el, _) => {
             // restrict array size along dimension zero (we only support 1D arrays at the moment)
@@ -477,8 +514,7 @@ fn read_subtype_bounds(
                 Some(type_to_id(types, restricted))
             };
             // create new type with restricted range and potentially a new, restricted element type
-            let sub = VhdlType::from_subtype_array(name, types, base, range, sub_element_tpe);
-            Ok(sub)
+            VhdlType::from_subtype_array(name, types, base, range, sub_element_tpe)
         }
         VhdlType::Record(base_name, base_fields) => {
             // check to see if any of the base record fields need are missing a range
@@ -497,14 +533,32 @@ fn read_subtype_bounds(
             }
 
             if is_finite {
+                let base = lookup_concrete_type_id(types, base);
                 Ok(VhdlType::TypeAlias(pick_best_name(name, base_name), base))
             } else {
                 Ok(VhdlType::Record(pick_best_name(name, base_name), fields))
             }
         }
-        other => unreachable!(
-            "This function is only expected to be called for arrays or records, not {other:?}"
-        ),
+        other => Err(GhwParseError::UnexpectedType(
+            format!("{other:?}"),
+            "only arrays and records can be constrained",
+        )),
+    }
+}
+
+/// Subtypes may only restrict the range of their base type.
+fn check_sub_range(
+    is_subset: bool,
+    range: &impl std::fmt::Debug,
+    base: &impl std::fmt::Debug,
+) -> Result<()> {
+    if is_subset {
+        Ok(())
+    } else {
+        Err(GhwParseError::FailedToParseSection(
+            "type",
+            format!("subtype range {range:?} is not part of {base:?}"),
+        ))
     }
 }
 
@@ -534,7 +588,7 @@ enum VhdlType {
     Record(StringId, Vec<(StringId, TypeId)>),
     /// An enum that was not detected to be a 9-value bit. The last entry is a unique ID, starting at 0.
     Enum(StringId, Vec<StringId>, u16),
-    /// Array
+    /// Array, multi-dimensional arrays are represented as arrays of arrays.
     Array(StringId, TypeId, Option<IntRange>),
     /// A type that has not been read in yet. Should never be encountered.
     Missing,
@@ -574,15 +628,17 @@ impl VhdlType {
         enum_count: &mut u16,
         name: StringId,
         literals: Vec<StringId>,
-    ) -> Self {
+    ) -> Result<Self> {
         if let Some(nine_value) = try_parse_nine_value_bit(strings, name, &literals) {
-            nine_value
+            Ok(nine_value)
         } else if let Some(two_value) = try_parse_two_value_bit(strings, name, &literals) {
-            two_value
+            Ok(two_value)
         } else {
             let e = VhdlType::Enum(name, literals, *enum_count);
-            *enum_count += 1;
-            e
+            *enum_count = enum_count
+                .checked_add(1)
+                .ok_or_else(|| GhwParseError::Unsupported("more than 65535 enums".to_string()))?;
+            Ok(e)
         }
     }
 
@@ -599,18 +655,36 @@ impl VhdlType {
         }
     }
 
+    /// Used for all but the outermost dimension of a multi-dimensional array. GHDL always
+    /// supplies the bounds of all dimensions in a subtype.
+    fn from_unbounded_array(name: StringId, types: &[VhdlType], element_tpe: TypeId) -> Self {
+        let element_tpe_id = lookup_concrete_type_id(types, element_tpe);
+        match &types[element_tpe_id.index()] {
+            VhdlType::NineValueBit(_) => VhdlType::NineValueVec(name, None),
+            VhdlType::Bit(_) => VhdlType::BitVec(name, None),
+            _ => VhdlType::Array(name, element_tpe_id, None),
+        }
+    }
+
     fn from_record(name: StringId, fields: Vec<(StringId, TypeId)>) -> Self {
         VhdlType::Record(name, fields)
     }
 
-    fn from_subtype_unbounded_array(name: StringId, types: &[VhdlType], base: TypeId) -> Self {
+    fn from_subtype_unbounded_array(
+        name: StringId,
+        types: &[VhdlType],
+        base: TypeId,
+    ) -> Result<Self> {
         // removes the range of the base array
         let base_tpe = lookup_concrete_type(types, base);
         match base_tpe {
-            VhdlType::Array(_, element_tpe, _) => VhdlType::Array(name, *element_tpe, None),
-            VhdlType::NineValueVec(_, _) => VhdlType::NineValueVec(name, None),
-            VhdlType::BitVec(_, _) => VhdlType::BitVec(name, None),
-            _ => panic!("unexpected base type {base_tpe:?}, expected array"),
+            VhdlType::Array(_, element_tpe, _) => Ok(VhdlType::Array(name, *element_tpe, None)),
+            VhdlType::NineValueVec(_, _) => Ok(VhdlType::NineValueVec(name, None)),
+            VhdlType::BitVec(_, _) => Ok(VhdlType::BitVec(name, None)),
+            _ => Err(GhwParseError::UnexpectedType(
+                format!("{base_tpe:?}"),
+                "expected array",
+            )),
         }
     }
 
@@ -620,119 +694,119 @@ impl VhdlType {
         base: TypeId,
         range: Range,
         new_element_tpe: Option<TypeId>,
-    ) -> Self {
+    ) -> Result<Self> {
         let base_tpe = lookup_concrete_type(types, base);
         match (base_tpe, range) {
             (VhdlType::Array(base_name, element_tpe, maybe_base_range), Range::Int(int_range)) => {
                 if let Some(base_range) = maybe_base_range {
-                    debug_assert!(
-                        int_range.is_subset_of(base_range),
-                        "{int_range:?} {base_range:?}"
-                    );
+                    check_sub_range(int_range.is_subset_of(base_range), &int_range, base_range)?;
                 }
-                VhdlType::Array(
+                Ok(VhdlType::Array(
                     pick_best_name(name, *base_name),
                     new_element_tpe.unwrap_or(*element_tpe),
                     Some(int_range),
-                )
+                ))
             }
             (VhdlType::NineValueVec(base_name, maybe_base_range), Range::Int(int_range)) => {
                 if let Some(base_range) = maybe_base_range {
-                    debug_assert!(
-                        int_range.is_subset_of(base_range),
-                        "{int_range:?} {base_range:?}"
-                    );
+                    check_sub_range(int_range.is_subset_of(base_range), &int_range, base_range)?;
                 }
                 debug_assert!(new_element_tpe.is_none());
-                VhdlType::NineValueVec(pick_best_name(name, *base_name), Some(int_range))
+                Ok(VhdlType::NineValueVec(
+                    pick_best_name(name, *base_name),
+                    Some(int_range),
+                ))
             }
             (VhdlType::BitVec(base_name, maybe_base_range), Range::Int(int_range)) => {
                 if let Some(base_range) = maybe_base_range {
-                    debug_assert!(
-                        int_range.is_subset_of(base_range),
-                        "{int_range:?} {base_range:?}"
-                    );
+                    check_sub_range(int_range.is_subset_of(base_range), &int_range, base_range)?;
                 }
                 debug_assert!(new_element_tpe.is_none());
-                VhdlType::BitVec(pick_best_name(name, *base_name), Some(int_range))
+                Ok(VhdlType::BitVec(
+                    pick_best_name(name, *base_name),
+                    Some(int_range),
+                ))
             }
-            other => todo!("Currently unsupported combination: {other:?}"),
+            other => Err(GhwParseError::Unsupported(format!("subtype of {other:?}"))),
         }
     }
 
-    fn from_subtype_scalar(name: StringId, types: &[VhdlType], base: TypeId, range: Range) -> Self {
+    fn from_subtype_scalar(
+        name: StringId,
+        types: &[VhdlType],
+        base: TypeId,
+        range: Range,
+    ) -> Result<Self> {
         let base_tpe = lookup_concrete_type(types, base);
+        let base = lookup_concrete_type_id(types, base);
         match (base_tpe, range) {
-            (VhdlType::Enum(_, lits, _), Range::Int(int_range)) => {
-                debug_assert!(int_range.start() >= 0 && int_range.start() < lits.len() as i64);
-                debug_assert!(int_range.end() >= 0 && int_range.end() < lits.len() as i64);
-                // check to see if this is just an alias or if we need to create a new enum
-                if int_range.start() == 0 && int_range.end() == (lits.len() - 1) as i64 {
-                    VhdlType::TypeAlias(name, base)
-                } else {
-                    todo!("actual sub enum!")
-                }
-            }
-            (VhdlType::NineValueBit(_), Range::Int(int_range)) => {
-                if int_range.start() == 0 && int_range.end() == 8 {
-                    VhdlType::TypeAlias(name, base)
-                } else {
-                    todo!("actual sub enum!")
-                }
+            // Values of enum subtypes are encoded by their position in the base type,
+            // thus the subtype can share the literals of the base.
+            (
+                VhdlType::Enum(..) | VhdlType::NineValueBit(_) | VhdlType::Bit(_),
+                Range::Int(int_range),
+            ) => {
+                let base_range = base_tpe.int_range().unwrap();
+                check_sub_range(int_range.is_subset_of(&base_range), &int_range, &base_range)?;
+                Ok(VhdlType::TypeAlias(name, base))
             }
             (VhdlType::P64(_, maybe_base_range, units), Range::Int(int_range)) => {
                 let base_range = IntRange::from_i64_option(*maybe_base_range);
-                debug_assert!(
-                    int_range.is_subset_of(&base_range),
-                    "{int_range:?} {base_range:?}"
-                );
-                VhdlType::P64(name, Some(int_range), units.clone())
+                check_sub_range(int_range.is_subset_of(&base_range), &int_range, &base_range)?;
+                Ok(VhdlType::P64(name, Some(int_range), units.clone()))
             }
             (VhdlType::I32(_, maybe_base_range), Range::Int(int_range)) => {
                 let base_range = IntRange::from_i32_option(*maybe_base_range);
-                debug_assert!(
-                    int_range.is_subset_of(&base_range),
-                    "{int_range:?} {base_range:?}"
-                );
-                VhdlType::I32(name, Some(int_range))
+                check_sub_range(int_range.is_subset_of(&base_range), &int_range, &base_range)?;
+                Ok(VhdlType::I32(name, Some(int_range)))
+            }
+            (VhdlType::I64(_, maybe_base_range), Range::Int(int_range)) => {
+                let base_range = IntRange::from_i64_option(*maybe_base_range);
+                check_sub_range(int_range.is_subset_of(&base_range), &int_range, &base_range)?;
+                Ok(VhdlType::I64(name, Some(int_range)))
             }
             (VhdlType::F64(_, maybe_base_range), Range::Float(float_range)) => {
                 let base_range = FloatRange::from_f64_option(*maybe_base_range);
-                debug_assert!(
+                check_sub_range(
                     float_range.is_subset_of(&base_range),
-                    "{float_range:?} {base_range:?}"
-                );
-                VhdlType::F64(name, Some(float_range))
+                    &float_range,
+                    &base_range,
+                )?;
+                Ok(VhdlType::F64(name, Some(float_range)))
             }
-            other => todo!("Currently unsupported combination: {other:?}"),
+            other => Err(GhwParseError::Unsupported(format!("subtype of {other:?}"))),
         }
     }
 
-    fn name(&self) -> StringId {
+    fn name(&self) -> Result<StringId> {
         match self {
-            VhdlType::NineValueBit(name) => *name,
-            VhdlType::NineValueVec(name, _) => *name,
-            VhdlType::Bit(name) => *name,
-            VhdlType::BitVec(name, _) => *name,
-            VhdlType::TypeAlias(name, _) => *name,
-            VhdlType::I32(name, _) => *name,
-            VhdlType::I64(name, _) => *name,
-            VhdlType::F64(name, _) => *name,
-            VhdlType::P64(name, _, _) => *name,
-            VhdlType::Record(name, _) => *name,
-            VhdlType::Enum(name, _, _) => *name,
-            VhdlType::Array(name, _, _) => *name,
-            VhdlType::Missing => panic!("missing type, no name"),
+            VhdlType::NineValueBit(name) => Ok(*name),
+            VhdlType::NineValueVec(name, _) => Ok(*name),
+            VhdlType::Bit(name) => Ok(*name),
+            VhdlType::BitVec(name, _) => Ok(*name),
+            VhdlType::TypeAlias(name, _) => Ok(*name),
+            VhdlType::I32(name, _) => Ok(*name),
+            VhdlType::I64(name, _) => Ok(*name),
+            VhdlType::F64(name, _) => Ok(*name),
+            VhdlType::P64(name, _, _) => Ok(*name),
+            VhdlType::Record(name, _) => Ok(*name),
+            VhdlType::Enum(name, _, _) => Ok(*name),
+            VhdlType::Array(name, _, _) => Ok(*name),
+            VhdlType::Missing => Err(GhwParseError::UnexpectedType(
+                format!("{self:?}"),
+                "type was never defined",
+            )),
         }
     }
 
     fn int_range(&self) -> Option<IntRange> {
         match self {
             VhdlType::NineValueBit(_) => Some(IntRange(RangeDir::To, 0, 8)),
+            VhdlType::Bit(_) => Some(IntRange(RangeDir::To, 0, 1)),
             VhdlType::I32(_, range) => *range,
             VhdlType::I64(_, range) => *range,
             VhdlType::P64(_, range, _) => *range,
-            VhdlType::Enum(_, lits, _) => Some(IntRange(RangeDir::To, 0, lits.len() as i64)),
+            VhdlType::Enum(_, lits, _) => Some(IntRange(RangeDir::To, 0, lits.len() as i64 - 1)),
             VhdlType::NineValueVec(_, range) => *range,
             VhdlType::BitVec(_, range) => *range,
             VhdlType::Array(_, _, range) => *range,
@@ -830,6 +904,7 @@ fn check_literals_match(strings: &[String], literals: &[StringId], expected: &[u
 
 fn read_well_known_types_section(
     input: &mut impl BufRead,
+    num_types: usize,
 ) -> Result<Vec<(TypeId, GhwWellKnownType)>> {
     let mut h = [0u8; 4];
     input.read_exact(&mut h)?;
@@ -839,7 +914,7 @@ fn read_well_known_types_section(
     let mut t = read_u8(input)?;
     while t > 0 {
         let wkt = GhwWellKnownType::try_from_primitive(t)?;
-        let type_id = read_type_id(input)?;
+        let type_id = read_type_id(input, num_types)?;
         out.push((type_id, wkt));
         t = read_u8(input)?;
     }
@@ -860,11 +935,11 @@ fn pick_best_name(a: StringId, b: StringId) -> StringId {
 }
 
 impl GhwTables {
-    fn get_type_and_name(&self, type_id: TypeId) -> (&VhdlType, HierarchyStringId) {
-        let top_name = self.types[type_id.index()].name();
+    fn get_type_and_name(&self, type_id: TypeId) -> Result<(&VhdlType, HierarchyStringId)> {
+        let top_name = self.types[type_id.index()].name()?;
         let tpe = lookup_concrete_type(&self.types, type_id);
-        let name = pick_best_name(top_name, tpe.name());
-        (tpe, self.get_str(name))
+        let name = pick_best_name(top_name, tpe.name()?);
+        Ok((tpe, self.get_str(name)))
     }
 
     fn get_str(&self, string_id: StringId) -> HierarchyStringId {
@@ -890,6 +965,7 @@ fn read_hierarchy_section(
 
     let mut num_declared_vars = 0;
     let mut index_string_cache = IndexCache::default();
+    let max_signal_id = header.check_count("hierarchy", max_signal_id as u64)?;
     let mut signal_info = GhwSignalTracker::new(max_signal_id);
 
     loop {
@@ -900,10 +976,15 @@ fn read_hierarchy_section(
             GhwHierarchyKind::EndOfScope => {
                 h.pop_scope();
             }
-            GhwHierarchyKind::Design => unreachable!(),
+            GhwHierarchyKind::Design => {
+                return Err(GhwParseError::FailedToParseSection(
+                    "hierarchy",
+                    "unexpected design entry".to_string(),
+                ));
+            }
             GhwHierarchyKind::Process => {
                 // for now we ignore processes since they seem to only add noise!
-                let _process_name = read_string_id(input)?;
+                let _process_name = read_string_id(input, tables.strings.len())?;
             }
             GhwHierarchyKind::Block
             | GhwHierarchyKind::GenerateIf
@@ -940,7 +1021,7 @@ fn read_hierarchy_section(
         }
     }
 
-    let (decode_info, aliases) = signal_info.into_decode_info();
+    let (decode_info, aliases) = signal_info.into_decode_info()?;
     for alias in aliases.into_iter() {
         h.add_slice(alias.signal_ref, alias.msb, alias.lsb, alias.sliced_signal);
     }
@@ -964,8 +1045,11 @@ fn read_signal_value_to_str(
         VhdlType::F64(_, _) => {
             format!("{}", read_f64_le(input)?)
         }
-        VhdlType::TypeAlias(_, _) => unreachable!("type should have been resolved"),
-        other => todo!("add support for {other:?}"),
+        other => {
+            return Err(GhwParseError::Unsupported(format!(
+                "generate iterator of type {other:?}"
+            )));
+        }
     };
     Ok(s)
 }
@@ -976,12 +1060,12 @@ fn read_hierarchy_scope(
     kind: GhwHierarchyKind,
     h: &mut HierarchyBuilder,
 ) -> Result<()> {
-    let name = read_string_id(input)?;
+    let name = read_string_id(input, tables.strings.len())?;
     let name = tables.get_str(name);
 
     let name = if kind == GhwHierarchyKind::GenerateFor {
-        let iter_tpe_id = read_type_id(input)?;
-        let (iter_tpe, _) = tables.get_type_and_name(iter_tpe_id);
+        let iter_tpe_id = read_type_id(input, tables.types.len())?;
+        let (iter_tpe, _) = tables.get_type_and_name(iter_tpe_id)?;
         let value = read_signal_value_to_str(tables, iter_tpe, input)?;
         // create a new name based on the value
         let name = format!("{}({})", h.get_str(name), value);
@@ -1025,9 +1109,9 @@ fn read_hierarchy_var(
     index_string_cache: &mut IndexCache,
     h: &mut HierarchyBuilder,
 ) -> Result<()> {
-    let name_id = read_string_id(input)?;
+    let name_id = read_string_id(input, tables.strings.len())?;
     let name = tables.get_str(name_id);
-    let tpe = read_type_id(input)?;
+    let tpe = read_type_id(input, tables.types.len())?;
     add_var(
         tables,
         input,
@@ -1080,8 +1164,8 @@ struct AliasInfo {
 }
 
 impl GhwSignalTracker {
-    fn new(max_signal_id: u32) -> Self {
-        let signals = vec![None; max_signal_id as usize];
+    fn new(max_signal_id: usize) -> Self {
+        let signals = vec![None; max_signal_id];
         Self {
             signals,
             signal_ref_count: 0,
@@ -1090,10 +1174,22 @@ impl GhwSignalTracker {
         }
     }
 
-    fn into_decode_info(self) -> (GhwDecodeInfo, Vec<AliasInfo>) {
+    fn into_decode_info(self) -> Result<(GhwDecodeInfo, Vec<AliasInfo>)> {
+        // value changes are decoded by signal id, thus only ids at the very end may be unused
+        let declared = self
+            .signals
+            .iter()
+            .rposition(|s| s.is_some())
+            .map_or(0, |i| i + 1);
+        if let Some(missing) = self.signals[..declared].iter().position(|s| s.is_none()) {
+            return Err(GhwParseError::FailedToParseSection(
+                "hierarchy",
+                format!("signal id {} is never declared", missing + 1),
+            ));
+        }
         let mut signals: Vec<_> = self.signals.into_iter().flatten().collect();
         signals.shrink_to_fit();
-        ((GhwSignals::new(signals), self.vectors), self.aliases)
+        Ok(((GhwSignals::new(signals), self.vectors), self.aliases))
     }
 
     fn max_signal_id(&self) -> usize {
@@ -1101,16 +1197,21 @@ impl GhwSignalTracker {
     }
 
     /// used for f64, i32, i64 and u8
-    fn register_scalar(&mut self, signal_id: GhwSignalId, tpe: SignalType) -> SignalRef {
+    fn register_scalar(&mut self, signal_id: GhwSignalId, tpe: SignalType) -> Result<SignalRef> {
         if let Some(prev) = &self.signals[signal_id.index()] {
-            debug_assert_eq!(prev.tpe(), tpe, "{signal_id:?}");
-            prev.signal_ref()
+            if prev.tpe() != tpe {
+                return Err(GhwParseError::FailedToParseSection(
+                    "hierarchy",
+                    format!("{signal_id:?} is used with {:?} and {tpe:?}", prev.tpe()),
+                ));
+            }
+            Ok(prev.signal_ref())
         } else {
             // create new id
             let id = self.new_signal_ref();
             // we do not need to store any extra aliasing info
             self.signals[signal_id.index()] = Some(GhwSignalInfo::new(tpe, id, None));
-            id
+            Ok(id)
         }
     }
 
@@ -1169,26 +1270,27 @@ impl GhwSignalTracker {
         min_id: GhwSignalId,
         max_id: GhwSignalId,
         is_binary: bool,
-    ) -> SignalRef {
+    ) -> Result<SignalRef> {
         let (min, max) = (min_id.index(), max_id.index());
-        debug_assert!(max >= min);
 
         // check to see if there is already a vector that this signal aliases with
-        let maybe_vector_id = self.find_vec(min, max);
+        let maybe_vector_id = self.find_vec(min, max)?;
         if let Some(vector_id) = maybe_vector_id {
             let vector = &self.vectors[vector_id.index()];
             let (prev_min, prev_max) = (vector.min().index(), vector.max().index());
             if max == prev_max && min == prev_min {
                 // perfect alias -> just return the signal ref
-                self.signals[min].unwrap().signal_ref()
+                Ok(self.signals[min].unwrap().signal_ref())
             } else if prev_min <= min && prev_max >= max {
                 // this means that a larger vector already exists and our signal is a slice of this vector
                 let (msb, lsb) = (max - prev_min, min - prev_min);
-                self.find_or_add_alias(vector_id, msb as u32, lsb as u32)
+                Ok(self.find_or_add_alias(vector_id, msb as u32, lsb as u32))
             } else if prev_min >= min && prev_max <= max {
-                todo!("super signal alias!")
+                Err(GhwParseError::Unsupported("super signal alias".to_string()))
             } else {
-                todo!("overlapped aliased vectors")
+                Err(GhwParseError::Unsupported(
+                    "overlapped aliased vectors".to_string(),
+                ))
             }
         } else {
             // no existing vector to alias with
@@ -1204,10 +1306,11 @@ impl GhwSignalTracker {
                 for ii in min..=max {
                     // this situation is very unlikely since assigning bit ids first would make it harder for GHDL
                     // to assign contiguous vec ids
-                    assert!(
-                        self.signals[ii].is_none(),
-                        "TODO: deal with bit that aliases with vector"
-                    );
+                    if self.signals[ii].is_some() {
+                        return Err(GhwParseError::Unsupported(
+                            "bit that aliases with vector".to_string(),
+                        ));
+                    }
                 }
 
                 // create a new vec entry
@@ -1225,30 +1328,31 @@ impl GhwSignalTracker {
                 for ii in min..=max {
                     self.signals[ii] = Some(GhwSignalInfo::new(tpe, id, Some(vec_id)));
                 }
-                id
+                Ok(id)
             }
         }
     }
 
     /// Searches for vector info in the specified range.
     #[inline]
-    fn find_vec(&self, min: usize, max: usize) -> Option<GhwVecId> {
+    fn find_vec(&self, min: usize, max: usize) -> Result<Option<GhwVecId>> {
         let mut res = None;
         for ii in min..=max {
             if let Some(info) = &self.signals[ii] {
                 let vec_id = info.vec_id();
-                debug_assert!(
-                    res.is_none() || vec_id.is_none() || res == vec_id,
-                    "Signal spans several sub-vectors"
-                );
+                if res.is_some() && vec_id.is_some() && res != vec_id {
+                    return Err(GhwParseError::Unsupported(
+                        "signal that spans several sub-vectors".to_string(),
+                    ));
+                }
                 res = vec_id;
                 // early returns in release mode
                 if !cfg!(debug_assertions) && res.is_some() {
-                    return res;
+                    return Ok(res);
                 }
             }
         }
-        res
+        Ok(res)
     }
 }
 
@@ -1263,14 +1367,14 @@ fn add_var(
     name: HierarchyStringId,
     type_id: TypeId,
 ) -> Result<()> {
-    let (vhdl_tpe, tpe_name) = tables.get_type_and_name(type_id);
+    let (vhdl_tpe, tpe_name) = tables.get_type_and_name(type_id)?;
     let dir = convert_kind_to_dir(kind);
     match vhdl_tpe {
         VhdlType::Enum(_, literals, enum_id) => {
             let enum_type = tables.enums[*enum_id as usize];
             let index = read_signal_id(input, signals.max_signal_id())?;
             let bits = get_enum_bits(literals);
-            let signal_ref = signals.register_scalar(index, SignalType::U8);
+            let signal_ref = signals.register_scalar(index, SignalType::U8)?;
             h.add_var(
                 name,
                 VarType::Enum,
@@ -1285,7 +1389,7 @@ fn add_var(
         VhdlType::NineValueBit(_) | VhdlType::Bit(_) => {
             let index = read_signal_id(input, signals.max_signal_id())?;
             let is_binary = matches!(vhdl_tpe, VhdlType::Bit(_));
-            let signal_ref = signals.register_bit_vec(index, index, is_binary);
+            let signal_ref = signals.register_bit_vec(index, index, is_binary)?;
             let var_type = match h.get_str(tpe_name).to_ascii_lowercase().as_str() {
                 "std_ulogic" => VarType::StdULogic,
                 "std_logic" => VarType::StdLogic,
@@ -1308,7 +1412,7 @@ fn add_var(
             let _range = IntRange::from_i32_option(*maybe_range);
             let bits = 32;
             let index = read_signal_id(input, signals.max_signal_id())?;
-            let signal_ref = signals.register_scalar(index, SignalType::Leb128Signed);
+            let signal_ref = signals.register_scalar(index, SignalType::Leb128Signed)?;
             let var_type = VarType::Integer;
             h.add_var(
                 name,
@@ -1321,12 +1425,12 @@ fn add_var(
                 Some(tpe_name),
             );
         }
-        VhdlType::P64(_, maybe_range, _) => {
-            // Tentatively treat them as normal integers
+        VhdlType::P64(_, maybe_range, _) | VhdlType::I64(_, maybe_range) => {
+            // Tentatively treat physical types as normal integers
             let _range = IntRange::from_i64_option(*maybe_range);
             let bits = 64;
             let index = read_signal_id(input, signals.max_signal_id())?;
-            let signal_ref = signals.register_scalar(index, SignalType::Leb128Signed);
+            let signal_ref = signals.register_scalar(index, SignalType::Leb128Signed)?;
             let var_type = VarType::Integer;
             h.add_var(
                 name,
@@ -1343,7 +1447,7 @@ fn add_var(
             // TODO: we could use the range to deduce indices and tighter widths
             let _range = FloatRange::from_f64_option(*maybe_range);
             let index = read_signal_id(input, signals.max_signal_id())?;
-            let signal_ref = signals.register_scalar(index, SignalType::F64);
+            let signal_ref = signals.register_scalar(index, SignalType::F64)?;
             let var_type = VarType::Real;
             h.add_var(
                 name,
@@ -1363,6 +1467,12 @@ fn add_var(
                 return Ok(());
             }
 
+            if num_bits as usize > signals.max_signal_id() {
+                return Err(GhwParseError::FailedToParseSection(
+                    "hierarchy",
+                    format!("{num_bits} bit vector exceeds the number of signals"),
+                ));
+            }
             let mut signal_ids = Vec::with_capacity(num_bits as usize);
             for _ in 0..num_bits {
                 signal_ids.push(read_signal_id(input, signals.max_signal_id())?);
@@ -1374,17 +1484,20 @@ fn add_var(
                 .take(signal_ids.len() - 1)
                 .zip(signal_ids.iter().skip(1))
             {
-                debug_assert_eq!(
-                    prev.index() + 1,
-                    cur.index(),
-                    "We expected signal ids increasing by exactly 1, not {prev:?} -> {cur:?}"
-                );
+                if prev.index() + 1 != cur.index() {
+                    return Err(GhwParseError::FailedToParseSection(
+                        "hierarchy",
+                        format!(
+                            "We expected signal ids increasing by exactly 1, not {prev:?} -> {cur:?}"
+                        ),
+                    ));
+                }
             }
 
             let is_binary = matches!(vhdl_tpe, VhdlType::BitVec(_, _));
             let min = *signal_ids.first().unwrap();
             let max = *signal_ids.last().unwrap();
-            let signal_ref = signals.register_bit_vec(min, max, is_binary);
+            let signal_ref = signals.register_bit_vec(min, max, is_binary)?;
             let var_type = match h.get_str(tpe_name).to_ascii_lowercase().as_str() {
                 "std_ulogic_vector" => VarType::StdULogicVector,
                 "std_logic_vector" => VarType::StdLogicVector,
@@ -1421,6 +1534,13 @@ fn add_var(
         // we treat arrays like records
         VhdlType::Array(_, element_tpe, maybe_range) => {
             let range = IntRange::from_i32_option(*maybe_range);
+            // every element needs at least one signal
+            if range.len() > signals.max_signal_id() as i64 {
+                return Err(GhwParseError::FailedToParseSection(
+                    "hierarchy",
+                    format!("array {range:?} has more elements than there are signals"),
+                ));
+            }
             h.add_scope(name, None, ScopeType::VhdlArray, None, None, false);
             for element_id in range.range() {
                 let name = get_index_string(index_string_cache, h, element_id);
@@ -1437,14 +1557,18 @@ fn add_var(
             }
             h.pop_scope();
         }
-        other => todo!("deal with {other:?}"),
+        other => {
+            return Err(GhwParseError::Unsupported(format!(
+                "variable of type {other:?}"
+            )));
+        }
     }
     Ok(())
 }
 
 fn read_signal_id(input: &mut impl BufRead, max_signal_id: usize) -> Result<GhwSignalId> {
     let index = leb128::read::unsigned(input)? as usize;
-    if index > max_signal_id {
+    if index == 0 || index > max_signal_id {
         Err(GhwParseError::FailedToParseSection(
             "hierarchy",
             format!("SignalId too large {index} > {max_signal_id}"),
@@ -1522,8 +1646,8 @@ impl IntRange {
 
     fn len(&self) -> i64 {
         match self.0 {
-            RangeDir::To => self.2 - self.1 + 1,
-            RangeDir::Downto => self.1 - self.2 + 1,
+            RangeDir::To => self.2.saturating_sub(self.1).saturating_add(1),
+            RangeDir::Downto => self.1.saturating_sub(self.2).saturating_add(1),
         }
     }
 
@@ -9,9 +9,9 @@ mod signals;
 
 pub use crate::ghw::common::GhwParseError;
 use crate::ghw::common::{GhwDecodeInfo, HeaderData};
-use crate::signals::SignalSource;
 use crate::viewers::ProgressCount;
-use crate::{Hierarchy, LoadOptions, TimeTable};
+use crate::wavemem::Reader;
+use crate::{FileFormat, Hierarchy, LoadOptions, TimeTable, WellenError};
 use std::io::{BufRead, Seek, SeekFrom};
 use std::sync::atomic::Ordering;
 
@@ -25,15 +25,52 @@ pub fn is_ghw(input: &mut (impl BufRead + Seek)) -> bool {
 
 pub type Result<T> = std::result::Result<T, GhwParseError>;
 
+/// Records the section and the position at which parsing failed.
+fn malformed(section: &'static str, input: &mut impl Seek, error: GhwParseError) -> WellenError {
+    WellenError::malformed(
+        FileFormat::Ghw,
+        section,
+        input.stream_position().ok(),
+        error,
+    )
+}
+
 pub fn read_header<R: BufRead + Seek>(
     mut input: R,
     options: &LoadOptions,
-) -> Result<(Hierarchy, ReadBodyContinuation<R>, u64)> {
-    let (hierarchy, header, decode_info, body_len) = read_header_internal(&mut input, options)?;
+) -> crate::Result<(Hierarchy, ReadBodyContinuation<R>, u64)> {
+    let (hierarchy, header, decode_info, body_len) = read_header_internal(&mut input, options)
+        .map_err(|e| malformed("header", &mut input, e))?;
     let cont = ReadBodyContinuation {
         header,
         decode_info,
-        input,
+        input: Input::Reader(input),
+        multi_thread: options.multi_thread,
+    };
+    Ok((hierarchy, cont, body_len))
+}
+
+/// Memory maps the file, so that the body can be split between threads without reading it
+/// into memory first.
+pub fn read_header_from_file<P: AsRef<std::path::Path>>(
+    filename: P,
+    options: &LoadOptions,
+) -> crate::Result<(
+    Hierarchy,
+    ReadBodyContinuation<std::io::BufReader<std::fs::File>>,
+    u64,
+)> {
+    let input_file = std::fs::File::open(filename)?;
+    let mmap = unsafe { memmap2::Mmap::map(&input_file)? };
+    let mut input = std::io::Cursor::new(&mmap[..]);
+    let (hierarchy, header, decode_info, body_len) = read_header_internal(&mut input, options)
+        .map_err(|e| malformed("header", &mut input, e))?;
+    let body_start = input.position() as usize;
+    let cont = ReadBodyContinuation {
+        header,
+        decode_info,
+        input: Input::Mmap(mmap, body_start),
+        multi_thread: options.multi_thread,
     };
     Ok((hierarchy, cont, body_len))
 }
@@ -42,36 +79,79 @@ pub fn read_body<R: BufRead + Seek>(
     data: ReadBodyContinuation<R>,
     hierarchy: &Hierarchy,
     progress: Option<ProgressCount>,
-) -> Result<(SignalSource, TimeTable)> {
-    let mut input = data.input;
+) -> crate::Result<(Reader, TimeTable)> {
+    match data.input {
+        Input::Mmap(mmap, body_start) if data.multi_thread => signals::read_signals_multi_threaded(
+            &data.header,
+            data.decode_info,
+            hierarchy,
+            &mmap[body_start..],
+            progress,
+        )
+        .map_err(|(offset, e)| {
+            WellenError::malformed(
+                FileFormat::Ghw,
+                "value changes",
+                Some(body_start as u64 + offset),
+                e,
+            )
+        }),
+        Input::Mmap(mmap, body_start) => {
+            let mut input = std::io::Cursor::new(&mmap[..]);
+            input.set_position(body_start as u64);
+            read_body_single_threaded(data.header, data.decode_info, hierarchy, input, progress)
+        }
+        Input::Reader(input) => {
+            read_body_single_threaded(data.header, data.decode_info, hierarchy, input, progress)
+        }
+    }
+}
+
+fn read_body_single_threaded(
+    header: HeaderData,
+    decode_info: GhwDecodeInfo,
+    hierarchy: &Hierarchy,
+    mut input: impl BufRead + Seek,
+    progress: Option<ProgressCount>,
+) -> crate::Result<(Reader, TimeTable)> {
     match progress {
         Some(p) => {
             let mut wrapped = ProgressTracker::new(input, p);
-            signals::read_signals(&data.header, data.decode_info, hierarchy, &mut wrapped)
+            signals::read_signals(&header, decode_info, hierarchy, &mut wrapped)
+                .map_err(|e| malformed("value changes", &mut wrapped.inner, e))
         }
-        None => signals::read_signals(&data.header, data.decode_info, hierarchy, &mut input),
+        None => signals::read_signals(&header, decode_info, hierarchy, &mut input)
+            .map_err(|e| malformed("value changes", &mut input, e)),
     }
 }
 
 pub struct ReadBodyContinuation<R: BufRead + Seek> {
     header: HeaderData,
     decode_info: GhwDecodeInfo,
-    input: R,
+    input: Input<R>,
+    multi_thread: bool,
+}
+
+enum Input<R: BufRead + Seek> {
+    Reader(R),
+    /// Memory mapped file and the offset at which the body starts.
+    Mmap(memmap2::Mmap, usize),
 }
 
 fn read_header_internal(
     input: &mut (impl BufRead + Seek),
-    _options: &LoadOptions,
+    options: &LoadOptions,
 ) -> Result<(Hierarchy, HeaderData, GhwDecodeInfo, u64)> {
-    let header = hierarchy::read_ghw_header(input)?;
+    let mut header = hierarchy::read_ghw_header(input)?;
     let header_len = input.stream_position()?;
+    header.file_size = Some(input.seek(SeekFrom::End(0))?);
 
     // currently we do read the directory, however we are not using it yet
     let _sections = hierarchy::try_read_directory(&header, input)?;
     input.seek(SeekFrom::Start(header_len))?;
     // TODO: use actual section positions
 
-    let (decode_info, hierarchy) = hierarchy::read_hierarchy(&header, input)?;
+    let (decode_info, hierarchy) = hierarchy::read_hierarchy(&header, input, options)?;
 
     // determine body length
     let body_start = input.stream_position()?;
@@ -4,10 +4,12 @@
 // author: Kevin Laeufer <laeufer@cornell.edu>
 
 use crate::ghw::common::*;
-use crate::signals::SignalSource;
-use crate::wavemem::{Encoder, States};
+use crate::viewers::ProgressCount;
+use crate::wavemem::{Encoder, Reader, States};
 use crate::{Hierarchy, SignalRef, TimeTable};
-use std::io::BufRead;
+use rayon::prelude::*;
+use std::io::{BufRead, Cursor};
+use std::sync::atomic::Ordering;
 
 /// Reads the GHW signal values. `input` should be advanced until right after the end of hierarchy
 pub fn read_signals(
@@ -15,11 +17,229 @@ pub fn read_signals(
     decode_info: GhwDecodeInfo,
     hierarchy: &Hierarchy,
     input: &mut impl BufRead,
-) -> Result<(SignalSource, TimeTable)> {
+) -> Result<(Reader, TimeTable)> {
     let (info, vectors) = decode_info;
-    // TODO: multi-threading
-    let mut encoder = Encoder::new(hierarchy);
-    let mut vecs = VecBuffer::from_vec_info(vectors);
+    let mut decoder = Decoder {
+        info: &info,
+        vecs: VecBuffer::from_vec_info(vectors),
+        enc: Encoder::new(hierarchy),
+        remaining_steps: usize::MAX,
+    };
+    read_sections(header, &info, &mut decoder, input, None)?;
+    Ok(decoder.enc.finish())
+}
+
+/// The minimum number of bytes we want to decode per thread.
+#[cfg(not(test))]
+const MIN_CHUNK_SIZE: usize = 8 * 1024;
+/// Our test inputs are small, thus we split them into much smaller chunks.
+#[cfg(test)]
+const MIN_CHUNK_SIZE: usize = 64;
+
+/// Reads the GHW signal values with multiple threads. `body` contains everything after the end of
+/// the hierarchy. On failure, the offset of the error inside the body is returned.
+pub fn read_signals_multi_threaded(
+    header: &HeaderData,
+    decode_info: GhwDecodeInfo,
+    hierarchy: &Hierarchy,
+    body: &[u8],
+    progress: Option<ProgressCount>,
+) -> std::result::Result<(Reader, TimeTable), (u64, GhwParseError)> {
+    let (info, vectors) = decode_info;
+    let vecs = VecBuffer::from_vec_info(vectors);
+
+    // A cheap first pass determines where to split the body. Since vectors are updated one bit at
+    // a time, we need to remember their value at every split point.
+    let chunk_size = std::cmp::max(
+        body.len().div_ceil(rayon::current_num_threads()),
+        MIN_CHUNK_SIZE,
+    );
+    let mut scanner = Scanner::new(&info, vecs.clone(), chunk_size);
+    let mut input = Cursor::new(body);
+    read_sections(header, &info, &mut scanner, &mut input, None)
+        .map_err(|e| (input.position(), e))?;
+    let chunks = scanner.finish();
+
+    let encoders: std::result::Result<Vec<Encoder>, _> = chunks
+        .par_iter()
+        .enumerate()
+        .map(|(ii, chunk)| {
+            let mut decoder = Decoder {
+                info: &info,
+                vecs: vecs.with_data(chunk.vec_data.clone()),
+                enc: Encoder::new(hierarchy),
+                remaining_steps: chunk.steps,
+            };
+            let mut input = Cursor::new(body);
+            input.set_position(chunk.start);
+            read_sections(header, &info, &mut decoder, &mut input, chunk.cycle_time)
+                .map_err(|e| (input.position(), e))?;
+            if let Some(p) = &progress {
+                let end = chunks
+                    .get(ii + 1)
+                    .map(|c| c.start)
+                    .unwrap_or(body.len() as u64);
+                p.fetch_add(end - chunk.start, Ordering::SeqCst);
+            }
+            Ok(decoder.enc)
+        })
+        .collect();
+
+    // combine encoders
+    let mut encoder_iter = encoders?.into_iter();
+    let mut encoder = encoder_iter.next().unwrap();
+    for other in encoder_iter {
+        encoder.append(other);
+    }
+    Ok(encoder.finish())
+}
+
+/// Receives the contents of the signal sections.
+trait Visitor<R: BufRead> {
+    fn time_change(&mut self, time: u64);
+    fn signal_value(&mut self, signal_id: GhwSignalId, input: &mut R) -> Result<()>;
+    fn finish_time_step(&mut self);
+    /// Called at the start of every new time step inside of a cycle section. At this point,
+    /// all vector changes have been dispatched, thus decoding could start from here.
+    /// Returns false in order to stop reading.
+    fn next_step(&mut self, time: u64, input: &R) -> bool;
+}
+
+/// Decodes signal values into an encoder.
+struct Decoder<'a> {
+    info: &'a GhwSignals,
+    vecs: VecBuffer,
+    enc: Encoder,
+    /// number of `next_step` calls until we stop
+    remaining_steps: usize,
+}
+
+impl<R: BufRead> Visitor<R> for Decoder<'_> {
+    fn time_change(&mut self, time: u64) {
+        self.enc.time_change(time);
+    }
+
+    fn signal_value(&mut self, signal_id: GhwSignalId, input: &mut R) -> Result<()> {
+        read_signal_value(self.info, signal_id, &mut self.vecs, &mut self.enc, input)
+    }
+
+    fn finish_time_step(&mut self) {
+        finish_time_step(&mut self.vecs, &mut self.enc);
+    }
+
+    fn next_step(&mut self, _time: u64, _input: &R) -> bool {
+        self.remaining_steps -= 1;
+        self.remaining_steps > 0
+    }
+}
+
+/// A part of the body that can be decoded independently.
+struct Chunk {
+    /// byte offset into the body
+    start: u64,
+    /// Set if the chunk starts with a time step inside a cycle section.
+    cycle_time: Option<u64>,
+    /// Number of time steps until the next chunk starts.
+    steps: usize,
+    /// Value of all vectors at the start of the chunk.
+    vec_data: Vec<u8>,
+}
+
+/// Skips over all signal values in order to find good places to split the body.
+struct Scanner<'a> {
+    info: &'a GhwSignals,
+    vecs: VecBuffer,
+    chunk_size: usize,
+    chunks: Vec<Chunk>,
+    steps: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(info: &'a GhwSignals, vecs: VecBuffer, chunk_size: usize) -> Self {
+        let first = Chunk {
+            start: 0,
+            cycle_time: None,
+            steps: usize::MAX,
+            vec_data: vecs.data.clone(),
+        };
+        Self {
+            info,
+            vecs,
+            chunk_size,
+            chunks: vec![first],
+            steps: 0,
+        }
+    }
+
+    fn finish(self) -> Vec<Chunk> {
+        self.chunks
+    }
+}
+
+impl Visitor<Cursor<&[u8]>> for Scanner<'_> {
+    fn time_change(&mut self, _time: u64) {}
+
+    fn signal_value(&mut self, signal_id: GhwSignalId, input: &mut Cursor<&[u8]>) -> Result<()> {
+        let signal_info = self.info.get_info(signal_id)?;
+        match signal_info.tpe() {
+            SignalType::NineState | SignalType::TwoState | SignalType::U8 => {
+                read_u8(input)?;
+            }
+            tpe @ (SignalType::NineStateVec | SignalType::TwoStateVec) => {
+                let ghdl_value = read_u8(input)?;
+                let value = if tpe == SignalType::NineStateVec {
+                    std_logic_value(ghdl_value)?
+                } else {
+                    bit_value(ghdl_value)?
+                };
+                let vec_id = signal_info.vec_id().ok_or_else(|| {
+                    GhwParseError::FailedToParseSection("signal", "missing vector info".to_string())
+                })?;
+                self.vecs.set_bit(vec_id, signal_id, value);
+            }
+            SignalType::Leb128Signed => {
+                leb128::read::signed(input)?;
+            }
+            SignalType::F64 => {
+                read_f64_le(input)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish_time_step(&mut self) {}
+
+    fn next_step(&mut self, time: u64, input: &Cursor<&[u8]>) -> bool {
+        self.steps += 1;
+        let last = self.chunks.last_mut().unwrap();
+        if input.position() - last.start >= self.chunk_size as u64 {
+            last.steps = self.steps;
+            self.steps = 0;
+            self.chunks.push(Chunk {
+                start: input.position(),
+                cycle_time: Some(time),
+                steps: usize::MAX,
+                vec_data: self.vecs.data.clone(),
+            });
+        }
+        true
+    }
+}
+
+/// Reads sections until the tailer is reached or the visitor asks us to stop. If `cycle_time`
+/// is set, `input` points to a time step in the middle of a cycle section.
+fn read_sections<R: BufRead>(
+    header: &HeaderData,
+    info: &GhwSignals,
+    visitor: &mut impl Visitor<R>,
+    input: &mut R,
+    cycle_time: Option<u64>,
+) -> Result<()> {
+    if let Some(time) = cycle_time {
+        if !read_cycle_steps(visitor, input, time)? {
+            return Ok(());
+        }
+    }
 
     // loop over signal sections
     loop {
@@ -28,10 +248,12 @@ pub fn read_signals(
 
         // read_sm_hdr
         match &mark {
-            GHW_SNAPSHOT_SECTION => {
-                read_snapshot_section(header, &info, &mut vecs, &mut encoder, input)?
+            GHW_SNAPSHOT_SECTION => read_snapshot_section(header, info, visitor, input)?,
+            GHW_CYCLE_SECTION => {
+                if !read_cycle_section(header, visitor, input)? {
+                    return Ok(());
+                }
             }
-            GHW_CYCLE_SECTION => read_cycle_section(header, &info, &mut vecs, &mut encoder, input)?,
             GHW_DIRECTORY_SECTION => {
                 // skip the directory by reading it
                 let _ = read_directory(header, input)?;
@@ -42,7 +264,7 @@ pub fn read_signals(
                 // 100%
                 let mut tailer_body = [0u8; GHW_TAILER_LEN - GHW_TAILER_SECTION.len()];
                 input.read_exact(&mut tailer_body)?;
-                break;
+                return Ok(());
             }
             other => {
                 return Err(GhwParseError::UnexpectedSection(
@@ -51,15 +273,13 @@ pub fn read_signals(
             }
         }
     }
-    Ok(encoder.finish())
 }
 
-fn read_snapshot_section(
+fn read_snapshot_section<R: BufRead>(
     header: &HeaderData,
     info: &GhwSignals,
-    vecs: &mut VecBuffer,
-    enc: &mut Encoder,
-    input: &mut impl BufRead,
+    visitor: &mut impl Visitor<R>,
+    input: &mut R,
 ) -> Result<()> {
     let mut h = [0u8; 12];
     input.read_exact(&mut h)?;
@@ -67,72 +287,80 @@ fn read_snapshot_section(
 
     // time in femto seconds
     let start_time = header.read_i64(&mut &h[4..12])? as u64;
-    enc.time_change(start_time);
+    visitor.time_change(start_time);
 
     for sig_index in 0..(info.signal_len() as u32) {
-        read_signal_value(info, GhwSignalId::new(sig_index + 1), vecs, enc, input)?;
+        visitor.signal_value(GhwSignalId::new(sig_index + 1), input)?;
     }
-    finish_time_step(vecs, enc);
+    visitor.finish_time_step();
 
     // check for correct end magic
     check_magic_end(input, "snapshot", GHW_END_SNAPSHOT_SECTION)?;
     Ok(())
 }
 
-fn read_cycle_section(
+/// Returns false if the visitor asked us to stop before the end of the section.
+fn read_cycle_section<R: BufRead>(
     header: &HeaderData,
-    info: &GhwSignals,
-    vecs: &mut VecBuffer,
-    enc: &mut Encoder,
-    input: &mut impl BufRead,
-) -> Result<()> {
+    visitor: &mut impl Visitor<R>,
+    input: &mut R,
+) -> Result<bool> {
     let mut h = [0u8; 8];
     input.read_exact(&mut h)?;
     // note: cycle sections do not have the four zero bytes!
 
     // time in femto seconds
-    let mut start_time = header.read_i64(&mut &h[..])? as u64;
+    let start_time = header.read_i64(&mut &h[..])? as u64;
+    read_cycle_steps(visitor, input, start_time)
+}
 
+fn read_cycle_steps<R: BufRead>(
+    visitor: &mut impl Visitor<R>,
+    input: &mut R,
+    mut time: u64,
+) -> Result<bool> {
     loop {
-        enc.time_change(start_time);
-        read_cycle_signals(info, vecs, enc, input)?;
-        finish_time_step(vecs, enc);
+        visitor.time_change(time);
+        read_cycle_signals(visitor, input)?;
+        visitor.finish_time_step();
 
         let time_delta = leb128::read::signed(input)?;
         if time_delta < 0 {
             break; // end of cycle
-        } else {
-            start_time += time_delta as u64;
+        }
+        time = time.wrapping_add(time_delta as u64);
+        // a time step without progress would be merged with the previous one
+        if time_delta > 0 && !visitor.next_step(time, input) {
+            return Ok(false);
         }
     }
 
     // check cycle end
     check_magic_end(input, "cycle", GHW_END_CYCLE_SECTION)?;
 
-    Ok(())
+    Ok(true)
 }
 
-fn read_cycle_signals(
-    info: &GhwSignals,
-    vecs: &mut VecBuffer,
-    enc: &mut Encoder,
-    input: &mut impl BufRead,
-) -> Result<()> {
-    let mut pos_signal_index = 0;
+fn read_cycle_signals<R: BufRead>(visitor: &mut impl Visitor<R>, input: &mut R) -> Result<()> {
+    let mut pos_signal_index: u32 = 0;
     loop {
         let delta = leb128::read::unsigned(input)? as usize;
         if delta == 0 {
             break;
         }
-        pos_signal_index += delta;
-        if pos_signal_index == 0 {
-            return Err(GhwParseError::FailedToParseSection(
-                "cycle",
-                "Expected a first delta > 0".to_string(),
-            ));
-        }
-        let sig_id = GhwSignalId::new(pos_signal_index as u32);
-        read_signal_value(info, sig_id, vecs, enc, input)?;
+        pos_signal_index = match u32::try_from(delta)
+            .ok()
+            .and_then(|d| pos_signal_index.checked_add(d))
+        {
+            Some(index) => index,
+            None => {
+                return Err(GhwParseError::FailedToParseSection(
+                    "cycle",
+                    format!("signal index overflow with delta {delta}"),
+                ));
+            }
+        };
+        visitor.signal_value(GhwSignalId::new(pos_signal_index), input)?;
     }
     Ok(())
 }
@@ -151,29 +379,29 @@ fn read_signal_value(
     enc: &mut Encoder,
     input: &mut impl BufRead,
 ) -> Result<()> {
-    let signal_info = info.get_info(signal_id);
+    let signal_info = info.get_info(signal_id)?;
     let (tpe, signal_ref) = (signal_info.tpe(), signal_info.signal_ref());
     match tpe {
         SignalType::NineState => {
             let ghdl_value = read_u8(input)?;
-            let value = [STD_LOGIC_LUT[ghdl_value as usize]];
+            let value = [std_logic_value(ghdl_value)?];
             enc.raw_value_change(signal_ref, &value, States::Nine);
         }
         SignalType::TwoState => {
-            let value = [read_u8(input)?];
-            debug_assert!(value[0] <= 1);
+            let value = [bit_value(read_u8(input)?)?];
             enc.raw_value_change(signal_ref, &value, States::Two);
         }
         SignalType::NineStateVec | SignalType::TwoStateVec => {
             let ghdl_value = read_u8(input)?;
             let (value, states) = if tpe == SignalType::NineStateVec {
-                (STD_LOGIC_LUT[ghdl_value as usize], States::Nine)
+                (std_logic_value(ghdl_value)?, States::Nine)
             } else {
-                debug_assert!(ghdl_value <= 1);
-                (ghdl_value, States::Two)
+                (bit_value(ghdl_value)?, States::Two)
             };
 
-            let vec_id = signal_info.vec_id().unwrap();
+            let vec_id = signal_info.vec_id().ok_or_else(|| {
+                GhwParseError::FailedToParseSection("signal", "missing vector info".to_string())
+            })?;
 
             // check to see if we already had a change to this same bit in the current time step
             if vecs.is_second_change(vec_id, signal_id, value) {
@@ -211,7 +439,7 @@ fn read_signal_value(
 }
 
 /// Keeps track of individual bits and combines them into a full bit vector.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct VecBuffer {
     info: Vec<VecBufferInfo>,
     data: Vec<u8>,
@@ -290,6 +518,15 @@ impl VecBuffer {
         }
     }
 
+    /// Creates a buffer with the same vectors but different values.
+    fn with_data(&self, data: Vec<u8>) -> Self {
+        debug_assert_eq!(data.len(), self.data.len());
+        Self {
+            data,
+            ..self.clone()
+        }
+    }
+
     fn process_changed_signals(&mut self, mut callback: impl FnMut(SignalRef, &[u8], States)) {
         let change_list = std::mem::take(&mut self.change_list);
         for vec_id in change_list.into_iter() {
@@ -321,6 +558,14 @@ impl VecBuffer {
         }
     }
 
+    /// Updates the value without recording a change.
+    #[inline]
+    fn set_bit(&mut self, vector_id: GhwVecId, signal_id: GhwSignalId, value: u8) {
+        let info = &self.info[vector_id.index()];
+        let bit = info.max_index - signal_id.index() as u32;
+        Self::set_value(&mut self.data, info, bit, value);
+    }
+
     /// Used in order to dispatch full signal changes as soon as possible
     #[inline]
     fn full_signal_has_changed(&self, vector_id: GhwVecId) -> bool {
@@ -451,6 +696,60 @@ fn get_data_index(bits: u32, bit: u32, states: States) -> (usize, usize) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{LoadOptions, simple};
+    use std::io::Read;
+
+    /// Compares multi-threaded against single-threaded loading and makes sure that the body
+    /// actually gets split.
+    #[test]
+    fn test_multi_threaded_matches_single_threaded() {
+        for filename in [
+            "inputs/ghdl/oscar/test.ghw",
+            "inputs/ghdl/tb_recv.ghw",
+            "inputs/ghdl/wellen_issue_53.ghw",
+        ] {
+            let mut input = std::io::BufReader::new(std::fs::File::open(filename).unwrap());
+            let (_, header, (info, vectors), _) =
+                crate::ghw::read_header_internal(&mut input, &LoadOptions::default()).unwrap();
+            let mut body = Vec::new();
+            input.read_to_end(&mut body).unwrap();
+            let mut scanner =
+                Scanner::new(&info, VecBuffer::from_vec_info(vectors), MIN_CHUNK_SIZE);
+            read_sections(
+                &header,
+                &info,
+                &mut scanner,
+                &mut Cursor::new(&body[..]),
+                None,
+            )
+            .unwrap();
+            assert!(scanner.finish().len() > 1, "{filename} was not split");
+
+            let single_thread = LoadOptions {
+                multi_thread: false,
+                ..Default::default()
+            };
+            let mut expected = simple::read_with_options(filename, &single_thread).unwrap();
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(4)
+                .build()
+                .unwrap();
+            let mut actual = pool.install(|| simple::read(filename).unwrap());
+            assert_eq!(expected.time_table(), actual.time_table());
+            let signals: Vec<_> = expected
+                .hierarchy()
+                .get_unique_signals_vars()
+                .iter()
+                .flatten()
+                .map(|v| v.signal_ref())
+                .collect();
+            expected.load_signals(&signals).unwrap();
+            actual.load_signals(&signals).unwrap();
+            for signal in signals {
+                assert_eq!(expected.get_signal(signal), actual.get_signal(signal));
+            }
+        }
+    }
 
     #[test]
     fn test_get_data_index() {
@@ -4,8 +4,14 @@
 // author: Kevin Laeufer <laeufer@cornell.edu>
 
 use crate::FileFormat;
+use crate::cache::{
+    invalid_data, read_bool, read_enum, read_id, read_int, read_required_id, read_string,
+    read_uint, write_bool, write_id, write_int, write_string, write_uint,
+};
 use indexmap::IndexSet;
+use num_enum::TryFromPrimitive;
 use rustc_hash::{FxBuildHasher, FxHashMap};
+use std::io::{Read, Write};
 use std::num::{NonZeroI32, NonZeroU16, NonZeroU32};
 use std::ops::Index;
 
@@ -22,7 +28,8 @@ impl Timescale {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, TryFromPrimitive)]
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 pub enum TimescaleUnit {
     ZeptoSeconds,
@@ -120,7 +127,8 @@ impl HierarchyStringId {
     }
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, TryFromPrimitive)]
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub enum ScopeType {
@@ -155,7 +163,8 @@ pub enum ScopeType {
     Unknown,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromPrimitive)]
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 pub enum VarType {
     // VCD
@@ -201,7 +210,8 @@ pub enum VarType {
 /// Signal directions of a variable. Currently these have the exact same meaning as in the FST format.
 ///
 /// For VCD inputs, all variables will be marked as `VarDirection::Unknown` since no direction information is included.
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, TryFromPrimitive)]
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 pub enum VarDirection {
     Unknown,
@@ -358,6 +368,16 @@ impl Var {
         self.enum_type.map(|id| hierarchy.get_enum_type(id))
     }
 
+    #[inline]
+    pub(crate) fn enum_type_id(&self) -> Option<EnumTypeId> {
+        self.enum_type
+    }
+
+    #[inline]
+    pub(crate) fn parent(&self) -> Option<ScopeRef> {
+        self.parent
+    }
+
     #[inline]
     pub fn vhdl_type_name<'a>(&self, hierarchy: &'a Hierarchy) -> Option<&'a str> {
         self.vhdl_type_name.map(|i| &hierarchy[i])
@@ -476,6 +496,11 @@ impl Scope {
         self.tpe
     }
 
+    #[inline]
+    pub(crate) fn parent(&self) -> Option<ScopeRef> {
+        self.parent
+    }
+
     pub fn source_loc<'a>(&self, hierarchy: &'a Hierarchy) -> Option<(&'a str, u64)> {
         self.declaration_source
             .map(|id| hierarchy.get_source_loc(id))
@@ -584,7 +609,7 @@ struct SourceLoc {
     is_instantiation: bool,
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 pub struct EnumTypeId(NonZeroU16);
 
@@ -618,6 +643,12 @@ pub struct Hierarchy {
     signal_idx_to_var: Vec<Option<VarRef>>,
     meta: HierarchyMetaData,
     slices: FxHashMap<SignalRef, SignalSlice>,
+    /// Encoding of signals that are only needed as the source of a slice, because their
+    /// variables were skipped by the scope filter.
+    slice_sources: FxHashMap<SignalRef, SignalEncoding>,
+    /// built on the first search
+    #[cfg_attr(feature = "serde1", serde(skip))]
+    search_index: std::sync::OnceLock<crate::search::SearchIndex>,
 }
 
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
@@ -762,13 +793,20 @@ impl Hierarchy {
     /// Retrieves the length of a signal identified by its id by looking up a
     /// variable that refers to the signal.
     pub fn get_signal_tpe(&self, signal_idx: SignalRef) -> Option<SignalEncoding> {
-        let var_id = (*self.signal_idx_to_var.get(signal_idx.index())?)?;
-        Some(self[var_id].signal_encoding)
+        match self.signal_idx_to_var.get(signal_idx.index())? {
+            Some(var_id) => Some(self[*var_id].signal_encoding),
+            None => self.slice_sources.get(&signal_idx).copied(),
+        }
     }
 
     pub fn get_slice_info(&self, signal_idx: SignalRef) -> Option<SignalSlice> {
         self.slices.get(&signal_idx).copied()
     }
+
+    pub(crate) fn search_index(&self) -> &crate::search::SearchIndex {
+        self.search_index
+            .get_or_init(|| crate::search::SearchIndex::new(self))
+    }
 }
 
 // private implementation
@@ -797,6 +835,326 @@ impl Hierarchy {
     }
 }
 
+// cache file serialization
+impl Hierarchy {
+    pub(crate) fn write_to_cache(&self, out: &mut impl Write) -> std::io::Result<()> {
+        write_uint(out, self.strings.len() as u64)?;
+        write_uint(out, self.vars.len() as u64)?;
+        write_uint(out, self.scopes.len() as u64)?;
+        write_uint(out, self.source_locs.len() as u64)?;
+        write_uint(out, self.enums.len() as u64)?;
+        write_uint(out, self.signal_idx_to_var.len() as u64)?;
+        for string in self.strings.iter() {
+            write_string(out, string)?;
+        }
+        for var in self.vars.iter() {
+            write_id(out, Some(var.name.0))?;
+            write_uint(out, var.var_tpe as u64)?;
+            write_uint(out, var.direction as u64)?;
+            write_encoding(out, var.signal_encoding)?;
+            match var.index {
+                None => write_bool(out, false)?,
+                Some(index) => {
+                    write_bool(out, true)?;
+                    write_int(out, index.lsb)?;
+                    write_int(out, index.width.get() as i64)?;
+                }
+            }
+            write_id(out, Some(var.signal_idx.0))?;
+            write_id(out, var.enum_type.map(|e| e.0.into()))?;
+            write_id(out, var.vhdl_type_name.map(|n| n.0))?;
+            write_id(out, var.parent.map(|p| p.0))?;
+            write_item(out, var.next)?;
+        }
+        for scope in self.scopes.iter() {
+            write_id(out, Some(scope.name.0))?;
+            write_id(out, scope.component.map(|c| c.0))?;
+            write_uint(out, scope.tpe as u64)?;
+            write_id(out, scope.declaration_source.map(|s| s.0))?;
+            write_id(out, scope.instance_source.map(|s| s.0))?;
+            write_item(out, scope.child)?;
+            write_id(out, scope.parent.map(|p| p.0))?;
+            write_item(out, scope.next)?;
+        }
+        for loc in self.source_locs.iter() {
+            write_id(out, Some(loc.path.0))?;
+            write_uint(out, loc.line)?;
+            write_bool(out, loc.is_instantiation)?;
+        }
+        for enum_tpe in self.enums.iter() {
+            write_id(out, Some(enum_tpe.name.0))?;
+            write_uint(out, enum_tpe.mapping.len() as u64)?;
+            for (a, b) in enum_tpe.mapping.iter() {
+                write_id(out, Some(a.0))?;
+                write_id(out, Some(b.0))?;
+            }
+        }
+        for var in self.signal_idx_to_var.iter() {
+            write_id(out, var.map(|v| v.0))?;
+        }
+        match self.meta.timescale {
+            None => write_bool(out, false)?,
+            Some(timescale) => {
+                write_bool(out, true)?;
+                write_uint(out, timescale.factor as u64)?;
+                write_uint(out, timescale.unit as u64)?;
+            }
+        }
+        write_string(out, &self.meta.date)?;
+        write_string(out, &self.meta.version)?;
+        write_uint(out, self.meta.comments.len() as u64)?;
+        for comment in self.meta.comments.iter() {
+            write_string(out, comment)?;
+        }
+        write_uint(out, self.meta.file_format as u64)?;
+        // sort slices in order to get a deterministic output
+        let mut slices: Vec<_> = self.slices.iter().collect();
+        slices.sort_by_key(|(id, _)| **id);
+        write_uint(out, slices.len() as u64)?;
+        for (id, slice) in slices {
+            write_id(out, Some(id.0))?;
+            write_uint(out, slice.msb as u64)?;
+            write_uint(out, slice.lsb as u64)?;
+            write_id(out, Some(slice.sliced_signal.0))?;
+        }
+        let mut slice_sources: Vec<_> = self.slice_sources.iter().collect();
+        slice_sources.sort_by_key(|(id, _)| **id);
+        write_uint(out, slice_sources.len() as u64)?;
+        for (id, encoding) in slice_sources {
+            write_id(out, Some(id.0))?;
+            write_encoding(out, *encoding)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a hierarchy from a cache file. All references are checked in order to make sure that
+    /// a corrupted cache file cannot lead to a panic later on.
+    pub(crate) fn read_from_cache(input: &mut impl Read) -> std::io::Result<Self> {
+        let num_strings = read_uint(input)? as usize;
+        let num_vars = read_uint(input)? as usize;
+        let num_scopes = read_uint(input)? as usize;
+        let num_source_locs = read_uint(input)? as usize;
+        let num_enums = read_uint(input)? as usize;
+        let num_signals = read_uint(input)? as usize;
+        let in_range = |id: NonZeroU32, len: usize| {
+            if id.get() as usize <= len {
+                Ok(id)
+            } else {
+                Err(invalid_data("reference out of range"))
+            }
+        };
+        let string = |input: &mut _| in_range(read_required_id(input)?, num_strings);
+        let opt_string = |input: &mut _| {
+            read_id(input)?
+                .map(|id| in_range(id, num_strings))
+                .transpose()
+        };
+        let signal = |input: &mut _| in_range(read_required_id(input)?, num_signals);
+        let opt_scope = |input: &mut _| {
+            read_id(input)?
+                .map(|id| in_range(id, num_scopes))
+                .transpose()
+        };
+        let opt_source_loc = |input: &mut _| {
+            read_id(input)?
+                .map(|id| in_range(id, num_source_locs))
+                .transpose()
+        };
+        let item = |input: &mut _| -> std::io::Result<Option<ScopeOrVarRef>> {
+            Ok(match read_item(input)? {
+                None => None,
+                Some(ScopeOrVarRef::Scope(s)) => Some(ScopeRef(in_range(s.0, num_scopes)?).into()),
+                Some(ScopeOrVarRef::Var(v)) => Some(VarRef(in_range(v.0, num_vars)?).into()),
+            })
+        };
+
+        let mut strings = Vec::with_capacity(std::cmp::min(num_strings, 1 << 16));
+        for _ in 0..num_strings {
+            strings.push(read_string(input)?);
+        }
+        let mut vars = Vec::with_capacity(std::cmp::min(num_vars, 1 << 16));
+        for _ in 0..num_vars {
+            let name = HierarchyStringId(string(input)?);
+            let var_tpe = read_enum(input)?;
+            let direction = read_enum(input)?;
+            let signal_encoding = read_encoding(input)?;
+            let index = if read_bool(input)? {
+                let lsb = read_int(input)?;
+                let width = i32::try_from(read_int(input)?)
+                    .ok()
+                    .and_then(NonZeroI32::new)
+                    .ok_or_else(|| invalid_data("invalid index width"))?;
+                Some(VarIndex { lsb, width })
+            } else {
+                None
+            };
+            let signal_idx = SignalRef(signal(input)?);
+            let enum_type = match read_id(input)? {
+                None => None,
+                Some(id) => Some(EnumTypeId(
+                    NonZeroU16::try_from(in_range(id, num_enums)?)
+                        .map_err(|_| invalid_data("invalid enum id"))?,
+                )),
+            };
+            vars.push(Var {
+                name,
+                var_tpe,
+                direction,
+                signal_encoding,
+                index,
+                signal_idx,
+                enum_type,
+                vhdl_type_name: opt_string(input)?.map(HierarchyStringId),
+                parent: opt_scope(input)?.map(ScopeRef),
+                next: item(input)?,
+            });
+        }
+        let mut scopes = Vec::with_capacity(std::cmp::min(num_scopes, 1 << 16));
+        for _ in 0..num_scopes {
+            scopes.push(Scope {
+                name: HierarchyStringId(string(input)?),
+                component: opt_string(input)?.map(HierarchyStringId),
+                tpe: read_enum(input)?,
+                declaration_source: opt_source_loc(input)?.map(SourceLocId),
+                instance_source: opt_source_loc(input)?.map(SourceLocId),
+                child: item(input)?,
+                parent: opt_scope(input)?.map(ScopeRef),
+                next: item(input)?,
+            });
+        }
+        let mut source_locs = Vec::with_capacity(std::cmp::min(num_source_locs, 1 << 16));
+        for _ in 0..num_source_locs {
+            source_locs.push(SourceLoc {
+                path: HierarchyStringId(string(input)?),
+                line: read_uint(input)?,
+                is_instantiation: read_bool(input)?,
+            });
+        }
+        let mut enums = Vec::with_capacity(std::cmp::min(num_enums, 1 << 16));
+        for _ in 0..num_enums {
+            let name = HierarchyStringId(string(input)?);
+            let len = read_uint(input)? as usize;
+            let mut mapping = Vec::with_capacity(std::cmp::min(len, 1 << 16));
+            for _ in 0..len {
+                let a = HierarchyStringId(string(input)?);
+                let b = HierarchyStringId(string(input)?);
+                mapping.push((a, b));
+            }
+            enums.push(EnumType { name, mapping });
+        }
+        let mut signal_idx_to_var = Vec::with_capacity(std::cmp::min(num_signals, 1 << 16));
+        for _ in 0..num_signals {
+            let var = read_id(input)?
+                .map(|id| in_range(id, num_vars))
+                .transpose()?;
+            signal_idx_to_var.push(var.map(VarRef));
+        }
+        let timescale = if read_bool(input)? {
+            let factor = u32::try_from(read_uint(input)?)
+                .map_err(|_| invalid_data("invalid timescale factor"))?;
+            Some(Timescale::new(factor, read_enum(input)?))
+        } else {
+            None
+        };
+        let date = read_string(input)?;
+        let version = read_string(input)?;
+        let num_comments = read_uint(input)? as usize;
+        let mut comments = Vec::with_capacity(std::cmp::min(num_comments, 1 << 10));
+        for _ in 0..num_comments {
+            comments.push(read_string(input)?);
+        }
+        let meta = HierarchyMetaData {
+            timescale,
+            date,
+            version,
+            comments,
+            file_format: read_enum(input)?,
+        };
+        let num_slices = read_uint(input)? as usize;
+        let mut slices = FxHashMap::default();
+        for _ in 0..num_slices {
+            let id = SignalRef(signal(input)?);
+            let msb = read_uint(input)? as u32;
+            let lsb = read_uint(input)? as u32;
+            let sliced_signal = SignalRef(signal(input)?);
+            slices.insert(
+                id,
+                SignalSlice {
+                    msb,
+                    lsb,
+                    sliced_signal,
+                },
+            );
+        }
+        let num_slice_sources = read_uint(input)? as usize;
+        let mut slice_sources = FxHashMap::default();
+        for _ in 0..num_slice_sources {
+            let id = SignalRef(signal(input)?);
+            slice_sources.insert(id, read_encoding(input)?);
+        }
+        Ok(Hierarchy {
+            vars,
+            scopes,
+            strings,
+            source_locs,
+            enums,
+            signal_idx_to_var,
+            meta,
+            slices,
+            slice_sources,
+            search_index: Default::default(),
+        })
+    }
+}
+
+/// Encodes an optional scope or var reference as a single integer.
+fn write_item(out: &mut impl Write, item: Option<ScopeOrVarRef>) -> std::io::Result<()> {
+    let value = match item {
+        None => 0,
+        Some(ScopeOrVarRef::Scope(s)) => (s.0.get() as u64) << 1,
+        Some(ScopeOrVarRef::Var(v)) => ((v.0.get() as u64) << 1) | 1,
+    };
+    write_uint(out, value)
+}
+
+fn read_item(input: &mut impl Read) -> std::io::Result<Option<ScopeOrVarRef>> {
+    let value = read_uint(input)?;
+    if value == 0 {
+        return Ok(None);
+    }
+    let id = u32::try_from(value >> 1)
+        .ok()
+        .and_then(NonZeroU32::new)
+        .ok_or_else(|| invalid_data("invalid scope or var reference"))?;
+    if value & 1 == 0 {
+        Ok(Some(ScopeRef(id).into()))
+    } else {
+        Ok(Some(VarRef(id).into()))
+    }
+}
+
+fn write_encoding(out: &mut impl Write, encoding: SignalEncoding) -> std::io::Result<()> {
+    let value = match encoding {
+        SignalEncoding::String => 0,
+        SignalEncoding::Real => 1,
+        SignalEncoding::BitVector(len) => len.get() as u64 + 1,
+    };
+    write_uint(out, value)
+}
+
+fn read_encoding(input: &mut impl Read) -> std::io::Result<SignalEncoding> {
+    Ok(match read_uint(input)? {
+        0 => SignalEncoding::String,
+        1 => SignalEncoding::Real,
+        len => SignalEncoding::BitVector(
+            u32::try_from(len - 1)
+                .ok()
+                .and_then(NonZeroU32::new)
+                .ok_or_else(|| invalid_data("invalid bit-vector length"))?,
+        ),
+    })
+}
+
 impl Index<VarRef> for Hierarchy {
     type Output = Var;
 
@@ -843,6 +1201,132 @@ pub struct HierarchyBuilder {
     /// deduplication strategy
     scope_child_count: Vec<u8>,
     scope_dedup_tables: FxHashMap<ScopeRef, FxHashMap<HierarchyStringId, ScopeRef>>,
+    filter: Option<Box<ScopeFilterState>>,
+    /// encoding of skipped variables, in case they turn out to be the source of a slice
+    skipped_signals: FxHashMap<SignalRef, SignalEncoding>,
+}
+
+/// Decides which scopes are part of the hierarchy, based on the scope related `LoadOptions`.
+struct ScopeFilterState {
+    include: Vec<crate::search::Pattern>,
+    exclude: Vec<crate::search::Pattern>,
+    max_depth: Option<usize>,
+    /// full name of the current scope
+    path: String,
+    /// one entry for every entry in the scope stack, as well as for all skipped scopes
+    stack: Vec<FilterStackEntry>,
+}
+
+struct FilterStackEntry {
+    /// length of the path of the parent scope
+    parent_path_len: usize,
+    depth: usize,
+    state: FilterState,
+    /// arguments of a pending scope that has not been added to the hierarchy yet
+    deferred: Option<DeferredScope>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FilterState {
+    /// scope and all of its children are included
+    Included,
+    /// scope is only included if it turns out to be the parent of an included scope
+    Pending,
+    /// scope and all of its children are skipped
+    Skipped,
+}
+
+struct DeferredScope {
+    name: HierarchyStringId,
+    component: Option<HierarchyStringId>,
+    tpe: ScopeType,
+    declaration_source: Option<SourceLocId>,
+    instance_source: Option<SourceLocId>,
+    flatten: bool,
+}
+
+impl ScopeFilterState {
+    /// Returns `None` if the options do not filter any scopes.
+    fn new(options: &crate::LoadOptions) -> Option<Self> {
+        let filter = options.scope_filter.as_ref().filter(|f| !f.is_empty())?;
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|p| crate::search::Pattern::glob(p))
+                .collect::<Vec<_>>()
+        };
+        let top = FilterStackEntry {
+            parent_path_len: 0,
+            depth: 0,
+            state: if filter.include_scopes.is_empty() {
+                FilterState::Included
+            } else {
+                FilterState::Pending
+            },
+            deferred: None,
+        };
+        Some(Self {
+            include: compile(&filter.include_scopes),
+            exclude: compile(&filter.exclude_scopes),
+            max_depth: filter.max_depth,
+            path: String::new(),
+            stack: vec![top],
+        })
+    }
+
+    fn push(&mut self, name: &str, flatten: bool) -> FilterState {
+        let parent = self.stack.last().unwrap();
+        let (parent_state, parent_depth) = (parent.state, parent.depth);
+        let parent_path_len = self.path.len();
+        let (state, depth) = if flatten || parent_state == FilterState::Skipped {
+            // flattened scopes do not show up in the path and thus simply inherit their state
+            (parent_state, parent_depth)
+        } else {
+            if !self.path.is_empty() {
+                self.path.push('.');
+            }
+            self.path.push_str(name);
+            let depth = parent_depth + 1;
+            let state = if self.max_depth.is_some_and(|max| depth > max)
+                || self.exclude.iter().any(|p| p.is_match(&self.path))
+            {
+                FilterState::Skipped
+            } else if parent_state == FilterState::Included
+                || self.include.iter().any(|p| p.is_match(&self.path))
+            {
+                FilterState::Included
+            } else {
+                FilterState::Pending
+            };
+            (state, depth)
+        };
+        self.stack.push(FilterStackEntry {
+            parent_path_len,
+            depth,
+            state,
+            deferred: None,
+        });
+        state
+    }
+
+    /// Returns true if the scope was added to the hierarchy and needs to be removed from the scope stack.
+    fn pop(&mut self) -> bool {
+        let entry = self.stack.pop().unwrap();
+        self.path.truncate(entry.parent_path_len);
+        entry.state != FilterState::Skipped && entry.deferred.is_none()
+    }
+
+    /// Returns all pending parent scopes that have not been added to the hierarchy yet.
+    fn take_deferred(&mut self) -> Vec<DeferredScope> {
+        self.stack
+            .iter_mut()
+            .filter_map(|e| e.deferred.take())
+            .collect()
+    }
+
+    fn includes_vars(&self) -> bool {
+        self.stack.last().unwrap().state == FilterState::Included
+    }
 }
 
 const EMPTY_STRING: HierarchyStringId = HierarchyStringId(NonZeroU32::new(1).unwrap());
@@ -885,8 +1369,17 @@ impl HierarchyBuilder {
             slices: FxHashMap::default(),
             scope_child_count: vec![0],
             scope_dedup_tables: Default::default(),
+            filter: None,
+            skipped_signals: Default::default(),
         }
     }
+
+    /// Skips scopes and variables according to `include_scopes`, `exclude_scopes` and
+    /// `max_depth`. Needs to be called before any scope or variable is added.
+    pub fn set_scope_filter(&mut self, options: &crate::LoadOptions) {
+        debug_assert!(self.vars.is_empty() && self.scopes.len() == 1);
+        self.filter = ScopeFilterState::new(options).map(Box::new);
+    }
 }
 
 impl HierarchyBuilder {
@@ -897,6 +1390,20 @@ impl HierarchyBuilder {
         self.source_locs.shrink_to_fit();
         self.enums.shrink_to_fit();
         self.handle_to_node.shrink_to_fit();
+        let mut slice_sources = FxHashMap::default();
+        if self.filter.is_some() {
+            let handle_to_node = &self.handle_to_node;
+            let has_var =
+                |s: &SignalRef| handle_to_node.get(s.index()).is_some_and(|v| v.is_some());
+            self.slices.retain(|id, _| has_var(id));
+            for slice in self.slices.values() {
+                if !has_var(&slice.sliced_signal) {
+                    if let Some(encoding) = self.skipped_signals.get(&slice.sliced_signal) {
+                        slice_sources.insert(slice.sliced_signal, *encoding);
+                    }
+                }
+            }
+        }
         self.slices.shrink_to_fit();
         Hierarchy {
             vars: self.vars,
@@ -907,6 +1414,8 @@ impl HierarchyBuilder {
             signal_idx_to_var: self.handle_to_node,
             meta: self.meta,
             slices: self.slices,
+            slice_sources,
+            search_index: Default::default(),
         }
     }
 
@@ -1038,6 +1547,61 @@ impl HierarchyBuilder {
         declaration_source: Option<SourceLocId>,
         instance_source: Option<SourceLocId>,
         flatten: bool,
+    ) {
+        let Some(filter) = self.filter.as_mut() else {
+            self.add_unfiltered_scope(
+                name,
+                component,
+                tpe,
+                declaration_source,
+                instance_source,
+                flatten,
+            );
+            return;
+        };
+        match filter.push(&self.strings[name.index()], flatten) {
+            FilterState::Included => {
+                for parent in filter.take_deferred() {
+                    self.add_unfiltered_scope(
+                        parent.name,
+                        parent.component,
+                        parent.tpe,
+                        parent.declaration_source,
+                        parent.instance_source,
+                        parent.flatten,
+                    );
+                }
+                self.add_unfiltered_scope(
+                    name,
+                    component,
+                    tpe,
+                    declaration_source,
+                    instance_source,
+                    flatten,
+                );
+            }
+            FilterState::Pending => {
+                filter.stack.last_mut().unwrap().deferred = Some(DeferredScope {
+                    name,
+                    component,
+                    tpe,
+                    declaration_source,
+                    instance_source,
+                    flatten,
+                });
+            }
+            FilterState::Skipped => {}
+        }
+    }
+
+    fn add_unfiltered_scope(
+        &mut self,
+        name: HierarchyStringId,
+        component: Option<HierarchyStringId>,
+        tpe: ScopeType,
+        declaration_source: Option<SourceLocId>,
+        instance_source: Option<SourceLocId>,
+        flatten: bool,
     ) {
         // check to see if there is a scope of the same name already
         // if so we just activate that scope instead of adding a new one
@@ -1160,6 +1724,15 @@ impl HierarchyBuilder {
         enum_type: Option<EnumTypeId>,
         vhdl_type_name: Option<HierarchyStringId>,
     ) {
+        if self.filter.as_ref().is_some_and(|f| !f.includes_vars()) {
+            // signal ids still need to be valid, even if the variable is skipped
+            let handle_idx = signal_idx.index();
+            if self.handle_to_node.len() <= handle_idx {
+                self.handle_to_node.resize(handle_idx + 1, None);
+            }
+            self.skipped_signals.insert(signal_idx, signal_tpe);
+            return;
+        }
         let node_id = self.vars.len();
         let var_id = VarRef::from_index(node_id).unwrap();
         let parent = self.add_to_hierarchy_tree(var_id.into());
@@ -1196,8 +1769,15 @@ impl HierarchyBuilder {
         }
     }
 
+    /// Superfluous calls are ignored, the implicit top scope is never removed.
     pub fn pop_scope(&mut self) {
-        self.scope_stack.pop().unwrap();
+        if let Some(filter) = self.filter.as_mut() {
+            if filter.stack.len() > 1 && filter.pop() {
+                self.scope_stack.pop();
+            }
+        } else if self.scope_stack.len() > 1 {
+            self.scope_stack.pop();
+        }
     }
 
     pub fn set_date(&mut self, value: String) {
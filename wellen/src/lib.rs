@@ -3,20 +3,35 @@
 // released under BSD 3-Clause License
 // author: Kevin Laeufer <laeufer@cornell.edu>
 
+#[cfg(feature = "arrow")]
+pub mod arrow;
+mod cache;
+pub mod checker;
 mod compressed;
-mod fst;
+pub mod coverage;
+pub mod cycles;
+pub mod diff;
+pub mod expr;
+pub mod fst;
 mod ghw;
 mod hierarchy;
+pub mod lod;
+pub mod saif;
+pub mod search;
+pub mod serial;
 mod signals;
 pub mod simple;
-mod vcd;
+pub mod stream;
+pub mod transactions;
+pub mod vcd;
 pub mod viewers;
 mod wavemem;
 
 /// Cargo.toml version of this library.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[repr(u8)]
+#[derive(Debug, PartialEq, Copy, Clone, num_enum::TryFromPrimitive)]
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 pub enum FileFormat {
     Vcd,
@@ -24,12 +39,19 @@ pub enum FileFormat {
     Ghw,
     Unknown,
 }
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct LoadOptions {
     /// Indicates that the loader should use multiple threads if possible.
     pub multi_thread: bool,
     /// Indicates that scopes with empty names should not be part of the hierarchy.
     pub remove_scopes_with_empty_name: bool,
+    /// Indicates that the parsed body of a VCD or GHW file should be stored in a cache file and
+    /// that an existing cache file should be used instead of parsing the source again.
+    /// The cache file is placed next to the source, unless the `WELLEN_CACHE_DIR`
+    /// environment variable points to a cache directory.
+    pub use_cache: bool,
+    /// Restricts the hierarchy to a subset of scopes.
+    pub scope_filter: Option<ScopeFilter>,
 }
 
 impl Default for LoadOptions {
@@ -37,16 +59,48 @@ impl Default for LoadOptions {
         Self {
             multi_thread: true,
             remove_scopes_with_empty_name: false,
+            use_cache: false,
+            scope_filter: None,
         }
     }
 }
 
+/// Selects the scopes that are loaded, see [`LoadOptions::scope_filter`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScopeFilter {
+    /// Glob patterns (see [`search::Pattern::glob`]) over full scope names, e.g., `top.cpu.*`.
+    /// If not empty, only matching scopes, their children and their parents are loaded.
+    /// Variables declared in a parent scope are skipped, unless the parent matches as well.
+    pub include_scopes: Vec<String>,
+    /// Glob patterns over full scope names. Matching scopes and their children are skipped.
+    pub exclude_scopes: Vec<String>,
+    /// Skips all scopes that are nested deeper. Top-level scopes have a depth of one.
+    pub max_depth: Option<usize>,
+}
+
+impl ScopeFilter {
+    /// Returns true iff the filter does not skip any scopes.
+    pub fn is_empty(&self) -> bool {
+        self.include_scopes.is_empty() && self.exclude_scopes.is_empty() && self.max_depth.is_none()
+    }
+}
+
 pub type TimeTable = Vec<Time>;
 
 #[derive(Debug, thiserror::Error)]
 pub enum WellenError {
     #[error("failed to load {0:?}:\n{1}")]
     FailedToLoad(FileFormat, String),
+    /// The input is malformed or uses a feature that we do not support.
+    #[error("failed to load {format:?} {section}{}:\n{message}", at_byte(.offset))]
+    Malformed {
+        format: FileFormat,
+        /// Part of the file that we were reading, e.g., `header` or `value changes`.
+        section: &'static str,
+        /// Position in the input, if known.
+        offset: Option<u64>,
+        message: String,
+    },
     #[error("unknown file format, only GHW, FST and VCD are supported")]
     UnknownFileFormat,
     #[error("io error")]
@@ -55,12 +109,35 @@ pub enum WellenError {
 
 pub type Result<T> = std::result::Result<T, WellenError>;
 
+impl WellenError {
+    pub(crate) fn malformed(
+        format: FileFormat,
+        section: &'static str,
+        offset: Option<u64>,
+        error: impl std::fmt::Display,
+    ) -> Self {
+        WellenError::Malformed {
+            format,
+            section,
+            offset,
+            message: error.to_string(),
+        }
+    }
+}
+
+fn at_byte(offset: &Option<u64>) -> String {
+    offset.map(|o| format!(" at byte {o}")).unwrap_or_default()
+}
+
 pub use compressed::{CompressedSignal, CompressedTimeTable, Compression};
 pub use hierarchy::{
     Hierarchy, Scope, ScopeOrVar, ScopeOrVarRef, ScopeRef, ScopeType, SignalEncoding, SignalRef,
     Timescale, TimescaleUnit, Var, VarDirection, VarIndex, VarRef, VarType,
 };
-pub use signals::{Real, Signal, SignalSource, SignalValue, Time, TimeTableIdx};
+pub use signals::{
+    Edge, Real, SearchDirection, Signal, SignalChange, SignalSource, SignalValue, Time,
+    TimeTableIdx,
+};
 
 #[cfg(feature = "benchmark")]
 pub use wavemem::check_states_pub;
@@ -0,0 +1,329 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// Level-of-detail summaries that allow a viewer to draw a signal at any zoom level without
+// visiting every single change.
+
+use crate::{Signal, SignalRef, SignalValue, Time};
+
+/// Summary of all changes of a signal that fall into a single pixel bucket.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bucket<'a> {
+    /// First time covered by the bucket.
+    pub start: Time,
+    /// First time after the bucket, i.e., the bucket covers `start..end`.
+    pub end: Time,
+    /// Value at the beginning of the bucket, before any of its transitions.
+    /// If the signal has no value before the bucket, this is the first value inside the bucket.
+    /// `None` if the signal has no value during the whole bucket.
+    pub first: Option<SignalValue<'a>>,
+    /// Value at the end of the bucket.
+    pub last: Option<SignalValue<'a>>,
+    /// Number of changes inside the bucket. The very first value of a signal does not count as a
+    /// transition.
+    pub transitions: u32,
+    /// Indicates that at least one value in effect during the bucket contains a state that
+    /// is not `0` or `1`, e.g., `x`, `z` or one of the other nine-value states.
+    pub has_non_binary: bool,
+}
+
+/// Pre-computed summary of a loaded signal. It only needs to be built once per signal and allows
+/// every bucket to be computed with a couple of binary searches, independent of how many changes
+/// fall into the bucket.
+///
+/// Internally we keep a pyramid where level `n` records whether any of the `2^n` changes covered by
+/// an entry contains a non-binary value.
+#[derive(Debug, Clone)]
+pub struct SignalSummary {
+    signal: SignalRef,
+    changes: usize,
+    /// Empty if none of the changes contains a non-binary value.
+    non_binary: Vec<Vec<u64>>,
+}
+
+impl SignalSummary {
+    pub fn new(signal: &Signal) -> Self {
+        let changes = signal.time_indices().len();
+        let mut level = vec![0u64; changes.div_ceil(64)];
+        let mut any = false;
+        for ii in 0..changes {
+            if is_non_binary(&signal.get_value_at_change(ii)) {
+                set_bit(&mut level, ii);
+                any = true;
+            }
+        }
+
+        let mut non_binary = vec![];
+        if any {
+            let mut len = changes;
+            non_binary.push(level);
+            while len > 1 {
+                let prev = non_binary.last().unwrap();
+                len = len.div_ceil(2);
+                let mut next = vec![0u64; len.div_ceil(64)];
+                for ii in 0..len {
+                    if get_bit(prev, 2 * ii) || get_bit(prev, 2 * ii + 1) {
+                        set_bit(&mut next, ii);
+                    }
+                }
+                non_binary.push(next);
+            }
+        }
+
+        Self {
+            signal: signal.signal_ref(),
+            changes,
+            non_binary,
+        }
+    }
+
+    pub fn signal_ref(&self) -> SignalRef {
+        self.signal
+    }
+
+    pub fn size_in_memory(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self
+                .non_binary
+                .iter()
+                .map(|l| l.len() * std::mem::size_of::<u64>())
+                .sum::<usize>()
+    }
+
+    /// Splits the time range from `start` to `end` (inclusive) into `count` buckets of equal
+    /// width and summarizes the changes of `signal` inside each of them.
+    /// `signal` must be the signal that this summary was created from.
+    pub fn buckets<'a>(
+        &self,
+        signal: &'a Signal,
+        time_table: &[Time],
+        start: Time,
+        end: Time,
+        count: usize,
+    ) -> Vec<Bucket<'a>> {
+        assert_eq!(
+            signal.signal_ref(),
+            self.signal,
+            "summary was created for a different signal"
+        );
+        debug_assert_eq!(signal.time_indices().len(), self.changes);
+        if count == 0 || end < start {
+            return vec![];
+        }
+        let span = (end - start) as u128 + 1;
+        let boundary = |ii: usize| start + (span * ii as u128 / count as u128) as Time;
+        let time_indices = signal.time_indices();
+        // index of the first change at or after time `t`
+        let first_change_from = |t: Time| {
+            let idx = time_table.partition_point(|tt| *tt < t);
+            time_indices.partition_point(|ii| (*ii as usize) < idx)
+        };
+
+        let mut out = Vec::with_capacity(count);
+        let mut bucket_start = start;
+        let mut lo = first_change_from(start);
+        for ii in 1..=count {
+            let bucket_end = boundary(ii);
+            let hi = first_change_from(bucket_end);
+            out.push(self.bucket(signal, bucket_start, bucket_end, lo, hi));
+            bucket_start = bucket_end;
+            lo = hi;
+        }
+        out
+    }
+
+    /// Summarizes the bucket that contains changes `lo..hi`.
+    fn bucket<'a>(
+        &self,
+        signal: &'a Signal,
+        start: Time,
+        end: Time,
+        lo: usize,
+        hi: usize,
+    ) -> Bucket<'a> {
+        // the value in effect when the bucket starts
+        let (first_change, transitions) = match lo.checked_sub(1) {
+            Some(before) => (Some(before), hi - lo),
+            None if hi > lo => (Some(lo), hi - lo - 1),
+            None => (None, 0),
+        };
+        let first = first_change.map(|ii| signal.get_value_at_change(ii));
+        let last = hi.checked_sub(1).map(|ii| signal.get_value_at_change(ii));
+        let has_non_binary = first_change.is_some_and(|ii| self.any_non_binary(ii, hi));
+        Bucket {
+            start,
+            end,
+            first,
+            last,
+            transitions: transitions as u32,
+            has_non_binary,
+        }
+    }
+
+    /// Checks whether any change in `lo..hi` is non-binary by visiting at most two entries
+    /// per pyramid level.
+    fn any_non_binary(&self, mut lo: usize, mut hi: usize) -> bool {
+        for level in self.non_binary.iter() {
+            if lo >= hi {
+                break;
+            }
+            if lo % 2 == 1 {
+                if get_bit(level, lo) {
+                    return true;
+                }
+                lo += 1;
+            }
+            if hi % 2 == 1 {
+                hi -= 1;
+                if get_bit(level, hi) {
+                    return true;
+                }
+            }
+            lo /= 2;
+            hi /= 2;
+        }
+        false
+    }
+}
+
+/// Convenience function that summarizes a signal without keeping the summary around.
+/// Use a [`SignalSummary`] if the same signal is going to be drawn more than once.
+pub fn summarize<'a>(
+    signal: &'a Signal,
+    time_table: &[Time],
+    start: Time,
+    end: Time,
+    count: usize,
+) -> Vec<Bucket<'a>> {
+    SignalSummary::new(signal).buckets(signal, time_table, start, end, count)
+}
+
+/// Returns true if the value contains any bit that is not `0` or `1`.
+fn is_non_binary(value: &SignalValue) -> bool {
+    let pattern = match value {
+        SignalValue::FourValue(..) => 0b1010_1010u8,
+        SignalValue::NineValue(..) => 0b1110_1110u8,
+        _ => return false,
+    };
+    let (data, mask) = value.data_and_mask().unwrap();
+    match data.split_first() {
+        None => false,
+        Some((first, rest)) => {
+            (first & mask & pattern) != 0 || rest.iter().any(|b| (b & pattern) != 0)
+        }
+    }
+}
+
+#[inline]
+fn get_bit(bits: &[u64], ii: usize) -> bool {
+    bits.get(ii / 64).is_some_and(|w| (w >> (ii % 64)) & 1 == 1)
+}
+
+#[inline]
+fn set_bit(bits: &mut [u64], ii: usize) {
+    bits[ii / 64] |= 1 << (ii % 64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signals::FixedWidthEncoding;
+    use crate::wavemem::States;
+
+    /// 1-bit, four state signal. Values are `0`, `1`, `2 = x` or `3 = z`.
+    fn four_state_signal(changes: &[(u32, u8)]) -> Signal {
+        let time_indices = changes.iter().map(|(t, _)| *t).collect();
+        let bytes = changes
+            .iter()
+            .map(|(_, v)| {
+                let states = if *v < 2 { States::Two } else { States::Four };
+                ((states as u8) << 6) | v
+            })
+            .collect();
+        let encoding = FixedWidthEncoding::BitVector {
+            max_states: States::Four,
+            bits: 1,
+            meta_byte: false,
+        };
+        Signal::new_fixed_len(
+            SignalRef::from_index(0).unwrap(),
+            time_indices,
+            encoding,
+            1,
+            bytes,
+        )
+    }
+
+    fn to_str(value: Option<SignalValue>) -> Option<String> {
+        value.map(|v| v.to_string())
+    }
+
+    #[test]
+    fn test_buckets() {
+        let time_table: Vec<Time> = (0..20).map(|t| t * 10).collect();
+        let signal = four_state_signal(&[(0, 2), (2, 0), (3, 1), (4, 0), (5, 1), (12, 3), (13, 0)]);
+        let buckets = summarize(&signal, &time_table, 0, 199, 4);
+        assert_eq!(buckets.len(), 4);
+        assert_eq!((buckets[0].start, buckets[0].end), (0, 50));
+        assert_eq!((buckets[3].start, buckets[3].end), (150, 200));
+
+        // initial x, then toggling
+        assert_eq!(to_str(buckets[0].first), Some("x".to_string()));
+        assert_eq!(to_str(buckets[0].last), Some("0".to_string()));
+        assert_eq!(buckets[0].transitions, 3);
+        assert!(buckets[0].has_non_binary);
+
+        // starts with 0 (last value of the previous bucket), change to 1 at time 50
+        assert_eq!(to_str(buckets[1].first), Some("0".to_string()));
+        assert_eq!(to_str(buckets[1].last), Some("1".to_string()));
+        assert_eq!(buckets[1].transitions, 1);
+        assert!(!buckets[1].has_non_binary);
+
+        // short glitch to z
+        assert_eq!(to_str(buckets[2].first), Some("1".to_string()));
+        assert_eq!(to_str(buckets[2].last), Some("0".to_string()));
+        assert_eq!(buckets[2].transitions, 2);
+        assert!(buckets[2].has_non_binary);
+
+        // no changes
+        assert_eq!(to_str(buckets[3].first), Some("0".to_string()));
+        assert_eq!(to_str(buckets[3].last), Some("0".to_string()));
+        assert_eq!(buckets[3].transitions, 0);
+        assert!(!buckets[3].has_non_binary);
+    }
+
+    #[test]
+    fn test_buckets_before_first_change() {
+        let time_table: Vec<Time> = vec![0, 10, 20];
+        let signal = four_state_signal(&[(1, 1)]);
+        let buckets = summarize(&signal, &time_table, 0, 19, 2);
+        assert_eq!(buckets[0].first, None);
+        assert_eq!(buckets[0].last, None);
+        assert_eq!(buckets[0].transitions, 0);
+        assert_eq!(to_str(buckets[1].first), Some("1".to_string()));
+        assert_eq!(buckets[1].transitions, 0);
+    }
+
+    #[test]
+    fn test_non_binary_pyramid() {
+        let n = 1000u32;
+        for x_at in [0, 1, 63, 64, 500, 998, 999] {
+            let changes: Vec<_> = (0..n)
+                .map(|ii| (ii, if ii == x_at { 2 } else { (ii % 2) as u8 }))
+                .collect();
+            let signal = four_state_signal(&changes);
+            let summary = SignalSummary::new(&signal);
+            for lo in [0, 1, 2, 63, 64, 65, 499, 500, 501, 997, 998, 999] {
+                for hi in [lo, lo + 1, lo + 2, lo + 100, n as usize] {
+                    let hi = std::cmp::min(hi, n as usize);
+                    let expected = (lo..hi).contains(&(x_at as usize));
+                    assert_eq!(
+                        summary.any_non_binary(lo, hi),
+                        expected,
+                        "x@{x_at} in {lo}..{hi}"
+                    );
+                }
+            }
+        }
+    }
+}
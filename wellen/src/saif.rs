@@ -0,0 +1,365 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// Writes the switching activity of all nets in the Switching Activity Interchange Format.
+
+use crate::signals::{BitState, bit_state};
+use crate::simple::Waveform;
+use crate::{Hierarchy, ScopeRef, Signal, SignalEncoding, SignalRef, Time, TimescaleUnit, VarRef};
+use rayon::prelude::*;
+use rustc_hash::FxHashMap;
+use std::io::Write;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SaifError {
+    #[error("[saif] the waveform has no timescale")]
+    UnknownTimescale,
+    #[error("[saif] {0:?} are not supported, the smallest SAIF time unit is fs")]
+    UnsupportedTimescale(TimescaleUnit),
+    #[error("[saif] the time window ends at {1} before it starts at {0}")]
+    InvalidTimeWindow(Time, Time),
+    #[error("[saif] the duration {0} overflows when multiplied with the timescale factor {1}")]
+    DurationOverflow(Time, u64),
+    #[error("[saif] failed to load signals: {0}")]
+    Load(#[from] crate::WellenError),
+    #[error("[saif] failed to write: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, SaifError>;
+
+#[derive(Debug, Clone, Default)]
+pub struct SaifOptions {
+    /// Start and end of the analyzed interval. Defaults to the first and last time in the
+    /// time table.
+    pub time_window: Option<(Time, Time)>,
+    /// Name of the design, which is left empty by default.
+    pub design: String,
+}
+
+/// Switching activity of a single bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BitActivity {
+    /// Time spent at `0`, `1`, `x` and `z`. Before its first value, a bit is `x`.
+    pub t0: Time,
+    pub t1: Time,
+    pub tx: Time,
+    pub tz: Time,
+    /// Transitions between `0` and `1`, including those through `x` or `z`, like `0 -> x -> 1`.
+    pub tc: u64,
+    /// Glitches through `x` or `z` that return to the previous value, like `0 -> x -> 0`.
+    pub ig: u64,
+}
+
+impl BitActivity {
+    fn add_time(&mut self, state: BitState, duration: Time) {
+        match state {
+            BitState::Zero => self.t0 += duration,
+            BitState::One => self.t1 += duration,
+            BitState::X => self.tx += duration,
+            BitState::Z => self.tz += duration,
+        }
+    }
+}
+
+/// Computes the activity of every bit of `signal` from `start` to `end`. Only the last value of
+/// each time step counts, i.e., changes in delta cycles are ignored. Transitions at `start` are
+/// not counted, while transitions at `end` are.
+pub fn switching_activity(
+    signal: &Signal,
+    width: u32,
+    time_table: &[Time],
+    (start, end): (Time, Time),
+) -> Result<Vec<BitActivity>> {
+    if end < start {
+        return Err(SaifError::InvalidTimeWindow(start, end));
+    }
+    let mut out = vec![BitActivity::default(); width as usize];
+    let mut state = vec![BitState::X; width as usize];
+    // value before the current `x` or `z` phase, used to tell toggles and glitches apart
+    let mut last_known: Vec<Option<BitState>> = vec![None; width as usize];
+    let mut since = start;
+    let mut changes = signal.iter_changes().peekable();
+    while let Some((idx, value)) = changes.next() {
+        if changes.peek().is_some_and(|(next, _)| *next == idx) {
+            continue;
+        }
+        let time = time_table[idx as usize];
+        if time > end {
+            break;
+        }
+        let count = time > start;
+        for bit in 0..width as usize {
+            let next = bit_state(&value, bit as u32);
+            let current = state[bit];
+            if count {
+                out[bit].add_time(current, time - since);
+            }
+            if next == current {
+                continue;
+            }
+            match (current, next) {
+                (BitState::Zero | BitState::One, BitState::Zero | BitState::One) => {
+                    out[bit].tc += count as u64
+                }
+                (BitState::Zero | BitState::One, _) => last_known[bit] = Some(current),
+                (_, BitState::Zero | BitState::One) => {
+                    if count {
+                        match last_known[bit] {
+                            Some(known) if known == next => out[bit].ig += 1,
+                            Some(_) => out[bit].tc += 1,
+                            None => {}
+                        }
+                    }
+                    last_known[bit] = None;
+                }
+                _ => {}
+            }
+            state[bit] = next;
+        }
+        if count {
+            since = time;
+        }
+    }
+    for (activity, state) in out.iter_mut().zip(state.iter()) {
+        activity.add_time(*state, end - since);
+    }
+    Ok(out)
+}
+
+/// Signals are loaded and analyzed in batches in order to limit memory usage.
+const BATCH_SIZE: usize = 1024;
+
+/// A scope with nets in it or in one of its children.
+struct Instance {
+    scope: ScopeRef,
+    nets: Vec<(VarRef, SignalRef, u32)>,
+    children: Vec<Instance>,
+}
+
+/// Writes the activity of all nets in `root` and its children, or in all scopes if `root` is
+/// `None`. Nets are bit-vector variables without an enum type. Vectors are split into one net
+/// per bit. Scopes without any nets are left out. Signals that need to be loaded are unloaded
+/// again afterward.
+pub fn write_saif(
+    wave: &mut Waveform,
+    root: Option<ScopeRef>,
+    options: &SaifOptions,
+    out: &mut impl Write,
+) -> Result<()> {
+    let timescale = wave
+        .hierarchy()
+        .timescale()
+        .ok_or(SaifError::UnknownTimescale)?;
+    let (factor, unit, scale) = saif_timescale(timescale.factor, timescale.unit)?;
+    let time_table = wave.time_table();
+    let window = options.time_window.unwrap_or_else(|| {
+        let first = time_table.first().copied().unwrap_or(0);
+        (first, time_table.last().copied().unwrap_or(first))
+    });
+    if window.1 < window.0 {
+        return Err(SaifError::InvalidTimeWindow(window.0, window.1));
+    }
+
+    let h = wave.hierarchy();
+    let instances: Vec<_> = match root {
+        Some(root) => collect(h, root).into_iter().collect(),
+        None => h.scopes().filter_map(|s| collect(h, s)).collect(),
+    };
+    let mut unique = vec![];
+    for instance in instances.iter() {
+        instance.signals(&mut unique);
+    }
+    unique.sort();
+    unique.dedup_by_key(|(signal, _)| *signal);
+
+    let mut activity = FxHashMap::default();
+    for batch in unique.chunks(BATCH_SIZE) {
+        let ids: Vec<_> = batch.iter().map(|(id, _)| *id).collect();
+        let missing = wave.load_missing_signals(&ids)?;
+        let wave_ref = &*wave;
+        let computed: Result<Vec<_>> = batch
+            .par_iter()
+            .map(|&(id, width)| {
+                let signal = wave_ref.get_signal(id).unwrap();
+                let bits = switching_activity(signal, width, wave_ref.time_table(), window)?;
+                Ok((id, bits))
+            })
+            .collect();
+        activity.extend(computed?);
+        wave.unload_signals(&missing);
+    }
+
+    let h = wave.hierarchy();
+    writeln!(out, "(SAIFILE")?;
+    writeln!(out, "(SAIFVERSION \"2.0\")")?;
+    writeln!(out, "(DIRECTION \"backward\")")?;
+    writeln!(out, "(DESIGN \"{}\")", options.design)?;
+    writeln!(out, "(DATE \"{}\")", h.date())?;
+    writeln!(out, "(VENDOR \"wellen\")")?;
+    writeln!(out, "(PROGRAM_NAME \"wellen\")")?;
+    writeln!(out, "(VERSION \"{}\")", crate::VERSION)?;
+    writeln!(out, "(DIVIDER / )")?;
+    writeln!(out, "(TIMESCALE {factor} {unit})")?;
+    writeln!(out, "(DURATION {})", scaled(window.1 - window.0, scale)?)?;
+    for instance in instances.iter() {
+        instance.write(h, &activity, scale, 0, out)?;
+    }
+    writeln!(out, ")")?;
+    Ok(())
+}
+
+/// SAIF only supports factors of 1, 10 and 100. Other factors are multiplied into all
+/// durations. Returns the factor, unit and scale of all durations.
+fn saif_timescale(factor: u32, unit: TimescaleUnit) -> Result<(u32, &'static str, u64)> {
+    let unit = match unit {
+        TimescaleUnit::FemtoSeconds => "fs",
+        TimescaleUnit::PicoSeconds => "ps",
+        TimescaleUnit::NanoSeconds => "ns",
+        TimescaleUnit::MicroSeconds => "us",
+        TimescaleUnit::MilliSeconds => "ms",
+        TimescaleUnit::Seconds => "s",
+        TimescaleUnit::Unknown => return Err(SaifError::UnknownTimescale),
+        other => return Err(SaifError::UnsupportedTimescale(other)),
+    };
+    if matches!(factor, 1 | 10 | 100) {
+        Ok((factor, unit, 1))
+    } else {
+        Ok((1, unit, factor as u64))
+    }
+}
+
+/// Converts a duration into SAIF time units.
+fn scaled(duration: Time, scale: u64) -> Result<u64> {
+    duration
+        .checked_mul(scale)
+        .ok_or(SaifError::DurationOverflow(duration, scale))
+}
+
+fn collect(h: &Hierarchy, scope: ScopeRef) -> Option<Instance> {
+    let nets: Vec<_> = h[scope]
+        .vars(h)
+        .filter_map(|v| {
+            let var = &h[v];
+            match var.signal_encoding() {
+                SignalEncoding::BitVector(width) if var.enum_type(h).is_none() => {
+                    Some((v, var.signal_ref(), width.get()))
+                }
+                _ => None,
+            }
+        })
+        .collect();
+    let children: Vec<_> = h[scope].scopes(h).filter_map(|s| collect(h, s)).collect();
+    if nets.is_empty() && children.is_empty() {
+        None
+    } else {
+        Some(Instance {
+            scope,
+            nets,
+            children,
+        })
+    }
+}
+
+impl Instance {
+    fn signals(&self, out: &mut Vec<(SignalRef, u32)>) {
+        out.extend(self.nets.iter().map(|(_, signal, width)| (*signal, *width)));
+        for child in self.children.iter() {
+            child.signals(out);
+        }
+    }
+
+    fn write(
+        &self,
+        h: &Hierarchy,
+        activity: &FxHashMap<SignalRef, Vec<BitActivity>>,
+        scale: u64,
+        depth: usize,
+        out: &mut impl Write,
+    ) -> Result<()> {
+        let indent = "  ".repeat(depth);
+        writeln!(out, "{indent}(INSTANCE {}", escape(h[self.scope].name(h)))?;
+        if !self.nets.is_empty() {
+            writeln!(out, "{indent}  (NET")?;
+            for (var, signal, width) in self.nets.iter() {
+                let name = escape(h[*var].name(h));
+                let index = h[*var].index();
+                for (bit, a) in activity[signal].iter().enumerate() {
+                    let net = match (index, *width) {
+                        (None, 1) => name.clone(),
+                        (None, _) => format!("{name}\\[{bit}\\]"),
+                        (Some(index), _) if index.msb() >= index.lsb() => {
+                            format!("{name}\\[{}\\]", index.lsb() + bit as i64)
+                        }
+                        (Some(index), _) => format!("{name}\\[{}\\]", index.lsb() - bit as i64),
+                    };
+                    writeln!(out, "{indent}    ({net}")?;
+                    writeln!(
+                        out,
+                        "{indent}      (T0 {}) (T1 {}) (TX {}) (TZ {})",
+                        scaled(a.t0, scale)?,
+                        scaled(a.t1, scale)?,
+                        scaled(a.tx, scale)?,
+                        scaled(a.tz, scale)?
+                    )?;
+                    writeln!(out, "{indent}      (TC {}) (IG {})", a.tc, a.ig)?;
+                    writeln!(out, "{indent}    )")?;
+                }
+            }
+            writeln!(out, "{indent}  )")?;
+        }
+        for child in self.children.iter() {
+            child.write(h, activity, scale, depth + 1, out)?;
+        }
+        writeln!(out, "{indent})")?;
+        Ok(())
+    }
+}
+
+/// Escapes all characters that are not allowed in SAIF identifiers with a backslash.
+fn escape(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for c in name.chars() {
+        if !(c.is_ascii_alphanumeric() || c == '_') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_saif_timescale() {
+        assert_eq!(
+            saif_timescale(10, TimescaleUnit::NanoSeconds).unwrap(),
+            (10, "ns", 1)
+        );
+        assert_eq!(
+            saif_timescale(1000, TimescaleUnit::PicoSeconds).unwrap(),
+            (1, "ps", 1000)
+        );
+        assert!(matches!(
+            saif_timescale(1, TimescaleUnit::AttoSeconds),
+            Err(SaifError::UnsupportedTimescale(TimescaleUnit::AttoSeconds))
+        ));
+    }
+
+    #[test]
+    fn test_scaled() {
+        assert_eq!(scaled(3, 1000).unwrap(), 3000);
+        assert!(matches!(
+            scaled(u64::MAX / 2, 3),
+            Err(SaifError::DurationOverflow(_, 3))
+        ));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("data_0"), "data_0");
+        assert_eq!(escape("gen[3].q"), "gen\\[3\\]\\.q");
+    }
+}
@@ -0,0 +1,386 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// Searching for variables by their hierarchical name.
+
+use crate::{Hierarchy, ScopeType, Var, VarDirection, VarRef, VarType};
+use rayon::prelude::*;
+use std::ops::RangeInclusive;
+
+#[cfg(feature = "regex")]
+#[derive(Debug, thiserror::Error, PartialEq)]
+#[non_exhaustive]
+pub enum SearchError {
+    #[error("[search] invalid regular expression: {0}")]
+    InvalidRegex(String),
+}
+
+#[cfg(feature = "regex")]
+pub type Result<T> = std::result::Result<T, SearchError>;
+
+/// A pattern that is matched against the full hierarchical name of a variable,
+/// e.g., `top.cpu.alu.result_q`.
+#[derive(Debug, Clone)]
+pub struct Pattern(PatternKind);
+
+#[derive(Debug, Clone)]
+enum PatternKind {
+    Glob(Vec<GlobToken>),
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+    /// lower case characters of the query
+    Fuzzy(Vec<char>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GlobToken {
+    Char(char),
+    /// `?`
+    AnyChar,
+    /// `*`
+    AnyInLevel,
+    /// `**`
+    Any,
+}
+
+impl GlobToken {
+    fn is_star(self) -> bool {
+        matches!(self, GlobToken::AnyInLevel | GlobToken::Any)
+    }
+}
+
+impl Pattern {
+    /// Glob patterns need to match the complete name. `*` matches any number of characters and
+    /// `?` a single character inside of one level of the hierarchy, while `**` matches across
+    /// levels, e.g., `top.cpu*.alu.*_q` or `top.**.valid`.
+    pub fn glob(pattern: &str) -> Self {
+        let mut tokens = Vec::with_capacity(pattern.len());
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            let token = match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    GlobToken::Any
+                }
+                '*' => GlobToken::AnyInLevel,
+                '?' => GlobToken::AnyChar,
+                other => GlobToken::Char(other),
+            };
+            tokens.push(token);
+        }
+        Self(PatternKind::Glob(tokens))
+    }
+
+    /// Regular expressions match if they are found anywhere in the name.
+    /// Use `^` and `$` to match the complete name.
+    #[cfg(feature = "regex")]
+    pub fn regex(pattern: &str) -> Result<Self> {
+        let re =
+            regex::Regex::new(pattern).map_err(|e| SearchError::InvalidRegex(e.to_string()))?;
+        Ok(Self(PatternKind::Regex(re)))
+    }
+
+    /// Matches all names that contain the characters of `query` in the same order, ignoring
+    /// case. Results are ranked, with consecutive characters and characters at the start of a
+    /// name or after a `_` counting more.
+    pub fn fuzzy(query: &str) -> Self {
+        let query = query.chars().flat_map(|c| c.to_lowercase()).collect();
+        Self(PatternKind::Fuzzy(query))
+    }
+
+    pub(crate) fn is_match(&self, name: &str) -> bool {
+        self.score(name, &mut MatchBuffers::default()).is_some()
+    }
+
+    /// Returns `None` if `name` does not match. Only fuzzy matches are scored.
+    fn score(&self, name: &str, buffers: &mut MatchBuffers) -> Option<i32> {
+        match &self.0 {
+            PatternKind::Glob(tokens) => glob_match(tokens, name, buffers).then_some(0),
+            #[cfg(feature = "regex")]
+            PatternKind::Regex(re) => re.is_match(name).then_some(0),
+            PatternKind::Fuzzy(query) => fuzzy_score(query, name, buffers),
+        }
+    }
+}
+
+/// Restricts search results. Empty lists allow everything.
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilter {
+    pub var_types: Vec<VarType>,
+    pub directions: Vec<VarDirection>,
+    /// Only bit-vectors with a width in this range pass.
+    pub width: Option<RangeInclusive<u32>>,
+    /// Type of the scope that directly contains the variable.
+    pub scope_types: Vec<ScopeType>,
+}
+
+impl SearchFilter {
+    fn passes(&self, hierarchy: &Hierarchy, var: &Var) -> bool {
+        if !self.var_types.is_empty() && !self.var_types.contains(&var.var_type()) {
+            return false;
+        }
+        if !self.directions.is_empty() && !self.directions.contains(&var.direction()) {
+            return false;
+        }
+        if let Some(width) = &self.width {
+            if !var.length().is_some_and(|l| width.contains(&l)) {
+                return false;
+            }
+        }
+        if !self.scope_types.is_empty() {
+            let Some(parent) = var.parent() else {
+                return false;
+            };
+            if !self.scope_types.contains(&hierarchy[parent].scope_type()) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Full names of all variables, stored back to back, so that searches do not need to build
+/// a name for every variable.
+pub(crate) struct SearchIndex {
+    names: String,
+    /// end of the name of every variable in `names`
+    ends: Vec<usize>,
+}
+
+impl SearchIndex {
+    pub(crate) fn new(hierarchy: &Hierarchy) -> Self {
+        let mut scope_paths: Vec<Box<str>> = Vec::with_capacity(hierarchy.iter_scopes().len());
+        for scope in hierarchy.iter_scopes() {
+            // parents are always created before their children
+            let path = match scope.parent() {
+                None => scope.name(hierarchy).into(),
+                Some(parent) => {
+                    let parent = &scope_paths[parent.index()];
+                    let name = scope.name(hierarchy);
+                    let mut path = String::with_capacity(parent.len() + 1 + name.len());
+                    path.push_str(parent);
+                    path.push('.');
+                    path.push_str(name);
+                    path.into_boxed_str()
+                }
+            };
+            scope_paths.push(path);
+        }
+        let mut names = String::new();
+        let mut ends = Vec::with_capacity(hierarchy.iter_vars().len());
+        for var in hierarchy.iter_vars() {
+            if let Some(parent) = var.parent() {
+                names.push_str(&scope_paths[parent.index()]);
+                names.push('.');
+            }
+            names.push_str(var.name(hierarchy));
+            ends.push(names.len());
+        }
+        names.shrink_to_fit();
+        Self { names, ends }
+    }
+
+    fn full_name(&self, index: usize) -> &str {
+        let start = if index == 0 { 0 } else { self.ends[index - 1] };
+        &self.names[start..self.ends[index]]
+    }
+}
+
+impl Hierarchy {
+    /// Returns all variables whose full name matches `pattern` and which pass the `filter`.
+    /// Fuzzy matches are ordered from best to worst, all other results are in the order
+    /// in which the variables were declared.
+    pub fn search(&self, pattern: &Pattern, filter: &SearchFilter) -> Vec<VarRef> {
+        let index = self.search_index();
+        let mut matches = self
+            .iter_vars()
+            .as_slice()
+            .par_iter()
+            .enumerate()
+            .map_init(MatchBuffers::default, |buffers, (ii, var)| {
+                if !filter.passes(self, var) {
+                    return None;
+                }
+                let score = pattern.score(index.full_name(ii), buffers)?;
+                Some((VarRef::from_index(ii).unwrap(), score))
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+        if matches!(pattern.0, PatternKind::Fuzzy(_)) {
+            // stable sort keeps the declaration order for equal scores
+            matches.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+        }
+        matches.into_iter().map(|(var, _)| var).collect()
+    }
+}
+
+/// Reused between matches in order to avoid allocations.
+#[derive(Default)]
+struct MatchBuffers {
+    text: Vec<char>,
+    prev: Vec<Option<i32>>,
+    current: Vec<Option<i32>>,
+    /// glob tokens that have been matched so far
+    states: Vec<bool>,
+    next_states: Vec<bool>,
+}
+
+/// Simulates the glob pattern as a nondeterministic automaton, with one state for every
+/// prefix of the pattern.
+fn glob_match(tokens: &[GlobToken], text: &str, buffers: &mut MatchBuffers) -> bool {
+    let MatchBuffers {
+        states,
+        next_states,
+        ..
+    } = buffers;
+    // stars may also match nothing
+    let skip_stars = |states: &mut Vec<bool>| {
+        for (ii, token) in tokens.iter().enumerate() {
+            if states[ii] && token.is_star() {
+                states[ii + 1] = true;
+            }
+        }
+    };
+    states.clear();
+    states.resize(tokens.len() + 1, false);
+    states[0] = true;
+    skip_stars(states);
+    for c in text.chars() {
+        next_states.clear();
+        next_states.resize(tokens.len() + 1, false);
+        let mut any = false;
+        for (ii, token) in tokens.iter().enumerate() {
+            if !states[ii] {
+                continue;
+            }
+            let next = match *token {
+                GlobToken::Char(expected) => (expected == c).then_some(ii + 1),
+                GlobToken::AnyChar => (c != '.').then_some(ii + 1),
+                GlobToken::AnyInLevel => (c != '.').then_some(ii),
+                GlobToken::Any => Some(ii),
+            };
+            if let Some(next) = next {
+                next_states[next] = true;
+                any = true;
+            }
+        }
+        if !any {
+            return false;
+        }
+        skip_stars(next_states);
+        std::mem::swap(states, next_states);
+    }
+    states[tokens.len()]
+}
+
+const FUZZY_MATCH: i32 = 16;
+const FUZZY_CONSECUTIVE: i32 = 12;
+const FUZZY_START_OF_WORD: i32 = 8;
+const FUZZY_GAP: i32 = 1;
+
+/// Returns the best score over all ways of matching the query as a subsequence of `text`,
+/// or `None` if there is no match.
+fn fuzzy_score(query: &[char], text: &str, buffers: &mut MatchBuffers) -> Option<i32> {
+    let MatchBuffers {
+        text: chars,
+        prev,
+        current,
+        ..
+    } = buffers;
+    chars.clear();
+    chars.extend(text.chars());
+    let text = &chars[..];
+    if query.is_empty() {
+        return Some(0);
+    }
+    if query.len() > text.len() {
+        return None;
+    }
+    let start_of_word = |j: usize| j == 0 || matches!(text[j - 1], '.' | '_' | '[' | '$');
+    // best score with query[..=i] matched and query[i] at text[j]
+    prev.clear();
+    prev.resize(text.len(), None);
+    current.clear();
+    current.resize(text.len(), None);
+    for (i, q) in query.iter().enumerate() {
+        // best score of query[..i] ending anywhere before j, minus the gap to j
+        let mut best_before: Option<i32> = None;
+        for (j, c) in text.iter().enumerate() {
+            let matches = c.to_lowercase().eq(std::iter::once(*q));
+            current[j] = if !matches {
+                None
+            } else {
+                let bonus = FUZZY_MATCH
+                    + if start_of_word(j) {
+                        FUZZY_START_OF_WORD
+                    } else {
+                        0
+                    };
+                if i == 0 {
+                    Some(bonus)
+                } else {
+                    let consecutive = if j > 0 {
+                        prev[j - 1].map(|s| s + FUZZY_CONSECUTIVE)
+                    } else {
+                        None
+                    };
+                    std::cmp::max(consecutive, best_before).map(|s| s + bonus)
+                }
+            };
+            if i > 0 {
+                best_before = std::cmp::max(best_before.map(|s| s - FUZZY_GAP), prev[j]);
+            }
+        }
+        std::mem::swap(prev, current);
+    }
+    // shorter names are better
+    prev.iter().flatten().max().map(|s| s - text.len() as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(query: &str, text: &str) -> Option<i32> {
+        let query: Vec<char> = query.chars().collect();
+        fuzzy_score(&query, text, &mut MatchBuffers::default())
+    }
+
+    fn glob(pattern: &str, text: &str) -> bool {
+        Pattern::glob(pattern).is_match(text)
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob("top.a", "top.a"));
+        assert!(!glob("top.a", "top.ab"));
+        assert!(!glob("top.ab", "top.a"));
+        assert!(glob("top.*", "top.ab"));
+        assert!(glob("top.*", "top."));
+        assert!(!glob("top.*", "top.a.b"));
+        assert!(glob("top.*.b", "top.a.b"));
+        assert!(glob("t?p.a*b*c", "top.abxbc"));
+        assert!(!glob("top?a", "top.a"));
+        assert!(glob("top.**", "top.a.b"));
+        assert!(glob("**.b", "top.a.b"));
+        assert!(glob("top.**.b", "top.a.c.b"));
+        assert!(!glob("top.**.b", "top.b"));
+        assert!(glob("**", ""));
+        assert!(glob("*a*a*a*b", "aaaaaaaab"));
+        assert!(!glob("*a*a*a*b", "aaaaaaaaa"));
+        // special characters of regular expressions are not special in globs
+        assert!(glob("top.a[1]", "top.a[1]"));
+        assert!(!glob("top.a[1]", "top.a1"));
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(score("xyz", "top.a"), None);
+        assert_eq!(score("ba", "top.ab"), None);
+        assert!(score("ab", "top.ab").is_some());
+        // consecutive matches are better than spread out ones
+        assert!(score("valid", "top.valid").unwrap() > score("valid", "top.v_a_l_i_d").unwrap());
+        // matches at the start of a word are better
+        assert!(score("rq", "top.result_q").unwrap() > score("rq", "top.xrxq").unwrap());
+    }
+}
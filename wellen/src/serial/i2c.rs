@@ -0,0 +1,145 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// Inter-Integrated Circuit bus.
+
+use super::{Annotations, Result, level_at, level_before, steps, to_word};
+use crate::{Signal, SignalRef, Time, TimeTableIdx};
+
+/// Configuration of an I2C bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct I2c {
+    /// Addresses that start with `11110` are 10-bit addresses, which span two bytes.
+    /// Otherwise, they are treated like any other 7-bit address.
+    pub ten_bit_addresses: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Phase {
+    /// waiting for a start condition
+    Idle,
+    Address,
+    /// waiting for the second byte of a 10-bit address
+    LowAddress {
+        high: u16,
+        header: TimeTableIdx,
+    },
+    Data,
+}
+
+impl I2c {
+    /// Decodes all transfers into a string signal with the provided `id`. Start, repeated start
+    /// and stop conditions are annotated as `S`, `Sr` and `P`. Addresses are annotated as,
+    /// e.g., `W 0x50 ACK` and data bytes as `0x12 NACK`, from the rising clock edge of their
+    /// first bit until the next annotation. Bytes that are interrupted by a start or stop
+    /// condition are marked as `incomplete`.
+    pub fn decode(
+        &self,
+        id: SignalRef,
+        time_table: &[Time],
+        scl: &Signal,
+        sda: &Signal,
+    ) -> Result<Signal> {
+        let mut out = Annotations::default();
+        let mut phase = Phase::Idle;
+        let mut bits = vec![];
+        let mut first_edge = 0;
+        // the last 10-bit address that was written to, which a read after a repeated start
+        // refers to without repeating the second address byte
+        let mut last_ten_bit = None;
+        for idx in steps(&[scl, sda]) {
+            let scl_before = level_before(scl, idx);
+            let scl_after = level_at(scl, idx);
+            let sda_before = level_before(sda, idx);
+            let sda_after = level_at(sda, idx);
+            if scl_before == Some(true) && scl_after == Some(true) && sda_before != sda_after {
+                // start and stop conditions, which always follow a rising clock edge that
+                // looks like the first bit of a byte
+                if bits.len() > 1 {
+                    out.set(first_edge, "incomplete byte".to_string());
+                }
+                bits.clear();
+                match (sda_before, sda_after) {
+                    (Some(true), Some(false)) => {
+                        let text = if phase == Phase::Idle { "S" } else { "Sr" };
+                        out.set(idx, text.to_string());
+                        phase = Phase::Address;
+                    }
+                    (Some(false), Some(true)) => {
+                        out.set(idx, "P".to_string());
+                        if (idx as usize) + 1 < time_table.len() {
+                            out.clear(idx + 1);
+                        }
+                        phase = Phase::Idle;
+                        last_ten_bit = None;
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            let rising = scl_before == Some(false) && scl_after == Some(true);
+            if !rising || phase == Phase::Idle {
+                continue;
+            }
+            if bits.is_empty() {
+                first_edge = idx;
+            }
+            // data is sampled just before the edge, since it may change in the same time step
+            bits.push(sda_before);
+            if bits.len() < 9 {
+                continue;
+            }
+            let ack = match bits[8] {
+                Some(false) => "ACK",
+                Some(true) => "NACK",
+                None => "undefined ACK",
+            };
+            let byte = to_word(&bits[..8], true);
+            bits.clear();
+            let Some(byte) = byte else {
+                out.set(first_edge, format!("undefined data {ack}"));
+                continue;
+            };
+            let read = byte & 1 == 1;
+            let rw = if read { "R" } else { "W" };
+            match phase {
+                Phase::Address if self.ten_bit_addresses && byte >> 3 == 0b11110 => {
+                    let high = ((byte >> 1) & 0b11) as u16;
+                    match last_ten_bit {
+                        Some(address) if read && address >> 8 == high => {
+                            out.set(first_edge, format!("R 0x{address:03x} {ack}"));
+                            phase = Phase::Data;
+                        }
+                        // reads need to be preceded by a write that sets the full address
+                        _ if read => {
+                            out.set(first_edge, format!("R 0x{high:x}xx {ack}"));
+                            phase = Phase::Data;
+                        }
+                        _ => {
+                            out.set(first_edge, format!("W 0x{high:x}xx {ack}"));
+                            phase = Phase::LowAddress {
+                                high,
+                                header: first_edge,
+                            };
+                        }
+                    }
+                }
+                Phase::Address => {
+                    out.set(first_edge, format!("{rw} 0x{:02x} {ack}", byte >> 1));
+                    phase = Phase::Data;
+                }
+                Phase::LowAddress { high, header } => {
+                    let address = (high << 8) | byte as u16;
+                    out.set(header, format!("W 0x{address:03x} {ack}"));
+                    last_ten_bit = Some(address);
+                    phase = Phase::Data;
+                }
+                Phase::Data => {
+                    out.set(first_edge, format!("0x{byte:02x} {ack}"));
+                }
+                Phase::Idle => unreachable!("bits are only collected after a start condition"),
+            }
+        }
+        Ok(out.into_signal(id, time_table.len()))
+    }
+}
@@ -0,0 +1,101 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// Decodes serial line protocols into string signals that annotate the decoded data.
+
+mod i2c;
+mod spi;
+mod uart;
+
+pub use i2c::I2c;
+pub use spi::Spi;
+pub use uart::{Parity, Uart};
+
+use crate::{Signal, SignalRef, SignalValue, TimeTableIdx};
+use std::collections::BTreeMap;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+#[non_exhaustive]
+pub enum SerialError {
+    #[error("[serial] the waveform has no timescale, which is needed to convert the baud rate")]
+    UnknownTimescale,
+    #[error("[serial] a bit at {0} baud lasts less than two time units of the waveform")]
+    BaudRateTooHigh(u32),
+    #[error("[serial] invalid configuration: {0}")]
+    InvalidConfig(String),
+}
+
+pub type Result<T> = std::result::Result<T, SerialError>;
+
+/// Values of the decoded signal, by the time table index at which they become valid.
+#[derive(Debug, Default)]
+struct Annotations {
+    values: BTreeMap<TimeTableIdx, String>,
+}
+
+impl Annotations {
+    fn set(&mut self, idx: TimeTableIdx, text: String) {
+        self.values.insert(idx, text);
+    }
+
+    /// Returns to the empty string, unless another annotation starts at the same index.
+    fn clear(&mut self, idx: TimeTableIdx) {
+        self.values.entry(idx).or_default();
+    }
+
+    /// The result starts with an empty string at the first time table index, so that
+    /// it is defined over the whole waveform.
+    fn into_signal(mut self, id: SignalRef, time_table_len: usize) -> Signal {
+        if time_table_len > 0 {
+            self.clear(0);
+        }
+        let (time_indices, strings) = self.values.into_iter().unzip();
+        Signal::new_var_len(id, time_indices, strings)
+    }
+}
+
+/// Logic level of a single bit signal, `None` for `x`, `z` and other undefined states.
+fn level(value: Option<SignalValue>) -> Option<bool> {
+    match value?.to_bit_string()?.chars().last()? {
+        '0' | 'l' => Some(false),
+        '1' | 'h' => Some(true),
+        _ => None,
+    }
+}
+
+/// Level at the end of time step `idx`.
+fn level_at(signal: &Signal, idx: TimeTableIdx) -> Option<bool> {
+    level(signal.value_at_idx(idx))
+}
+
+/// Level before time step `idx`.
+fn level_before(signal: &Signal, idx: TimeTableIdx) -> Option<bool> {
+    level(signal.value_at_idx(idx.checked_sub(1)?))
+}
+
+/// All time steps in which at least one of the signals changes.
+fn steps(signals: &[&Signal]) -> Vec<TimeTableIdx> {
+    let mut out: Vec<_> = signals
+        .iter()
+        .flat_map(|s| s.time_indices().iter().cloned())
+        .collect();
+    out.sort_unstable();
+    out.dedup();
+    out
+}
+
+/// Combines bits into a word. Returns `None` if any bit is undefined.
+fn to_word(bits: &[Option<bool>], msb_first: bool) -> Option<u64> {
+    let mut word = 0;
+    for (ii, bit) in bits.iter().enumerate() {
+        let position = if msb_first { bits.len() - 1 - ii } else { ii };
+        word |= ((*bit)? as u64) << position;
+    }
+    Some(word)
+}
+
+/// Hexadecimal with enough digits for `bits`, e.g., `0x0a`.
+fn hex(word: u64, bits: u32) -> String {
+    let digits = bits.div_ceil(4).max(2) as usize;
+    format!("0x{word:0digits$x}")
+}
@@ -0,0 +1,108 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// Serial Peripheral Interface.
+
+use super::{Annotations, Result, SerialError, hex, level_at, level_before, steps, to_word};
+use crate::{Signal, SignalRef, Time, TimeTableIdx};
+
+/// Configuration of an SPI bus. The clock polarity `cpol` and phase `cpha` determine the
+/// clock edge at which data is sampled, following the usual SPI modes 0 to 3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Spi {
+    /// The clock idles high.
+    pub cpol: bool,
+    /// Data is sampled at the second, i.e., trailing clock edge.
+    pub cpha: bool,
+    /// Between 1 and 64.
+    pub bits_per_word: u32,
+    pub msb_first: bool,
+    pub cs_active_low: bool,
+}
+
+impl Default for Spi {
+    /// Mode 0 with 8-bit words, most significant bit first and an active-low chip select.
+    fn default() -> Self {
+        Self {
+            cpol: false,
+            cpha: false,
+            bits_per_word: 8,
+            msb_first: true,
+            cs_active_low: true,
+        }
+    }
+}
+
+impl Spi {
+    /// Decodes the words on the `data` line, e.g., MOSI or MISO, into a string signal with the
+    /// provided `id`. Each word is annotated from the clock edge at which its first bit is
+    /// sampled until the next word starts or the chip select `cs` is deasserted.
+    /// If `cs` is `None`, all clock edges are part of a single transfer.
+    /// Words that are cut short by the chip select are marked as `incomplete`.
+    pub fn decode(
+        &self,
+        id: SignalRef,
+        time_table: &[Time],
+        sclk: &Signal,
+        cs: Option<&Signal>,
+        data: &Signal,
+    ) -> Result<Signal> {
+        if !(1..=64).contains(&self.bits_per_word) {
+            return Err(SerialError::InvalidConfig(format!(
+                "{} bits per word, expected 1 to 64",
+                self.bits_per_word
+            )));
+        }
+        // leading edges are rising if the clock idles low
+        let sample_on_rising = self.cpol == self.cpha;
+        let is_active = |idx: TimeTableIdx| match cs {
+            None => true,
+            Some(cs) => level_at(cs, idx) == Some(!self.cs_active_low),
+        };
+
+        let mut out = Annotations::default();
+        let mut bits = vec![];
+        let mut first_edge = 0;
+        let mut was_active = false;
+        let inputs: Vec<_> = [sclk].into_iter().chain(cs).collect();
+        for idx in steps(&inputs) {
+            let active = is_active(idx);
+            if was_active && !active {
+                if !bits.is_empty() {
+                    let text = format!("incomplete word after {} bits", bits.len());
+                    out.set(first_edge, text);
+                    bits.clear();
+                }
+                out.clear(idx);
+            }
+            // bits from a previous transfer are dropped when the chip select is reasserted
+            if !was_active && active {
+                bits.clear();
+            }
+            was_active = active;
+            let (before, after) = (level_before(sclk, idx), level_at(sclk, idx));
+            let edge = match (before, after) {
+                (Some(false), Some(true)) => sample_on_rising,
+                (Some(true), Some(false)) => !sample_on_rising,
+                _ => false,
+            };
+            if !edge || !active {
+                continue;
+            }
+            if bits.is_empty() {
+                first_edge = idx;
+            }
+            // data is sampled just before the edge, since it may change in the same time step
+            bits.push(level_before(data, idx));
+            if bits.len() == self.bits_per_word as usize {
+                let text = match to_word(&bits, self.msb_first) {
+                    Some(word) => hex(word, self.bits_per_word),
+                    None => "undefined data".to_string(),
+                };
+                out.set(first_edge, text);
+                bits.clear();
+            }
+        }
+        Ok(out.into_signal(id, time_table.len()))
+    }
+}
@@ -0,0 +1,152 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// Asynchronous serial lines, e.g., the TX line of a UART.
+
+use super::{Annotations, Result, SerialError, hex, level, level_at, steps, to_word};
+use crate::{Signal, SignalRef, Time, Timescale};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Parity {
+    #[default]
+    None,
+    Even,
+    Odd,
+}
+
+/// Configuration of an asynchronous serial line. Frames start with a start bit, followed by
+/// the data bits, least significant bit first, an optional parity bit and the stop bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Uart {
+    /// Bits per second.
+    pub baud_rate: u32,
+    /// Between 5 and 9.
+    pub data_bits: u32,
+    pub parity: Parity,
+    /// 1 or 2.
+    pub stop_bits: u32,
+    /// The line idles low and all bits are inverted.
+    pub inverted: bool,
+}
+
+impl Uart {
+    /// 8 data bits, no parity and one stop bit.
+    pub fn new(baud_rate: u32) -> Self {
+        Self {
+            baud_rate,
+            data_bits: 8,
+            parity: Parity::None,
+            stop_bits: 1,
+            inverted: false,
+        }
+    }
+
+    /// Duration of one bit in units of the `timescale`.
+    pub fn bit_period(&self, timescale: Option<Timescale>) -> Result<f64> {
+        let timescale = timescale.ok_or(SerialError::UnknownTimescale)?;
+        let exponent = timescale
+            .unit
+            .to_exponent()
+            .ok_or(SerialError::UnknownTimescale)?;
+        let seconds_per_unit = timescale.factor as f64 * 10f64.powi(exponent as i32);
+        let period = 1.0 / (self.baud_rate as f64 * seconds_per_unit);
+        if period < 2.0 {
+            Err(SerialError::BaudRateTooHigh(self.baud_rate))
+        } else {
+            Ok(period)
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.baud_rate == 0 {
+            Err(SerialError::InvalidConfig("baud rate of 0".to_string()))
+        } else if !(5..=9).contains(&self.data_bits) {
+            Err(SerialError::InvalidConfig(format!(
+                "{} data bits, expected 5 to 9",
+                self.data_bits
+            )))
+        } else if !(1..=2).contains(&self.stop_bits) {
+            Err(SerialError::InvalidConfig(format!(
+                "{} stop bits, expected 1 or 2",
+                self.stop_bits
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Decodes all frames on the `line` into a string signal with the provided `id`. Each frame
+    /// is annotated with its data from the falling edge of its start bit until the first time
+    /// step at or after the end of its stop bits. Frames with a wrong parity or stop bit are
+    /// marked as `parity error` or `framing error`.
+    pub fn decode(
+        &self,
+        id: SignalRef,
+        time_table: &[Time],
+        timescale: Option<Timescale>,
+        line: &Signal,
+    ) -> Result<Signal> {
+        self.validate()?;
+        let period = self.bit_period(timescale)?;
+        let parity_bits = if self.parity == Parity::None { 0 } else { 1 };
+        let frame_bits = 1 + self.data_bits + parity_bits + self.stop_bits;
+        // samples in the middle of a bit, with bit 0 being the start bit
+        let sample = |start: Time, bit: u32| -> Option<bool> {
+            let time = start as f64 + (bit as f64 + 0.5) * period;
+            let value = line.value_at_time(time_table, time.round() as Time);
+            level(value).map(|l| l != self.inverted)
+        };
+
+        let mut out = Annotations::default();
+        let mut prev = None;
+        // a new frame cannot start before the middle of the last stop bit
+        let mut busy_until = 0.0;
+        for idx in steps(&[line]) {
+            let value = level_at(line, idx).map(|l| l != self.inverted);
+            let is_start = prev == Some(true) && value == Some(false);
+            prev = value;
+            let start = time_table[idx as usize];
+            if !is_start || (start as f64) < busy_until {
+                continue;
+            }
+            // glitches are shorter than half a bit
+            if sample(start, 0) != Some(false) {
+                continue;
+            }
+            let bits: Vec<_> = (1..=self.data_bits).map(|ii| sample(start, ii)).collect();
+            let mut text = match to_word(&bits, false) {
+                Some(word) => self.format_data(word),
+                None => "undefined data".to_string(),
+            };
+            if self.parity != Parity::None {
+                let ones = bits.iter().filter(|b| **b == Some(true)).count() as u32;
+                let expected = (ones % 2 == 1) ^ (self.parity == Parity::Odd);
+                if sample(start, 1 + self.data_bits) != Some(expected) {
+                    text += " parity error";
+                }
+            }
+            let stop = (1 + self.data_bits + parity_bits..frame_bits)
+                .all(|ii| sample(start, ii) == Some(true));
+            if !stop {
+                text += " framing error";
+            }
+            out.set(idx, text);
+            let end = start as f64 + frame_bits as f64 * period;
+            let end_idx = time_table.partition_point(|t| (*t as f64) < end);
+            if end_idx < time_table.len() {
+                out.clear(end_idx as u32);
+            }
+            busy_until = start as f64 + (frame_bits as f64 - 0.5) * period;
+        }
+        Ok(out.into_signal(id, time_table.len()))
+    }
+
+    /// Hexadecimal value, followed by the ASCII character if it is printable.
+    fn format_data(&self, word: u64) -> String {
+        let text = hex(word, self.data_bits);
+        match u8::try_from(word).map(char::from) {
+            Ok(c) if c.is_ascii_graphic() || c == ' ' => format!("{text} '{c}'"),
+            _ => text,
+        }
+    }
+}
@@ -5,11 +5,13 @@
 
 use crate::fst::{get_bytes_per_entry, get_len_and_meta, push_zeros};
 use crate::hierarchy::SignalRef;
+use crate::viewers::ProgressCount;
 use crate::wavemem::{States, check_if_changed_and_truncate};
-use crate::{Hierarchy, SignalEncoding};
+use crate::{Hierarchy, Result, SignalEncoding};
 use num_enum::TryFromPrimitive;
 use std::fmt::{Debug, Display, Formatter};
 use std::num::NonZeroU32;
+use std::sync::atomic::Ordering;
 
 pub type Real = f64;
 pub type Time = u64;
@@ -48,18 +50,22 @@ impl PartialEq for SignalValue<'_> {
         match (self, other) {
             (SignalValue::String(a), SignalValue::String(b)) => a == b,
             (SignalValue::Real(a), SignalValue::Real(b)) => a == b,
-            _ => self.to_bit_string().unwrap() == other.to_bit_string().unwrap(),
+            _ => match (self.to_bit_string(), other.to_bit_string()) {
+                (Some(a), Some(b)) => a == b,
+                _ => false,
+            },
         }
     }
 }
 
 impl SignalValue<'_> {
+    /// Returns None if the value is a real or string.
     pub fn to_bit_string(&self) -> Option<String> {
         match &self {
             SignalValue::Binary(data, bits) => Some(two_state_to_bit_string(data, *bits)),
             SignalValue::FourValue(data, bits) => Some(four_state_to_bit_string(data, *bits)),
             SignalValue::NineValue(data, bits) => Some(nine_state_to_bit_string(data, *bits)),
-            other => panic!("Cannot convert {other:?} to bit string"),
+            SignalValue::String(_) | SignalValue::Real(_) => None,
         }
     }
 
@@ -294,6 +300,11 @@ impl Signal {
         self.data.get_value_at(offset.start + element as usize)
     }
 
+    /// Returns the value of the n-th change.
+    pub(crate) fn get_value_at_change(&self, change: usize) -> SignalValue<'_> {
+        self.data.get_value_at(change)
+    }
+
     pub fn get_first_time_idx(&self) -> Option<TimeTableIdx> {
         self.time_indices.first().cloned()
     }
@@ -313,6 +324,255 @@ impl Signal {
     pub(crate) fn signal_encoding(&self) -> SignalEncoding {
         self.data.signal_encoding()
     }
+
+    /// Only keeps the changes up to the end of `window` and the last change at or before its start.
+    /// A window of `None` removes all changes.
+    pub(crate) fn retain_window(&mut self, window: Option<(TimeTableIdx, TimeTableIdx)>) {
+        let (first, last) = match window {
+            None => (0, 0),
+            Some((start, end)) => {
+                let first = self.get_offset(start).map(|o| o.start).unwrap_or(0);
+                let last = self.time_indices.partition_point(|t| *t <= end);
+                (first, std::cmp::max(first, last))
+            }
+        };
+        self.time_indices.truncate(last);
+        self.time_indices.drain(..first);
+        match &mut self.data {
+            SignalChangeData::FixedLength { width, bytes, .. } => {
+                bytes.truncate(last * *width as usize);
+                bytes.drain(..first * *width as usize);
+            }
+            SignalChangeData::VariableLength(strings) => {
+                strings.truncate(last);
+                strings.drain(..first);
+            }
+        }
+    }
+}
+
+/// Direction in which to search for a change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchDirection {
+    /// Look for the first matching change strictly after the start time.
+    Forward,
+    /// Look for the last matching change strictly before the start time.
+    Backward,
+}
+
+/// Kind of edge. Like in Verilog, only the least significant bit of a bit-vector is considered and
+/// `0 -> x` or `x -> 1` count as a rising edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    Rising,
+    Falling,
+    Any,
+}
+
+/// A change found by one of the search functions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SignalChange<'a> {
+    pub time_idx: TimeTableIdx,
+    pub time: Time,
+    /// The value after all delta cycles of this time step.
+    pub value: SignalValue<'a>,
+}
+
+/// Queries that operate on actual times instead of time table indices.
+/// Multiple changes at the same time (delta cycles) are treated as a single change to the last value.
+impl Signal {
+    /// Returns the value at `time` or `None` if the signal has not been assigned yet.
+    pub fn value_at_time(&self, time_table: &[Time], time: Time) -> Option<SignalValue<'_>> {
+        let idx = time_table.partition_point(|t| *t <= time).checked_sub(1)?;
+        self.value_at_idx(idx as TimeTableIdx)
+    }
+
+    /// Returns the value at the end of the time step `idx`, after all delta cycles.
+    pub fn value_at_idx(&self, idx: TimeTableIdx) -> Option<SignalValue<'_>> {
+        let offset = self.get_offset(idx)?;
+        Some(self.get_value_at(&offset, offset.elements - 1))
+    }
+
+    /// Finds the next or previous change relative to `time`.
+    pub fn find_change(
+        &self,
+        time_table: &[Time],
+        time: Time,
+        direction: SearchDirection,
+    ) -> Option<SignalChange<'_>> {
+        self.search(time_table, time, direction, |_, _| true)
+    }
+
+    /// Finds the next or previous edge relative to `time`. The first value of a signal is never an edge.
+    pub fn find_edge(
+        &self,
+        time_table: &[Time],
+        time: Time,
+        direction: SearchDirection,
+        edge: Edge,
+    ) -> Option<SignalChange<'_>> {
+        self.search(time_table, time, direction, |prev, value| {
+            let (Some(prev), Some(value)) = (prev.and_then(lsb_state), lsb_state(value)) else {
+                return false;
+            };
+            let rising = matches!((prev, value), (0, 1) | (0, 2) | (2, 1));
+            let falling = matches!((prev, value), (1, 0) | (1, 2) | (2, 0));
+            match edge {
+                Edge::Rising => rising,
+                Edge::Falling => falling,
+                Edge::Any => rising || falling,
+            }
+        })
+    }
+
+    /// Finds the next or previous change to exactly `value`.
+    pub fn find_value(
+        &self,
+        time_table: &[Time],
+        time: Time,
+        direction: SearchDirection,
+        value: SignalValue,
+    ) -> Option<SignalChange<'_>> {
+        self.find_matching(time_table, time, direction, |v| values_equal(&v, &value))
+    }
+
+    /// Finds the next or previous change to a value that fulfills the `predicate`.
+    pub fn find_matching(
+        &self,
+        time_table: &[Time],
+        time: Time,
+        direction: SearchDirection,
+        mut predicate: impl FnMut(SignalValue) -> bool,
+    ) -> Option<SignalChange<'_>> {
+        self.search(time_table, time, direction, |_, value| predicate(value))
+    }
+
+    /// Visits time steps with changes, starting at `time` and going in `direction`.
+    /// The predicate receives the value before the time step and the final value of the time step.
+    fn search(
+        &self,
+        time_table: &[Time],
+        time: Time,
+        direction: SearchDirection,
+        mut predicate: impl FnMut(Option<SignalValue>, SignalValue) -> bool,
+    ) -> Option<SignalChange<'_>> {
+        let indices = &self.time_indices;
+        let time_of = |ii: usize| time_table[indices[ii] as usize];
+        let mut check = |first: usize, last: usize| {
+            let prev = first.checked_sub(1).map(|ii| self.data.get_value_at(ii));
+            let value = self.data.get_value_at(last);
+            predicate(prev, value).then(|| SignalChange {
+                time_idx: indices[last],
+                time: time_of(last),
+                value,
+            })
+        };
+        match direction {
+            SearchDirection::Forward => {
+                let mut first = indices.partition_point(|ii| time_table[*ii as usize] <= time);
+                while first < indices.len() {
+                    let mut last = first;
+                    while last + 1 < indices.len() && indices[last + 1] == indices[first] {
+                        last += 1;
+                    }
+                    if let Some(change) = check(first, last) {
+                        return Some(change);
+                    }
+                    first = last + 1;
+                }
+                None
+            }
+            SearchDirection::Backward => {
+                let mut end = indices.partition_point(|ii| time_table[*ii as usize] < time);
+                while end > 0 {
+                    let last = end - 1;
+                    let mut first = last;
+                    while first > 0 && indices[first - 1] == indices[last] {
+                        first -= 1;
+                    }
+                    if let Some(change) = check(first, last) {
+                        return Some(change);
+                    }
+                    end = first;
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Returns `0`, `1` or `2` (for `x`) for the least significant bit. Returns `None` for reals and strings.
+fn lsb_state(value: SignalValue) -> Option<u8> {
+    let (data, states) = match value {
+        SignalValue::Binary(data, _) => (data, States::Two),
+        SignalValue::FourValue(data, _) => (data, States::Four),
+        SignalValue::NineValue(data, _) => (data, States::Nine),
+        _ => return None,
+    };
+    // the nine value states are ordered as: `0 1 x z h u w l -`
+    match data.last()? & states.mask() {
+        0 | 7 => Some(0),
+        1 | 4 => Some(1),
+        _ => Some(2),
+    }
+}
+
+/// State of a single bit. `h` and `l` count as `One` and `Zero`, all other undefined states
+/// of the nine value encoding as `X`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BitState {
+    Zero,
+    One,
+    X,
+    Z,
+}
+
+/// Returns the state of `bit`, counting from the least significant bit.
+/// Strings and reals are treated as `X`.
+pub(crate) fn bit_state(value: &SignalValue, bit: u32) -> BitState {
+    let (data, bits_per_state) = match value {
+        SignalValue::Binary(data, _) => (data, 1),
+        SignalValue::FourValue(data, _) => (data, 2),
+        SignalValue::NineValue(data, _) => (data, 4),
+        SignalValue::String(_) | SignalValue::Real(_) => return BitState::X,
+    };
+    // states are right aligned, i.e., the least significant bit is stored in the last byte
+    let position = bit as usize * bits_per_state;
+    let byte = data[data.len() - 1 - position / 8];
+    let state = (byte >> (position % 8)) & ((1u8 << bits_per_state) - 1);
+    // the nine value states are ordered as: `0 1 x z h u w l -`
+    match state {
+        0 | 7 => BitState::Zero,
+        1 | 4 => BitState::One,
+        3 => BitState::Z,
+        _ => BitState::X,
+    }
+}
+
+/// Unlike `SignalValue::eq`, this does not panic when comparing a bit-vector with a string or real.
+fn values_equal(a: &SignalValue, b: &SignalValue) -> bool {
+    match (a, b) {
+        (SignalValue::String(_), SignalValue::String(_))
+        | (SignalValue::Real(_), SignalValue::Real(_)) => a == b,
+        _ => a.bits().is_some() && b.bits().is_some() && a == b,
+    }
+}
+
+/// Converts a time window into the indices of the time table entries that are in effect at
+/// `start` and `end`. Returns `None` if the window ends before the first time table entry.
+pub(crate) fn time_window_to_indices(
+    time_table: &[Time],
+    start: Time,
+    end: Time,
+) -> Option<(TimeTableIdx, TimeTableIdx)> {
+    let end_idx = time_table.partition_point(|t| *t <= end).checked_sub(1)?;
+    let start_idx = time_table
+        .partition_point(|t| *t <= start)
+        .saturating_sub(1);
+    Some((
+        std::cmp::min(start_idx, end_idx) as TimeTableIdx,
+        end_idx as TimeTableIdx,
+    ))
 }
 
 pub struct SignalChangeIterator<'a> {
@@ -351,7 +611,7 @@ pub struct BitVectorBuilder {
 }
 
 impl BitVectorBuilder {
-    fn new(max_states: States, bits: u32) -> Self {
+    pub(crate) fn new(max_states: States, bits: u32) -> Self {
         assert!(bits > 0);
         let (len, has_meta) = get_len_and_meta(max_states, bits);
         let bytes_per_entry = get_bytes_per_entry(len, has_meta);
@@ -368,10 +628,10 @@ impl BitVectorBuilder {
         }
     }
 
-    fn add_change(&mut self, time_idx: TimeTableIdx, value: SignalValue) {
+    pub(crate) fn add_change(&mut self, time_idx: TimeTableIdx, value: SignalValue) {
         debug_assert_eq!(value.bits().unwrap(), self.bits);
         let local_encoding = value.states().unwrap();
-        debug_assert!(local_encoding.bits() >= self.max_states.bits());
+        debug_assert!(local_encoding.bits() <= self.max_states.bits());
         if self.bits == 1 {
             let (value, mask) = value.data_and_mask().unwrap();
             let value = value[0] & mask;
@@ -411,7 +671,7 @@ impl BitVectorBuilder {
         }
     }
 
-    fn finish(self, id: SignalRef) -> Signal {
+    pub(crate) fn finish(self, id: SignalRef) -> Signal {
         debug_assert_eq!(
             self.data.len(),
             self.time_indices.len() * self.bytes_per_entry
@@ -708,7 +968,36 @@ pub trait SignalSourceImplementation: Sync + Send {
         ids: &[SignalRef],
         types: &[SignalEncoding],
         multi_threaded: bool,
-    ) -> Vec<Signal>;
+    ) -> Result<Vec<Signal>>;
+    /// Like `load_signals`, but increments `progress` by one for every loaded signal.
+    /// Implementations that decode signals in several steps can override this in order to
+    /// report progress while still loading.
+    fn load_signals_with_progress(
+        &mut self,
+        ids: &[SignalRef],
+        types: &[SignalEncoding],
+        multi_threaded: bool,
+        progress: &ProgressCount,
+    ) -> Result<Vec<Signal>> {
+        let signals = self.load_signals(ids, types, multi_threaded)?;
+        progress.fetch_add(signals.len() as u64, Ordering::SeqCst);
+        Ok(signals)
+    }
+    /// Loads new signals, but only decodes the data that is needed to represent the time window
+    /// from `start` to `end` (inclusive). The signals contain all changes inside the window as well
+    /// as the last change before it. Time indices still refer to the complete time table.
+    /// The default implementation loads the complete signals.
+    fn load_signals_in_range(
+        &mut self,
+        ids: &[SignalRef],
+        types: &[SignalEncoding],
+        start: Time,
+        end: Time,
+        multi_threaded: bool,
+    ) -> Result<Vec<Signal>> {
+        let _ = (start, end);
+        self.load_signals(ids, types, multi_threaded)
+    }
     /// Print memory size / speed statistics.
     fn print_statistics(&self);
 }
@@ -724,12 +1013,79 @@ impl SignalSource {
 
     /// Loads new signals.
     /// Many implementations take advantage of loading multiple signals at a time.
+    /// Fails if the signal data turns out to be malformed.
     pub fn load_signals(
         &mut self,
         ids: &[SignalRef],
         hierarchy: &Hierarchy,
         multi_threaded: bool,
-    ) -> Vec<(SignalRef, Signal)> {
+    ) -> Result<Vec<(SignalRef, Signal)>> {
+        self.load_signals_with(ids, hierarchy, |inner, ids, types| {
+            inner.load_signals(ids, types, multi_threaded)
+        })
+    }
+
+    /// Loads every signal that is referred to by a variable in `hierarchy`.
+    pub(crate) fn load_all_signals(
+        &mut self,
+        hierarchy: &Hierarchy,
+        multi_threaded: bool,
+    ) -> Result<Vec<(SignalRef, Signal)>> {
+        let ids: Vec<_> = hierarchy
+            .get_unique_signals_vars()
+            .iter()
+            .flatten()
+            .map(|v| v.signal_ref())
+            .collect();
+        self.load_signals(&ids, hierarchy, multi_threaded)
+    }
+
+    /// Like `load_signals`, but increments `progress` by one for every id in `ids`, i.e., it
+    /// reaches `ids.len()` once all signals are loaded.
+    pub fn load_signals_with_progress(
+        &mut self,
+        ids: &[SignalRef],
+        hierarchy: &Hierarchy,
+        multi_threaded: bool,
+        progress: &ProgressCount,
+    ) -> Result<Vec<(SignalRef, Signal)>> {
+        let mut decoded = 0;
+        let signals = self.load_signals_with(ids, hierarchy, |inner, ids, types| {
+            decoded = ids.len();
+            inner.load_signals_with_progress(ids, types, multi_threaded, progress)
+        })?;
+        // duplicates and aliases are not decoded by the implementation
+        progress.fetch_add((ids.len() - decoded) as u64, Ordering::SeqCst);
+        Ok(signals)
+    }
+
+    /// Loads new signals, but only decodes the data that is needed to represent the time window
+    /// from `start` to `end` (inclusive). The resulting signals contain all changes inside the
+    /// window as well as the last change before it, i.e., the value at `start` is always known.
+    /// Time indices still point into the complete time table.
+    pub fn load_signals_in_range(
+        &mut self,
+        ids: &[SignalRef],
+        hierarchy: &Hierarchy,
+        start: Time,
+        end: Time,
+        multi_threaded: bool,
+    ) -> Result<Vec<(SignalRef, Signal)>> {
+        self.load_signals_with(ids, hierarchy, |inner, ids, types| {
+            inner.load_signals_in_range(ids, types, start, end, multi_threaded)
+        })
+    }
+
+    fn load_signals_with(
+        &mut self,
+        ids: &[SignalRef],
+        hierarchy: &Hierarchy,
+        load: impl FnOnce(
+            &mut dyn SignalSourceImplementation,
+            &[SignalRef],
+            &[SignalEncoding],
+        ) -> Result<Vec<Signal>>,
+    ) -> Result<Vec<(SignalRef, Signal)>> {
         // sort and dedup ids
         let mut ids = Vec::from_iter(ids.iter().cloned());
         ids.sort();
@@ -750,15 +1106,11 @@ impl SignalSource {
             .iter()
             .map(|i| hierarchy.get_signal_tpe(*i).unwrap())
             .collect();
-        let signals = self.inner.load_signals(&ids, &types, multi_threaded);
+        let signals = load(self.inner.as_mut(), &ids, &types)?;
         // the signal source must always return the correct number of signals!
         assert_eq!(signals.len(), ids.len());
         let mut out = Vec::with_capacity(orig_ids.len());
-        for ((id, is_alias), signal) in orig_ids
-            .iter()
-            .zip(is_alias.iter())
-            .zip(signals.into_iter())
-        {
+        for ((id, is_alias), signal) in orig_ids.iter().zip(is_alias.iter()).zip(signals) {
             if *is_alias {
                 let slice = hierarchy.get_slice_info(*id).unwrap();
                 let sliced = slice_signal(*id, &signal, slice.msb, slice.lsb);
@@ -767,7 +1119,7 @@ impl SignalSource {
                 out.push((*id, signal));
             }
         }
-        out
+        Ok(out)
     }
 
     /// Print memory size / speed statistics.
@@ -780,6 +1132,34 @@ impl SignalSource {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_bit_state() {
+        let two = [0b1, 0b0000_0010];
+        let states: Vec<_> = [0, 1, 8]
+            .into_iter()
+            .map(|b| bit_state(&SignalValue::Binary(&two, 9), b))
+            .collect();
+        assert_eq!(states, [BitState::Zero, BitState::One, BitState::One]);
+        // 4-state `1x0z`
+        let four = [0b0110_0011];
+        let states: Vec<_> = (0..4)
+            .map(|b| bit_state(&SignalValue::FourValue(&four, 4), b))
+            .collect();
+        assert_eq!(
+            states,
+            [BitState::Z, BitState::Zero, BitState::X, BitState::One]
+        );
+        // 9-state `hluz`
+        let nine = [0b0100_0111, 0b0101_0011];
+        let states: Vec<_> = (0..4)
+            .map(|b| bit_state(&SignalValue::NineValue(&nine, 4), b))
+            .collect();
+        assert_eq!(
+            states,
+            [BitState::Z, BitState::X, BitState::Zero, BitState::One]
+        );
+    }
+
     #[test]
     fn test_sizes() {
         assert_eq!(std::mem::size_of::<SignalRef>(), 4);
@@ -852,6 +1232,102 @@ mod tests {
         assert_eq!(out, expected);
     }
 
+    /// 1-bit, four-state signal with values `0`, `1`, `2 = x` or `3 = z`
+    fn one_bit_signal(changes: &[(TimeTableIdx, u8)]) -> Signal {
+        let time_indices = changes.iter().map(|(t, _)| *t).collect();
+        let bytes = changes
+            .iter()
+            .map(|(_, v)| (if *v < 2 { 0 } else { 1u8 << 6 }) | v)
+            .collect();
+        let encoding = FixedWidthEncoding::BitVector {
+            max_states: States::Four,
+            bits: 1,
+            meta_byte: false,
+        };
+        Signal::new_fixed_len(
+            SignalRef::from_index(0).unwrap(),
+            time_indices,
+            encoding,
+            1,
+            bytes,
+        )
+    }
+
+    #[test]
+    fn test_value_at_time() {
+        let time_table = [5, 10, 20, 30];
+        // delta cycle at time 20: 1 -> 0 -> x
+        let signal = one_bit_signal(&[(1, 1), (2, 0), (2, 2), (3, 0)]);
+        let value = |t| signal.value_at_time(&time_table, t).map(|v| v.to_string());
+        assert_eq!(value(0), None);
+        assert_eq!(value(5), None);
+        assert_eq!(value(10), Some("1".to_string()));
+        assert_eq!(value(19), Some("1".to_string()));
+        assert_eq!(value(20), Some("x".to_string()));
+        assert_eq!(value(1000), Some("0".to_string()));
+    }
+
+    #[test]
+    fn test_find_change() {
+        let time_table = [5, 10, 20, 30];
+        let signal = one_bit_signal(&[(1, 1), (2, 0), (2, 2), (3, 0)]);
+        let find = |t, dir| {
+            signal
+                .find_change(&time_table, t, dir)
+                .map(|c| (c.time_idx, c.time, c.value.to_string()))
+        };
+        use SearchDirection::*;
+        assert_eq!(find(0, Forward), Some((1, 10, "1".to_string())));
+        assert_eq!(find(10, Forward), Some((2, 20, "x".to_string())));
+        assert_eq!(find(25, Forward), Some((3, 30, "0".to_string())));
+        assert_eq!(find(30, Forward), None);
+        assert_eq!(find(10, Backward), None);
+        assert_eq!(find(11, Backward), Some((1, 10, "1".to_string())));
+        assert_eq!(find(30, Backward), Some((2, 20, "x".to_string())));
+        assert_eq!(find(31, Backward), Some((3, 30, "0".to_string())));
+    }
+
+    #[test]
+    fn test_find_edge_and_value() {
+        let time_table: Vec<Time> = (0..8).map(|t| t * 10).collect();
+        let signal = one_bit_signal(&[(0, 1), (1, 0), (2, 2), (3, 1), (4, 0), (5, 3), (6, 1)]);
+        let edge = |t, dir, edge| signal.find_edge(&time_table, t, dir, edge).map(|c| c.time);
+        use SearchDirection::*;
+        // the initial value is not an edge
+        assert_eq!(edge(0, Backward, Edge::Any), None);
+        assert_eq!(edge(0, Forward, Edge::Falling), Some(10));
+        // 0 -> x is a rising edge
+        assert_eq!(edge(0, Forward, Edge::Rising), Some(20));
+        assert_eq!(edge(20, Forward, Edge::Rising), Some(30));
+        // 1 -> 0
+        assert_eq!(edge(30, Forward, Edge::Falling), Some(40));
+        // 0 -> z
+        assert_eq!(edge(40, Forward, Edge::Rising), Some(50));
+        assert_eq!(edge(40, Forward, Edge::Any), Some(50));
+        assert_eq!(edge(1000, Backward, Edge::Falling), Some(40));
+        assert_eq!(edge(35, Backward, Edge::Rising), Some(30));
+
+        let one = [1u8];
+        let value = |t, dir| {
+            signal
+                .find_value(&time_table, t, dir, SignalValue::Binary(&one, 1))
+                .map(|c| c.time)
+        };
+        assert_eq!(value(0, Forward), Some(30));
+        assert_eq!(value(30, Forward), Some(60));
+        assert_eq!(value(30, Backward), Some(0));
+        assert_eq!(
+            signal
+                .find_value(&time_table, 0, Forward, SignalValue::String("1"))
+                .map(|c| c.time),
+            None
+        );
+        let unknown = signal.find_matching(&time_table, 0, Forward, |v| {
+            v.to_bit_string().unwrap().contains(['x', 'z'])
+        });
+        assert_eq!(unknown.map(|c| c.time), Some(20));
+    }
+
     #[test]
     fn test_slice_signal() {
         let mut out = vec![];
@@ -859,4 +1335,33 @@ mod tests {
         assert_eq!(out[0], 1);
         out.clear();
     }
+
+    /// Source that only implements the required methods.
+    struct CompleteSignalsOnly;
+
+    impl SignalSourceImplementation for CompleteSignalsOnly {
+        fn load_signals(
+            &mut self,
+            ids: &[SignalRef],
+            _types: &[SignalEncoding],
+            _multi_threaded: bool,
+        ) -> Result<Vec<Signal>> {
+            Ok(ids
+                .iter()
+                .map(|id| Signal::new_var_len(*id, vec![0, 2], vec!["a".into(), "b".into()]))
+                .collect())
+        }
+
+        fn print_statistics(&self) {}
+    }
+
+    #[test]
+    fn test_load_signals_in_range_falls_back_to_complete_signals() {
+        let id = SignalRef::from_index(0).unwrap();
+        let signals = CompleteSignalsOnly
+            .load_signals_in_range(&[id], &[SignalEncoding::String], 1, 1, false)
+            .unwrap();
+        assert_eq!(signals.len(), 1);
+        assert_eq!(signals[0].time_indices(), [0, 2]);
+    }
 }
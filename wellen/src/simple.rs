@@ -6,12 +6,17 @@
 // A simpler interface to load waves. Use this instead of `wellen::viewers` if you are building
 // a batch processing instead of a GUI app.
 
+use crate::cycles::CycleTrace;
+use crate::expr::{Expr, ExprError};
 use crate::{
-    Hierarchy, LoadOptions, Result, Signal, SignalRef, SignalSource, Time, TimeTable, viewers,
+    CompressedSignal, Edge, Hierarchy, LoadOptions, Result, SearchDirection, Signal, SignalChange,
+    SignalRef, SignalSource, SignalValue, Time, TimeTable, viewers,
 };
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 use std::fmt::{Debug, Formatter};
 use std::io::{BufRead, Seek};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
 
 /// Read a waveform file with the default options. Reads in header and body at once.
 pub fn read<P: AsRef<std::path::Path>>(filename: P) -> Result<Waveform> {
@@ -47,11 +52,58 @@ pub fn read_from_reader<R: BufRead + Seek + Send + Sync + 'static>(input: R) ->
 /// Provides file format independent access to a waveform file.
 pub struct Waveform {
     hierarchy: Hierarchy,
-    source: SignalSource,
+    /// Protected by a mutex, since evicted signals may need to be reloaded by `get_signal`.
+    source: Mutex<SignalSource>,
     time_table: TimeTable,
     /// Signals are stored in a HashMap since we expect only a small subset of signals to be
     /// loaded at a time.
-    signals: FxHashMap<SignalRef, Signal>,
+    signals: FxHashMap<SignalRef, LoadedSignal>,
+    /// Derived signals use the ids right after the last signal in the hierarchy.
+    derived: Vec<Expr>,
+    budget: Option<MemoryBudget>,
+    /// Incremented on every signal access in order to track which signal was least recently used.
+    clock: AtomicU64,
+}
+
+/// Limits the amount of memory used by loaded signals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryBudget {
+    /// Maximum number of bytes, as reported by [`Signal::size_in_memory`], that loaded signals may
+    /// take up. Signals that were requested by the latest call to `load_signals` are never evicted,
+    /// thus the budget may be exceeded if they do not fit. Evicted signals that are restored by
+    /// `get_signal` are only taken into account by the next call to `load_signals` or
+    /// `set_memory_budget`, since `get_signal` cannot evict signals that may still be borrowed.
+    pub bytes: usize,
+    /// Keeps evicted signals as [`CompressedSignal`] instead of dropping them. Compressed signals
+    /// count towards the budget and are only dropped once all uncompressed signals are evicted.
+    pub keep_compressed: bool,
+}
+
+/// A signal that was loaded by the user. If it got evicted, it is restored on demand.
+struct LoadedSignal {
+    signal: OnceLock<Signal>,
+    compressed: Option<CompressedSignal>,
+    last_used: AtomicU64,
+}
+
+impl LoadedSignal {
+    fn new(signal: Signal, time: u64) -> Self {
+        Self {
+            signal: OnceLock::from(signal),
+            compressed: None,
+            last_used: AtomicU64::new(time),
+        }
+    }
+
+    fn size_in_memory(&self) -> usize {
+        let signal = self.signal.get().map(|s| s.size_in_memory()).unwrap_or(0);
+        let compressed = self
+            .compressed
+            .as_ref()
+            .map(|c| c.size_in_memory())
+            .unwrap_or(0);
+        signal + compressed
+    }
 }
 
 impl Debug for Waveform {
@@ -64,9 +116,12 @@ impl Waveform {
     fn new(hierarchy: Hierarchy, source: SignalSource, time_table: TimeTable) -> Self {
         Waveform {
             hierarchy,
-            source,
+            source: Mutex::new(source),
             time_table,
             signals: FxHashMap::default(),
+            derived: vec![],
+            budget: None,
+            clock: AtomicU64::new(0),
         }
     }
 
@@ -78,27 +133,66 @@ impl Waveform {
         &self.time_table
     }
 
-    fn load_signals_internal(&mut self, ids: &[SignalRef], multi_threaded: bool) {
-        // make sure that we do not load signals that have already been loaded
-        let filtered_ids = ids
+    fn load_signals_internal(&mut self, ids: &[SignalRef], multi_threaded: bool) -> Result<()> {
+        // signals that were evicted with a compressed copy do not need to be reloaded
+        for id in ids.iter() {
+            if let Some(loaded) = self.signals.get_mut(id) {
+                if loaded.signal.get().is_none() {
+                    if let Some(compressed) = &loaded.compressed {
+                        loaded.signal = OnceLock::from(compressed.uncompress());
+                    }
+                }
+            }
+        }
+
+        // make sure that we do not load signals that are already loaded
+        let mut needed: Vec<_> = ids
             .iter()
-            .filter(|id| !self.signals.contains_key(id))
+            .filter(|id| !self.is_resident(**id))
             .cloned()
-            .collect::<Vec<_>>();
+            .collect();
+        // derived signals are computed from other signals which we need to load first
+        for id in needed.clone() {
+            if let Some(expr) = self.get_derived(id) {
+                needed.extend(expr.signals());
+            }
+        }
+        needed.retain(|id| !self.is_resident(*id));
+        needed.sort();
+        needed.dedup();
+        let (derived_ids, source_ids): (Vec<_>, Vec<_>) = needed
+            .into_iter()
+            .partition(|id| self.get_derived(*id).is_some());
 
-        let res = self
-            .source
-            .load_signals(&filtered_ids, &self.hierarchy, multi_threaded);
+        let res = self.source.get_mut().unwrap().load_signals(
+            &source_ids,
+            &self.hierarchy,
+            multi_threaded,
+        )?;
         for (id, signal) in res.into_iter() {
-            self.signals.insert(id, signal);
+            let time = self.tick();
+            self.signals.insert(id, LoadedSignal::new(signal, time));
+        }
+
+        for id in derived_ids {
+            let signal = self.evaluate_derived(id);
+            let time = self.tick();
+            self.signals.insert(id, LoadedSignal::new(signal, time));
         }
+
+        // all requested signals count as used
+        for id in ids.iter() {
+            self.get_signal(*id);
+        }
+        self.enforce_budget(ids);
+        Ok(())
     }
 
-    pub fn load_signals(&mut self, ids: &[SignalRef]) {
+    pub fn load_signals(&mut self, ids: &[SignalRef]) -> Result<()> {
         self.load_signals_internal(ids, false)
     }
 
-    pub fn load_signals_multi_threaded(&mut self, ids: &[SignalRef]) {
+    pub fn load_signals_multi_threaded(&mut self, ids: &[SignalRef]) -> Result<()> {
         self.load_signals_internal(ids, true)
     }
 
@@ -108,11 +202,249 @@ impl Waveform {
         }
     }
 
+    /// Returns a loaded signal. Signals that were evicted because of the memory budget are
+    /// decompressed or reloaded on demand. Returns `None` if the signal was never loaded or if
+    /// reloading it fails.
     pub fn get_signal(&self, id: SignalRef) -> Option<&Signal> {
-        self.signals.get(&id)
+        let loaded = self.signals.get(&id)?;
+        loaded.last_used.store(self.tick(), Ordering::Relaxed);
+        if let Some(signal) = loaded.signal.get() {
+            return Some(signal);
+        }
+        let signal = match &loaded.compressed {
+            Some(compressed) => compressed.uncompress(),
+            None => self.restore(id)?,
+        };
+        Some(loaded.signal.get_or_init(|| signal))
+    }
+
+    /// Returns `true` if the signal was loaded and not unloaded since, even if it is currently
+    /// evicted because of the memory budget.
+    pub fn is_loaded(&self, id: SignalRef) -> bool {
+        self.signals.contains_key(&id)
+    }
+
+    /// Returns `true` if the signal is loaded and was not evicted.
+    fn is_resident(&self, id: SignalRef) -> bool {
+        self.signals
+            .get(&id)
+            .is_some_and(|loaded| loaded.signal.get().is_some())
+    }
+
+    /// Loads all signals in `ids` and returns the ones that were not loaded before, such that they
+    /// can be unloaded again once they are no longer needed.
+    pub(crate) fn load_missing_signals(&mut self, ids: &[SignalRef]) -> Result<Vec<SignalRef>> {
+        let mut missing: Vec<_> = ids
+            .iter()
+            .copied()
+            .filter(|id| !self.is_loaded(*id))
+            .collect();
+        missing.sort();
+        missing.dedup();
+        self.load_signals_multi_threaded(ids)?;
+        Ok(missing)
+    }
+
+    /// Limits the memory used by loaded signals. Least recently used signals are evicted as soon as
+    /// the budget is exceeded. Pass `None` to remove the limit.
+    pub fn set_memory_budget(&mut self, budget: Option<MemoryBudget>) {
+        self.budget = budget;
+        self.enforce_budget(&[]);
+    }
+
+    /// Number of bytes currently used by loaded signals, including compressed copies of evicted
+    /// signals.
+    pub fn signal_memory_usage(&self) -> usize {
+        self.signals.values().map(|s| s.size_in_memory()).sum()
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Evicts least recently used signals until we are within the memory budget.
+    /// Signals in `keep` are never evicted.
+    fn enforce_budget(&mut self, keep: &[SignalRef]) {
+        let Some(budget) = self.budget else {
+            return;
+        };
+        let mut used = self.signal_memory_usage();
+        if used <= budget.bytes {
+            return;
+        }
+        let keep: FxHashSet<SignalRef> = keep.iter().copied().collect();
+        let mut candidates: Vec<_> = self
+            .signals
+            .iter()
+            .filter(|(id, _)| !keep.contains(*id))
+            .map(|(id, s)| (s.last_used.load(Ordering::Relaxed), *id))
+            .collect();
+        candidates.sort_unstable();
+
+        // first we evict uncompressed signals
+        for (_, id) in candidates.iter() {
+            if used <= budget.bytes {
+                return;
+            }
+            let loaded = self.signals.get_mut(id).unwrap();
+            if let Some(signal) = loaded.signal.take() {
+                used -= signal.size_in_memory();
+                if budget.keep_compressed && loaded.compressed.is_none() {
+                    let compressed = CompressedSignal::compress(&signal);
+                    used += compressed.size_in_memory();
+                    loaded.compressed = Some(compressed);
+                }
+            }
+        }
+        // if that was not enough, we also drop compressed copies
+        for (_, id) in candidates.iter() {
+            if used <= budget.bytes {
+                return;
+            }
+            let loaded = self.signals.get_mut(id).unwrap();
+            if let Some(compressed) = loaded.compressed.take() {
+                used -= compressed.size_in_memory();
+            }
+        }
+    }
+
+    /// Re-creates an evicted signal that has no compressed copy.
+    fn restore(&self, id: SignalRef) -> Option<Signal> {
+        let Some(expr) = self.get_derived(id) else {
+            return self.load_from_source(id);
+        };
+        // inputs that were unloaded in the meantime are only loaded temporarily
+        let mut inputs = vec![];
+        let mut temporary = vec![];
+        for input in expr.signals() {
+            match self.get_signal(input) {
+                Some(signal) => inputs.push(signal),
+                None => temporary.push(self.load_from_source(input)?),
+            }
+        }
+        inputs.extend(temporary.iter());
+        expr.evaluate(id, &inputs).ok()
+    }
+
+    fn load_from_source(&self, id: SignalRef) -> Option<Signal> {
+        let mut source = self.source.lock().ok()?;
+        let (_, signal) = source
+            .load_signals(&[id], &self.hierarchy, false)
+            .ok()?
+            .pop()?;
+        Some(signal)
+    }
+
+    fn evaluate_derived(&self, id: SignalRef) -> Signal {
+        let expr = self.get_derived(id).unwrap();
+        // inputs are loaded by `load_signals_internal` before any derived signal is evaluated
+        let inputs: Vec<_> = expr
+            .signals()
+            .iter()
+            .map(|i| self.get_signal(*i).unwrap())
+            .collect();
+        expr.evaluate(id, &inputs)
+            .expect("inputs are loaded before derived signals are evaluated")
+    }
+
+    // The following queries return `None` if the signal is not loaded, just like `get_signal`.
+
+    /// Returns the value of a loaded signal at `time`.
+    pub fn value_at_time(&self, id: SignalRef, time: Time) -> Option<SignalValue<'_>> {
+        self.get_signal(id)?.value_at_time(&self.time_table, time)
+    }
+
+    /// Finds the next or previous change of a loaded signal.
+    pub fn find_change(
+        &self,
+        id: SignalRef,
+        time: Time,
+        direction: SearchDirection,
+    ) -> Option<SignalChange<'_>> {
+        self.get_signal(id)?
+            .find_change(&self.time_table, time, direction)
+    }
+
+    /// Finds the next or previous edge of a loaded signal.
+    pub fn find_edge(
+        &self,
+        id: SignalRef,
+        time: Time,
+        direction: SearchDirection,
+        edge: Edge,
+    ) -> Option<SignalChange<'_>> {
+        self.get_signal(id)?
+            .find_edge(&self.time_table, time, direction, edge)
+    }
+
+    /// Finds the next or previous time at which a loaded signal changes to `value`.
+    pub fn find_value(
+        &self,
+        id: SignalRef,
+        time: Time,
+        direction: SearchDirection,
+        value: SignalValue,
+    ) -> Option<SignalChange<'_>> {
+        self.get_signal(id)?
+            .find_value(&self.time_table, time, direction, value)
+    }
+
+    /// Finds the next or previous time at which a loaded signal changes to a value that
+    /// fulfills the `predicate`.
+    pub fn find_matching(
+        &self,
+        id: SignalRef,
+        time: Time,
+        direction: SearchDirection,
+        predicate: impl FnMut(SignalValue) -> bool,
+    ) -> Option<SignalChange<'_>> {
+        self.get_signal(id)?
+            .find_matching(&self.time_table, time, direction, predicate)
+    }
+
+    /// Samples loaded signals at every `edge` of a loaded `clock`. See [`CycleTrace::sample`].
+    /// Returns `None` if the clock or any of the signals is not loaded.
+    pub fn cycle_trace(
+        &self,
+        clock: SignalRef,
+        edge: Edge,
+        signals: &[SignalRef],
+        skip_unchanged: bool,
+    ) -> Option<CycleTrace<'_>> {
+        let signals = signals
+            .iter()
+            .map(|id| self.get_signal(*id))
+            .collect::<Option<Vec<_>>>()?;
+        Some(CycleTrace::sample(
+            self.get_signal(clock)?,
+            &self.time_table,
+            edge,
+            &signals,
+            skip_unchanged,
+        ))
+    }
+
+    /// Registers a signal that is computed from an expression over other signals,
+    /// e.g., `top.valid & top.ready`. See [`Expr`] for the supported syntax.
+    /// The returned id can be loaded and accessed just like any other signal.
+    pub fn add_derived_signal(
+        &mut self,
+        expression: &str,
+    ) -> std::result::Result<SignalRef, ExprError> {
+        let expr = Expr::parse(expression, &self.hierarchy)?;
+        let index = self.hierarchy.num_unique_signals() + self.derived.len();
+        self.derived.push(expr);
+        Ok(SignalRef::from_index(index).unwrap())
+    }
+
+    fn get_derived(&self, id: SignalRef) -> Option<&Expr> {
+        let index = id
+            .index()
+            .checked_sub(self.hierarchy.num_unique_signals())?;
+        self.derived.get(index)
     }
 
     pub fn print_backend_statistics(&self) {
-        self.source.print_statistics();
+        self.source.lock().unwrap().print_statistics();
     }
 }
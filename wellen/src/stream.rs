@@ -0,0 +1,256 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// Ingests waveform data while a simulation is still running.
+
+use crate::vcd::BodyFollower;
+use crate::wavemem::{Encoder, PublishedBlocks};
+use crate::{
+    Hierarchy, LoadOptions, Result, SignalEncoding, SignalRef, SignalSource, SignalValue, Time,
+    TimeTable,
+};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Every published block stores an offset for every signal. Thus, we limit how often new blocks
+/// are published by default.
+const DEFAULT_PUBLISH_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+#[non_exhaustive]
+pub enum StreamError {
+    #[error("[stream] value changes need a call to `time_change` first")]
+    NoTimeStep,
+    #[error("[stream] signal {0:?} is not part of the hierarchy")]
+    UnknownSignal(SignalRef),
+    #[error("[stream] cannot record value {value} of signal {id:?} with encoding {encoding:?}")]
+    InvalidValue {
+        id: SignalRef,
+        encoding: SignalEncoding,
+        value: String,
+    },
+}
+
+/// Accepts value changes for a waveform that is still being recorded.
+/// Changes become visible to readers through [`StreamingWaveform::flush`], once their time step
+/// is complete, or through [`StreamingWaveform::finish`].
+pub struct StreamingWaveform {
+    encoder: Encoder,
+    shared: Arc<Shared>,
+    publish_interval: Duration,
+    last_publish: Option<Instant>,
+}
+
+/// State shared between the writer and all handles.
+#[derive(Default)]
+struct Shared {
+    published: RwLock<PublishedBlocks>,
+    subscribers: Mutex<Vec<Sender<Time>>>,
+}
+
+impl StreamingWaveform {
+    pub fn new(hierarchy: &Hierarchy) -> Self {
+        Self {
+            encoder: Encoder::new(hierarchy),
+            shared: Arc::default(),
+            publish_interval: DEFAULT_PUBLISH_INTERVAL,
+            last_publish: None,
+        }
+    }
+
+    /// Sets the minimum time between two publications. Shorter intervals make changes visible
+    /// sooner, but use more memory, since every publication creates a new block.
+    pub fn set_publish_interval(&mut self, interval: Duration) {
+        self.publish_interval = interval;
+    }
+
+    /// Returns a handle that can be used to access the published data from any thread.
+    pub fn handle(&self) -> StreamHandle {
+        StreamHandle {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Starts a new time step. Time needs to be increasing, time steps that go back in time are
+    /// skipped together with their value changes.
+    pub fn time_change(&mut self, time: Time) {
+        self.encoder.time_change(time);
+    }
+
+    /// Records a change of signal `id` in the current time step. Fails if there was no call to
+    /// `time_change` yet, if `id` is not part of the hierarchy or if the value does not match the
+    /// encoding of the signal.
+    pub fn value_change(
+        &mut self,
+        id: SignalRef,
+        value: SignalValue,
+    ) -> std::result::Result<(), StreamError> {
+        if !self.encoder.has_time_step() {
+            return Err(StreamError::NoTimeStep);
+        }
+        let encoding = match self.encoder.signal_encoding(id) {
+            None => return Err(StreamError::UnknownSignal(id)),
+            // changes of signals that were filtered out of the hierarchy are ignored
+            Some(None) => return Ok(()),
+            Some(Some(encoding)) => encoding,
+        };
+        let matches = match (encoding, &value) {
+            (SignalEncoding::String, SignalValue::String(_)) => true,
+            (SignalEncoding::Real, SignalValue::Real(_)) => true,
+            (SignalEncoding::BitVector(len), value) => value.bits() == Some(len.get()),
+            _ => false,
+        };
+        if !matches {
+            return Err(StreamError::InvalidValue {
+                id,
+                encoding,
+                value: value.to_string(),
+            });
+        }
+        match value {
+            SignalValue::Binary(data, _)
+            | SignalValue::FourValue(data, _)
+            | SignalValue::NineValue(data, _) => {
+                let states = value.states().unwrap();
+                self.encoder.raw_value_change(id, data, states);
+            }
+            SignalValue::String(value) => self.encoder.string_change(id, value),
+            SignalValue::Real(value) => self.encoder.real_change(id, value),
+        }
+        Ok(())
+    }
+
+    /// Makes complete time steps visible to readers and notifies subscribers if new time steps
+    /// are available. Since more changes could still be recorded for the current time step, it
+    /// is only published by a later call after the next `time_change`. At most one block is
+    /// started per publish interval, thus some calls may not publish anything new.
+    pub fn flush(&mut self) {
+        let due = self
+            .last_publish
+            .is_none_or(|last| last.elapsed() >= self.publish_interval);
+        if due {
+            self.encoder.end_block_after_time_step();
+            self.last_publish = Some(Instant::now());
+        }
+        self.publish(false);
+    }
+
+    /// Publishes all remaining changes, including those of the last time step.
+    pub fn finish(mut self) {
+        self.publish(true);
+    }
+
+    fn publish(&mut self, include_current: bool) {
+        let last_time = {
+            let mut published = self.shared.published.write().unwrap();
+            if !self.encoder.publish(&mut published, include_current) {
+                return;
+            }
+            published.time_table().last().copied()
+        };
+        if let Some(time) = last_time {
+            // subscribers that hung up are removed
+            let mut subscribers = self.shared.subscribers.lock().unwrap();
+            subscribers.retain(|s| s.send(time).is_ok());
+        }
+    }
+}
+
+/// Provides access to the data of a [`StreamingWaveform`] from any thread.
+#[derive(Clone)]
+pub struct StreamHandle {
+    shared: Arc<Shared>,
+}
+
+impl StreamHandle {
+    /// Returns a consistent view of all data published so far. Blocks are shared with the
+    /// writer, thus a snapshot only copies the time table.
+    pub fn snapshot(&self) -> (SignalSource, TimeTable) {
+        let (reader, time_table) = self.shared.published.read().unwrap().snapshot();
+        (SignalSource::new(Box::new(reader)), time_table)
+    }
+
+    /// Last time step that was published.
+    pub fn last_time(&self) -> Option<Time> {
+        self.shared
+            .published
+            .read()
+            .unwrap()
+            .time_table()
+            .last()
+            .copied()
+    }
+
+    /// Returns a channel that receives the latest time whenever new time steps are published.
+    pub fn subscribe(&self) -> Receiver<Time> {
+        let (sender, receiver) = channel();
+        self.shared.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+}
+
+/// Follows a VCD file that is still being written, e.g., by a running simulation.
+pub struct VcdFollower {
+    input: BufReader<std::fs::File>,
+    /// position of the first byte that has not been parsed yet
+    pos: u64,
+    body: BodyFollower,
+    stream: StreamingWaveform,
+    buf: Vec<u8>,
+}
+
+/// Reads the header of a VCD that is still being written. Fails if the header is not complete yet.
+/// Call [`VcdFollower::poll`] to parse any data that was appended to the body.
+pub fn follow_vcd<P: AsRef<Path>>(
+    filename: P,
+    options: &LoadOptions,
+) -> Result<(Hierarchy, VcdFollower)> {
+    let mut input = BufReader::new(std::fs::File::open(filename)?);
+    let (hierarchy, body, header_len) = crate::vcd::read_header_to_follow(&mut input, options)?;
+    let follower = VcdFollower {
+        input,
+        pos: header_len as u64,
+        body,
+        stream: StreamingWaveform::new(&hierarchy),
+        buf: vec![],
+    };
+    Ok((hierarchy, follower))
+}
+
+impl VcdFollower {
+    pub fn handle(&self) -> StreamHandle {
+        self.stream.handle()
+    }
+
+    /// Parses and publishes everything that was appended to the file since the last call.
+    /// Returns false if there was no new data.
+    pub fn poll(&mut self) -> Result<bool> {
+        self.input.seek(SeekFrom::Start(self.pos))?;
+        self.buf.clear();
+        let len = self.input.read_to_end(&mut self.buf)?;
+        if len == 0 {
+            return Ok(false);
+        }
+        self.pos += len as u64;
+        self.body.parse(&self.buf, &mut self.stream.encoder)?;
+        self.stream.flush();
+        Ok(true)
+    }
+
+    /// See [`StreamingWaveform::set_publish_interval`].
+    pub fn set_publish_interval(&mut self, interval: Duration) {
+        self.stream.set_publish_interval(interval);
+    }
+
+    /// Parses the remaining data once the simulation has terminated. This includes a last token
+    /// which is not followed by a newline.
+    pub fn finish(mut self) -> Result<()> {
+        self.poll()?;
+        self.body.finish(&mut self.stream.encoder)?;
+        self.stream.finish();
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// AMBA APB.
+
+use super::{Beat, Cycles, Decoder, Port, Result, Transaction, port};
+use crate::{Signal, Time};
+
+/// Decodes reads and writes of an APB interface. A transaction starts with the setup phase,
+/// in which `psel` is high and `penable` is low, and ends in the cycle in which `penable` and
+/// `pready` are high. If `pready` is not bound, every access phase takes a single cycle.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Apb;
+
+// the clock is always port 0
+const PRESETN: usize = 1;
+const PSEL: usize = 2;
+const PENABLE: usize = 3;
+const PWRITE: usize = 4;
+const PADDR: usize = 5;
+const PWDATA: usize = 6;
+const PRDATA: usize = 7;
+const PREADY: usize = 8;
+const PSLVERR: usize = 9;
+const PPROT: usize = 10;
+const PSTRB: usize = 11;
+
+const PORTS: &[Port] = &[
+    port("pclk", &["pclk", "clk"], true),
+    port("presetn", &["presetn", "resetn", "rst_n"], false),
+    port("psel", &["psel"], true),
+    port("penable", &["penable"], true),
+    port("pwrite", &["pwrite"], true),
+    port("paddr", &["paddr"], true),
+    port("pwdata", &["pwdata"], false),
+    port("prdata", &["prdata"], false),
+    port("pready", &["pready"], false),
+    port("pslverr", &["pslverr"], false),
+    port("pprot", &["pprot"], false),
+    port("pstrb", &["pstrb"], false),
+];
+
+impl Decoder for Apb {
+    fn ports(&self) -> &'static [Port] {
+        PORTS
+    }
+
+    fn decode(&self, time_table: &[Time], signals: &[Option<&Signal>]) -> Result<Vec<Transaction>> {
+        let c = Cycles::sample(time_table, self.ports(), signals)?;
+        let mut setup = None;
+        let mut out = vec![];
+        for cycle in 0..c.len() {
+            if c.in_reset(cycle, PRESETN) || !c.high(cycle, PSEL) {
+                setup = None;
+                continue;
+            }
+            if !c.high(cycle, PENABLE) {
+                setup = Some(cycle);
+                continue;
+            }
+            if !c.high_or(cycle, PREADY, true) {
+                continue;
+            }
+            // an access phase without a setup phase is the result of a waveform that starts
+            // in the middle of a transfer
+            let Some(start) = setup.take() else {
+                continue;
+            };
+            let (kind, data) = if c.high(cycle, PWRITE) {
+                ("write", PWDATA)
+            } else {
+                ("read", PRDATA)
+            };
+            out.push(Transaction {
+                kind,
+                start: c.time(start),
+                end: c.time(cycle),
+                fields: c.fields(
+                    cycle,
+                    &[
+                        ("addr", PADDR),
+                        ("slverr", PSLVERR),
+                        ("prot", PPROT),
+                        ("strb", PSTRB),
+                    ],
+                ),
+                beats: vec![Beat {
+                    time_idx: c.edge(cycle),
+                    fields: c.fields(cycle, &[("data", data)]),
+                }],
+            });
+        }
+        Ok(out)
+    }
+}
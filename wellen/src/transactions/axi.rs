@@ -0,0 +1,369 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// AXI4 memory mapped and AXI4-Stream interfaces.
+
+use super::{Beat, Channel, Cycles, Decoder, Port, Result, Transaction, port};
+use crate::{Signal, Time};
+use rustc_hash::FxHashMap;
+use std::collections::VecDeque;
+
+/// Decodes reads and writes of an AXI4 interface. Writes start with the address or the first
+/// data beat, whichever comes first, and end with the write response. Reads start with the
+/// address and end with the last data beat. Responses are matched to requests by their ID,
+/// thus any number of transactions can be outstanding. Transactions that are still incomplete
+/// at the end of the waveform or when the reset is asserted are left out.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Axi4;
+
+// the clock is always port 0
+const ARESETN: usize = 1;
+const AWVALID: usize = 2;
+const AWREADY: usize = 3;
+const AWID: usize = 4;
+const AWADDR: usize = 5;
+const AWLEN: usize = 6;
+const AWSIZE: usize = 7;
+const AWBURST: usize = 8;
+const AWPROT: usize = 9;
+const WVALID: usize = 10;
+const WREADY: usize = 11;
+const WDATA: usize = 12;
+const WSTRB: usize = 13;
+const WLAST: usize = 14;
+const BVALID: usize = 15;
+const BREADY: usize = 16;
+const BID: usize = 17;
+const BRESP: usize = 18;
+const ARVALID: usize = 19;
+const ARREADY: usize = 20;
+const ARID: usize = 21;
+const ARADDR: usize = 22;
+const ARLEN: usize = 23;
+const ARSIZE: usize = 24;
+const ARBURST: usize = 25;
+const ARPROT: usize = 26;
+const RVALID: usize = 27;
+const RREADY: usize = 28;
+const RID: usize = 29;
+const RDATA: usize = 30;
+const RRESP: usize = 31;
+const RLAST: usize = 32;
+
+const AXI4_PORTS: &[Port] = &[
+    port("aclk", &["aclk", "clk"], true),
+    port("aresetn", &["aresetn", "resetn", "rst_n"], false),
+    port("awvalid", &["awvalid"], true),
+    port("awready", &["awready"], true),
+    port("awid", &["awid"], false),
+    port("awaddr", &["awaddr"], true),
+    port("awlen", &["awlen"], false),
+    port("awsize", &["awsize"], false),
+    port("awburst", &["awburst"], false),
+    port("awprot", &["awprot"], false),
+    port("wvalid", &["wvalid"], true),
+    port("wready", &["wready"], true),
+    port("wdata", &["wdata"], true),
+    port("wstrb", &["wstrb"], false),
+    port("wlast", &["wlast"], false),
+    port("bvalid", &["bvalid"], true),
+    port("bready", &["bready"], true),
+    port("bid", &["bid"], false),
+    port("bresp", &["bresp"], false),
+    port("arvalid", &["arvalid"], true),
+    port("arready", &["arready"], true),
+    port("arid", &["arid"], false),
+    port("araddr", &["araddr"], true),
+    port("arlen", &["arlen"], false),
+    port("arsize", &["arsize"], false),
+    port("arburst", &["arburst"], false),
+    port("arprot", &["arprot"], false),
+    port("rvalid", &["rvalid"], true),
+    port("rready", &["rready"], true),
+    port("rid", &["rid"], false),
+    port("rdata", &["rdata"], true),
+    port("rresp", &["rresp"], false),
+    port("rlast", &["rlast"], false),
+];
+
+/// A transaction that has not completed yet.
+#[derive(Debug)]
+struct Pending {
+    start: usize,
+    fields: Vec<(&'static str, String)>,
+    beats: Vec<Beat>,
+    /// number of beats according to `axlen`
+    len: u64,
+}
+
+impl Pending {
+    fn finish(self, c: &Cycles, kind: &'static str, end: usize) -> Transaction {
+        Transaction {
+            kind,
+            start: c.time(self.start),
+            end: c.time(end),
+            fields: self.fields,
+            beats: self.beats,
+        }
+    }
+}
+
+/// Writes need to be matched with their data, which comes in the same order as the addresses,
+/// but might arrive earlier.
+#[derive(Debug, Default)]
+struct Writes {
+    /// addresses without complete data
+    addresses: VecDeque<Pending>,
+    /// complete bursts without an address, with the cycle in which their first beat started
+    bursts: VecDeque<(usize, Vec<Beat>)>,
+    /// burst that is still receiving beats
+    current: Option<(usize, Vec<Beat>)>,
+    /// writes with complete data that wait for their response, by id
+    responses: FxHashMap<String, VecDeque<Pending>>,
+}
+
+impl Writes {
+    /// Moves writes for which address and data are complete to `responses`.
+    fn match_data(&mut self) {
+        while !self.addresses.is_empty() && !self.bursts.is_empty() {
+            let mut write = self.addresses.pop_front().unwrap();
+            let (start, beats) = self.bursts.pop_front().unwrap();
+            write.start = std::cmp::min(write.start, start);
+            write.beats = beats;
+            let id = find_id(&write.fields);
+            self.responses.entry(id).or_default().push_back(write);
+        }
+    }
+
+    /// Number of beats that the current burst is expected to have if `wlast` is not bound.
+    fn expected_len(&self) -> u64 {
+        self.addresses
+            .get(self.bursts.len())
+            .map(|w| w.len)
+            .unwrap_or(1)
+    }
+}
+
+fn find_id(fields: &[(&'static str, String)]) -> String {
+    fields
+        .iter()
+        .find(|(n, _)| *n == "id")
+        .map(|(_, v)| v.clone())
+        .unwrap_or_default()
+}
+
+impl Decoder for Axi4 {
+    fn ports(&self) -> &'static [Port] {
+        AXI4_PORTS
+    }
+
+    fn decode(&self, time_table: &[Time], signals: &[Option<&Signal>]) -> Result<Vec<Transaction>> {
+        let c = Cycles::sample(time_table, self.ports(), signals)?;
+        let (mut aw, mut w, mut b) = Default::default();
+        let (mut ar, mut r) = Default::default();
+        let mut writes = Writes::default();
+        let mut reads: FxHashMap<String, VecDeque<Pending>> = FxHashMap::default();
+        let mut out = vec![];
+        for cycle in 0..c.len() {
+            if c.in_reset(cycle, ARESETN) {
+                writes = Writes::default();
+                reads.clear();
+                (aw, w, b, ar, r) = Default::default();
+                continue;
+            }
+            decode_writes(&c, cycle, [&mut aw, &mut w, &mut b], &mut writes, &mut out);
+            decode_reads(&c, cycle, [&mut ar, &mut r], &mut reads, &mut out);
+        }
+        out.sort_by_key(|t| t.start);
+        Ok(out)
+    }
+}
+
+fn decode_writes(
+    c: &Cycles,
+    cycle: usize,
+    [aw, w, b]: [&mut Channel; 3],
+    writes: &mut Writes,
+    out: &mut Vec<Transaction>,
+) {
+    if let Some(start) = aw.transfer(c, cycle, AWVALID, AWREADY) {
+        writes.addresses.push_back(Pending {
+            start,
+            fields: c.fields(
+                cycle,
+                &[
+                    ("id", AWID),
+                    ("addr", AWADDR),
+                    ("len", AWLEN),
+                    ("size", AWSIZE),
+                    ("burst", AWBURST),
+                    ("prot", AWPROT),
+                ],
+            ),
+            beats: vec![],
+            len: c.number(cycle, AWLEN).unwrap_or(0) + 1,
+        });
+    }
+    if let Some(start) = w.transfer(c, cycle, WVALID, WREADY) {
+        let (_, beats) = writes.current.get_or_insert_with(|| (start, vec![]));
+        beats.push(Beat {
+            time_idx: c.edge(cycle),
+            fields: c.fields(cycle, &[("data", WDATA), ("strb", WSTRB)]),
+        });
+        let beats = beats.len() as u64;
+        let last = if c.is_bound(WLAST) {
+            c.high(cycle, WLAST)
+        } else {
+            beats >= writes.expected_len()
+        };
+        if last {
+            let burst = writes.current.take().unwrap();
+            writes.bursts.push_back(burst);
+        }
+    }
+    writes.match_data();
+    if b.transfer(c, cycle, BVALID, BREADY).is_some() {
+        let id = c.bits(cycle, BID).unwrap_or_default();
+        if let Some(mut write) = writes.responses.get_mut(&id).and_then(|q| q.pop_front()) {
+            write.fields.extend(c.fields(cycle, &[("resp", BRESP)]));
+            out.push(write.finish(c, "write", cycle));
+        }
+    }
+}
+
+fn decode_reads(
+    c: &Cycles,
+    cycle: usize,
+    [ar, r]: [&mut Channel; 2],
+    reads: &mut FxHashMap<String, VecDeque<Pending>>,
+    out: &mut Vec<Transaction>,
+) {
+    if let Some(start) = ar.transfer(c, cycle, ARVALID, ARREADY) {
+        let fields = c.fields(
+            cycle,
+            &[
+                ("id", ARID),
+                ("addr", ARADDR),
+                ("len", ARLEN),
+                ("size", ARSIZE),
+                ("burst", ARBURST),
+                ("prot", ARPROT),
+            ],
+        );
+        reads
+            .entry(find_id(&fields))
+            .or_default()
+            .push_back(Pending {
+                start,
+                fields,
+                beats: vec![],
+                len: c.number(cycle, ARLEN).unwrap_or(0) + 1,
+            });
+    }
+    if r.transfer(c, cycle, RVALID, RREADY).is_some() {
+        let id = c.bits(cycle, RID).unwrap_or_default();
+        // beats without an outstanding request, e.g., at the start of a waveform, are skipped
+        let Some(queue) = reads.get_mut(&id) else {
+            return;
+        };
+        let Some(read) = queue.front_mut() else {
+            return;
+        };
+        read.beats.push(Beat {
+            time_idx: c.edge(cycle),
+            fields: c.fields(cycle, &[("data", RDATA), ("resp", RRESP)]),
+        });
+        let last = if c.is_bound(RLAST) {
+            c.high(cycle, RLAST)
+        } else {
+            read.beats.len() as u64 >= read.len
+        };
+        if last {
+            let read = queue.pop_front().unwrap();
+            out.push(read.finish(c, "read", cycle));
+        }
+    }
+}
+
+/// Decodes the packets of an AXI4-Stream interface. A packet ends with the beat in which
+/// `tlast` is high, or after every beat if `tlast` is not bound. Packets with a different
+/// `tid` or `tdest` may be interleaved. Packets that are still incomplete at the end of the
+/// waveform or when the reset is asserted are left out.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Axi4Stream;
+
+// the clock is always port 0
+const S_ARESETN: usize = 1;
+const TVALID: usize = 2;
+const TREADY: usize = 3;
+const TDATA: usize = 4;
+const TSTRB: usize = 5;
+const TKEEP: usize = 6;
+const TLAST: usize = 7;
+const TID: usize = 8;
+const TDEST: usize = 9;
+const TUSER: usize = 10;
+
+const AXI4_STREAM_PORTS: &[Port] = &[
+    port("aclk", &["aclk", "clk"], true),
+    port("aresetn", &["aresetn", "resetn", "rst_n"], false),
+    port("tvalid", &["tvalid"], true),
+    port("tready", &["tready"], false),
+    port("tdata", &["tdata"], false),
+    port("tstrb", &["tstrb"], false),
+    port("tkeep", &["tkeep"], false),
+    port("tlast", &["tlast"], false),
+    port("tid", &["tid"], false),
+    port("tdest", &["tdest"], false),
+    port("tuser", &["tuser"], false),
+];
+
+impl Decoder for Axi4Stream {
+    fn ports(&self) -> &'static [Port] {
+        AXI4_STREAM_PORTS
+    }
+
+    fn decode(&self, time_table: &[Time], signals: &[Option<&Signal>]) -> Result<Vec<Transaction>> {
+        let c = Cycles::sample(time_table, self.ports(), signals)?;
+        let mut channel = Channel::default();
+        // packets that are still receiving beats, by `tid` and `tdest`
+        let mut packets: FxHashMap<Vec<(&'static str, String)>, Pending> = FxHashMap::default();
+        let mut out = vec![];
+        for cycle in 0..c.len() {
+            if c.in_reset(cycle, S_ARESETN) {
+                packets.clear();
+                channel = Channel::default();
+                continue;
+            }
+            let Some(start) = channel.transfer(&c, cycle, TVALID, TREADY) else {
+                continue;
+            };
+            let fields = c.fields(cycle, &[("id", TID), ("dest", TDEST)]);
+            let packet = packets.entry(fields.clone()).or_insert_with(|| Pending {
+                start,
+                fields,
+                beats: vec![],
+                len: 0,
+            });
+            packet.beats.push(Beat {
+                time_idx: c.edge(cycle),
+                fields: c.fields(
+                    cycle,
+                    &[
+                        ("data", TDATA),
+                        ("strb", TSTRB),
+                        ("keep", TKEEP),
+                        ("user", TUSER),
+                    ],
+                ),
+            });
+            if c.high_or(cycle, TLAST, true) {
+                let key = packet.fields.clone();
+                let packet = packets.remove(&key).unwrap();
+                out.push(packet.finish(&c, "packet", cycle));
+            }
+        }
+        out.sort_by_key(|t| t.start);
+        Ok(out)
+    }
+}
@@ -0,0 +1,52 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// Generic valid/ready handshake.
+
+use super::{Beat, Channel, Cycles, Decoder, Port, Result, Transaction, port};
+use crate::{Signal, Time};
+
+/// Every cycle in which `valid` and `ready` are both high transfers one beat of `data`.
+/// Transfers start in the cycle in which `valid` is asserted. If `ready` is not bound, the
+/// receiver is always ready.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Handshake;
+
+// the clock is always port 0
+const VALID: usize = 1;
+const READY: usize = 2;
+const DATA: usize = 3;
+
+const PORTS: &[Port] = &[
+    port("clk", &["clk", "clock", "aclk"], true),
+    port("valid", &["valid", "vld"], true),
+    port("ready", &["ready", "rdy"], false),
+    port("data", &["data"], false),
+];
+
+impl Decoder for Handshake {
+    fn ports(&self) -> &'static [Port] {
+        PORTS
+    }
+
+    fn decode(&self, time_table: &[Time], signals: &[Option<&Signal>]) -> Result<Vec<Transaction>> {
+        let c = Cycles::sample(time_table, self.ports(), signals)?;
+        let mut channel = Channel::default();
+        let mut out = vec![];
+        for cycle in 0..c.len() {
+            if let Some(start) = channel.transfer(&c, cycle, VALID, READY) {
+                out.push(Transaction {
+                    kind: "transfer",
+                    start: c.time(start),
+                    end: c.time(cycle),
+                    fields: vec![],
+                    beats: vec![Beat {
+                        time_idx: c.edge(cycle),
+                        fields: c.fields(cycle, &[("data", DATA)]),
+                    }],
+                });
+            }
+        }
+        Ok(out)
+    }
+}
@@ -0,0 +1,328 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// Decodes bus protocols from the values of their signals into transactions.
+
+mod apb;
+mod axi;
+mod handshake;
+
+pub use apb::Apb;
+pub use axi::{Axi4, Axi4Stream};
+pub use handshake::Handshake;
+
+use crate::cycles::CycleTrace;
+use crate::simple::Waveform;
+use crate::{Edge, Hierarchy, ScopeRef, Signal, SignalValue, Time, TimeTableIdx, VarRef};
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum TransactionError {
+    #[error("[transactions] no variable in scope {scope} matches the required port `{port}`")]
+    MissingPort { scope: String, port: &'static str },
+    #[error("[transactions] several variables in scope {scope} match port `{port}`: {}", .candidates.join(", "))]
+    AmbiguousPort {
+        scope: String,
+        port: &'static str,
+        candidates: Vec<String>,
+    },
+    #[error("[transactions] expected one signal for each of the {expected} ports, got {actual}")]
+    PortCount { expected: usize, actual: usize },
+    #[error("[transactions] the required port `{port}` is not bound to a signal")]
+    UnboundPort { port: &'static str },
+    #[error("[transactions] failed to load signals: {0}")]
+    Load(#[from] crate::WellenError),
+}
+
+pub type Result<T> = std::result::Result<T, TransactionError>;
+
+/// A signal that a decoder reads, e.g., the `awvalid` of an AXI4 interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Port {
+    /// Name of the port in the protocol specification, e.g., `awvalid`.
+    pub name: &'static str,
+    /// Lower case names that are tried when binding the port automatically.
+    /// The first entry is always the same as `name`.
+    pub aliases: &'static [&'static str],
+    pub required: bool,
+}
+
+/// A decoder for a synchronous bus protocol.
+pub trait Decoder {
+    /// The first port is always the clock, all other signals are sampled at its rising edges.
+    fn ports(&self) -> &'static [Port];
+    /// `signals` contains one entry per port, `None` for optional ports that are not bound.
+    /// Transactions are sorted by their start time.
+    fn decode(&self, time_table: &[Time], signals: &[Option<&Signal>]) -> Result<Vec<Transaction>>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    /// Decoder specific, e.g., `"read"` or `"write"`.
+    pub kind: &'static str,
+    pub start: Time,
+    pub end: Time,
+    /// Values that apply to the whole transaction, e.g., the address. Fields of optional ports
+    /// that are not bound are left out.
+    pub fields: Vec<(&'static str, String)>,
+    pub beats: Vec<Beat>,
+}
+
+impl Transaction {
+    pub fn field(&self, name: &str) -> Option<&str> {
+        find_field(&self.fields, name)
+    }
+}
+
+/// A single transfer of data inside a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Beat {
+    /// Time table index of the clock edge at which the data was transferred.
+    pub time_idx: TimeTableIdx,
+    pub fields: Vec<(&'static str, String)>,
+}
+
+impl Beat {
+    pub fn field(&self, name: &str) -> Option<&str> {
+        find_field(&self.fields, name)
+    }
+}
+
+fn find_field<'a>(fields: &'a [(&'static str, String)], name: &str) -> Option<&'a str> {
+    fields
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, v)| v.as_str())
+}
+
+/// Binds every port to the variable in `scope` whose name, ignoring case and after removing
+/// `prefix`, is one of the port aliases or ends in `_` followed by one of them. For example,
+/// `s_axi_awvalid` is bound to `awvalid`. A prefix like `m_axi_` can be used to pick one of
+/// several interfaces that share a scope. Ports that no variable with the prefix matches,
+/// e.g., a shared clock, are bound to variables without the prefix.
+pub fn bind(
+    hierarchy: &Hierarchy,
+    scope: ScopeRef,
+    prefix: &str,
+    ports: &[Port],
+) -> Result<Vec<Option<VarRef>>> {
+    let prefix = prefix.to_lowercase();
+    let all: Vec<_> = hierarchy[scope]
+        .vars(hierarchy)
+        .map(|v| (v, hierarchy[v].name(hierarchy).to_lowercase()))
+        .collect();
+    let prefixed: Vec<_> = all
+        .iter()
+        .filter_map(|(v, name)| Some((*v, name.strip_prefix(&prefix)?.to_string())))
+        .collect();
+    let mut out = Vec::with_capacity(ports.len());
+    for port in ports.iter() {
+        let mut bound = find_port(hierarchy, scope, &prefixed, port)?;
+        if bound.is_none() && !prefix.is_empty() {
+            bound = find_port(hierarchy, scope, &all, port)?;
+        }
+        if bound.is_none() && port.required {
+            return Err(TransactionError::MissingPort {
+                scope: hierarchy[scope].full_name(hierarchy),
+                port: port.name,
+            });
+        }
+        out.push(bound);
+    }
+    Ok(out)
+}
+
+fn find_port(
+    hierarchy: &Hierarchy,
+    scope: ScopeRef,
+    names: &[(VarRef, String)],
+    port: &Port,
+) -> Result<Option<VarRef>> {
+    for alias in port.aliases.iter() {
+        let suffix = format!("_{alias}");
+        let exact: Vec<_> = names.iter().filter(|(_, n)| n == alias).collect();
+        let candidates = if exact.is_empty() {
+            names.iter().filter(|(_, n)| n.ends_with(&suffix)).collect()
+        } else {
+            exact
+        };
+        match candidates.as_slice() {
+            [] => {}
+            [(var, _)] => return Ok(Some(*var)),
+            _ => {
+                return Err(TransactionError::AmbiguousPort {
+                    scope: hierarchy[scope].full_name(hierarchy),
+                    port: port.name,
+                    candidates: candidates
+                        .iter()
+                        .map(|(v, _)| hierarchy[*v].name(hierarchy).to_string())
+                        .collect(),
+                });
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Binds the ports of `decoder` to variables in `scope`, see [`bind`], loads their signals
+/// and decodes all transactions.
+pub fn decode(
+    wave: &mut Waveform,
+    scope: ScopeRef,
+    prefix: &str,
+    decoder: &dyn Decoder,
+) -> Result<Vec<Transaction>> {
+    let vars = bind(wave.hierarchy(), scope, prefix, decoder.ports())?;
+    let ids: Vec<_> = vars
+        .iter()
+        .flatten()
+        .map(|v| wave.hierarchy()[*v].signal_ref())
+        .collect();
+    wave.load_signals_multi_threaded(&ids)?;
+    let signals: Vec<_> = vars
+        .iter()
+        .map(|v| v.map(|v| wave.get_signal(wave.hierarchy()[v].signal_ref()).unwrap()))
+        .collect();
+    decoder.decode(wave.time_table(), &signals)
+}
+
+/// Values of all ports at the rising edges of the clock, which is the first port.
+struct Cycles<'a> {
+    trace: CycleTrace<'a>,
+    time_table: &'a [Time],
+    /// column of each port in the trace
+    columns: Vec<Option<usize>>,
+}
+
+impl<'a> Cycles<'a> {
+    fn sample(
+        time_table: &'a [Time],
+        ports: &[Port],
+        signals: &[Option<&'a Signal>],
+    ) -> Result<Self> {
+        if ports.len() != signals.len() {
+            return Err(TransactionError::PortCount {
+                expected: ports.len(),
+                actual: signals.len(),
+            });
+        }
+        if let Some((port, _)) = ports
+            .iter()
+            .zip(signals.iter())
+            .find(|(port, signal)| port.required && signal.is_none())
+        {
+            return Err(TransactionError::UnboundPort { port: port.name });
+        }
+        let clock = signals[0].ok_or(TransactionError::UnboundPort {
+            port: ports[0].name,
+        })?;
+        let mut bound = vec![];
+        let mut columns = vec![None];
+        for signal in signals[1..].iter() {
+            columns.push(signal.map(|s| {
+                bound.push(s);
+                bound.len() - 1
+            }));
+        }
+        let trace = CycleTrace::sample(clock, time_table, Edge::Rising, &bound, false);
+        Ok(Self {
+            trace,
+            time_table,
+            columns,
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.trace.len()
+    }
+
+    fn edge(&self, cycle: usize) -> TimeTableIdx {
+        self.trace.edges()[cycle]
+    }
+
+    fn time(&self, cycle: usize) -> Time {
+        self.time_table[self.edge(cycle) as usize]
+    }
+
+    fn value(&self, cycle: usize, port: usize) -> Option<SignalValue<'a>> {
+        self.trace.value(cycle, self.columns[port]?)
+    }
+
+    fn is_bound(&self, port: usize) -> bool {
+        self.columns[port].is_some()
+    }
+
+    /// Returns `default` if the port is not bound.
+    fn high_or(&self, cycle: usize, port: usize, default: bool) -> bool {
+        if !self.is_bound(port) {
+            return default;
+        }
+        self.value(cycle, port)
+            .and_then(|v| v.to_bit_string())
+            .is_some_and(|v| v == "1" || v == "h")
+    }
+
+    fn high(&self, cycle: usize, port: usize) -> bool {
+        self.high_or(cycle, port, false)
+    }
+
+    /// Active-low resets are deasserted if they are not bound.
+    fn in_reset(&self, cycle: usize, reset_n: usize) -> bool {
+        !self.high_or(cycle, reset_n, true)
+    }
+
+    /// Bit string of the value. `None` if the port is not bound.
+    fn bits(&self, cycle: usize, port: usize) -> Option<String> {
+        self.columns[port]?;
+        Some(
+            self.value(cycle, port)
+                .map_or_else(String::new, |v| v.to_string()),
+        )
+    }
+
+    /// Numeric value, `None` if the port is not bound or the value is not a 2-state number.
+    fn number(&self, cycle: usize, port: usize) -> Option<u64> {
+        u64::from_str_radix(&self.value(cycle, port)?.to_bit_string()?, 2).ok()
+    }
+
+    /// Named values of all bound ports.
+    fn fields(&self, cycle: usize, ports: &[(&'static str, usize)]) -> Vec<(&'static str, String)> {
+        ports
+            .iter()
+            .filter_map(|(name, port)| Some((*name, self.bits(cycle, *port)?)))
+            .collect()
+    }
+}
+
+/// Tracks since when `valid` is asserted for a transfer that is waiting for `ready`.
+#[derive(Debug, Default)]
+struct Channel {
+    waiting_since: Option<usize>,
+}
+
+impl Channel {
+    /// Returns the cycle in which `valid` was first asserted if the transfer completes in
+    /// `cycle`. Unbound `ready` ports are always asserted.
+    fn transfer(&mut self, c: &Cycles, cycle: usize, valid: usize, ready: usize) -> Option<usize> {
+        if !c.high(cycle, valid) {
+            self.waiting_since = None;
+            return None;
+        }
+        let start = *self.waiting_since.get_or_insert(cycle);
+        if c.high_or(cycle, ready, true) {
+            self.waiting_since = None;
+            Some(start)
+        } else {
+            None
+        }
+    }
+}
+
+/// Shorthand for declaring ports.
+const fn port(name: &'static str, aliases: &'static [&'static str], required: bool) -> Port {
+    Port {
+        name,
+        aliases,
+        required,
+    }
+}
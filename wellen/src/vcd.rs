@@ -5,16 +5,18 @@
 
 use crate::fst::{Attribute, parse_scope_attributes, parse_var_attributes};
 use crate::hierarchy::*;
-use crate::signals::SignalSource;
+use crate::signals::{Signal, SignalSource, SignalValue, Time, TimeTableIdx};
 use crate::viewers::ProgressCount;
-use crate::wavemem::Encoder;
+use crate::wavemem::{Encoder, Reader};
 use crate::{FileFormat, LoadOptions, TimeTable};
 use fst_reader::{FstVhdlDataType, FstVhdlVarType};
 use num_enum::TryFromPrimitive;
 use rayon::prelude::*;
 use rustc_hash::FxHashMap;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::fmt::Debug;
-use std::io::{BufRead, Read, Seek, SeekFrom};
+use std::io::{BufRead, Read, Seek, SeekFrom, Write};
 use std::sync::atomic::Ordering;
 
 #[derive(Debug, thiserror::Error)]
@@ -48,6 +50,10 @@ pub enum VcdParseError {
     VcdUnexpectedBodyToken(String),
     #[error("[vcd] expected an id for a value change, but did not find one")]
     VcdEmptyId,
+    #[error("[vcd] value change for an unknown id: {0}")]
+    VcdUnknownId(String),
+    #[error("[vcd] invalid value: {0}")]
+    VcdInvalidValue(String),
     /// This is not really an error, but our parser has to terminate and start a new attempt
     /// at interpreting ids. This error should never reach any user.
     #[error("[vcd] non-contiguous ids detected, applying a work around.")]
@@ -60,20 +66,21 @@ pub enum VcdParseError {
     Io(#[from] std::io::Error),
 }
 
-pub type Result<T> = std::result::Result<T, VcdParseError>;
+pub(crate) type Result<T> = std::result::Result<T, VcdParseError>;
 
-pub fn read_header_from_file<P: AsRef<std::path::Path>>(
+pub(crate) fn read_header_from_file<P: AsRef<std::path::Path>>(
     filename: P,
     options: &LoadOptions,
-) -> Result<(
+) -> crate::Result<(
     Hierarchy,
     ReadBodyContinuation<std::io::BufReader<std::fs::File>>,
     u64,
 )> {
     let input_file = std::fs::File::open(filename)?;
     let mmap = unsafe { memmap2::Mmap::map(&input_file)? };
+    let mut input = std::io::Cursor::new(&mmap[..]);
     let (header_len, hierarchy, lookup) =
-        read_hierarchy(&mut std::io::Cursor::new(&mmap[..]), options)?;
+        read_hierarchy(&mut input, options).map_err(|e| header_error(e, input.position()))?;
     let body_len = (mmap.len() - header_len) as u64;
     let cont = ReadBodyContinuation {
         multi_thread: options.multi_thread,
@@ -84,10 +91,10 @@ pub fn read_header_from_file<P: AsRef<std::path::Path>>(
     Ok((hierarchy, cont, body_len))
 }
 
-pub fn read_header<R: BufRead + Seek>(
+pub(crate) fn read_header<R: BufRead + Seek>(
     mut input: R,
     options: &LoadOptions,
-) -> Result<(Hierarchy, ReadBodyContinuation<R>, u64)> {
+) -> crate::Result<(Hierarchy, ReadBodyContinuation<R>, u64)> {
     // determine the length of the input
     let start = input.stream_position()?;
     input.seek(SeekFrom::End(0))?;
@@ -96,7 +103,8 @@ pub fn read_header<R: BufRead + Seek>(
     let input_len = end - start;
 
     // actually read the header
-    let (header_len, hierarchy, lookup) = read_hierarchy(&mut input, options)?;
+    let (header_len, hierarchy, lookup) = read_hierarchy(&mut input, options)
+        .map_err(|e| header_error(e, input.stream_position().unwrap_or(start)))?;
     let body_len = input_len - header_len as u64;
     let cont = ReadBodyContinuation {
         multi_thread: options.multi_thread,
@@ -107,7 +115,7 @@ pub fn read_header<R: BufRead + Seek>(
     Ok((hierarchy, cont, body_len))
 }
 
-pub struct ReadBodyContinuation<R: BufRead + Seek> {
+pub(crate) struct ReadBodyContinuation<R: BufRead + Seek> {
     multi_thread: bool,
     header_len: usize,
     lookup: IdLookup,
@@ -119,12 +127,12 @@ enum Input<R: BufRead + Seek> {
     Mmap(memmap2::Mmap),
 }
 
-pub fn read_body<R: BufRead + Seek>(
+pub(crate) fn read_body<R: BufRead + Seek>(
     data: ReadBodyContinuation<R>,
     hierarchy: &Hierarchy,
     progress: Option<ProgressCount>,
-) -> Result<(SignalSource, TimeTable)> {
-    let (source, time_table) = match data.input {
+) -> crate::Result<(Reader, TimeTable)> {
+    let (reader, time_table) = match data.input {
         Input::Reader(mut input) => {
             // determine binput length
             let start = input.stream_position()?;
@@ -140,21 +148,106 @@ pub fn read_body<R: BufRead + Seek>(
                 hierarchy,
                 &data.lookup,
                 progress,
-            )?;
+            )
+            .map_err(|e| e.at(start as usize))?;
             encoder.finish()
         }
         Input::Mmap(mmap) => read_values(
             &mmap[data.header_len..],
+            data.header_len,
             data.multi_thread,
             hierarchy,
             &data.lookup,
             progress,
         )?,
     };
-    Ok((source, time_table))
+    Ok((reader, time_table))
 }
 
-const FST_SUP_VAR_DATA_TYPE_BITS: u32 = 10;
+fn header_error(error: VcdParseError, offset: u64) -> crate::WellenError {
+    crate::WellenError::malformed(FileFormat::Vcd, "header", Some(offset), error)
+}
+
+/// An error in the value change section and the position of the byte that we were parsing,
+/// relative to the start of the parsed input.
+#[derive(Debug)]
+struct BodyError {
+    pos: usize,
+    error: VcdParseError,
+}
+
+impl BodyError {
+    /// `start` is the position of the parsed input in the file.
+    fn at(self, start: usize) -> crate::WellenError {
+        let offset = Some((start + self.pos) as u64);
+        crate::WellenError::malformed(FileFormat::Vcd, "value changes", offset, self.error)
+    }
+}
+
+/// Reads the header of a VCD that is still being written. Returns the number of header bytes,
+/// i.e., the position at which the body starts.
+pub(crate) fn read_header_to_follow(
+    input: &mut (impl BufRead + Seek),
+    options: &LoadOptions,
+) -> crate::Result<(Hierarchy, BodyFollower, usize)> {
+    let (header_len, hierarchy, lookup) = read_hierarchy(input, options)
+        .map_err(|e| header_error(e, input.stream_position().unwrap_or(0)))?;
+    let follower = BodyFollower {
+        lookup,
+        parser: BodyParser::new(),
+        pos: header_len,
+        found_first_time_step: false,
+    };
+    Ok((hierarchy, follower, header_len))
+}
+
+/// Parses the body of a VCD that is still being written. Data may arrive in arbitrary chunks,
+/// a token that is cut off at the end of a chunk is completed by the next chunk.
+pub(crate) struct BodyFollower {
+    lookup: IdLookup,
+    parser: BodyParser,
+    /// position of the next byte in the file
+    pos: usize,
+    found_first_time_step: bool,
+}
+
+impl BodyFollower {
+    pub(crate) fn parse(&mut self, data: &[u8], enc: &mut Encoder) -> crate::Result<()> {
+        let mut out = VcdEncoder::new(enc, &self.lookup, true);
+        out.found_first_time_step = self.found_first_time_step;
+        for b in data.iter() {
+            self.parser
+                .parse_byte(*b, self.pos, usize::MAX, &mut out)
+                .map_err(|error| {
+                    BodyError {
+                        pos: self.pos,
+                        error,
+                    }
+                    .at(0)
+                })?;
+            self.pos += 1;
+        }
+        self.found_first_time_step = out.found_first_time_step;
+        Ok(())
+    }
+
+    /// Parses the last token once we know that no more data will arrive.
+    pub(crate) fn finish(&mut self, enc: &mut Encoder) -> crate::Result<()> {
+        let mut out = VcdEncoder::new(enc, &self.lookup, true);
+        out.found_first_time_step = self.found_first_time_step;
+        self.parser.finish(&mut out).map_err(|error| {
+            BodyError {
+                pos: self.pos,
+                error,
+            }
+            .at(0)
+        })?;
+        self.found_first_time_step = out.found_first_time_step;
+        Ok(())
+    }
+}
+
+pub(crate) const FST_SUP_VAR_DATA_TYPE_BITS: u32 = 10;
 const FST_SUP_VAR_DATA_TYPE_MASK: u64 = (1 << FST_SUP_VAR_DATA_TYPE_BITS) - 1;
 
 // VCD attributes are a GTKWave extension which is also used by nvc
@@ -353,6 +446,7 @@ fn read_hierarchy_inner(
 ) -> Result<(usize, Hierarchy, IdLookup)> {
     let start = input.stream_position().unwrap();
     let mut h = HierarchyBuilder::new(FileFormat::Vcd);
+    h.set_scope_filter(options);
     let mut attributes = Vec::new();
     let mut path_names = FxHashMap::default();
     // this map is used to translate identifiers to signal references for cases where we detect ids that are too large
@@ -410,9 +504,9 @@ fn read_hierarchy_inner(
             Ok(())
         }
         HeaderCmd::Var(tpe, size, id, name) => {
-            let length = match std::str::from_utf8(size).unwrap().parse::<u32>() {
-                Ok(len) => len,
-                Err(_) => {
+            let length = match std::str::from_utf8(size).map(|s| s.parse::<u32>()) {
+                Ok(Ok(len)) => len,
+                _ => {
                     return Err(VcdParseError::VcdVarLengthParsing(
                         String::from_utf8_lossy(size).to_string(),
                         String::from_utf8_lossy(name).to_string(),
@@ -557,17 +651,19 @@ type ScopeNames<'a> = Vec<std::borrow::Cow<'a, str>>;
 /// 3. any extra scopes generated by a multidimensional arrays
 ///
 /// `length` is used in order to distinguish bit-indices and array scopes.
-pub fn parse_name(
+pub(crate) fn parse_name(
     raw_name: &[u8],
     length: u32,
 ) -> Result<(std::borrow::Cow<'_, str>, Option<VarIndex>, ScopeNames<'_>)> {
     if raw_name.is_empty() {
         return Ok(("".into(), None, vec![]));
     }
-    debug_assert!(
-        raw_name[0] != b'[',
-        "we assume that the first character is not `[`!"
-    );
+    // we assume that the first character is not `[`
+    if raw_name[0] == b'[' {
+        return Err(VcdParseError::VcdVarNameParsing(
+            String::from_utf8_lossy(raw_name).into(),
+        ));
+    }
 
     // find the bit index from the back
     let (name, index) = extract_suffix_index(raw_name);
@@ -709,7 +805,8 @@ fn convert_var_tpe(tpe: &[u8]) -> Result<VarType> {
         b"longint" => Ok(VarType::LongInt),
         b"byte" => Ok(VarType::Byte),
         b"enum" => Ok(VarType::Enum),
-        b"shortread" => Ok(VarType::ShortReal),
+        // `shortread` is accepted for backwards compatibility
+        b"shortreal" | b"shortread" => Ok(VarType::ShortReal),
         _ => Err(VcdParseError::VcdUnknownVarType(
             String::from_utf8_lossy(tpe).to_string(),
         )),
@@ -904,7 +1001,7 @@ impl VcdCmd {
 }
 
 /// Tries to guess whether this input could be a VCD by looking at the first token.
-pub fn is_vcd(input: &mut (impl BufRead + Seek)) -> bool {
+pub(crate) fn is_vcd(input: &mut (impl BufRead + Seek)) -> bool {
     let is_vcd = matches!(internal_is_vcd(input), Ok(true));
     // try to reset input
     let _ = input.seek(std::io::SeekFrom::Start(0));
@@ -1065,17 +1162,19 @@ fn determine_thread_chunks(body_len: usize) -> Vec<(usize, usize)> {
         .collect()
 }
 
-/// Reads the body of a VCD with multiple threads
+/// Reads the body of a VCD with multiple threads. `header_len` is the position of the body in the
+/// file.
 fn read_values(
     input: &[u8],
+    header_len: usize,
     multi_thread: bool,
     hierarchy: &Hierarchy,
     lookup: &IdLookup,
     progress: Option<ProgressCount>,
-) -> Result<(SignalSource, TimeTable)> {
+) -> crate::Result<(Reader, TimeTable)> {
     if multi_thread {
         let chunks = determine_thread_chunks(input.len());
-        let encoders: Result<Vec<crate::wavemem::Encoder>> = chunks
+        let encoders: crate::Result<Vec<crate::wavemem::Encoder>> = chunks
             .par_iter()
             .map(|(start, len)| {
                 let is_first = *start == 0;
@@ -1088,6 +1187,7 @@ fn read_values(
                     lookup,
                     progress.clone(),
                 )
+                .map_err(|e| e.at(header_len + *start))
             })
             .collect();
         let encoders = encoders?;
@@ -1108,7 +1208,8 @@ fn read_values(
             hierarchy,
             lookup,
             progress,
-        )?;
+        )
+        .map_err(|e| e.at(header_len))?;
         Ok(encoder.finish())
     }
 }
@@ -1174,7 +1275,7 @@ fn parse_first_token(token: &[u8]) -> Result<FirstTokenResult> {
 
 /// wraps a wavemem encoder and adds vcd specific handling of input time
 struct VcdEncoder<'a> {
-    enc: Encoder,
+    enc: &'a mut Encoder,
     lookup: &'a IdLookup,
     is_first_part_of_vcd: bool,
     found_first_time_step: bool,
@@ -1182,20 +1283,15 @@ struct VcdEncoder<'a> {
 
 impl<'a> VcdEncoder<'a> {
     #[inline]
-    fn new(hierarchy: &Hierarchy, lookup: &'a IdLookup, is_first_part_of_vcd: bool) -> Self {
+    fn new(enc: &'a mut Encoder, lookup: &'a IdLookup, is_first_part_of_vcd: bool) -> Self {
         let found_first_time_step = false;
         Self {
-            enc: Encoder::new(hierarchy),
+            enc,
             lookup,
             is_first_part_of_vcd,
             found_first_time_step,
         }
     }
-
-    #[inline]
-    fn into_inner(self) -> Encoder {
-        self.enc
-    }
 }
 
 impl ParseBodyOutput for VcdEncoder<'_> {
@@ -1219,14 +1315,25 @@ impl ParseBodyOutput for VcdEncoder<'_> {
             let num_id = match self.lookup {
                 None => match id_to_int(id) {
                     Some(ii) => ii,
+                    None if id.is_empty() => return Err(VcdParseError::VcdEmptyId),
+                    None => {
+                        return Err(VcdParseError::VcdUnknownId(
+                            String::from_utf8_lossy(id).to_string(),
+                        ));
+                    }
+                },
+                Some(lookup) => match lookup.get(id) {
+                    Some(signal_ref) => signal_ref.index() as u64,
                     None => {
-                        debug_assert!(id.is_empty());
-                        return Err(VcdParseError::VcdEmptyId);
+                        return Err(VcdParseError::VcdUnknownId(
+                            String::from_utf8_lossy(id).to_string(),
+                        ));
                     }
                 },
-                Some(lookup) => lookup[id].index() as u64,
             };
-            self.enc.vcd_value_change(num_id, value);
+            self.enc
+                .vcd_value_change(num_id, value)
+                .map_err(VcdParseError::VcdInvalidValue)?;
         }
         Ok(())
     }
@@ -1269,10 +1376,11 @@ fn read_single_stream_of_values<R: BufRead + Seek>(
     hierarchy: &Hierarchy,
     lookup: &IdLookup,
     progress: Option<ProgressCount>,
-) -> Result<crate::wavemem::Encoder> {
-    let mut encoder = VcdEncoder::new(hierarchy, lookup, is_first);
+) -> std::result::Result<crate::wavemem::Encoder, BodyError> {
+    let mut enc = Encoder::new(hierarchy);
+    let mut encoder = VcdEncoder::new(&mut enc, lookup, is_first);
     parse_body(input, &mut encoder, stop_pos, progress)?;
-    Ok(encoder.into_inner())
+    Ok(enc)
 }
 
 trait ParseBodyOutput {
@@ -1285,47 +1393,86 @@ fn parse_body(
     out: &mut impl ParseBodyOutput,
     stop_pos: usize,
     progress: Option<ProgressCount>,
-) -> Result<()> {
+) -> std::result::Result<(), BodyError> {
     let mut progress_report = ProgressReporter::new(progress, stop_pos);
-
-    let mut state = BodyState::SkippingNewLine;
-
-    let mut first = Vec::with_capacity(32);
-    let mut id = Vec::with_capacity(32);
+    let mut parser = BodyParser::new();
     let mut final_pos = 0;
 
     for (pos, b) in input.bytes().enumerate() {
         final_pos = pos;
         progress_report.report(pos, false);
-        let b = b?;
-        match state {
+        let done = b
+            .map_err(VcdParseError::from)
+            .and_then(|b| parser.parse_byte(b, pos, stop_pos, out))
+            .map_err(|error| BodyError { pos, error })?;
+        if done {
+            // exit
+            progress_report.report(pos, true);
+            return Ok(());
+        }
+    }
+
+    // we reached the end of the file
+    parser.finish(out).map_err(|error| BodyError {
+        pos: final_pos,
+        error,
+    })?;
+    progress_report.report(final_pos, true);
+    Ok(())
+}
+
+/// State of the VCD body parser. Since the parser consumes one byte at a time, it can be
+/// suspended at any point, e.g., in order to wait for more data to be written to a file.
+struct BodyParser {
+    state: BodyState,
+    first: Vec<u8>,
+    id: Vec<u8>,
+}
+
+impl BodyParser {
+    fn new() -> Self {
+        Self {
+            state: BodyState::SkippingNewLine,
+            first: Vec::with_capacity(32),
+            id: Vec::with_capacity(32),
+        }
+    }
+
+    /// Returns true iff a time step past the `stop_pos` was encountered.
+    #[inline]
+    fn parse_byte(
+        &mut self,
+        b: u8,
+        pos: usize,
+        stop_pos: usize,
+        out: &mut impl ParseBodyOutput,
+    ) -> Result<bool> {
+        match self.state {
             BodyState::SkippingNewLine => {
                 if b == b'\n' {
-                    debug_assert!(first.is_empty());
-                    state = BodyState::ParsingFirstToken;
+                    debug_assert!(self.first.is_empty());
+                    self.state = BodyState::ParsingFirstToken;
                 }
             }
             BodyState::ParsingFirstToken => {
                 if is_white_space(b) {
-                    if first.is_empty() {
+                    if self.first.is_empty() {
                         // we are in front of the token => nothing to do
                     } else {
-                        state = match parse_first_token(&first)? {
+                        self.state = match parse_first_token(&self.first)? {
                             FirstTokenResult::Time(value) => {
                                 // check to see if this time value is already fully past
                                 // the stop position
-                                let time_token_start = pos - first.len() - 1;
+                                let time_token_start = pos - self.first.len() - 1;
                                 if time_token_start > stop_pos {
-                                    // exit
-                                    progress_report.report(pos, true);
-                                    return Ok(());
+                                    return Ok(true);
                                 }
                                 // record time step if we aren't exiting
                                 out.time(value)?;
                                 BodyState::ParsingFirstToken
                             }
                             FirstTokenResult::OneBitValue => {
-                                out.value(&first[0..1], &first[1..])?;
+                                out.value(&self.first[0..1], &self.first[1..])?;
                                 BodyState::ParsingFirstToken
                             }
                             FirstTokenResult::MultiBitValue => BodyState::ParsingIdToken,
@@ -1334,68 +1481,71 @@ fn parse_body(
                         };
 
                         // clear buffer to find next token
-                        if state != BodyState::ParsingIdToken {
-                            first.clear();
+                        if self.state != BodyState::ParsingIdToken {
+                            self.first.clear();
                         }
                     }
                 } else {
-                    first.push(b);
+                    self.first.push(b);
                 }
             }
 
             BodyState::ParsingIdToken => {
                 if is_white_space(b) {
-                    if id.is_empty() {
+                    if self.id.is_empty() {
                         // we are in front of the token => nothing to do
                     } else {
-                        out.value(first.as_slice(), id.as_slice())?;
-                        first.clear();
-                        id.clear();
-                        state = BodyState::ParsingFirstToken;
+                        out.value(self.first.as_slice(), self.id.as_slice())?;
+                        self.first.clear();
+                        self.id.clear();
+                        self.state = BodyState::ParsingFirstToken;
                     }
                 } else {
-                    id.push(b);
+                    self.id.push(b);
                 }
             }
             BodyState::LookingForEndToken => {
                 if is_white_space(b) {
-                    if first.is_empty() {
+                    if self.first.is_empty() {
                         // we are in front of the token => nothing to do
                     } else {
-                        if first == b"$end" {
-                            state = BodyState::ParsingFirstToken;
+                        if self.first == b"$end" {
+                            self.state = BodyState::ParsingFirstToken;
                         }
-                        first.clear();
+                        self.first.clear();
                     }
                 } else {
-                    first.push(b);
+                    self.first.push(b);
                 }
             }
         }
+        Ok(false)
     }
 
-    // we reached the end of the file
-    match state {
-        BodyState::ParsingFirstToken => {
-            if !first.is_empty() {
-                match parse_first_token(&first)? {
+    /// Processes the last token, which might not be followed by any whitespace.
+    fn finish(&mut self, out: &mut impl ParseBodyOutput) -> Result<()> {
+        match self.state {
+            BodyState::ParsingFirstToken if !self.first.is_empty() => {
+                match parse_first_token(&self.first)? {
                     FirstTokenResult::Time(value) => {
                         out.time(value)?;
                     }
                     FirstTokenResult::OneBitValue => {
-                        out.value(&first[0..1], &first[1..])?;
+                        out.value(&self.first[0..1], &self.first[1..])?;
                     }
                     _ => {} // nothing to do
                 };
             }
+            BodyState::ParsingIdToken => {
+                out.value(self.first.as_slice(), self.id.as_slice())?;
+            }
+            _ => {} // nothing to do
         }
-        BodyState::ParsingIdToken => {
-            out.value(first.as_slice(), id.as_slice())?;
-        }
-        _ => {} // nothing to do
+        self.first.clear();
+        self.id.clear();
+        self.state = BodyState::ParsingFirstToken;
+        Ok(())
     }
-    progress_report.report(final_pos, true);
-    Ok(())
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -1407,6 +1557,416 @@ enum BodyState {
     LookingForEndToken,
 }
 
+/// Writes a VCD containing every variable in `hierarchy` whose signal is part of `signals`.
+/// Variables without a matching signal are omitted, which allows for writing out a subset of a
+/// waveform. Scope source locations, enum tables and VHDL type names are preserved through the
+/// GTKWave `$attrbegin` extension.
+///
+/// `time_table` needs to be the time table that the signals were loaded against.
+/// Returns an error for values that do not match the encoding of their variable, for strings
+/// that contain whitespace and for `NaN` reals, since none of them can be expressed in a VCD.
+/// Infinite reals are written as the largest finite value of the same sign.
+pub fn write<'a, W: Write>(
+    out: &mut W,
+    hierarchy: &Hierarchy,
+    time_table: &[Time],
+    signals: impl IntoIterator<Item = &'a Signal>,
+) -> std::io::Result<()> {
+    let signals: FxHashMap<SignalRef, &Signal> =
+        signals.into_iter().map(|s| (s.signal_ref(), s)).collect();
+
+    // assign dense ids in order of declaration to avoid the id map when reading the file back in
+    let mut ids = FxHashMap::default();
+    let mut encodings = FxHashMap::default();
+    for var in hierarchy.iter_vars() {
+        let signal_ref = var.signal_ref();
+        if signals.contains_key(&signal_ref) && !ids.contains_key(&signal_ref) {
+            ids.insert(signal_ref, int_to_id(ids.len() as u64));
+            encodings.insert(signal_ref, var.signal_encoding());
+        }
+    }
+
+    write_vcd_header(out, hierarchy, &ids)?;
+    write_vcd_body(out, time_table, &signals, &ids, &encodings)
+}
+
+/// Loads all signals in `hierarchy` from `source` and writes them to a VCD.
+/// See [`write`] for details.
+pub fn write_from_source<W: Write>(
+    out: &mut W,
+    hierarchy: &Hierarchy,
+    time_table: &[Time],
+    source: &mut SignalSource,
+    multi_threaded: bool,
+) -> crate::Result<()> {
+    let loaded = source.load_all_signals(hierarchy, multi_threaded)?;
+    write(out, hierarchy, time_table, loaded.iter().map(|(_, s)| s))?;
+    Ok(())
+}
+
+/// Inverse of `id_to_int`.
+fn int_to_id(mut value: u64) -> String {
+    let mut out = String::with_capacity(4);
+    value += 1;
+    while value > 0 {
+        value -= 1;
+        out.push((ID_CHAR_MIN + (value % NUM_ID_CHARS) as u8) as char);
+        value /= NUM_ID_CHARS;
+    }
+    out
+}
+
+/// Keeps track of path names and enum tables that were already declared through attributes.
+#[derive(Default)]
+pub(crate) struct AttributeHandles {
+    pub(crate) paths: FxHashMap<String, u64>,
+    pub(crate) enums: FxHashMap<EnumTypeId, u64>,
+}
+
+fn write_vcd_header(
+    out: &mut impl Write,
+    h: &Hierarchy,
+    ids: &FxHashMap<SignalRef, String>,
+) -> std::io::Result<()> {
+    if !h.date().is_empty() {
+        writeln!(out, "$date\n\t{}\n$end", h.date())?;
+    }
+    if !h.version().is_empty() {
+        writeln!(out, "$version\n\t{}\n$end", h.version())?;
+    }
+    if let Some(timescale) = h.timescale() {
+        if let Some(unit) = timescale_unit_to_str(timescale.unit) {
+            writeln!(out, "$timescale {}{} $end", timescale.factor, unit)?;
+        }
+    }
+    let mut handles = AttributeHandles::default();
+    write_vcd_items(out, h, h.items(), "", ids, &mut handles)?;
+    writeln!(out, "$enddefinitions $end")
+}
+
+fn write_vcd_items(
+    out: &mut impl Write,
+    h: &Hierarchy,
+    items: impl Iterator<Item = ScopeOrVarRef>,
+    name_prefix: &str,
+    ids: &FxHashMap<SignalRef, String>,
+    handles: &mut AttributeHandles,
+) -> std::io::Result<()> {
+    for item in items {
+        match item.deref(h) {
+            ScopeOrVar::Scope(scope) => {
+                // array scopes are folded back into the variable name, the reader will split them off again
+                if scope.scope_type() == ScopeType::VhdlArray && can_fold_array_scope(h, scope) {
+                    let prefix = format!("{name_prefix}{}", scope.name(h));
+                    write_vcd_items(out, h, scope.items(h), &prefix, ids, handles)?;
+                } else {
+                    write_scope_attributes(out, h, scope, handles)?;
+                    writeln!(
+                        out,
+                        "$scope {} {} $end",
+                        scope_tpe_to_str(scope.scope_type()),
+                        scope.name(h)
+                    )?;
+                    write_vcd_items(out, h, scope.items(h), "", ids, handles)?;
+                    writeln!(out, "$upscope $end")?;
+                }
+            }
+            ScopeOrVar::Var(var) => {
+                if let Some(id) = ids.get(&var.signal_ref()) {
+                    write_var(out, h, var, name_prefix, id, handles)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Array scopes are generated by `parse_name` from names like `mem[3][2]`.
+/// We can only recreate them from the variable name if all children are array elements.
+pub(crate) fn can_fold_array_scope(h: &Hierarchy, scope: &Scope) -> bool {
+    scope.items(h).all(|item| match item.deref(h) {
+        ScopeOrVar::Scope(child) => {
+            child.scope_type() == ScopeType::VhdlArray
+                && child.name(h).starts_with('[')
+                && can_fold_array_scope(h, child)
+        }
+        ScopeOrVar::Var(var) => var.name(h).starts_with('['),
+    })
+}
+
+fn write_scope_attributes(
+    out: &mut impl Write,
+    h: &Hierarchy,
+    scope: &Scope,
+    handles: &mut AttributeHandles,
+) -> std::io::Result<()> {
+    if let Some((path, line)) = scope.source_loc(h) {
+        let path_id = declare_path(out, path, handles)?;
+        writeln!(out, "$attrbegin misc 04 {path_id} {line} $end")?;
+    }
+    if let Some((path, line)) = scope.instantiation_source_loc(h) {
+        let path_id = declare_path(out, path, handles)?;
+        writeln!(out, "$attrbegin misc 05 {path_id} {line} $end")?;
+    }
+    Ok(())
+}
+
+fn declare_path(
+    out: &mut impl Write,
+    path: &str,
+    handles: &mut AttributeHandles,
+) -> std::io::Result<u64> {
+    if let Some(id) = handles.paths.get(path) {
+        return Ok(*id);
+    }
+    let id = handles.paths.len() as u64 + 1;
+    writeln!(out, "$attrbegin misc 03 {path} {id} $end")?;
+    handles.paths.insert(path.to_string(), id);
+    Ok(id)
+}
+
+fn write_var(
+    out: &mut impl Write,
+    h: &Hierarchy,
+    var: &Var,
+    name_prefix: &str,
+    id: &str,
+    handles: &mut AttributeHandles,
+) -> std::io::Result<()> {
+    let (tpe, vhdl_data_type) = var_tpe_to_str(var.var_type(), var.signal_encoding());
+
+    // VHDL type information
+    if vhdl_data_type.is_some() || var.vhdl_type_name(h).is_some() {
+        let type_name = var
+            .vhdl_type_name(h)
+            .unwrap_or_else(|| default_vhdl_type_name(var.var_type()));
+        let data_type = vhdl_data_type.unwrap_or(FstVhdlDataType::None);
+        let arg =
+            ((FstVhdlVarType::Signal as u64) << FST_SUP_VAR_DATA_TYPE_BITS) | data_type as u64;
+        writeln!(out, "$attrbegin misc 02 {type_name} {arg} $end")?;
+    }
+
+    // enum table
+    if let (Some(enum_id), Some((name, mapping))) = (var.enum_type_id(), var.enum_type(h)) {
+        let handle = match handles.enums.get(&enum_id) {
+            Some(handle) => *handle,
+            None => {
+                let handle = handles.enums.len() as u64 + 1;
+                write!(out, "$attrbegin misc 07 {name} {}", mapping.len())?;
+                for (_, value) in mapping.iter() {
+                    write!(out, " {value}")?;
+                }
+                for (key, _) in mapping.iter() {
+                    write!(out, " {key}")?;
+                }
+                writeln!(out, " {handle} $end")?;
+                handles.enums.insert(enum_id, handle);
+                handle
+            }
+        };
+        writeln!(out, "$attrbegin misc 07 \"\" {handle} $end")?;
+    }
+
+    let width = match var.signal_encoding() {
+        SignalEncoding::String => 1,
+        SignalEncoding::Real => 64,
+        SignalEncoding::BitVector(len) => len.get(),
+    };
+    write!(out, "$var {tpe} {width} {id} {name_prefix}{}", var.name(h))?;
+    // an index that does not match the width would be interpreted as an array index
+    if let Some(index) = var.index().filter(|i| i.length() == width) {
+        if index.msb() == index.lsb() {
+            write!(out, " [{}]", index.lsb())?;
+        } else {
+            write!(out, " [{}:{}]", index.msb(), index.lsb())?;
+        }
+    }
+    writeln!(out, " $end")
+}
+
+fn write_vcd_body(
+    out: &mut impl Write,
+    time_table: &[Time],
+    signals: &FxHashMap<SignalRef, &Signal>,
+    ids: &FxHashMap<SignalRef, String>,
+    encodings: &FxHashMap<SignalRef, SignalEncoding>,
+) -> std::io::Result<()> {
+    // merge the changes of all signals by time index
+    let mut changes: Vec<_> = ids
+        .iter()
+        .map(|(signal_ref, id)| {
+            let changes = signals[signal_ref].iter_changes().peekable();
+            (id.as_str(), encodings[signal_ref], changes)
+        })
+        .collect();
+    let mut queue: BinaryHeap<Reverse<(TimeTableIdx, usize)>> = changes
+        .iter_mut()
+        .enumerate()
+        .filter_map(|(ii, (_, _, c))| c.peek().map(|(t, _)| Reverse((*t, ii))))
+        .collect();
+
+    for (time_idx, time) in time_table.iter().enumerate() {
+        writeln!(out, "#{time}")?;
+        while let Some(Reverse((next_idx, ii))) = queue.peek().cloned() {
+            if next_idx as usize != time_idx {
+                break;
+            }
+            queue.pop();
+            let (id, encoding, signal_changes) = &mut changes[ii];
+            let (_, value) = signal_changes.next().unwrap();
+            write_value(out, value, *encoding, id)?;
+            if let Some((t, _)) = signal_changes.peek() {
+                queue.push(Reverse((*t, ii)));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_value(
+    out: &mut impl Write,
+    value: SignalValue,
+    encoding: SignalEncoding,
+    id: &str,
+) -> std::io::Result<()> {
+    match (encoding, value) {
+        // there is no way to escape whitespace in a VCD
+        (SignalEncoding::String, SignalValue::String(value))
+            if !value.contains(|c: char| c.is_whitespace()) =>
+        {
+            writeln!(out, "s{value} {id}")
+        }
+        (SignalEncoding::Real, SignalValue::Real(value)) if !value.is_nan() => {
+            // VCD has no notation for infinity, thus we use the largest finite value instead
+            let value = value.clamp(f64::MIN, f64::MAX);
+            writeln!(out, "r{value:e} {id}")
+        }
+        (SignalEncoding::BitVector(len), value) if value.bits() == Some(len.get()) => {
+            if len.get() == 1 {
+                writeln!(out, "{value}{id}")
+            } else {
+                writeln!(out, "b{value} {id}")
+            }
+        }
+        (encoding, value) => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("[vcd] cannot write value {value:?} of signal with encoding {encoding:?}"),
+        )),
+    }
+}
+
+fn timescale_unit_to_str(unit: TimescaleUnit) -> Option<&'static str> {
+    match unit {
+        TimescaleUnit::ZeptoSeconds => Some("zs"),
+        TimescaleUnit::AttoSeconds => Some("as"),
+        TimescaleUnit::FemtoSeconds => Some("fs"),
+        TimescaleUnit::PicoSeconds => Some("ps"),
+        TimescaleUnit::NanoSeconds => Some("ns"),
+        TimescaleUnit::MicroSeconds => Some("us"),
+        TimescaleUnit::MilliSeconds => Some("ms"),
+        TimescaleUnit::Seconds => Some("s"),
+        TimescaleUnit::Unknown => None,
+    }
+}
+
+fn scope_tpe_to_str(tpe: ScopeType) -> &'static str {
+    match tpe {
+        ScopeType::Module => "module",
+        ScopeType::Task => "task",
+        ScopeType::Function => "function",
+        ScopeType::Begin => "begin",
+        ScopeType::Fork => "fork",
+        ScopeType::Generate => "generate",
+        ScopeType::Struct => "struct",
+        ScopeType::Union => "union",
+        ScopeType::Class => "class",
+        ScopeType::Interface => "interface",
+        ScopeType::Package => "package",
+        ScopeType::Program => "program",
+        ScopeType::VhdlArchitecture => "vhdl_architecture",
+        ScopeType::VhdlProcedure => "vhdl_procedure",
+        ScopeType::VhdlFunction => "vhdl_function",
+        ScopeType::VhdlRecord => "vhdl_record",
+        ScopeType::VhdlProcess => "vhdl_process",
+        ScopeType::VhdlBlock => "vhdl_block",
+        ScopeType::VhdlForGenerate => "vhdl_for_generate",
+        ScopeType::VhdlIfGenerate => "vhdl_if_generate",
+        ScopeType::VhdlGenerate => "vhdl_generate",
+        ScopeType::VhdlPackage => "vhdl_package",
+        // there is no VCD equivalent for these
+        ScopeType::GhwGeneric | ScopeType::VhdlArray => "module",
+        ScopeType::Unknown => "unknown",
+    }
+}
+
+/// Returns the VCD var type and an optional VHDL data type which is required in order to
+/// recreate the `VarType` through `merge_vhdl_data_and_var_type`.
+fn var_tpe_to_str(
+    tpe: VarType,
+    encoding: SignalEncoding,
+) -> (&'static str, Option<FstVhdlDataType>) {
+    match encoding {
+        SignalEncoding::String => return ("string", None),
+        SignalEncoding::Real => {
+            let vcd_tpe = match tpe {
+                VarType::Parameter => "real_parameter",
+                VarType::RealTime => "realtime",
+                VarType::ShortReal => "shortreal",
+                _ => "real",
+            };
+            return (vcd_tpe, None);
+        }
+        SignalEncoding::BitVector(_) => {}
+    }
+    match tpe {
+        VarType::Event => ("event", None),
+        VarType::Integer => ("integer", None),
+        VarType::Parameter => ("parameter", None),
+        VarType::Reg => ("reg", None),
+        VarType::Supply0 => ("supply0", None),
+        VarType::Supply1 => ("supply1", None),
+        VarType::Time => ("time", None),
+        VarType::Tri => ("tri", None),
+        VarType::TriAnd => ("triand", None),
+        VarType::TriOr => ("trior", None),
+        VarType::TriReg => ("trireg", None),
+        VarType::Tri0 => ("tri0", None),
+        VarType::Tri1 => ("tri1", None),
+        VarType::WAnd => ("wand", None),
+        VarType::Wire => ("wire", None),
+        VarType::WOr => ("wor", None),
+        VarType::Port => ("port", None),
+        VarType::SparseArray => ("sparray", None),
+        VarType::Bit => ("bit", None),
+        VarType::Logic => ("logic", None),
+        VarType::Int => ("int", None),
+        VarType::ShortInt => ("shortint", None),
+        VarType::LongInt => ("longint", None),
+        VarType::Byte => ("byte", None),
+        VarType::Enum => ("enum", None),
+        // a bit-vector encoded real or string does not have a VCD equivalent
+        VarType::Real | VarType::RealTime | VarType::ShortReal | VarType::String => ("wire", None),
+        VarType::Boolean => ("bit", Some(FstVhdlDataType::Boolean)),
+        VarType::BitVector => ("bit", Some(FstVhdlDataType::Vector)),
+        VarType::StdLogic => ("logic", Some(FstVhdlDataType::Logic)),
+        VarType::StdLogicVector => ("logic", Some(FstVhdlDataType::LogicVector)),
+        VarType::StdULogic => ("logic", Some(FstVhdlDataType::ULogic)),
+        VarType::StdULogicVector => ("logic", Some(FstVhdlDataType::ULogicVector)),
+    }
+}
+
+pub(crate) fn default_vhdl_type_name(tpe: VarType) -> &'static str {
+    match tpe {
+        VarType::Boolean => "boolean",
+        VarType::BitVector => "bit_vector",
+        VarType::StdLogic => "std_logic",
+        VarType::StdLogicVector => "std_logic_vector",
+        VarType::StdULogic => "std_ulogic",
+        VarType::StdULogicVector => "std_ulogic_vector",
+        _ => "unknown",
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1565,4 +2125,41 @@ x%i"
         );
         do_test_parse_name(11, "test [10:0]", "test", Some((10, 0)), &[]);
     }
+
+    fn write_value_to_string(value: SignalValue, encoding: SignalEncoding) -> Option<String> {
+        let mut out = vec![];
+        write_value(&mut out, value, encoding, "!").ok()?;
+        Some(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_write_value() {
+        let bits = SignalEncoding::bit_vec_of_len(4);
+        assert_eq!(
+            write_value_to_string(SignalValue::Binary(&[0b1010], 4), bits).unwrap(),
+            "b1010 !\n"
+        );
+        // the width needs to match the variable
+        assert!(write_value_to_string(SignalValue::Binary(&[0b1010], 3), bits).is_none());
+        assert!(write_value_to_string(SignalValue::Real(1.0), bits).is_none());
+
+        let real = SignalEncoding::Real;
+        assert_eq!(
+            write_value_to_string(SignalValue::Real(1.5), real).unwrap(),
+            "r1.5e0 !\n"
+        );
+        assert_eq!(
+            write_value_to_string(SignalValue::Real(f64::NEG_INFINITY), real).unwrap(),
+            format!("r{:e} !\n", f64::MIN)
+        );
+        assert!(write_value_to_string(SignalValue::Real(f64::NAN), real).is_none());
+
+        let string = SignalEncoding::String;
+        assert_eq!(
+            write_value_to_string(SignalValue::String("idle"), string).unwrap(),
+            "sidle !\n"
+        );
+        assert!(write_value_to_string(SignalValue::String("two words"), string).is_none());
+        assert!(write_value_to_string(SignalValue::String("a\tb"), string).is_none());
+    }
 }
@@ -5,6 +5,7 @@
 //
 // Interface for waveform viewers
 
+use crate::cache::{CacheKey, CacheTarget};
 use crate::{FileFormat, Hierarchy, LoadOptions, Result, SignalSource, TimeTable, WellenError};
 use std::io::{BufRead, Seek};
 
@@ -20,12 +21,6 @@ impl From<crate::vcd::VcdParseError> for WellenError {
     }
 }
 
-impl From<fst_reader::ReaderError> for WellenError {
-    fn from(value: fst_reader::ReaderError) -> Self {
-        WellenError::FailedToLoad(FileFormat::Fst, value.to_string())
-    }
-}
-
 pub struct HeaderResult<R: BufRead + Seek> {
     pub hierarchy: Hierarchy,
     pub file_format: FileFormat,
@@ -34,45 +29,76 @@ pub struct HeaderResult<R: BufRead + Seek> {
     pub body: ReadBodyContinuation<R>,
 }
 
+/// Reads the header of a waveform file. If `options.use_cache` is set and there is an up-to-date
+/// cache file for a VCD or GHW input, the hierarchy is read from the cache and the body will be
+/// memory mapped from it instead of being parsed again.
 pub fn read_header_from_file<P: AsRef<std::path::Path>>(
     filename: P,
     options: &LoadOptions,
 ) -> Result<HeaderResult<std::io::BufReader<std::fs::File>>> {
-    let file_format = open_and_detect_file_format(filename.as_ref());
-    match file_format {
-        FileFormat::Unknown => Err(WellenError::UnknownFileFormat),
+    let filename = filename.as_ref();
+    let file_format =
+        detect_file_format(&mut std::io::BufReader::new(std::fs::File::open(filename)?));
+    let cache = if options.use_cache && matches!(file_format, FileFormat::Vcd | FileFormat::Ghw) {
+        // if we cannot determine size and modification time, we just do not use the cache
+        match CacheKey::new(filename, options) {
+            Ok(key) => {
+                let path = crate::cache::cache_path(filename);
+                if let Some((hierarchy, file_format, body)) = crate::cache::load(&path, &key) {
+                    let body = ReadBodyContinuation::new(ReadBodyData::Cached(Box::new(body)));
+                    return Ok(HeaderResult {
+                        hierarchy,
+                        file_format,
+                        body_len: 0, // nothing needs to be parsed
+                        body,
+                    });
+                }
+                Some(CacheTarget {
+                    path,
+                    key,
+                    file_format,
+                })
+            }
+            Err(_) => None,
+        }
+    } else {
+        None
+    };
+    let mut result = match file_format {
+        FileFormat::Unknown => return Err(WellenError::UnknownFileFormat),
         FileFormat::Vcd => {
             let (hierarchy, body, body_len) = crate::vcd::read_header_from_file(filename, options)?;
-            let body = ReadBodyContinuation(ReadBodyData::Vcd(Box::new(body)));
-            Ok(HeaderResult {
+            let body = ReadBodyContinuation::new(ReadBodyData::Vcd(Box::new(body)));
+            HeaderResult {
                 hierarchy,
                 file_format,
                 body_len,
                 body,
-            })
+            }
         }
         FileFormat::Ghw => {
-            let input = std::io::BufReader::new(std::fs::File::open(filename)?);
-            let (hierarchy, body, body_len) = crate::ghw::read_header(input, options)?;
-            let body = ReadBodyContinuation(ReadBodyData::Ghw(Box::new(body)));
-            Ok(HeaderResult {
+            let (hierarchy, body, body_len) = crate::ghw::read_header_from_file(filename, options)?;
+            let body = ReadBodyContinuation::new(ReadBodyData::Ghw(Box::new(body)));
+            HeaderResult {
                 hierarchy,
                 file_format,
                 body_len,
                 body,
-            })
+            }
         }
         FileFormat::Fst => {
             let (hierarchy, body) = crate::fst::read_header_from_file(filename, options)?;
-            let body = ReadBodyContinuation(ReadBodyData::Fst(Box::new(body)));
-            Ok(HeaderResult {
+            let body = ReadBodyContinuation::new(ReadBodyData::Fst(Box::new(body)));
+            HeaderResult {
                 hierarchy,
                 file_format,
                 body_len: 0, // fst never reads the full body (unless all signals are displayed)
                 body,
-            })
+            }
         }
-    }
+    };
+    result.body.cache = cache;
+    Ok(result)
 }
 
 pub fn read_header<R: BufRead + Seek>(
@@ -84,7 +110,7 @@ pub fn read_header<R: BufRead + Seek>(
         FileFormat::Unknown => Err(WellenError::UnknownFileFormat),
         FileFormat::Vcd => {
             let (hierarchy, body, body_len) = crate::vcd::read_header(input, options)?;
-            let body = ReadBodyContinuation(ReadBodyData::Vcd(Box::new(body)));
+            let body = ReadBodyContinuation::new(ReadBodyData::Vcd(Box::new(body)));
             Ok(HeaderResult {
                 hierarchy,
                 file_format,
@@ -94,7 +120,7 @@ pub fn read_header<R: BufRead + Seek>(
         }
         FileFormat::Ghw => {
             let (hierarchy, body, body_len) = crate::ghw::read_header(input, options)?;
-            let body = ReadBodyContinuation(ReadBodyData::Ghw(Box::new(body)));
+            let body = ReadBodyContinuation::new(ReadBodyData::Ghw(Box::new(body)));
             Ok(HeaderResult {
                 hierarchy,
                 file_format,
@@ -104,7 +130,7 @@ pub fn read_header<R: BufRead + Seek>(
         }
         FileFormat::Fst => {
             let (hierarchy, body) = crate::fst::read_header(input, options)?;
-            let body = ReadBodyContinuation(ReadBodyData::Fst(Box::new(body)));
+            let body = ReadBodyContinuation::new(ReadBodyData::Fst(Box::new(body)));
             Ok(HeaderResult {
                 hierarchy,
                 file_format,
@@ -115,12 +141,23 @@ pub fn read_header<R: BufRead + Seek>(
     }
 }
 
-pub struct ReadBodyContinuation<R: BufRead + Seek>(ReadBodyData<R>);
+pub struct ReadBodyContinuation<R: BufRead + Seek> {
+    data: ReadBodyData<R>,
+    /// Where to store the parsed body, if caching is enabled.
+    cache: Option<CacheTarget>,
+}
+
+impl<R: BufRead + Seek> ReadBodyContinuation<R> {
+    fn new(data: ReadBodyData<R>) -> Self {
+        Self { data, cache: None }
+    }
+}
 
 enum ReadBodyData<R: BufRead + Seek> {
     Vcd(Box<crate::vcd::ReadBodyContinuation<R>>),
     Fst(Box<crate::fst::ReadBodyContinuation<R>>),
     Ghw(Box<crate::ghw::ReadBodyContinuation<R>>),
+    Cached(Box<crate::cache::CachedBody>),
 }
 
 pub struct BodyResult {
@@ -135,21 +172,43 @@ pub fn read_body<R: BufRead + Seek + Sync + Send + 'static>(
     hierarchy: &Hierarchy,
     progress: Option<ProgressCount>,
 ) -> Result<BodyResult> {
-    match body.0 {
+    match body.data {
         ReadBodyData::Vcd(data) => {
-            let (source, time_table) = crate::vcd::read_body(*data, hierarchy, progress)?;
-            Ok(BodyResult { source, time_table })
+            let (reader, time_table) = crate::vcd::read_body(*data, hierarchy, progress)?;
+            Ok(finish_body(reader, time_table, hierarchy, body.cache))
         }
         ReadBodyData::Fst(data) => {
-            // fst does not support a progress count since it is not actually reading the body
+            // fst does not support a progress count since it is not actually reading the body,
+            // instead, progress can be tracked while loading signals
             let (source, time_table) = crate::fst::read_body(*data)?;
             Ok(BodyResult { source, time_table })
         }
         ReadBodyData::Ghw(data) => {
-            let (source, time_table) = crate::ghw::read_body(*data, hierarchy, progress)?;
-            Ok(BodyResult { source, time_table })
+            let (reader, time_table) = crate::ghw::read_body(*data, hierarchy, progress)?;
+            Ok(finish_body(reader, time_table, hierarchy, body.cache))
         }
+        ReadBodyData::Cached(data) => {
+            let source = SignalSource::new(Box::new(data.reader));
+            Ok(BodyResult {
+                source,
+                time_table: data.time_table,
+            })
+        }
+    }
+}
+
+fn finish_body(
+    reader: crate::wavemem::Reader,
+    time_table: TimeTable,
+    hierarchy: &Hierarchy,
+    cache: Option<CacheTarget>,
+) -> BodyResult {
+    if let Some(target) = cache {
+        // failing to write the cache is not fatal, since we already have all the data
+        let _ = crate::cache::store(&target, hierarchy, &reader, &time_table);
     }
+    let source = SignalSource::new(Box::new(reader));
+    BodyResult { source, time_table }
 }
 
 /// Tries to guess the format of the file.
@@ -163,7 +222,7 @@ pub fn open_and_detect_file_format<P: AsRef<std::path::Path>>(filename: P) -> Fi
 pub fn detect_file_format(input: &mut (impl BufRead + Seek)) -> FileFormat {
     if crate::vcd::is_vcd(input) {
         FileFormat::Vcd
-    } else if fst_reader::is_fst_file(input) {
+    } else if crate::fst::is_fst_file(input) {
         FileFormat::Fst
     } else if crate::ghw::is_ghw(input) {
         FileFormat::Ghw
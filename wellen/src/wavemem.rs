@@ -5,11 +5,12 @@
 //
 // Fast and compact wave-form representation inspired by the FST on disk format.
 
+use crate::cache::{invalid_data, read_id, read_uint, write_id, write_uint};
 use crate::compressed::Compression;
 use crate::fst::{get_bytes_per_entry, get_len_and_meta, push_zeros};
 use crate::hierarchy::{Hierarchy, SignalRef};
 use crate::signals::{
-    FixedWidthEncoding, Real, Signal, SignalSource, SignalSourceImplementation, Time, TimeTableIdx,
+    FixedWidthEncoding, Real, Signal, SignalSourceImplementation, Time, TimeTableIdx,
 };
 use crate::{SignalEncoding, SignalValue, TimeTable};
 use num_enum::TryFromPrimitive;
@@ -18,10 +19,12 @@ use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::io::Read;
 use std::num::NonZeroU32;
+use std::ops::{Deref, Range};
+use std::sync::Arc;
 
 /// Holds queryable waveform data. Use the `Encoder` to generate.
 pub struct Reader {
-    blocks: Vec<Block>,
+    blocks: Vec<Arc<Block>>,
 }
 
 impl SignalSourceImplementation for Reader {
@@ -30,17 +33,44 @@ impl SignalSourceImplementation for Reader {
         ids: &[SignalRef],
         types: &[SignalEncoding],
         multi_threaded: bool,
-    ) -> Vec<Signal> {
+    ) -> crate::Result<Vec<Signal>> {
+        // our own encoding cannot be malformed
         if multi_threaded {
-            ids.par_iter()
+            Ok(ids
+                .par_iter()
                 .zip(types.par_iter())
                 .map(|(id, len)| self.load_signal(*id, *len))
-                .collect::<Vec<_>>()
+                .collect::<Vec<_>>())
         } else {
-            ids.iter()
+            Ok(ids
+                .iter()
                 .zip(types.iter())
                 .map(|(id, len)| self.load_signal(*id, *len))
-                .collect::<Vec<_>>()
+                .collect::<Vec<_>>())
+        }
+    }
+
+    fn load_signals_in_range(
+        &mut self,
+        ids: &[SignalRef],
+        types: &[SignalEncoding],
+        start: Time,
+        end: Time,
+        multi_threaded: bool,
+    ) -> crate::Result<Vec<Signal>> {
+        let window = self.find_window(start, end);
+        if multi_threaded {
+            Ok(ids
+                .par_iter()
+                .zip(types.par_iter())
+                .map(|(id, len)| self.load_signal_in_range(*id, *len, window))
+                .collect::<Vec<_>>())
+        } else {
+            Ok(ids
+                .iter()
+                .zip(types.iter())
+                .map(|(id, len)| self.load_signal_in_range(*id, *len, window))
+                .collect::<Vec<_>>())
         }
     }
 
@@ -92,18 +122,32 @@ impl Reader {
         );
     }
 
+    /// Returns the global time table indices that are in effect at `start` and `end`.
+    fn find_window(&self, start: Time, end: Time) -> Option<(TimeTableIdx, TimeTableIdx)> {
+        let mut time_idx_offset = 0;
+        let mut start_idx = 0;
+        let mut end_idx = None;
+        for block in self.blocks.iter() {
+            if block.start_time > end {
+                break;
+            }
+            if block.start_time <= start {
+                let local = block.time_table.partition_point(|t| *t <= start) - 1;
+                start_idx = time_idx_offset + local as TimeTableIdx;
+            }
+            let local = block.time_table.partition_point(|t| *t <= end) - 1;
+            end_idx = Some(time_idx_offset + local as TimeTableIdx);
+            time_idx_offset += block.time_table.len() as TimeTableIdx;
+        }
+        end_idx.map(|end_idx| (std::cmp::min(start_idx, end_idx), end_idx))
+    }
+
     fn collect_signal_meta_data(&self, id: SignalRef) -> SignalMetaData<'_> {
         let mut time_idx_offset = 0;
         let mut blocks = Vec::with_capacity(self.blocks.len());
         for block in self.blocks.iter() {
-            if let Some((start_ii, data_len)) = block.get_offset_and_length(id) {
-                let end_ii = start_ii + data_len;
-                // uncompress if necessary
-                let mut reader = std::io::Cursor::new(&block.data[start_ii..end_ii]);
-                let meta_data_raw = leb128::read::unsigned(&mut reader).unwrap();
-                let meta_data = SignalEncodingMetaData::decode(meta_data_raw);
-                let data_block = &block.data[start_ii + reader.position() as usize..end_ii];
-                blocks.push((time_idx_offset, data_block, meta_data));
+            if let Some(data) = block.get_signal_data(id) {
+                blocks.push((time_idx_offset, data.0, data.1));
             }
             time_idx_offset += block.time_table.len() as u32;
         }
@@ -119,6 +163,47 @@ impl Reader {
         let meta = self.collect_signal_meta_data(id);
         load_compressed_signal(meta, id, tpe)
     }
+
+    /// Only decodes the blocks that overlap with the window, plus the last block before the window
+    /// that contains a change.
+    fn load_signal_in_range(
+        &self,
+        id: SignalRef,
+        tpe: SignalEncoding,
+        window: Option<(TimeTableIdx, TimeTableIdx)>,
+    ) -> Signal {
+        let mut blocks = Vec::new();
+        if let Some((start_idx, end_idx)) = window {
+            let mut time_idx_offset = 0;
+            let mut before = None;
+            for block in self.blocks.iter() {
+                if time_idx_offset > end_idx {
+                    break;
+                }
+                let block_end_idx = time_idx_offset + block.time_table.len() as u32 - 1;
+                if let Some((data, meta_data)) = block.get_signal_data(id) {
+                    if block_end_idx < start_idx {
+                        before = Some((time_idx_offset, data, meta_data));
+                    } else {
+                        blocks.push((time_idx_offset, data, meta_data));
+                    }
+                }
+                time_idx_offset = block_end_idx + 1;
+            }
+            if let Some(before) = before {
+                blocks.insert(0, before);
+            }
+        }
+        let max_states = blocks
+            .iter()
+            .map(|b| b.2.max_states)
+            .reduce(States::join)
+            .unwrap_or(States::Nine);
+        let meta = SignalMetaData { max_states, blocks };
+        let mut signal = load_compressed_signal(meta, id, tpe);
+        signal.retain_window(window);
+        signal
+    }
 }
 
 pub(crate) fn load_compressed_signal(
@@ -358,6 +443,92 @@ fn load_signal_strings(
     }
 }
 
+// cache file serialization
+impl Reader {
+    pub(crate) fn write_to_cache(
+        &self,
+        out: &mut impl std::io::Write,
+        time_table: &[Time],
+    ) -> std::io::Result<()> {
+        write_uint(out, time_table.len() as u64)?;
+        for time in time_table.iter() {
+            write_uint(out, *time)?;
+        }
+        write_uint(out, self.blocks.len() as u64)?;
+        for block in self.blocks.iter() {
+            write_uint(out, block.start_time)?;
+            write_uint(out, block.time_table.len() as u64)?;
+            write_uint(out, block.offsets.len() as u64)?;
+            for offset in block.offsets.iter() {
+                write_id(out, offset.map(|o| o.0))?;
+            }
+            write_uint(out, block.data.len() as u64)?;
+            out.write_all(&block.data)?;
+        }
+        Ok(())
+    }
+
+    /// Reads the time table and all blocks. The signal data of each block is not copied,
+    /// instead it refers to the memory mapped cache file.
+    pub(crate) fn read_from_cache(
+        input: &mut std::io::Cursor<&[u8]>,
+        mmap: &Arc<memmap2::Mmap>,
+        num_signals: usize,
+    ) -> std::io::Result<(Self, TimeTable)> {
+        let time_table_len = read_uint(input)? as usize;
+        let mut time_table = Vec::with_capacity(std::cmp::min(time_table_len, 1 << 20));
+        for _ in 0..time_table_len {
+            time_table.push(read_uint(input)?);
+        }
+        let num_blocks = read_uint(input)? as usize;
+        let mut blocks = Vec::with_capacity(std::cmp::min(num_blocks, 1 << 10));
+        let mut time_idx_offset = 0;
+        for _ in 0..num_blocks {
+            let start_time = read_uint(input)?;
+            let block_time_len = read_uint(input)? as usize;
+            let block_time_table = time_table
+                .get(time_idx_offset..time_idx_offset + block_time_len)
+                .filter(|t| !t.is_empty())
+                .ok_or_else(|| invalid_data("invalid block time table"))?
+                .to_vec();
+            time_idx_offset += block_time_len;
+            let num_offsets = read_uint(input)? as usize;
+            if num_offsets != num_signals {
+                return Err(invalid_data("block does not match hierarchy"));
+            }
+            let mut offsets = Vec::with_capacity(num_offsets);
+            for _ in 0..num_offsets {
+                offsets.push(read_id(input)?.map(SignalDataOffset));
+            }
+            let data_len = read_uint(input)? as usize;
+            let data_start = input.position() as usize;
+            let data_end = data_start
+                .checked_add(data_len)
+                .filter(|end| *end <= mmap.len())
+                .ok_or_else(|| invalid_data("block data out of range"))?;
+            input.set_position(data_end as u64);
+            // offsets need to be increasing and inside the data section
+            let mut prev = 0;
+            for offset in offsets.iter().flatten() {
+                if offset.get_index() < prev || offset.get_index() >= data_len {
+                    return Err(invalid_data("invalid signal data offset"));
+                }
+                prev = offset.get_index();
+            }
+            blocks.push(Arc::new(Block {
+                start_time,
+                time_table: block_time_table,
+                offsets,
+                data: BlockData::Mapped(mmap.clone(), data_start..data_end),
+            }));
+        }
+        if time_idx_offset != time_table.len() {
+            return Err(invalid_data("time table does not match blocks"));
+        }
+        Ok((Reader { blocks }, time_table))
+    }
+}
+
 /// A block that contains all value changes in a certain time segment.
 /// Note that while in FST blocks can be skipped, here we only use blocks
 /// in order to combine data from different threads and to compress partial data.
@@ -367,7 +538,25 @@ struct Block {
     /// Offsets of (potentially compressed) signal data.
     offsets: Vec<Option<SignalDataOffset>>,
     /// Data for all signals in block
-    data: Vec<u8>,
+    data: BlockData,
+}
+
+/// Signal data of a block. Blocks that were loaded from a cache file point directly into the
+/// memory mapped file instead of holding a copy.
+enum BlockData {
+    Owned(Vec<u8>),
+    Mapped(Arc<memmap2::Mmap>, Range<usize>),
+}
+
+impl Deref for BlockData {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            BlockData::Owned(data) => data,
+            BlockData::Mapped(mmap, range) => &mmap[range.clone()],
+        }
+    }
 }
 
 impl Block {
@@ -375,7 +564,11 @@ impl Block {
         let base = std::mem::size_of::<Self>();
         let time = self.time_table.len() * std::mem::size_of::<Time>();
         let offsets = self.offsets.len() * std::mem::size_of::<SignalDataOffset>();
-        let data = self.data.len() * std::mem::size_of::<u8>();
+        // mapped data is paged in by the OS and thus does not count towards our memory usage
+        let data = match &self.data {
+            BlockData::Owned(data) => data.len() * std::mem::size_of::<u8>(),
+            BlockData::Mapped(..) => 0,
+        };
         base + time + offsets + data
     }
 
@@ -383,6 +576,19 @@ impl Block {
         *self.time_table.last().unwrap()
     }
 
+    /// Returns the (potentially compressed) data of a signal and its meta-data.
+    fn get_signal_data(&self, id: SignalRef) -> Option<(&[u8], SignalEncodingMetaData)> {
+        let (start_ii, data_len) = self.get_offset_and_length(id)?;
+        let end_ii = start_ii + data_len;
+        let mut reader = std::io::Cursor::new(&self.data[start_ii..end_ii]);
+        let meta_data_raw = leb128::read::unsigned(&mut reader).unwrap();
+        let meta_data = SignalEncodingMetaData::decode(meta_data_raw);
+        Some((
+            &self.data[start_ii + reader.position() as usize..end_ii],
+            meta_data,
+        ))
+    }
+
     fn get_offset_and_length(&self, id: SignalRef) -> Option<(usize, usize)> {
         let offset = match self.offsets[id.index()] {
             None => return None,
@@ -417,12 +623,14 @@ impl SignalDataOffset {
 pub struct Encoder {
     /// Time table under construction
     time_table: Vec<Time>,
-    /// Signals under construction
-    signals: Vec<SignalEncoder>,
+    /// Signals under construction, `None` for signals that are not part of the hierarchy
+    signals: Vec<Option<SignalEncoder>>,
     /// Tracks if there has been any new data that would require us to create another block.
     has_new_data: bool,
     /// Tracks if we are skipping a timestep because it came with an invalid time.
     skipping_time_step: bool,
+    /// Set by `end_block_after_time_step`.
+    end_block_requested: bool,
     /// Finished blocks
     blocks: Vec<Block>,
 }
@@ -432,21 +640,23 @@ type BlockTimeIdx = u16;
 
 impl Encoder {
     pub fn new(hierarchy: &Hierarchy) -> Self {
-        let mut signals = Vec::with_capacity(hierarchy.num_unique_signals());
-        for var in hierarchy.get_unique_signals_vars() {
-            let tpe = match var {
-                None => SignalEncoding::String, // we do not know!
-                Some(var) => var.signal_encoding(),
-            };
-            let pos = signals.len();
-            signals.push(SignalEncoder::new(tpe, pos));
-        }
+        // value changes of signals without a variable (e.g., because the scope filter skipped it)
+        // are ignored
+        let signals = (0..hierarchy.num_unique_signals())
+            .map(|pos| {
+                let id = SignalRef::from_index(pos).unwrap();
+                hierarchy
+                    .get_signal_tpe(id)
+                    .map(|tpe| SignalEncoder::new(tpe, pos))
+            })
+            .collect();
 
         Encoder {
             time_table: Vec::default(),
             signals,
             has_new_data: false,
             skipping_time_step: false,
+            end_block_requested: false,
             blocks: Vec::default(),
         }
     }
@@ -471,6 +681,16 @@ impl Encoder {
                 }
             }
         }
+        // the previous time step is complete, thus we can end the block as requested
+        if self.end_block_requested {
+            self.finish_block();
+            self.end_block_requested = false;
+        }
+        // the last time step of a finished block does not need to be repeated in the next block,
+        // unless there were changes after the block was finished
+        if !self.has_new_data {
+            self.time_table.clear();
+        }
         // if we run out of time indices => start a new block
         if self.time_table.len() >= BlockTimeIdx::MAX as usize {
             self.finish_block();
@@ -480,17 +700,38 @@ impl Encoder {
         self.skipping_time_step = false;
     }
 
-    /// Call with an unaltered VCD value.
-    pub fn vcd_value_change(&mut self, id: u64, value: &[u8]) {
+    /// Call with an unaltered VCD value. Returns an error message if the id is out of range or
+    /// if the value does not fit the signal.
+    pub fn vcd_value_change(&mut self, id: u64, value: &[u8]) -> Result<(), String> {
         assert!(
             !self.time_table.is_empty(),
             "We need a call to time_change first!"
         );
         if !self.skipping_time_step {
             let time_idx = (self.time_table.len() - 1) as TimeTableIdx;
-            self.signals[id as usize].add_vcd_change(time_idx, value);
-            self.has_new_data = true;
+            let num_signals = self.signals.len();
+            let signal = usize::try_from(id)
+                .ok()
+                .and_then(|ii| self.signals.get_mut(ii))
+                .ok_or_else(|| format!("signal id {id} is out of range (0..{num_signals})"))?;
+            if let Some(signal) = signal {
+                signal.add_vcd_change(time_idx, value)?;
+                self.has_new_data = true;
+            }
         }
+        Ok(())
+    }
+
+    /// Returns `None` if `id` is out of range and `Some(None)` for signals without a variable.
+    pub(crate) fn signal_encoding(&self, id: SignalRef) -> Option<Option<SignalEncoding>> {
+        self.signals
+            .get(id.index())
+            .map(|s| s.as_ref().map(|s| s.tpe))
+    }
+
+    /// Returns true once `time_change` has been called.
+    pub(crate) fn has_time_step(&self) -> bool {
+        !self.time_table.is_empty()
     }
 
     /// Call with a value that is already encoded in our internal format.
@@ -501,8 +742,10 @@ impl Encoder {
         );
         if !self.skipping_time_step {
             let time_idx = (self.time_table.len() - 1) as TimeTableIdx;
-            self.signals[id.index()].add_n_bit_change(time_idx, value, states);
-            self.has_new_data = true;
+            if let Some(signal) = &mut self.signals[id.index()] {
+                signal.add_n_bit_change(time_idx, value, states);
+                self.has_new_data = true;
+            }
         }
     }
 
@@ -513,20 +756,60 @@ impl Encoder {
         );
         if !self.skipping_time_step {
             let time_idx = (self.time_table.len() - 1) as TimeTableIdx;
-            self.signals[id.index()].add_real_change(time_idx, value);
-            self.has_new_data = true;
+            if let Some(signal) = &mut self.signals[id.index()] {
+                signal.add_real_change(time_idx, value);
+                self.has_new_data = true;
+            }
+        }
+    }
+
+    pub fn string_change(&mut self, id: SignalRef, value: &str) {
+        assert!(
+            !self.time_table.is_empty(),
+            "We need a call to time_change first!"
+        );
+        if !self.skipping_time_step {
+            let time_idx = (self.time_table.len() - 1) as TimeTableIdx;
+            if let Some(signal) = &mut self.signals[id.index()] {
+                signal.add_str_change(time_idx, value);
+                self.has_new_data = true;
+            }
+        }
+    }
+
+    /// Ends the current block with the next call to `time_change`. Ending the block right away
+    /// could split the current time step over two blocks, which would repeat its time in the
+    /// combined time table.
+    pub(crate) fn end_block_after_time_step(&mut self) {
+        self.end_block_requested = true;
+    }
+
+    /// Hands all finished blocks over to `out`, which makes them available while we are still
+    /// encoding. Returns false if there were no finished blocks. Pass `include_current = true`
+    /// once no more changes will be recorded in order to also finish the current block.
+    pub(crate) fn publish(&mut self, out: &mut PublishedBlocks, include_current: bool) -> bool {
+        if include_current {
+            self.finish_block();
         }
+        if self.blocks.is_empty() {
+            return false;
+        }
+        for block in self.blocks.drain(..) {
+            out.time_table.extend_from_slice(&block.time_table);
+            out.blocks.push(Arc::new(block));
+        }
+        true
     }
 
-    pub fn finish(mut self) -> (SignalSource, TimeTable) {
+    pub fn finish(mut self) -> (Reader, TimeTable) {
         // ensure that we have no open blocks
         self.finish_block();
         // create a new reader with the blocks that we have
+        let time_table = Self::combine_time_tables(&self.blocks);
         let reader = Reader {
-            blocks: self.blocks,
+            blocks: self.blocks.into_iter().map(Arc::new).collect(),
         };
-        let time_table = Self::combine_time_tables(&reader.blocks);
-        (SignalSource::new(Box::new(reader)), time_table)
+        (reader, time_table)
     }
 
     fn combine_time_tables(blocks: &[Block]) -> TimeTable {
@@ -567,7 +850,8 @@ impl Encoder {
         let mut offsets = Vec::with_capacity(signal_count);
         let mut data: Vec<u8> = Vec::with_capacity(128);
         for signal in self.signals.iter_mut() {
-            if let Some((mut signal_data, is_compressed)) = signal.finish() {
+            if let Some((mut signal_data, is_compressed)) = signal.as_mut().and_then(|s| s.finish())
+            {
                 let offset = SignalDataOffset::new(data.len());
                 offsets.push(Some(offset));
                 let meta_data = is_compressed.encode();
@@ -589,13 +873,34 @@ impl Encoder {
             start_time,
             time_table,
             offsets,
-            data,
+            data: BlockData::Owned(data),
         };
         self.blocks.push(block);
         self.has_new_data = false;
     }
 }
 
+/// Blocks that were published by an `Encoder` while a waveform is still being recorded.
+#[derive(Default)]
+pub(crate) struct PublishedBlocks {
+    blocks: Vec<Arc<Block>>,
+    time_table: TimeTable,
+}
+
+impl PublishedBlocks {
+    /// Creates a reader that shares all blocks published so far.
+    pub(crate) fn snapshot(&self) -> (Reader, TimeTable) {
+        let reader = Reader {
+            blocks: self.blocks.clone(),
+        };
+        (reader, self.time_table.clone())
+    }
+
+    pub(crate) fn time_table(&self) -> &[Time] {
+        &self.time_table
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) struct SignalEncodingMetaData {
@@ -758,7 +1063,7 @@ impl SignalEncoder {
     }
 
     /// Adds a change from a VCD string.
-    fn add_vcd_change(&mut self, time_index: TimeTableIdx, value: &[u8]) {
+    fn add_vcd_change(&mut self, time_index: TimeTableIdx, value: &[u8]) -> Result<(), String> {
         let time_idx_delta = time_index - self.prev_time_idx;
         match self.tpe {
             SignalEncoding::BitVector(len) => {
@@ -773,12 +1078,12 @@ impl SignalEncoder {
                     };
                     let states =
                         try_write_1_bit_9_state(time_idx_delta, value_char, &mut self.data)
-                            .unwrap_or_else(|| {
-                                panic!(
+                            .ok_or_else(|| {
+                                format!(
                                     "Failed to parse nine-state value: {} for signal of size 1",
                                     String::from_utf8_lossy(value)
                                 )
-                            });
+                            })?;
                     self.max_states = States::join(self.max_states, states);
                 } else {
                     let value_bits: &[u8] = match value[0] {
@@ -794,64 +1099,67 @@ impl SignalEncoder {
                             _ => value_bits,
                         }
                     };
-                    let states = check_states(value_bits).unwrap_or_else(|| {
-                        panic!(
+                    let states = check_states(value_bits).ok_or_else(|| {
+                        format!(
                             "Bit-vector contains invalid character. Only 2-, 4-, and 9-state signals are supported: {}",
                             String::from_utf8_lossy(value)
                         )
-                    });
+                    })?;
                     self.max_states = States::join(self.max_states, states);
 
-                    // write time delta + num-states meta-data
-                    let time_and_meta = (time_idx_delta as u64) << 2 | (states as u64);
-                    leb128::write::unsigned(&mut self.data, time_and_meta).unwrap();
-                    // write actual data
+                    // check the value before we write anything
                     let bits = len.get() as usize;
                     let data_to_write = if value_bits.len() == bits {
                         Cow::Borrowed(value_bits)
                     } else {
                         let expanded = expand_special_vector_cases(value_bits, bits)
-                            .unwrap_or_else(|| {
-                                panic!(
+                            .filter(|expanded| expanded.len() == bits)
+                            .ok_or_else(|| {
+                                format!(
                                     "Failed to parse four-state value: {} for signal of size {}",
                                     String::from_utf8_lossy(value),
                                     bits
                                 )
-                            });
-                        assert_eq!(expanded.len(), bits);
+                            })?;
                         Cow::Owned(expanded)
                     };
+                    // write time delta + num-states meta-data
+                    let time_and_meta = (time_idx_delta as u64) << 2 | (states as u64);
+                    leb128::write::unsigned(&mut self.data, time_and_meta).unwrap();
+                    // write actual data
                     write_n_state(states, &data_to_write, &mut self.data, None);
                 }
             }
             SignalEncoding::String => {
-                assert!(
-                    matches!(value[0], b's' | b'S'),
-                    "expected a string, not {}",
-                    String::from_utf8_lossy(value)
-                );
+                if !matches!(value.first(), Some(b's' | b'S')) {
+                    return Err(format!(
+                        "expected a string, not {}",
+                        String::from_utf8_lossy(value)
+                    ));
+                }
                 // string: var-length time index + var-len length + content
                 leb128::write::unsigned(&mut self.data, time_idx_delta as u64).unwrap();
                 leb128::write::unsigned(&mut self.data, (value.len() - 1) as u64).unwrap();
                 self.data.extend_from_slice(&value[1..]);
             }
             SignalEncoding::Real => {
-                assert!(
-                    matches!(value[0], b'r' | b'R'),
-                    "expected a real, not {}",
-                    String::from_utf8_lossy(value)
-                );
                 // parse float
-                let float_value: Real = std::str::from_utf8(&value[1..])
-                    .unwrap()
-                    .parse::<Real>()
-                    .unwrap();
+                let float_value: Real = match value.split_first() {
+                    Some((b'r' | b'R', float)) => std::str::from_utf8(float)
+                        .ok()
+                        .and_then(|f| f.parse::<Real>().ok()),
+                    _ => None,
+                }
+                .ok_or_else(|| {
+                    format!("expected a real, not {}", String::from_utf8_lossy(value))
+                })?;
                 // write var-length time index + fixed little endian float bytes
                 leb128::write::unsigned(&mut self.data, time_idx_delta as u64).unwrap();
                 self.data.extend_from_slice(&float_value.to_le_bytes());
             }
         }
         self.prev_time_idx = time_index;
+        Ok(())
     }
 
     /// returns a compressed signal representation
@@ -918,7 +1226,7 @@ fn expand_special_vector_cases(value: &[u8], len: usize) -> Option<Vec<u8>> {
     }
 
     // zero, x or z extend
-    match value[0] {
+    match *value.first()? {
         b'1' | b'0' => {
             let mut extended = Vec::with_capacity(len);
             extended.resize(len - value.len(), b'0');
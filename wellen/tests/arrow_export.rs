@@ -0,0 +1,181 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// tests for the export to Arrow and Parquet
+#![cfg(feature = "arrow")]
+
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float64Type, UInt32Type, UInt64Type};
+use arrow_array::{Array, RecordBatch};
+use wellen::arrow::*;
+use wellen::simple::*;
+use wellen::*;
+
+const VCD: &str = r#"
+$timescale 10ns $end
+$scope module top $end
+$var wire 8 ! data $end
+$var wire 1 " valid $end
+$var wire 70 # wide $end
+$var real 1 $ temp $end
+$var wire 4 % late $end
+$upscope $end
+$enddefinitions $end
+#0
+b101 !
+x"
+b1 #
+r1.5 $
+#10
+b11111111 !
+1"
+b11 %
+#20
+b1000000000000000000000000000000000000000000000000000000000000000000000 #
+r2.5 $
+"#;
+
+fn load() -> (Waveform, Vec<VarRef>) {
+    let wave = read_from_reader(std::io::Cursor::new(VCD.as_bytes().to_vec())).unwrap();
+    let vars = (0..5).map(|ii| VarRef::from_index(ii).unwrap()).collect();
+    (wave, vars)
+}
+
+fn u64_column(batch: &RecordBatch, name: &str) -> Vec<Option<u64>> {
+    let column = batch.column_by_name(name).unwrap();
+    column.as_primitive::<UInt64Type>().iter().collect()
+}
+
+fn string_column(batch: &RecordBatch, name: &str) -> Vec<Option<String>> {
+    let column = batch.column_by_name(name).unwrap();
+    column
+        .as_string::<i32>()
+        .iter()
+        .map(|v| v.map(|v| v.to_string()))
+        .collect()
+}
+
+#[test]
+fn export_sampled() {
+    let (mut wave, vars) = load();
+    let batch = to_record_batch(&mut wave, &vars, Layout::Sampled).unwrap();
+    assert_eq!(
+        batch.schema().metadata()[TIMESCALE_METADATA],
+        "10e-9".to_string()
+    );
+    assert_eq!(batch.num_rows(), 3);
+    assert_eq!(u64_column(&batch, "time"), [Some(0), Some(10), Some(20)]);
+    assert_eq!(
+        u64_column(&batch, "top.data"),
+        [Some(5), Some(255), Some(255)]
+    );
+    // 4-state values fall back to strings
+    assert_eq!(
+        string_column(&batch, "top.valid"),
+        [
+            Some("x".to_string()),
+            Some("1".to_string()),
+            Some("1".to_string())
+        ]
+    );
+    // values wider than 64 bits are stored as big-endian bytes
+    let wide = batch.column_by_name("top.wide").unwrap();
+    let wide = wide.as_fixed_size_binary();
+    assert_eq!(wide.value_length(), 9);
+    assert_eq!(wide.value(1), [0, 0, 0, 0, 0, 0, 0, 0, 1]);
+    assert_eq!(wide.value(2), [0x20, 0, 0, 0, 0, 0, 0, 0, 0]);
+    let temp = batch.column_by_name("top.temp").unwrap();
+    let temp: Vec<_> = temp.as_primitive::<Float64Type>().iter().collect();
+    assert_eq!(temp, [Some(1.5), Some(1.5), Some(2.5)]);
+    // no value before the first change
+    assert_eq!(u64_column(&batch, "top.late"), [None, Some(3), Some(3)]);
+
+    // signals are unloaded after the export
+    assert_eq!(wave.signal_memory_usage(), 0);
+}
+
+#[test]
+fn export_changes() {
+    let (mut wave, vars) = load();
+    // a single bit-vector type results in an integer column
+    let batch = to_record_batch(&mut wave, &[vars[0], vars[4]], Layout::Changes).unwrap();
+    assert_eq!(u64_column(&batch, "time"), [Some(0), Some(10), Some(10)]);
+    assert_eq!(u64_column(&batch, "value"), [Some(5), Some(255), Some(3)]);
+    let signals = batch.column_by_name("signal").unwrap();
+    let signals = signals.as_dictionary::<UInt32Type>();
+    let names = signals.values().as_string::<i32>();
+    let names: Vec<_> = signals
+        .keys()
+        .iter()
+        .map(|k| names.value(k.unwrap() as usize))
+        .collect();
+    assert_eq!(names, ["top.data", "top.data", "top.late"]);
+
+    // different types result in a string column
+    let batch = to_record_batch(&mut wave, &vars, Layout::Changes).unwrap();
+    assert_eq!(batch.num_rows(), 9);
+    let values = string_column(&batch, "value");
+    assert_eq!(values[0].as_deref(), Some("00000101"));
+    assert_eq!(values[1].as_deref(), Some("x"));
+    assert_eq!(values[3].as_deref(), Some("1.5"));
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn export_parquet() {
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    let (mut wave, vars) = load();
+    let expected = to_record_batch(&mut wave, &vars, Layout::Sampled).unwrap();
+    let filename = std::env::temp_dir().join(format!("wellen-{}.parquet", std::process::id()));
+    let out = std::fs::File::create(&filename).unwrap();
+    write_parquet(&mut wave, &vars, Layout::Sampled, out).unwrap();
+
+    let file = std::fs::File::open(&filename).unwrap();
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+        .unwrap()
+        .build()
+        .unwrap();
+    let batches: Vec<_> = reader.map(|b| b.unwrap()).collect();
+    std::fs::remove_file(&filename).unwrap();
+    assert_eq!(batches.len(), 1);
+    assert_eq!(batches[0].columns(), expected.columns());
+}
+
+#[test]
+fn export_matches_value_at_time() {
+    let mut wave = read("inputs/icarus/CPU.vcd").unwrap();
+    let vars: Vec<_> = (0..wave.hierarchy().iter_vars().len())
+        .map(|ii| VarRef::from_index(ii).unwrap())
+        .collect();
+    let batch = to_record_batch(&mut wave, &vars, Layout::Sampled).unwrap();
+    assert_eq!(batch.num_columns(), vars.len() + 1);
+    wave.load_signals(
+        &vars
+            .iter()
+            .map(|v| wave.hierarchy()[*v].signal_ref())
+            .collect::<Vec<_>>(),
+    )
+    .unwrap();
+    for (ii, var) in vars.iter().enumerate() {
+        let var = &wave.hierarchy()[*var];
+        let signal = wave.get_signal(var.signal_ref()).unwrap();
+        let column = batch.column(ii + 1);
+        for (idx, time) in wave.time_table().iter().enumerate() {
+            let expected = signal.value_at_time(wave.time_table(), *time);
+            assert_eq!(column.is_null(idx), expected.is_none());
+            let Some(expected) = expected else { continue };
+            match column.data_type() {
+                arrow_schema::DataType::UInt64 => {
+                    let actual = column.as_primitive::<UInt64Type>().value(idx);
+                    let expected = u64::from_str_radix(&expected.to_string(), 2).unwrap();
+                    assert_eq!(actual, expected);
+                }
+                arrow_schema::DataType::Utf8 => {
+                    let actual = column.as_string::<i32>().value(idx);
+                    assert_eq!(actual, expected.to_string());
+                }
+                other => panic!("unexpected column type {other:?}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,215 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// test that waveforms loaded from a cache file are the same as the original
+
+use std::path::{Path, PathBuf};
+use wellen::*;
+
+struct Loaded {
+    hierarchy: Hierarchy,
+    body_len: u64,
+    time_table: TimeTable,
+    /// full variable names and all changes of the corresponding signal
+    vars: Vec<(String, Vec<(TimeTableIdx, String)>)>,
+}
+
+fn load(filename: &Path, use_cache: bool) -> Loaded {
+    let options = LoadOptions {
+        use_cache,
+        ..Default::default()
+    };
+    load_with_options(filename, &options)
+}
+
+fn load_with_options(filename: &Path, options: &LoadOptions) -> Loaded {
+    let header = viewers::read_header_from_file(filename, options).expect("failed to load header");
+    let body =
+        viewers::read_body(header.body, &header.hierarchy, None).expect("failed to load body");
+    let hierarchy = header.hierarchy;
+    let mut source = body.source;
+    let ids: Vec<_> = hierarchy.iter_vars().map(|v| v.signal_ref()).collect();
+    let signals = source.load_signals(&ids, &hierarchy, true).unwrap();
+    let vars = hierarchy
+        .iter_vars()
+        .map(|var| {
+            let (_, signal) = signals
+                .iter()
+                .find(|(id, _)| *id == var.signal_ref())
+                .unwrap();
+            let changes = signal
+                .iter_changes()
+                .map(|(t, v)| (t, v.to_string()))
+                .collect();
+            (var.full_name(&hierarchy), changes)
+        })
+        .collect();
+    Loaded {
+        hierarchy,
+        body_len: header.body_len,
+        time_table: body.time_table,
+        vars,
+    }
+}
+
+fn assert_same(expected: &Loaded, actual: &Loaded) {
+    assert_eq!(expected.time_table, actual.time_table);
+    assert_eq!(
+        expected.hierarchy.file_format(),
+        actual.hierarchy.file_format()
+    );
+    assert_eq!(expected.hierarchy.timescale(), actual.hierarchy.timescale());
+    assert_eq!(expected.hierarchy.date(), actual.hierarchy.date());
+    assert_eq!(expected.hierarchy.version(), actual.hierarchy.version());
+    assert_eq!(
+        expected.hierarchy.num_unique_signals(),
+        actual.hierarchy.num_unique_signals()
+    );
+    let scopes = |h: &Hierarchy| -> Vec<_> {
+        h.iter_scopes()
+            .map(|s| {
+                (
+                    s.full_name(h),
+                    s.scope_type(),
+                    s.component(h).map(String::from),
+                )
+            })
+            .collect()
+    };
+    assert_eq!(scopes(&expected.hierarchy), scopes(&actual.hierarchy));
+    let var_info = |h: &Hierarchy| -> Vec<_> {
+        h.iter_vars()
+            .map(|v| {
+                (
+                    v.var_type(),
+                    v.direction(),
+                    v.signal_encoding(),
+                    v.index(),
+                    v.vhdl_type_name(h).map(String::from),
+                    v.enum_type(h).map(|(n, m)| {
+                        let m: Vec<_> = m.iter().map(|(a, b)| format!("{a}={b}")).collect();
+                        (n.to_string(), m)
+                    }),
+                )
+            })
+            .collect()
+    };
+    assert_eq!(var_info(&expected.hierarchy), var_info(&actual.hierarchy));
+    assert_eq!(expected.vars, actual.vars);
+}
+
+/// Copies the input into a fresh directory, so that we can place a cache file next to it.
+fn copy_to_tmp(filename: &str, test_name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "wellen-cache-test-{}-{test_name}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let copy = dir.join(Path::new(filename).file_name().unwrap());
+    std::fs::copy(filename, &copy).unwrap();
+    copy
+}
+
+fn run_cache_test(filename: &str, test_name: &str) {
+    let source = copy_to_tmp(filename, test_name);
+    let mut cache_file = source.clone().into_os_string();
+    cache_file.push(".wellen");
+    let cache_file = PathBuf::from(cache_file);
+
+    let expected = load(&source, false);
+    assert!(
+        !cache_file.exists(),
+        "cache should only be written on demand"
+    );
+
+    // first load parses the file and creates the cache
+    let first = load(&source, true);
+    assert!(first.body_len > 0);
+    assert!(cache_file.exists());
+    assert_same(&expected, &first);
+
+    // second load is served from the cache
+    let second = load(&source, true);
+    assert_eq!(second.body_len, 0);
+    assert_same(&expected, &second);
+
+    // a corrupted cache file is ignored and replaced
+    let cache_len = std::fs::metadata(&cache_file).unwrap().len();
+    let cache_data = std::fs::read(&cache_file).unwrap();
+    std::fs::write(&cache_file, &cache_data[..cache_len as usize / 2]).unwrap();
+    let third = load(&source, true);
+    assert!(third.body_len > 0);
+    assert_same(&expected, &third);
+    assert_eq!(std::fs::metadata(&cache_file).unwrap().len(), cache_len);
+
+    std::fs::remove_dir_all(source.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn cache_vcd() {
+    run_cache_test("inputs/icarus/CPU.vcd", "cpu_vcd");
+}
+
+#[test]
+fn cache_ghw() {
+    run_cache_test("inputs/ghdl/tb_recv.ghw", "tb_recv_ghw");
+}
+
+#[test]
+fn cache_ghw_with_enums() {
+    run_cache_test("inputs/ghdl/oscar/test.ghw", "oscar_ghw");
+}
+
+#[test]
+fn cache_is_invalidated_by_source_changes() {
+    let source = copy_to_tmp("inputs/icarus/CPU.vcd", "invalidate");
+    let _ = load(&source, true);
+    assert_eq!(load(&source, true).body_len, 0);
+
+    // add a comment to the end of the file, which changes its size
+    let mut content = std::fs::read(&source).unwrap();
+    content.extend_from_slice(b"\n$comment cache test $end\n");
+    std::fs::write(&source, &content).unwrap();
+    let expected = load(&source, false);
+    let changed = load(&source, true);
+    assert!(changed.body_len > 0, "outdated cache should not be used");
+    assert_same(&expected, &changed);
+    assert_eq!(load(&source, true).body_len, 0);
+
+    std::fs::remove_dir_all(source.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn cache_depends_on_scope_filter() {
+    let source = copy_to_tmp("inputs/ghdl/wellen_issue_12.ghw", "scope_filter");
+    // the alu contains slices of signals from the register file, which is skipped
+    let filtered = LoadOptions {
+        use_cache: true,
+        scope_filter: Some(ScopeFilter {
+            include_scopes: vec!["test_rom_tb.soc_inst.core_inst.alu_inst".to_string()],
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let expected = load_with_options(
+        &source,
+        &LoadOptions {
+            use_cache: false,
+            ..filtered.clone()
+        },
+    );
+    let first = load_with_options(&source, &filtered);
+    assert!(first.body_len > 0);
+    assert_same(&expected, &first);
+    let second = load_with_options(&source, &filtered);
+    assert_eq!(second.body_len, 0);
+    assert_same(&expected, &second);
+
+    // a cache created with a different filter cannot be used
+    let complete = load(&source, true);
+    assert!(complete.body_len > 0);
+    assert!(complete.vars.len() > expected.vars.len());
+
+    std::fs::remove_dir_all(source.parent().unwrap()).unwrap();
+}
@@ -0,0 +1,209 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// tests for checking temporal properties
+
+use wellen::checker::*;
+use wellen::expr::ExprError;
+use wellen::simple::*;
+
+/// Creates a waveform with a clock `top.clk` that rises at `10 * cycle + 5` and
+/// signals that change at `10 * cycle`. Values are listed per cycle, separated by spaces.
+fn waveform(signals: &[(&str, &str)]) -> Waveform {
+    let mut vcd =
+        "$timescale 1ns $end\n$scope module top $end\n$var wire 1 ! clk $end\n".to_string();
+    let values: Vec<Vec<&str>> = signals
+        .iter()
+        .map(|(_, v)| v.split_whitespace().collect())
+        .collect();
+    for (ii, (name, _)) in signals.iter().enumerate() {
+        vcd += &format!("$var wire {} s{ii} {name} $end\n", values[ii][0].len());
+    }
+    vcd += "$upscope $end\n$enddefinitions $end\n";
+    let cycles = values.iter().map(|v| v.len()).max().unwrap();
+    for cycle in 0..cycles {
+        vcd += &format!("#{}\n0!\n", cycle * 10);
+        for (ii, v) in values.iter().enumerate() {
+            match v.get(cycle) {
+                Some(v) if v.len() == 1 => vcd += &format!("{v}s{ii}\n"),
+                Some(v) => vcd += &format!("b{v} s{ii}\n"),
+                None => {}
+            }
+        }
+        vcd += &format!("#{}\n1!\n", cycle * 10 + 5);
+    }
+    read_from_reader(std::io::Cursor::new(vcd.into_bytes())).unwrap()
+}
+
+fn check(wave: &mut Waveform, property: &str) -> CheckReport {
+    let property = Property::parse(property, wave.hierarchy()).unwrap();
+    let report = check_property(wave, &property).unwrap();
+    assert_eq!(wave.signal_memory_usage(), 0);
+    report
+}
+
+fn failure(start: u64, end: u64) -> Failure {
+    Failure { start, end }
+}
+
+#[test]
+fn request_acknowledge() {
+    let mut wave = waveform(&[
+        ("req", "0 1 0 0 0 1 0 0 1 0"),
+        ("ack", "0 0 0 1 0 0 0 0 0 0"),
+    ]);
+    let report = check(&mut wave, "@(posedge top.clk) top.req |-> ##[1:3] top.ack");
+    // the last request could still be acknowledged after the end of the waveform
+    assert_eq!(
+        report,
+        CheckReport {
+            attempts: 10,
+            passed: 1,
+            vacuous: 7,
+            disabled: 0,
+            unfinished: 1,
+            failures: vec![failure(55, 85)],
+        }
+    );
+}
+
+#[test]
+fn mutual_exclusion() {
+    let mut wave = waveform(&[("grant0", "1 1 0 0"), ("grant1", "0 1 1 0")]);
+    let report = check(&mut wave, "@(posedge top.clk) !(top.grant0 && top.grant1)");
+    assert_eq!(report.passed, 3);
+    assert_eq!(report.failures, [failure(15, 15)]);
+    // values are sampled before the falling edge that coincides with the next change
+    let report = check(&mut wave, "@(negedge top.clk) !(top.grant0 && top.grant1)");
+    assert_eq!(report.failures, [failure(20, 20)]);
+}
+
+#[test]
+fn disable_iff() {
+    let mut wave = waveform(&[
+        ("req", "0 1 0 1 0 0 0"),
+        ("ack", "0 0 0 0 0 0 0"),
+        ("rst", "0 0 0 0 1 0 0"),
+    ]);
+    let report = check(
+        &mut wave,
+        "@(posedge top.clk) disable iff (top.rst) top.req |=> top.ack",
+    );
+    // the second request fails in the cycle of the reset
+    assert_eq!(report.failures, [failure(15, 25)]);
+    assert_eq!(report.disabled, 2);
+    assert_eq!(report.vacuous, 4);
+}
+
+#[test]
+fn sampled_value_functions() {
+    let mut wave = waveform(&[
+        ("a", "0 1 1 0 1 0 0"),
+        ("b", "0 0 1 1 0 1 0"),
+        ("data", "0000 0011 0011 0111 0111 0111 0000"),
+        ("en", "0 0 1 1 1 1 0"),
+    ]);
+    let report = check(
+        &mut wave,
+        "@(posedge top.clk) $rose(top.a) |=> $fell(top.a)",
+    );
+    assert_eq!(report.failures, [failure(15, 25)]);
+    assert_eq!(report.passed, 1);
+
+    let report = check(&mut wave, "@(posedge top.clk) ##1 $past(top.a) == top.b");
+    assert_eq!((report.passed, report.unfinished), (6, 1));
+    assert!(report.failures.is_empty());
+    // before the first cycle, the past value is unknown
+    let report = check(&mut wave, "@(posedge top.clk) $past(top.a, 2) == 0");
+    let starts: Vec<_> = report.failures.iter().map(|f| f.start).collect();
+    assert_eq!(starts, [5, 15, 35, 45, 65]);
+
+    let report = check(
+        &mut wave,
+        "@(posedge top.clk) top.en |-> $stable(top.data[3:1])",
+    );
+    assert_eq!(report.failures, [failure(35, 35)]);
+    let report = check(
+        &mut wave,
+        "@(posedge top.clk) top.en |-> $changed(top.data)",
+    );
+    assert_eq!(
+        report.failures,
+        [failure(25, 25), failure(45, 45), failure(55, 55)]
+    );
+}
+
+#[test]
+fn sequences() {
+    let mut wave = waveform(&[
+        ("a", "1 0 0 1 0 0 1 0"),
+        ("b", "0 1 0 0 1 0 0 0"),
+        ("c", "0 0 0 0 0 0 1 0"),
+    ]);
+    // the first match of the antecedent is only followed by `c` after five cycles
+    let report = check(
+        &mut wave,
+        "@(posedge top.clk) (top.a ##1 top.b) |-> ##[1:2] top.c",
+    );
+    assert_eq!(report.failures, [failure(5, 35)]);
+    assert_eq!(report.passed, 1);
+    let report = check(
+        &mut wave,
+        "@(posedge top.clk) (top.a ##1 top.b) |-> ##[1:$] top.c",
+    );
+    assert_eq!(report.passed, 2);
+    assert!(report.failures.is_empty());
+    // a leading delay in a group adds up with the delay in front of it
+    let report = check(
+        &mut wave,
+        "@(posedge top.clk) top.a |-> ##1 (##2 top.a ##[0:1] top.b)",
+    );
+    assert_eq!(
+        (report.passed, report.vacuous, report.unfinished),
+        (1, 5, 1)
+    );
+    assert_eq!(report.failures, [failure(35, 75)]);
+}
+
+#[test]
+fn parse_errors() {
+    let wave = waveform(&[("a", "0 1")]);
+    let h = wave.hierarchy();
+    let parse = |property: &str| Property::parse(property, h).unwrap_err();
+    assert!(matches!(parse("top.a |-> top.a"), CheckError::MissingClock));
+    assert!(matches!(
+        parse("@(posedge top.clk) top.a ##[3:1] top.a"),
+        CheckError::InvalidDelay(3, 1)
+    ));
+    assert!(matches!(
+        parse("@(posedge top.clk) top.b"),
+        CheckError::Parse(ExprError::UnknownSignal(name)) if name == "top.b"
+    ));
+    assert!(matches!(
+        parse("@(posedge top.clk) $onehot(top.a)"),
+        CheckError::Parse(ExprError::UnknownFunction(name)) if name == "$onehot"
+    ));
+    assert!(matches!(
+        parse("@(posedge top.clk) disable (top.a) top.a"),
+        CheckError::Parse(ExprError::UnexpectedToken(..))
+    ));
+    assert!(matches!(
+        parse("@(posedge top.clk) top.a |-> top.a top.a"),
+        CheckError::Parse(ExprError::UnexpectedToken(token, _, "end of property")) if token == "top"
+    ));
+    assert_eq!(
+        parse("@(posedge top.clk) top.a ##").to_string(),
+        "[expr] unexpected end of expression, expected a bit index"
+    );
+}
+
+#[test]
+fn check_reports_missing_signals() {
+    let mut wave = waveform(&[("a", "0 1")]);
+    let property = Property::parse("@(posedge top.clk) top.a", wave.hierarchy()).unwrap();
+    let ids = property.signals();
+    wave.load_signals(&ids).unwrap();
+    let clock = wave.get_signal(ids[0]).unwrap();
+    let err = property.check(wave.time_table(), &[clock]).unwrap_err();
+    assert!(matches!(err, CheckError::MissingSignal(id) if id == ids[1]));
+}
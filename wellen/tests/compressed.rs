@@ -16,7 +16,7 @@ fn test_compression(wave: &mut Waveform) {
         .map(|v| (v.signal_ref(), v.full_name(wave.hierarchy())))
         .collect();
     for (idx, signal_name) in all_signals {
-        wave.load_signals(&[idx]);
+        wave.load_signals(&[idx]).unwrap();
         let signal = wave.get_signal(idx).expect("signal should be loaded!");
         let compressed = CompressedSignal::compress(signal);
         let uncompressed: Signal = compressed.uncompress();
@@ -0,0 +1,231 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// tests for the toggle coverage report
+
+use wellen::coverage::*;
+use wellen::simple::*;
+
+const VCD: &str = r#"
+$timescale 1ns $end
+$scope module tb $end
+$var wire 2 ! a $end
+$var wire 1 " b $end
+$scope module dut $end
+$var wire 2 ! a_alias $end
+$var wire 1 # c $end
+$var real 1 $ r $end
+$upscope $end
+$upscope $end
+$enddefinitions $end
+#0
+b00 !
+0"
+x#
+r0 $
+#10
+b01 !
+1"
+0#
+#20
+b11 !
+x"
+1#
+r1.5 $
+#30
+b10 !
+0"
+0#
+#40
+b00 !
+1"
+"#;
+
+fn read_vcd(content: &str) -> Waveform {
+    read_from_reader(std::io::Cursor::new(content.as_bytes().to_vec())).unwrap()
+}
+
+fn coverage(wave: &mut Waveform, options: CoverageOptions) -> CoverageReport {
+    toggle_coverage(wave, None, &options).unwrap()
+}
+
+fn toggles(wave: &Waveform, report: &CoverageReport, path: &[&str], name: &str) -> String {
+    let var = wave.hierarchy().lookup_var(path, &name).unwrap();
+    let signal = wave.hierarchy()[var].signal_ref();
+    report.toggles(signal).unwrap().to_bit_string()
+}
+
+#[test]
+fn strict_policy() {
+    let mut wave = read_vcd(VCD);
+    let report = coverage(&mut wave, CoverageOptions::default());
+    // the alias of `a` is only counted once and the real variable is left out
+    assert_eq!(
+        report.totals,
+        Totals {
+            bits: 4,
+            rose: 4,
+            fell: 3,
+            toggled: 3
+        }
+    );
+    assert_eq!(report.vars.len(), 4);
+    assert_eq!(toggles(&wave, &report, &["tb"], "a"), "11");
+    // `1 -> x -> 0` is not a fall
+    assert_eq!(toggles(&wave, &report, &["tb"], "b"), "r");
+    assert_eq!(toggles(&wave, &report, &["tb", "dut"], "c"), "1");
+
+    let scopes: Vec<_> = report
+        .scopes
+        .iter()
+        .map(|s| {
+            (
+                wave.hierarchy()[s.scope].name(wave.hierarchy()),
+                s.depth,
+                s.totals,
+            )
+        })
+        .collect();
+    assert_eq!(
+        scopes,
+        [
+            ("tb", 0, report.totals),
+            (
+                "dut",
+                1,
+                Totals {
+                    bits: 3,
+                    rose: 3,
+                    fell: 3,
+                    toggled: 3
+                }
+            )
+        ]
+    );
+    // all signals are unloaded again
+    assert_eq!(wave.signal_memory_usage(), 0);
+}
+
+#[test]
+fn xz_policies() {
+    let mut wave = read_vcd(VCD);
+    for (policy, b, toggled) in [
+        (XzPolicy::Strict, "r", 3),
+        (XzPolicy::Bridge, "1", 4),
+        (XzPolicy::Optimistic, "1", 4),
+    ] {
+        let options = CoverageOptions {
+            xz_policy: policy,
+            ..Default::default()
+        };
+        let report = coverage(&mut wave, options);
+        assert_eq!(toggles(&wave, &report, &["tb"], "b"), b, "{policy:?}");
+        assert_eq!(report.totals.toggled, toggled, "{policy:?}");
+    }
+}
+
+#[test]
+fn time_window() {
+    let mut wave = read_vcd(VCD);
+    let options = CoverageOptions {
+        time_window: Some((20, 30)),
+        ..Default::default()
+    };
+    let report = coverage(&mut wave, options);
+    // bit 0 of `a` only falls in the window and bit 1 only rises
+    assert_eq!(toggles(&wave, &report, &["tb"], "a"), "rf");
+    assert_eq!(toggles(&wave, &report, &["tb"], "b"), "0");
+    assert_eq!(toggles(&wave, &report, &["tb", "dut"], "c"), "1");
+    assert_eq!(report.totals.toggled, 1);
+}
+
+#[test]
+fn sub_scope() {
+    let mut wave = read_vcd(VCD);
+    let dut = wave.hierarchy().lookup_scope(&["tb", "dut"]).unwrap();
+    let report = toggle_coverage(&mut wave, Some(dut), &CoverageOptions::default()).unwrap();
+    assert_eq!(report.vars.len(), 2);
+    assert_eq!(report.scopes.len(), 1);
+    assert_eq!(report.scopes[0].totals, report.totals);
+    assert_eq!(report.totals.bits, 3);
+}
+
+#[test]
+fn json_and_summary() {
+    let mut wave = read_vcd(VCD);
+    let report = coverage(&mut wave, CoverageOptions::default());
+    let mut json = vec![];
+    report.write_json(wave.hierarchy(), &mut json).unwrap();
+    let json = String::from_utf8(json).unwrap();
+    assert!(json.contains("\"time_window\": null,"), "{json}");
+    assert!(json.contains("\"xz_policy\": \"strict\","), "{json}");
+    assert!(
+        json.contains(
+            "{\"name\": \"tb.dut\", \"totals\": {\"bits\": 3, \"rose\": 3, \"fell\": 3, \"toggled\": 3}}"
+        ),
+        "{json}"
+    );
+    assert!(
+        json.contains("{\"name\": \"tb.b\", \"width\": 1, \"toggled\": 0, \"bits\": \"r\"},"),
+        "{json}"
+    );
+
+    let mut summary = vec![];
+    report
+        .write_summary(wave.hierarchy(), None, &mut summary)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(summary).unwrap(),
+        "toggle coverage: 3/4 bits toggled (75.0%), 4 rose, 3 fell
+  tb: 3/4 bits toggled (75.0%), 4 rose, 3 fell
+    dut: 3/3 bits toggled (100.0%), 3 rose, 3 fell
+"
+    );
+    let mut summary = vec![];
+    report
+        .write_summary(wave.hierarchy(), Some(0), &mut summary)
+        .unwrap();
+    assert_eq!(String::from_utf8(summary).unwrap().lines().count(), 2);
+}
+
+/// Compares against a direct computation on bit strings.
+#[test]
+fn matches_bit_strings() {
+    let mut wave = read("inputs/icarus/CPU.vcd").unwrap();
+    let report = coverage(&mut wave, CoverageOptions::default());
+    let vars: Vec<_> = report.vars.clone();
+    let ids: Vec<_> = vars.iter().map(|v| v.signal).collect();
+    wave.load_signals(&ids).unwrap();
+    let mut totals = Totals::default();
+    let mut seen = std::collections::HashSet::new();
+    for var in vars.iter() {
+        let signal = wave.get_signal(var.signal).unwrap();
+        let width = var.width as usize;
+        let mut rose = vec![false; width];
+        let mut fell = vec![false; width];
+        let mut prev: Option<Vec<char>> = None;
+        for (_, value) in signal.iter_changes() {
+            let bits: Vec<char> = value.to_bit_string().unwrap().chars().rev().collect();
+            if let Some(prev) = prev {
+                for bit in 0..width {
+                    rose[bit] |= prev[bit] == '0' && bits[bit] == '1';
+                    fell[bit] |= prev[bit] == '1' && bits[bit] == '0';
+                }
+            }
+            prev = Some(bits);
+        }
+        let toggles = report.toggles(var.signal).unwrap();
+        for bit in 0..width {
+            assert_eq!(toggles.rose(bit as u32), rose[bit]);
+            assert_eq!(toggles.fell(bit as u32), fell[bit]);
+        }
+        if seen.insert(var.signal) {
+            totals.bits += width as u64;
+            totals.rose += rose.iter().filter(|b| **b).count() as u64;
+            totals.fell += fell.iter().filter(|b| **b).count() as u64;
+            totals.toggled += (0..width).filter(|b| rose[*b] && fell[*b]).count() as u64;
+        }
+    }
+    assert_eq!(report.totals, totals);
+    assert!(totals.toggled > 0);
+}
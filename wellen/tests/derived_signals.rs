@@ -0,0 +1,150 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// test signals derived from expressions against a direct computation on bit strings
+
+use wellen::*;
+
+const FILENAME: &str = "inputs/icarus/CPU.vcd";
+
+fn signal_ref(wave: &simple::Waveform, path: &str) -> SignalRef {
+    let parts: Vec<_> = path.split('.').collect();
+    let (name, scopes) = parts.split_last().unwrap();
+    let var = wave.hierarchy().lookup_var(scopes, name).unwrap();
+    wave.hierarchy()[var].signal_ref()
+}
+
+/// Value at a time table index as bit string. Undefined values are reported as all `x`.
+fn value_at(wave: &simple::Waveform, id: SignalRef, width: usize, idx: TimeTableIdx) -> String {
+    let signal = wave.get_signal(id).unwrap();
+    match signal.get_offset(idx) {
+        Some(offset) => signal
+            .get_value_at(&offset, offset.elements - 1)
+            .to_bit_string()
+            .unwrap(),
+        None => "x".repeat(width),
+    }
+}
+
+fn to_u64(value: &str) -> Option<u64> {
+    u64::from_str_radix(value, 2).ok()
+}
+
+fn run_derived_test(
+    expression: &str,
+    inputs: &[(&str, usize)],
+    expected: impl Fn(&[String]) -> String,
+) {
+    let mut wave = simple::read(FILENAME).expect("failed to load file");
+    let derived = wave.add_derived_signal(expression).unwrap();
+    let ids: Vec<_> = inputs.iter().map(|(p, _)| signal_ref(&wave, p)).collect();
+    wave.load_signals(&[derived]).unwrap();
+    wave.load_signals(&ids).unwrap();
+    let derived_signal = wave.get_signal(derived).unwrap();
+    assert_eq!(derived_signal.signal_ref(), derived);
+    let width = derived_signal
+        .iter_changes()
+        .next()
+        .unwrap()
+        .1
+        .bits()
+        .unwrap() as usize;
+
+    for idx in 0..wave.time_table().len() as TimeTableIdx {
+        let values: Vec<_> = ids
+            .iter()
+            .zip(inputs.iter())
+            .map(|(id, (_, w))| value_at(&wave, *id, *w, idx))
+            .collect();
+        assert_eq!(
+            value_at(&wave, derived, width, idx),
+            expected(&values),
+            "{expression} @ {idx} with inputs {values:?}"
+        );
+    }
+}
+
+#[test]
+fn derived_and() {
+    run_derived_test(
+        "ID_EX.ID_EX_MemRead & ID_EX.ID_EX_MemWrite",
+        &[("ID_EX.ID_EX_MemRead", 1), ("ID_EX.ID_EX_MemWrite", 1)],
+        |v| match (v[0].as_str(), v[1].as_str()) {
+            ("0", _) | (_, "0") => "0".to_string(),
+            ("1", "1") => "1".to_string(),
+            _ => "x".to_string(),
+        },
+    );
+}
+
+#[test]
+fn derived_slice_and_concat() {
+    run_derived_test(
+        "{ID_EX.ID_EX_RD, ID_EX.ID_EX_IMM[15:8]}",
+        &[("ID_EX.ID_EX_RD", 5), ("ID_EX.ID_EX_IMM", 32)],
+        |v| format!("{}{}", v[0], &v[1][16..24]),
+    );
+}
+
+#[test]
+fn derived_comparison() {
+    run_derived_test(
+        "ID_EX.ID_EX_RS1 == ID_EX.ID_EX_RS2",
+        &[("ID_EX.ID_EX_RS1", 5), ("ID_EX.ID_EX_RS2", 5)],
+        |v| match (to_u64(&v[0]), to_u64(&v[1])) {
+            (Some(a), Some(b)) => if a == b { "1" } else { "0" }.to_string(),
+            _ => "x".to_string(),
+        },
+    );
+}
+
+#[test]
+fn derived_arithmetic_and_ternary() {
+    run_derived_test(
+        "ID_EX.ID_EX_MemWrite ? ID_EX.ID_EX_RS1data + ID_EX.ID_EX_IMM : 32'd0",
+        &[
+            ("ID_EX.ID_EX_MemWrite", 1),
+            ("ID_EX.ID_EX_RS1data", 32),
+            ("ID_EX.ID_EX_IMM", 32),
+        ],
+        |v| {
+            let sum = match (to_u64(&v[1]), to_u64(&v[2])) {
+                (Some(a), Some(b)) => format!("{:032b}", (a + b) & 0xffff_ffff),
+                _ => "x".repeat(32),
+            };
+            match v[0].as_str() {
+                "1" => sum,
+                "0" => "0".repeat(32),
+                _ => sum
+                    .chars()
+                    .map(|c| if c == '0' { '0' } else { 'x' })
+                    .collect(),
+            }
+        },
+    );
+}
+
+#[test]
+fn derived_unknown_signal() {
+    let mut wave = simple::read(FILENAME).expect("failed to load file");
+    assert!(wave.add_derived_signal("ID_EX.does_not_exist + 1").is_err());
+    assert!(wave.add_derived_signal("ID_EX.ID_EX_RD[5:0]").is_err());
+}
+
+#[test]
+fn evaluate_reports_missing_inputs() {
+    let mut wave = simple::read(FILENAME).expect("failed to load file");
+    let expr =
+        expr::Expr::parse("ID_EX.ID_EX_RS1 == ID_EX.ID_EX_RS2[4:0]", wave.hierarchy()).unwrap();
+    let rs1 = signal_ref(&wave, "ID_EX.ID_EX_RS1");
+    let rs2 = signal_ref(&wave, "ID_EX.ID_EX_RS2");
+    wave.load_signals(&[rs1, rs2]).unwrap();
+    let id = SignalRef::from_index(wave.hierarchy().num_unique_signals()).unwrap();
+    let only_rs1 = [wave.get_signal(rs1).unwrap()];
+    assert_eq!(
+        expr.evaluate(id, &only_rs1).unwrap_err(),
+        expr::ExprError::MissingSignal(rs2)
+    );
+    let both = [only_rs1[0], wave.get_signal(rs2).unwrap()];
+    assert!(expr.evaluate(id, &both).is_ok());
+}
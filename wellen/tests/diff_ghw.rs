@@ -126,7 +126,7 @@ fn get_all_signals(w: &Waveform) -> Vec<SignalRef> {
 
 fn load_all_signals(our: &mut Waveform) {
     let all_signals = get_all_signals(our);
-    our.load_signals(&all_signals);
+    our.load_signals(&all_signals).unwrap();
 }
 
 fn diff_signals(ghw: &mut Waveform, fst: &mut Waveform, time_factor: u64) {
@@ -234,3 +234,34 @@ fn ensure_minimal_format(signal_value: SignalValue) {
 fn diff_ghdl_oscar_test() {
     run_diff_test("inputs/ghdl/oscar/test.ghw", "inputs/ghdl/oscar/vhdl3.fst");
 }
+
+/// Compares multi-threaded against single-threaded loading. We use more threads than the
+/// machine might have in order to make sure that the body actually gets split.
+fn run_multi_thread_diff_test(ghw_filename: &str) {
+    let single_thread = LoadOptions {
+        multi_thread: false,
+        ..Default::default()
+    };
+    let mut expected = read_with_options(ghw_filename, &single_thread).unwrap();
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(4)
+        .build()
+        .unwrap();
+    let mut actual = pool.install(|| read(ghw_filename).unwrap());
+
+    assert_eq!(expected.time_table(), actual.time_table());
+    load_all_signals(&mut expected);
+    load_all_signals(&mut actual);
+    for signal in get_all_signals(&expected) {
+        assert_eq!(
+            expected.get_signal(signal),
+            actual.get_signal(signal),
+            "{signal:?}"
+        );
+    }
+}
+
+#[test]
+fn diff_multi_thread_wellen_issue_12() {
+    run_multi_thread_diff_test("inputs/ghdl/wellen_issue_12.ghw");
+}
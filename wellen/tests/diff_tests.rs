@@ -40,6 +40,7 @@ fn run_load_test_vcd(vcd_filename: &str) {
 }
 
 #[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 enum LoadType {
     FromFile,
     FromBytes,
@@ -105,6 +106,117 @@ fn run_diff_test_internal(
     }
 }
 
+/// Writes `filename` back out with our VCD writer and then compares the result against the
+/// original, with the help of the (3rd party!) rust vcd library and by loading it again.
+fn run_vcd_writer_test(filename: &str) {
+    run_writer_test(filename, None, FileFormat::Vcd);
+}
+
+/// Writes `filename` back out with our FST writer and then compares the result against
+/// `vcd_filename` with the help of the (3rd party!) rust vcd library and against the original
+/// by loading it again.
+fn run_fst_writer_test(filename: &str, vcd_filename: Option<&str>) {
+    run_writer_test(filename, vcd_filename, FileFormat::Fst);
+}
+
+fn run_writer_test(filename: &str, vcd_filename: Option<&str>, format: FileFormat) {
+    let mut original = read(filename).expect("failed to load original");
+    load_all_signals(&mut original);
+    let extension = match format {
+        FileFormat::Vcd => "vcd",
+        FileFormat::Fst => "fst",
+        other => unreachable!("no writer for {other:?}"),
+    };
+    let out_filename = std::env::temp_dir().join(format!(
+        "wellen_{extension}_writer_{}_{}.{extension}",
+        std::process::id(),
+        filename.replace(['/', '.'], "_")
+    ));
+    {
+        let signals: Vec<&Signal> = original
+            .hierarchy()
+            .get_unique_signals_vars()
+            .iter()
+            .flatten()
+            .map(|v| original.get_signal(v.signal_ref()).unwrap())
+            .collect();
+        let mut out = std::io::BufWriter::new(std::fs::File::create(&out_filename).unwrap());
+        match format {
+            FileFormat::Vcd => wellen::vcd::write(
+                &mut out,
+                original.hierarchy(),
+                original.time_table(),
+                signals,
+            )
+            .expect("failed to write VCD"),
+            _ => wellen::fst::write(
+                &mut out,
+                original.hierarchy(),
+                original.time_table(),
+                signals,
+            )
+            .expect("failed to write FST"),
+        }
+    }
+    let out_filename_str = out_filename.to_str().unwrap();
+    match format {
+        FileFormat::Vcd => {
+            run_diff_test_internal(out_filename_str, Some(filename), false, LoadType::FromFile)
+        }
+        _ => {
+            if let Some(vcd_filename) = vcd_filename {
+                run_diff_test(vcd_filename, out_filename_str);
+            }
+        }
+    }
+    let mut written = read(out_filename_str).expect("failed to load written waveform");
+    load_all_signals(&mut written);
+    diff_written_waveform(&original, &written, format);
+    std::fs::remove_file(&out_filename).unwrap();
+}
+
+/// Compares attributes that the rust vcd library does not know about.
+/// VCD has no way of encoding variable directions, thus they are only compared for FST.
+fn diff_written_waveform(original: &Waveform, written: &Waveform, format: FileFormat) {
+    let (oh, wh) = (original.hierarchy(), written.hierarchy());
+    assert_eq!(oh.timescale(), wh.timescale());
+    assert_eq!(original.time_table(), written.time_table());
+    for (o, w) in itertools::zip_eq(oh.iter_scopes(), wh.iter_scopes()) {
+        assert_eq!(o.full_name(oh), w.full_name(wh));
+        assert_eq!(o.scope_type(), w.scope_type());
+        assert_eq!(o.source_loc(oh), w.source_loc(wh));
+        assert_eq!(
+            o.instantiation_source_loc(oh),
+            w.instantiation_source_loc(wh)
+        );
+    }
+    for (o, w) in itertools::zip_eq(oh.iter_vars(), wh.iter_vars()) {
+        let name = o.full_name(oh);
+        assert_eq!(name, w.full_name(wh));
+        assert_eq!(o.var_type(), w.var_type(), "{name}");
+        assert_eq!(o.signal_encoding(), w.signal_encoding(), "{name}");
+        assert_eq!(o.index(), w.index(), "{name}");
+        assert_eq!(o.vhdl_type_name(oh), w.vhdl_type_name(wh), "{name}");
+        assert_eq!(o.enum_type(oh), w.enum_type(wh), "{name}");
+        if format == FileFormat::Fst && o.direction() != VarDirection::Unknown {
+            assert_eq!(o.direction(), w.direction(), "{name}");
+        }
+        let o_changes: Vec<_> = original
+            .get_signal(o.signal_ref())
+            .unwrap()
+            .iter_changes()
+            .map(|(t, v)| (t, v.to_string()))
+            .collect();
+        let w_changes: Vec<_> = written
+            .get_signal(w.signal_ref())
+            .unwrap()
+            .iter_changes()
+            .map(|(t, v)| (t, v.to_string()))
+            .collect();
+        assert_eq!(o_changes, w_changes, "{name}");
+    }
+}
+
 fn diff_test_one(vcd_filename: &str, mut our: Waveform, skip_content_comparison: bool) {
     let mut ref_parser =
         ::vcd::Parser::new(BufReader::new(std::fs::File::open(vcd_filename).unwrap()));
@@ -303,7 +415,7 @@ fn load_all_signals(our: &mut Waveform) {
         .flatten()
         .map(|v| v.signal_ref())
         .collect();
-    our.load_signals(&all_signals);
+    our.load_signals(&all_signals).unwrap();
 }
 
 fn diff_signals<R: BufRead>(
@@ -847,3 +959,72 @@ fn diff_scope_with_comment() {
 fn diff_yosys_smtbmc_surfer_issue_315() {
     run_diff_test_vcd_only("inputs/yosys_smtbmc/surfer_issue_315.vcd");
 }
+
+#[test]
+fn write_vcd_icarus_cpu() {
+    run_vcd_writer_test("inputs/icarus/CPU.vcd");
+}
+
+#[test]
+fn write_vcd_ghdl_oscar_vhdltype() {
+    run_vcd_writer_test("inputs/ghdl/oscar/vhdltype.vcd");
+}
+
+#[test]
+fn write_vcd_gtkwave_des() {
+    // contains source locations
+    run_vcd_writer_test("inputs/gtkwave-analyzer/des.fst");
+}
+
+#[test]
+fn write_vcd_nvc_xwb_fofb_shaper_filt_tb_arrays() {
+    // contains array scopes
+    run_vcd_writer_test("inputs/nvc/xwb_fofb_shaper_filt_tb_arrays.fst");
+}
+
+#[test]
+fn write_vcd_verilator_many_sv_datatypes() {
+    // contains enums
+    run_vcd_writer_test("inputs/verilator/many_sv_datatypes.fst");
+}
+
+#[test]
+fn write_fst_icarus_cpu() {
+    run_fst_writer_test("inputs/icarus/CPU.vcd", Some("inputs/icarus/CPU.vcd"));
+}
+
+#[test]
+fn write_fst_model_sim_cpu_design() {
+    run_fst_writer_test(
+        "inputs/model-sim/CPU_Design.msim.vcd",
+        Some("inputs/model-sim/CPU_Design.msim.vcd"),
+    );
+}
+
+#[test]
+fn write_fst_ghdl_oscar_vhdltype() {
+    run_fst_writer_test("inputs/ghdl/oscar/vhdltype.vcd", None);
+}
+
+#[test]
+fn write_fst_gtkwave_des() {
+    // contains source locations
+    run_fst_writer_test("inputs/gtkwave-analyzer/des.fst", None);
+}
+
+#[test]
+fn write_fst_nvc_xwb_fofb_shaper_filt_tb_arrays() {
+    // contains array scopes and variable directions
+    run_fst_writer_test("inputs/nvc/xwb_fofb_shaper_filt_tb_arrays.fst", None);
+}
+
+#[test]
+fn write_fst_verilator_many_sv_datatypes() {
+    // contains enums
+    run_fst_writer_test("inputs/verilator/many_sv_datatypes.fst", None);
+}
+
+#[test]
+fn write_fst_ghw_tb_recv() {
+    run_fst_writer_test("inputs/ghdl/tb_recv.ghw", None);
+}
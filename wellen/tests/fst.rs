@@ -190,7 +190,7 @@ fn load_all_signals(waves: &mut Waveform) {
         .flatten()
         .map(|v| v.signal_ref())
         .collect::<Vec<_>>();
-    waves.load_signals(&all_signals);
+    waves.load_signals(&all_signals).unwrap();
 }
 
 /// This file was provided by Augusto Fraga Giachero in the following issue:
@@ -236,3 +236,43 @@ fn test_verilator_incomplete() {
     let mut waves = read(filename).expect("failed to parse");
     load_all_signals(&mut waves);
 }
+
+/// Loads all signals once with a single reader and once split between several readers.
+/// We use more threads than the machine might have in order to make sure that the signals
+/// actually get split.
+fn load_multi_threaded_and_compare(filename: &str) {
+    let load = |multi_threaded: bool| {
+        let header = viewers::read_header_from_file(filename, &LoadOptions::default()).unwrap();
+        let hierarchy = header.hierarchy;
+        let body = viewers::read_body(header.body, &hierarchy, None).unwrap();
+        let mut ids: Vec<_> = hierarchy.iter_vars().map(|v| v.signal_ref()).collect();
+        ids.sort();
+        ids.dedup();
+        let progress = viewers::ProgressCount::default();
+        let mut source = body.source;
+        let signals = source
+            .load_signals_with_progress(&ids, &hierarchy, multi_threaded, &progress)
+            .unwrap();
+        assert_eq!(
+            progress.load(std::sync::atomic::Ordering::SeqCst),
+            ids.len() as u64
+        );
+        signals
+    };
+    let expected = load(false);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(4)
+        .build()
+        .unwrap();
+    let actual = pool.install(|| load(true));
+    assert_eq!(expected.len(), actual.len());
+    for ((expected_ref, expected), (actual_ref, actual)) in expected.iter().zip(actual.iter()) {
+        assert_eq!(expected_ref, actual_ref);
+        assert_eq!(expected, actual, "{expected_ref:?}");
+    }
+}
+
+#[test]
+fn test_multi_threaded_loading() {
+    load_multi_threaded_and_compare("inputs/gtkwave-analyzer/des.fst");
+}
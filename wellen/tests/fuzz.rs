@@ -0,0 +1,155 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// test that malformed inputs result in errors instead of panics
+
+use proptest::prelude::*;
+use std::io::Cursor;
+use wellen::*;
+
+/// Loads the header, the body and all signals. Errors are fine, panics are not.
+fn try_load(data: Vec<u8>) -> Result<()> {
+    let options = LoadOptions {
+        multi_thread: false,
+        ..Default::default()
+    };
+    let header = viewers::read_header(Cursor::new(data), &options)?;
+    let body = viewers::read_body(header.body, &header.hierarchy, None)?;
+    let mut source = body.source;
+    let ids: Vec<_> = header
+        .hierarchy
+        .get_unique_signals_vars()
+        .iter()
+        .flatten()
+        .map(|v| v.signal_ref())
+        .collect();
+    for (_, signal) in source.load_signals(&ids, &header.hierarchy, false)? {
+        for (_, value) in signal.iter_changes() {
+            let _ = value.to_bit_string();
+        }
+    }
+    Ok(())
+}
+
+/// Overwrites bytes and optionally truncates the input.
+fn mutate(mut data: Vec<u8>, changes: &[(usize, u8)], truncate: Option<usize>) -> Vec<u8> {
+    for (pos, value) in changes.iter() {
+        let len = data.len();
+        data[pos % len] = *value;
+    }
+    if let Some(len) = truncate {
+        data.truncate(len % (data.len() + 1));
+    }
+    data
+}
+
+fn fuzz_file(filename: &str, changes: &[(usize, u8)], truncate: Option<usize>) {
+    let data = std::fs::read(filename).unwrap();
+    let _ = try_load(mutate(data, changes, truncate));
+}
+
+/// Makes sure that the fuzzer starts from inputs that can be loaded.
+#[test]
+fn unmodified_inputs_load() {
+    for filename in [
+        "inputs/amaranth/up_counter.vcd",
+        "inputs/gtkwave-analyzer/vcd_extensions.vcd",
+        "inputs/amaranth/up_counter.vcd.fst",
+        "inputs/ghdl/wellen_issue_6.ghw",
+        "inputs/ghdl/tb_recv.ghw",
+    ] {
+        try_load(std::fs::read(filename).unwrap()).unwrap();
+    }
+}
+
+#[test]
+fn errors_report_format_section_and_offset() {
+    let mut data = std::fs::read("inputs/amaranth/up_counter.vcd").unwrap();
+    let pos = data.windows(9).position(|w| w == b"#500000\n1").unwrap() + 9;
+    data[pos] = b'?'; // unknown id
+    match try_load(data) {
+        Err(WellenError::Malformed {
+            format: FileFormat::Vcd,
+            section: "value changes",
+            offset: Some(offset),
+            ..
+        }) => assert!(offset.abs_diff(pos as u64) <= 2, "{offset} vs {pos}"),
+        other => panic!("unexpected result: {other:?}"),
+    }
+}
+
+#[test]
+fn fst_length_errors_report_block_offset() {
+    let data = std::fs::read("inputs/amaranth/up_counter.vcd.fst").unwrap();
+    // the geometry block starts at 579 and the hierarchy block at 614
+    let mut geometry = data.clone();
+    geometry[579 + 1 + 16..579 + 1 + 24].copy_from_slice(&u64::MAX.to_be_bytes());
+    let mut hierarchy = data;
+    hierarchy[614 + 1 + 8..614 + 1 + 16].copy_from_slice(&u64::MAX.to_be_bytes());
+    for (data, expected_section, expected_offset) in
+        [(geometry, "header", 579), (hierarchy, "hierarchy", 614)]
+    {
+        match try_load(data) {
+            Err(WellenError::Malformed {
+                format: FileFormat::Fst,
+                section,
+                offset: Some(offset),
+                ..
+            }) => {
+                assert_eq!(section, expected_section);
+                assert_eq!(offset, expected_offset);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+}
+
+/// Bytes that are likely to confuse a VCD parser.
+fn vcd_byte() -> impl Strategy<Value = u8> {
+    prop_oneof![any::<u8>(), prop::sample::select(b"01xzbrs#$ \n".to_vec()),]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn fuzz_vcd(changes in prop::collection::vec((any::<usize>(), vcd_byte()), 1..8),
+                truncate in prop::option::of(any::<usize>())) {
+        fuzz_file("inputs/amaranth/up_counter.vcd", &changes, truncate);
+    }
+
+    #[test]
+    fn fuzz_vcd_with_attributes(changes in prop::collection::vec((any::<usize>(), vcd_byte()), 1..8),
+                                truncate in prop::option::of(any::<usize>())) {
+        fuzz_file("inputs/gtkwave-analyzer/vcd_extensions.vcd", &changes, truncate);
+    }
+
+    #[test]
+    fn fuzz_fst(changes in prop::collection::vec((any::<usize>(), any::<u8>()), 1..8),
+                truncate in prop::option::of(any::<usize>())) {
+        fuzz_file("inputs/amaranth/up_counter.vcd.fst", &changes, truncate);
+    }
+
+    #[test]
+    fn fuzz_fst_with_gzip_wrapper(changes in prop::collection::vec((any::<usize>(), any::<u8>()), 1..8),
+                                  truncate in prop::option::of(any::<usize>())) {
+        fuzz_file("inputs/nvc/vhdl_test_bool_issue_16.fst", &changes, truncate);
+    }
+
+    #[test]
+    fn fuzz_ghw(changes in prop::collection::vec((any::<usize>(), any::<u8>()), 1..8),
+                truncate in prop::option::of(any::<usize>())) {
+        fuzz_file("inputs/ghdl/wellen_issue_6.ghw", &changes, truncate);
+    }
+
+    #[test]
+    fn fuzz_ghw_with_records(changes in prop::collection::vec((any::<usize>(), any::<u8>()), 1..8),
+                             truncate in prop::option::of(any::<usize>())) {
+        fuzz_file("inputs/ghdl/tb_recv.ghw", &changes, truncate);
+    }
+
+    #[test]
+    fn fuzz_random_bytes(data in prop::collection::vec(any::<u8>(), 0..256)) {
+        let _ = try_load(data);
+    }
+}
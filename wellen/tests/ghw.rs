@@ -55,7 +55,7 @@ fn test_ghw_enum_encoding() {
 
     // make sure that all enum values are binary
     let ee_signal_ref = h[ee].signal_ref();
-    waves.load_signals(&[ee_signal_ref]);
+    waves.load_signals(&[ee_signal_ref]).unwrap();
     let ee_signal = waves.get_signal(ee_signal_ref).unwrap();
     for id in 0..waves.time_table().len() {
         let off = ee_signal.get_offset(id as TimeTableIdx).unwrap();
@@ -83,7 +83,7 @@ fn test_issue_12_regression() {
         .lookup_var(&["test_rom_tb", "soc_inst", "core_inst"], &"state")
         .unwrap();
     let signal_ref = wave.hierarchy()[var_id].signal_ref();
-    wave.load_signals(&[signal_ref]);
+    wave.load_signals(&[signal_ref]).unwrap();
 
     let signal = wave.get_signal(signal_ref).unwrap();
     let mut values = vec![];
@@ -188,7 +188,7 @@ fn test_physical_type_parsing() {
         .iter_vars()
         .map(|v| v.signal_ref())
         .collect::<Vec<_>>();
-    wave.load_signals(&signal_refs);
+    wave.load_signals(&signal_refs).unwrap();
     let var_values: Vec<String> = signal_refs
         .iter()
         .filter_map(|s| wave.get_signal(*s))
@@ -228,7 +228,7 @@ fn test_issue_53_array_range_ordering() {
         .iter_vars()
         .map(|v| v.signal_ref())
         .collect::<Vec<_>>();
-    wave.load_signals(&signal_refs);
+    wave.load_signals(&signal_refs).unwrap();
 
     let h = wave.hierarchy();
 
@@ -266,3 +266,84 @@ fn test_issue_53_array_range_ordering() {
     // Check that they are the same
     assert_eq!(values, values2);
 }
+
+fn values(wave: &mut Waveform, path: &[&str], name: &str) -> Vec<String> {
+    let h = wave.hierarchy();
+    let signal_ref = h[h.lookup_var(path, &name).unwrap()].signal_ref();
+    wave.load_signals(&[signal_ref]).unwrap();
+    wave.get_signal(signal_ref)
+        .unwrap()
+        .iter_changes()
+        .map(|(_, v)| v.to_bit_string().unwrap())
+        .collect()
+}
+
+const MULTI_DIM: &str = "inputs/ghdl/wellen_multi_dim.ghw";
+const TOP: &str = "wellen_multi_dim";
+
+#[test]
+fn test_ghw_multi_dimensional_arrays() {
+    let mut wave = read(MULTI_DIM).unwrap();
+    let h = wave.hierarchy();
+
+    // the first dimension becomes a scope, the std_ulogic dimension a bit-vector
+    let mem = &h[h.lookup_scope(&[TOP, "mem"]).unwrap()];
+    assert_eq!(mem.scope_type(), ScopeType::VhdlArray);
+    let names: Vec<_> = mem.vars(h).map(|v| h[v].name(h).to_string()).collect();
+    assert_eq!(names, ["[0]", "[1]"]);
+    let mem_1 = &h[h.lookup_var(&[TOP, "mem"], &"[1]").unwrap()];
+    assert_eq!(mem_1.length(), Some(4));
+    assert_eq!(mem_1.index().unwrap().msb(), 3);
+    assert_eq!(mem_1.index().unwrap().lsb(), 0);
+
+    // all other dimensions turn into nested scopes
+    let grid_1 = &h[h.lookup_scope(&[TOP, "grid", "[1]"]).unwrap()];
+    assert_eq!(grid_1.scope_type(), ScopeType::VhdlArray);
+    assert_eq!(grid_1.full_name(h), format!("{TOP}.grid.[1]"));
+    let names: Vec<_> = grid_1.vars(h).map(|v| h[v].name(h).to_string()).collect();
+    assert_eq!(names, ["[0]", "[1]", "[2]"]);
+    let cube_1 = &h[h.lookup_scope(&[TOP, "cube", "[1]"]).unwrap()];
+    let names: Vec<_> = cube_1.vars(h).map(|v| h[v].name(h).to_string()).collect();
+    assert_eq!(names, ["[0]", "[1]"]);
+
+    assert_eq!(values(&mut wave, &[TOP, "mem"], "[0]"), ["1010"]);
+    assert_eq!(values(&mut wave, &[TOP, "mem"], "[1]"), ["0110", "0111"]);
+    let grid_1_2 = values(&mut wave, &[TOP, "grid", "[1]"], "[2]");
+    assert_eq!(grid_1_2, [format!("{:032b}", 6)]);
+    assert_eq!(
+        values(&mut wave, &[TOP, "cube", "[1]"], "[0]"),
+        ["10", "00"]
+    );
+    assert_eq!(values(&mut wave, &[TOP, "cube", "[1]"], "[1]"), ["11"]);
+}
+
+#[test]
+fn test_ghw_scalar_subtypes() {
+    let mut wave = read(MULTI_DIM).unwrap();
+    let h = wave.hierarchy();
+
+    // a constrained enum keeps the encoding and literals of its base type
+    let active = &h[h.lookup_var(&[TOP], &"active").unwrap()];
+    assert_eq!(active.var_type(), VarType::Enum);
+    assert_eq!(active.vhdl_type_name(h), Some("active_t"));
+    let (enum_name, literals) = active.enum_type(h).unwrap();
+    assert_eq!(enum_name, "state_t");
+    assert_eq!(
+        literals.iter().map(|(_, l)| *l).collect::<Vec<_>>(),
+        ["idle", "busy", "done", "error"]
+    );
+    assert_eq!(values(&mut wave, &[TOP], "active"), ["10", "01"]);
+
+    let h = wave.hierarchy();
+    let b = &h[h.lookup_var(&[TOP], &"b").unwrap()];
+    assert_eq!(b.vhdl_type_name(h), Some("x01_t"));
+    assert_eq!(values(&mut wave, &[TOP], "b"), ["x"]);
+
+    let h = wave.hierarchy();
+    let big = &h[h.lookup_var(&[TOP], &"big").unwrap()];
+    assert_eq!(big.vhdl_type_name(h), Some("int64_t"));
+    assert_eq!(
+        values(&mut wave, &[TOP], "big"),
+        [format!("{:064b}", 1u64 << 40), "1".repeat(64)]
+    );
+}
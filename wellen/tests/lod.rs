@@ -0,0 +1,89 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// compare level-of-detail summaries against a direct computation from all changes
+
+use wellen::lod::SignalSummary;
+use wellen::*;
+
+fn is_non_binary(value: &SignalValue) -> bool {
+    match value {
+        SignalValue::FourValue(..) | SignalValue::NineValue(..) => value
+            .to_bit_string()
+            .unwrap()
+            .chars()
+            .any(|c| c != '0' && c != '1'),
+        _ => false,
+    }
+}
+
+fn run_lod_test(filename: &str, bucket_counts: &[usize]) {
+    let mut wave = simple::read(filename).expect("failed to load file");
+    let ids: Vec<_> = wave
+        .hierarchy()
+        .get_unique_signals_vars()
+        .iter()
+        .flatten()
+        .map(|v| v.signal_ref())
+        .collect();
+    wave.load_signals_multi_threaded(&ids).unwrap();
+    let time_table = wave.time_table();
+    let start = time_table[0];
+    let end = *time_table.last().unwrap();
+
+    for id in ids {
+        let signal = wave.get_signal(id).unwrap();
+        let summary = SignalSummary::new(signal);
+        let changes: Vec<_> = signal
+            .iter_changes()
+            .map(|(idx, value)| (time_table[idx as usize], value))
+            .collect();
+        for &count in bucket_counts {
+            let buckets = summary.buckets(signal, time_table, start, end, count);
+            assert_eq!(buckets.len(), count);
+            assert_eq!(buckets[0].start, start);
+            assert_eq!(buckets.last().unwrap().end, end + 1);
+            for bucket in buckets {
+                let before = changes.iter().rposition(|(t, _)| *t < bucket.start);
+                let inside: Vec<_> = changes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (t, _))| bucket.start <= *t && *t < bucket.end)
+                    .map(|(ii, _)| ii)
+                    .collect();
+                let first = before.or(inside.first().cloned());
+                let last = inside.last().cloned().or(before);
+                let transitions = if before.is_some() {
+                    inside.len()
+                } else {
+                    inside.len().saturating_sub(1)
+                };
+                let non_binary = first.is_some_and(|first| {
+                    changes[first..=last.unwrap()]
+                        .iter()
+                        .any(|(_, v)| is_non_binary(v))
+                });
+                let context = format!("{id:?} in {}..{}", bucket.start, bucket.end);
+                assert_eq!(bucket.first, first.map(|ii| changes[ii].1), "{context}");
+                assert_eq!(bucket.last, last.map(|ii| changes[ii].1), "{context}");
+                assert_eq!(bucket.transitions as usize, transitions, "{context}");
+                assert_eq!(bucket.has_non_binary, non_binary, "{context}");
+            }
+        }
+    }
+}
+
+#[test]
+fn lod_vcd() {
+    run_lod_test("inputs/icarus/CPU.vcd", &[1, 7, 100, 1000]);
+}
+
+#[test]
+fn lod_ghw_nine_value() {
+    run_lod_test("inputs/ghdl/tb_recv.ghw", &[1, 13, 200]);
+}
+
+#[test]
+fn lod_fst() {
+    run_lod_test("inputs/nvc/xwb_fofb_shaper_filt_tb_arrays.fst", &[3, 64]);
+}
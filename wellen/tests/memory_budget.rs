@@ -0,0 +1,129 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// test that signals evicted because of a memory budget are restored correctly
+
+use wellen::simple::MemoryBudget;
+use wellen::*;
+
+const FILENAME: &str = "inputs/icarus/CPU.vcd";
+
+fn all_signals(wave: &simple::Waveform) -> Vec<SignalRef> {
+    wave.hierarchy()
+        .get_unique_signals_vars()
+        .iter()
+        .flatten()
+        .map(|v| v.signal_ref())
+        .collect()
+}
+
+fn changes(signal: &Signal) -> Vec<(TimeTableIdx, String)> {
+    signal
+        .iter_changes()
+        .map(|(t, v)| (t, v.to_string()))
+        .collect()
+}
+
+fn run_budget_test(keep_compressed: bool) {
+    let mut reference = simple::read(FILENAME).expect("failed to load file");
+    let ids = all_signals(&reference);
+    reference.load_signals(&ids).unwrap();
+    let expected: Vec<_> = ids
+        .iter()
+        .map(|id| changes(reference.get_signal(*id).unwrap()))
+        .collect();
+    let largest = ids
+        .iter()
+        .map(|id| reference.get_signal(*id).unwrap().size_in_memory())
+        .max()
+        .unwrap();
+    let total = reference.signal_memory_usage();
+    let budget = total / 4;
+    assert!(budget > largest);
+
+    let mut wave = simple::read(FILENAME).expect("failed to load file");
+    wave.set_memory_budget(Some(MemoryBudget {
+        bytes: budget,
+        keep_compressed,
+    }));
+    for id in ids.iter() {
+        wave.load_signals(&[*id]).unwrap();
+        assert!(wave.signal_memory_usage() <= budget);
+        assert!(wave.get_signal(*id).is_some());
+    }
+
+    // evicted signals are restored on demand
+    for (id, expected) in ids.iter().zip(expected.iter()) {
+        assert!(wave.is_loaded(*id));
+        assert_eq!(&changes(wave.get_signal(*id).unwrap()), expected, "{id:?}");
+    }
+    assert!(wave.signal_memory_usage() > budget);
+    // the budget is enforced again by the next call to `load_signals`
+    wave.load_signals(&ids[..1]).unwrap();
+    assert!(wave.signal_memory_usage() <= budget);
+
+    // evicted signals are also restored by loading them again
+    for (id, expected) in ids.iter().zip(expected.iter()) {
+        wave.load_signals(&[*id]).unwrap();
+        assert!(wave.signal_memory_usage() <= budget);
+        assert_eq!(&changes(wave.get_signal(*id).unwrap()), expected, "{id:?}");
+    }
+
+    // unloaded signals are gone for good
+    wave.unload_signals(&ids[..1]);
+    assert!(wave.get_signal(ids[0]).is_none());
+    assert!(!wave.is_loaded(ids[0]));
+
+    // removing the budget keeps everything that is restored
+    wave.set_memory_budget(None);
+    wave.load_signals(&ids[1..]).unwrap();
+    for (id, expected) in ids.iter().zip(expected.iter()).skip(1) {
+        assert_eq!(&changes(wave.get_signal(*id).unwrap()), expected, "{id:?}");
+    }
+    assert!(wave.signal_memory_usage() > budget);
+}
+
+#[test]
+fn memory_budget_drop_evicted() {
+    run_budget_test(false);
+}
+
+#[test]
+fn memory_budget_keep_compressed() {
+    run_budget_test(true);
+}
+
+#[test]
+fn memory_budget_derived_signal() {
+    let mut wave = simple::read(FILENAME).expect("failed to load file");
+    let derived = wave
+        .add_derived_signal("ID_EX.ID_EX_RS1data + ID_EX.ID_EX_IMM")
+        .unwrap();
+    wave.load_signals(&[derived]).unwrap();
+    let expected = changes(wave.get_signal(derived).unwrap());
+
+    // a tiny budget evicts everything but the signals that were just loaded
+    wave.set_memory_budget(Some(MemoryBudget {
+        bytes: 1,
+        keep_compressed: false,
+    }));
+    assert_eq!(wave.signal_memory_usage(), 0);
+    assert_eq!(changes(wave.get_signal(derived).unwrap()), expected);
+
+    // inputs that were unloaded are loaded again in order to restore the derived signal
+    let inputs: Vec<_> = ["ID_EX_RS1data", "ID_EX_IMM"]
+        .iter()
+        .map(|name| {
+            let var = wave.hierarchy().lookup_var(&["ID_EX"], name).unwrap();
+            wave.hierarchy()[var].signal_ref()
+        })
+        .collect();
+    wave.unload_signals(&inputs);
+    wave.set_memory_budget(Some(MemoryBudget {
+        bytes: 1,
+        keep_compressed: false,
+    }));
+    assert_eq!(wave.signal_memory_usage(), 0);
+    assert_eq!(changes(wave.get_signal(derived).unwrap()), expected);
+    assert!(inputs.iter().all(|id| !wave.is_loaded(*id)));
+}
@@ -0,0 +1,191 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// tests for the SAIF writer
+
+use wellen::saif::*;
+use wellen::simple::*;
+
+const VCD: &str = r#"
+$timescale 10ns $end
+$scope module tb $end
+$var wire 2 ! a [1:0] $end
+$var real 1 $ r $end
+$scope module dut $end
+$var wire 1 " b $end
+$upscope $end
+$scope module empty $end
+$var real 1 $ r_alias $end
+$upscope $end
+$upscope $end
+$enddefinitions $end
+#0
+b00 !
+x"
+r0 $
+#10
+b01 !
+1"
+#20
+b11 !
+z"
+#30
+b10 !
+1"
+#40
+b00 !
+0"
+"#;
+
+fn read_vcd(content: &str) -> Waveform {
+    read_from_reader(std::io::Cursor::new(content.as_bytes().to_vec())).unwrap()
+}
+
+fn saif(wave: &mut Waveform, options: &SaifOptions) -> String {
+    let mut out = vec![];
+    write_saif(wave, None, options, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+/// Everything after the header, which contains the date and version.
+fn body(saif: &str) -> &str {
+    &saif[saif.find("(TIMESCALE").unwrap()..]
+}
+
+#[test]
+fn activity() {
+    let mut wave = read_vcd(VCD);
+    let h = wave.hierarchy();
+    let a = h[h.lookup_var(&["tb"], &"a").unwrap()].signal_ref();
+    let b = h[h.lookup_var(&["tb", "dut"], &"b").unwrap()].signal_ref();
+    wave.load_signals(&[a, b]).unwrap();
+    let tt = wave.time_table();
+    assert_eq!(
+        switching_activity(wave.get_signal(a).unwrap(), 2, tt, (0, 40)).unwrap(),
+        [
+            BitActivity {
+                t0: 20,
+                t1: 20,
+                tc: 2,
+                ..Default::default()
+            },
+            BitActivity {
+                t0: 20,
+                t1: 20,
+                tc: 2,
+                ..Default::default()
+            }
+        ]
+    );
+    // `1 -> z -> 1` is a glitch and `x -> 1` is not counted at all
+    assert_eq!(
+        switching_activity(wave.get_signal(b).unwrap(), 1, tt, (0, 40)).unwrap(),
+        [BitActivity {
+            t0: 0,
+            t1: 20,
+            tx: 10,
+            tz: 10,
+            tc: 1,
+            ig: 1
+        }]
+    );
+    // the value at the start of the window is known, but transitions at the start are not counted
+    assert_eq!(
+        switching_activity(wave.get_signal(b).unwrap(), 1, tt, (10, 25)).unwrap(),
+        [BitActivity {
+            t1: 10,
+            tz: 5,
+            ..Default::default()
+        }]
+    );
+}
+
+#[test]
+fn write_hierarchy() {
+    let mut wave = read_vcd(VCD);
+    let out = saif(
+        &mut wave,
+        &SaifOptions {
+            design: "tb".to_string(),
+            ..Default::default()
+        },
+    );
+    assert!(
+        out.starts_with("(SAIFILE\n(SAIFVERSION \"2.0\")\n"),
+        "{out}"
+    );
+    assert!(out.contains("(DESIGN \"tb\")\n"), "{out}");
+    // the real variables and the scope that only contains one are left out
+    assert_eq!(
+        body(&out),
+        r#"(TIMESCALE 10 ns)
+(DURATION 40)
+(INSTANCE tb
+  (NET
+    (a\[0\]
+      (T0 20) (T1 20) (TX 0) (TZ 0)
+      (TC 2) (IG 0)
+    )
+    (a\[1\]
+      (T0 20) (T1 20) (TX 0) (TZ 0)
+      (TC 2) (IG 0)
+    )
+  )
+  (INSTANCE dut
+    (NET
+      (b
+        (T0 0) (T1 20) (TX 10) (TZ 10)
+        (TC 1) (IG 1)
+      )
+    )
+  )
+)
+)
+"#
+    );
+    assert_eq!(wave.signal_memory_usage(), 0);
+}
+
+#[test]
+fn scope_and_time_window() {
+    let mut wave = read_vcd(VCD);
+    let dut = wave.hierarchy().lookup_scope(&["tb", "dut"]).unwrap();
+    let options = SaifOptions {
+        time_window: Some((10, 25)),
+        ..Default::default()
+    };
+    let mut out = vec![];
+    write_saif(&mut wave, Some(dut), &options, &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains("(DURATION 15)\n(INSTANCE dut\n"), "{out}");
+    assert!(out.contains("(T0 0) (T1 10) (TX 0) (TZ 5)"), "{out}");
+    assert!(!out.contains("INSTANCE tb"), "{out}");
+
+    let inverted = SaifOptions {
+        time_window: Some((25, 10)),
+        ..Default::default()
+    };
+    assert!(matches!(
+        write_saif(&mut wave, Some(dut), &inverted, &mut vec![]),
+        Err(SaifError::InvalidTimeWindow(25, 10))
+    ));
+}
+
+#[test]
+fn vcd_and_fst_agree() {
+    let mut vcd = read("inputs/ghdl/idea.vcd").unwrap();
+    let mut fst = read("inputs/ghdl/idea.vcd.fst").unwrap();
+    let options = SaifOptions::default();
+    let vcd = saif(&mut vcd, &options);
+    let fst = saif(&mut fst, &options);
+    assert!(vcd.contains("(TC "));
+    assert_eq!(body(&vcd), body(&fst));
+}
+
+#[test]
+fn ghw() {
+    let mut wave = read("inputs/ghdl/tb_recv.ghw").unwrap();
+    let out = saif(&mut wave, &SaifOptions::default());
+    assert!(out.contains("(TIMESCALE 1 fs)\n"), "{out}");
+    assert!(out.contains("(INSTANCE tb_recv\n"), "{out}");
+}
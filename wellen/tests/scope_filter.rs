@@ -0,0 +1,164 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// compare waveforms loaded with a scope filter against the complete waveform
+
+use wellen::simple::*;
+use wellen::*;
+
+type Changes = Vec<(TimeTableIdx, String)>;
+
+struct Loaded {
+    /// names of all scopes, split into their components
+    scopes: Vec<Vec<String>>,
+    /// names of all variables together with the name of their parent scope
+    vars: Vec<(Vec<String>, String, Changes)>,
+    time_table: TimeTable,
+}
+
+fn load(filename: &str, options: &LoadOptions) -> Loaded {
+    let mut wave = read_with_options(filename, options).expect("failed to load waveform");
+    let ids: Vec<_> = wave
+        .hierarchy()
+        .iter_vars()
+        .map(|v| v.signal_ref())
+        .collect();
+    wave.load_signals(&ids).unwrap();
+    let mut out = Loaded {
+        scopes: vec![],
+        vars: vec![],
+        time_table: wave.time_table().to_vec(),
+    };
+    let h = wave.hierarchy();
+    let top_vars: Vec<_> = h.vars().collect();
+    collect_vars(&wave, &[], &top_vars, &mut out);
+    for scope in h.scopes() {
+        collect_scope(&wave, &mut vec![], scope, &mut out);
+    }
+    out
+}
+
+fn collect_scope(wave: &Waveform, path: &mut Vec<String>, scope: ScopeRef, out: &mut Loaded) {
+    let h = wave.hierarchy();
+    path.push(h[scope].name(h).to_string());
+    out.scopes.push(path.clone());
+    let vars: Vec<_> = h[scope].vars(h).collect();
+    collect_vars(wave, path, &vars, out);
+    for child in h[scope].scopes(h) {
+        collect_scope(wave, path, child, out);
+    }
+    path.pop();
+}
+
+fn collect_vars(wave: &Waveform, path: &[String], vars: &[VarRef], out: &mut Loaded) {
+    let h = wave.hierarchy();
+    for var in vars.iter() {
+        let signal = wave.get_signal(h[*var].signal_ref()).unwrap();
+        let changes = signal
+            .iter_changes()
+            .map(|(t, v)| (t, v.to_string()))
+            .collect();
+        out.vars
+            .push((path.to_vec(), h[*var].name(h).to_string(), changes));
+    }
+}
+
+/// `keep` decides, based on its name, whether a scope is loaded. The empty name stands
+/// for variables that are not part of any scope.
+fn run_filter_test(filename: &str, options: LoadOptions, keep: impl Fn(&[String]) -> bool) {
+    let complete = load(filename, &LoadOptions::default());
+    let filtered = load(filename, &options);
+    assert_eq!(complete.time_table, filtered.time_table);
+
+    // parents of loaded scopes are loaded as well
+    let expected_scopes: Vec<_> = complete
+        .scopes
+        .iter()
+        .filter(|s| {
+            complete
+                .scopes
+                .iter()
+                .any(|other| other.starts_with(s) && keep(other))
+        })
+        .cloned()
+        .collect();
+    assert!(!expected_scopes.is_empty());
+    assert!(expected_scopes.len() < complete.scopes.len());
+    assert_eq!(expected_scopes, filtered.scopes);
+
+    // only variables in scopes that were selected are loaded
+    let expected_vars: Vec<_> = complete
+        .vars
+        .into_iter()
+        .filter(|(path, _, _)| keep(path))
+        .collect();
+    assert!(!expected_vars.is_empty());
+    assert_eq!(expected_vars, filtered.vars);
+}
+
+fn options(include: &[&str], exclude: &[&str], max_depth: Option<usize>) -> LoadOptions {
+    LoadOptions {
+        scope_filter: Some(ScopeFilter {
+            include_scopes: include.iter().map(|s| s.to_string()).collect(),
+            exclude_scopes: exclude.iter().map(|s| s.to_string()).collect(),
+            max_depth,
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn filter_vcd_include_and_exclude() {
+    run_filter_test(
+        "inputs/ghdl/idea.vcd",
+        options(&["tb_idea.uut.cround_*"], &["tb_idea.uut.*.cxor_?"], None),
+        |path| {
+            path.len() >= 3
+                && path[..2] == ["tb_idea", "uut"]
+                && path[2].starts_with("cround_")
+                && !(path.len() >= 4 && path[3].starts_with("cxor_") && path[3].len() == 6)
+        },
+    );
+}
+
+#[test]
+fn filter_vcd_max_depth() {
+    run_filter_test("inputs/ghdl/idea.vcd", options(&[], &[], Some(2)), |path| {
+        path.len() <= 2
+    });
+}
+
+#[test]
+fn filter_fst_exclude() {
+    run_filter_test(
+        "inputs/gtkwave-analyzer/des.fst",
+        options(&[], &["top.des.round*"], None),
+        |path| !(path.len() >= 3 && path[..2] == ["top", "des"] && path[2].starts_with("round")),
+    );
+}
+
+#[test]
+fn filter_ghw_include_with_slices() {
+    // variables in the alu are slices of signals declared in the (skipped) register file
+    let alu = "test_rom_tb.soc_inst.core_inst.alu_inst";
+    run_filter_test(
+        "inputs/ghdl/wellen_issue_12.ghw",
+        options(&[alu], &[], None),
+        |path| path.len() >= 4 && path[..4].join(".") == alu,
+    );
+}
+
+#[test]
+fn filter_ghw_max_depth_multi_threaded() {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(4)
+        .build()
+        .unwrap();
+    pool.install(|| {
+        run_filter_test(
+            "inputs/ghdl/wellen_issue_12.ghw",
+            options(&[], &[], Some(3)),
+            |path| path.len() <= 3,
+        );
+    });
+}
@@ -0,0 +1,119 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// compare hierarchy searches against matching the full name of every variable
+
+use wellen::search::*;
+use wellen::*;
+
+fn load_des() -> Hierarchy {
+    let header =
+        viewers::read_header_from_file("inputs/gtkwave-analyzer/des.fst", &LoadOptions::default())
+            .unwrap();
+    header.hierarchy
+}
+
+fn names(h: &Hierarchy, vars: &[VarRef]) -> Vec<String> {
+    vars.iter().map(|v| h[*v].full_name(h)).collect()
+}
+
+/// All variables for which `predicate` is true, in declaration order.
+fn reference(h: &Hierarchy, predicate: impl Fn(&Var, &str) -> bool) -> Vec<String> {
+    h.iter_vars()
+        .map(|v| (v, v.full_name(h)))
+        .filter(|(v, name)| predicate(v, name))
+        .map(|(_, name)| name)
+        .collect()
+}
+
+#[test]
+fn test_glob() {
+    let h = load_des();
+    let found = h.search(
+        &Pattern::glob("top.des.round1*.r*"),
+        &SearchFilter::default(),
+    );
+    let expected = reference(&h, |_, name| {
+        let parts: Vec<_> = name.split('.').collect();
+        parts.len() == 4
+            && parts[..2] == ["top", "des"]
+            && parts[2].starts_with("round1")
+            && parts[3].starts_with('r')
+    });
+    assert!(!expected.is_empty());
+    assert_eq!(names(&h, &found), expected);
+
+    // `**` matches across levels
+    let found = h.search(&Pattern::glob("**.clk"), &SearchFilter::default());
+    let expected = reference(&h, |_, name| name.ends_with(".clk"));
+    assert!(expected.len() > 2);
+    assert_eq!(names(&h, &found), expected);
+
+    // `?` matches a single character
+    let found = h.search(&Pattern::glob("top.des.r?x"), &SearchFilter::default());
+    assert_eq!(names(&h, &found)[..2], ["top.des.r9x", "top.des.r8x"]);
+    assert_eq!(found.len(), 10);
+}
+
+#[test]
+#[cfg(feature = "regex")]
+fn test_regex() {
+    let h = load_des();
+    let found = h.search(
+        &Pattern::regex(r"\.l1[0-6]x$").unwrap(),
+        &SearchFilter::default(),
+    );
+    let expected = reference(&h, |_, name| {
+        let name = name.rsplit('.').next().unwrap();
+        name.len() == 4 && name.starts_with("l1") && name.ends_with('x')
+    });
+    assert!(!expected.is_empty());
+    assert_eq!(names(&h, &found), expected);
+
+    assert!(matches!(
+        Pattern::regex("top.(des"),
+        Err(SearchError::InvalidRegex(_))
+    ));
+}
+
+#[test]
+fn test_filter() {
+    let h = load_des();
+    let filter = SearchFilter {
+        var_types: vec![VarType::Wire],
+        directions: vec![VarDirection::Input],
+        width: Some(2..=64),
+        scope_types: vec![ScopeType::Module],
+    };
+    let found = h.search(&Pattern::glob("**"), &filter);
+    let expected = reference(&h, |v, _| {
+        v.var_type() == VarType::Wire
+            && v.direction() == VarDirection::Input
+            && v.length().is_some_and(|l| (2..=64).contains(&l))
+    });
+    assert!(expected.contains(&"top.des.key".to_string()));
+    assert!(!expected.contains(&"top.des.clk".to_string()));
+    assert_eq!(names(&h, &found), expected);
+
+    // top-level vars are not inside of a scope of the right type
+    let filter = SearchFilter {
+        scope_types: vec![ScopeType::VhdlArchitecture],
+        ..Default::default()
+    };
+    assert!(h.search(&Pattern::glob("**"), &filter).is_empty());
+}
+
+#[test]
+fn test_fuzzy() {
+    let h = load_des();
+    let found = h.search(&Pattern::fuzzy("DesKey"), &SearchFilter::default());
+    let found = names(&h, &found);
+    // the exact name should be the best match
+    assert_eq!(found[0], "top.des.key");
+    // every result contains the characters in order
+    let expected = reference(&h, |_, name| {
+        let mut chars = name.chars();
+        "deskey".chars().all(|q| chars.any(|c| c == q))
+    });
+    assert_eq!(found.len(), expected.len());
+}
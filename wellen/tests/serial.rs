@@ -0,0 +1,419 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// tests for the UART, SPI and I2C decoders
+
+use wellen::serial::*;
+use wellen::simple::*;
+use wellen::*;
+
+/// Creates a VCD with a 1ns timescale and single bit variables in scope `tb`.
+/// Changes to the current value of a variable are skipped.
+fn trace(vars: &[&str], mut changes: Vec<(Time, usize, char)>) -> Waveform {
+    let id = |ii: usize| char::from(b'!' + ii as u8);
+    let mut out = "$timescale 1ns $end\n$scope module tb $end\n".to_string();
+    for (ii, name) in vars.iter().enumerate() {
+        out += &format!("$var wire 1 {} {name} $end\n", id(ii));
+    }
+    out += "$upscope $end\n$enddefinitions $end\n";
+    changes.sort_by_key(|(time, _, _)| *time);
+    let mut current = vec![' '; vars.len()];
+    let mut last_time = None;
+    for (time, var, value) in changes {
+        if current[var] == value {
+            continue;
+        }
+        current[var] = value;
+        if last_time != Some(time) {
+            out += &format!("#{time}\n");
+            last_time = Some(time);
+        }
+        out += &format!("{value}{}\n", id(var));
+    }
+    read_from_reader(std::io::Cursor::new(out.into_bytes())).unwrap()
+}
+
+fn load(wave: &mut Waveform, names: &[&str]) -> Vec<SignalRef> {
+    let h = wave.hierarchy();
+    let ids: Vec<_> = names
+        .iter()
+        .map(|n| h[h.lookup_var(&["tb"], n).unwrap()].signal_ref())
+        .collect();
+    wave.load_signals(&ids).unwrap();
+    ids
+}
+
+/// An id for the decoded signal that does not clash with any signal in the waveform.
+fn output_id(wave: &Waveform) -> SignalRef {
+    SignalRef::from_index(wave.hierarchy().num_unique_signals()).unwrap()
+}
+
+fn changes(wave: &Waveform, signal: &Signal) -> Vec<(Time, String)> {
+    signal
+        .iter_changes()
+        .map(|(idx, value)| (wave.time_table()[idx as usize], value.to_string()))
+        .collect()
+}
+
+fn values(signal: &Signal) -> Vec<String> {
+    signal.iter_changes().map(|(_, v)| v.to_string()).collect()
+}
+
+const BIT: Time = 1000;
+
+/// Drives a frame that starts at `start`, `bits` include the start and stop bits.
+fn uart_frame(start: Time, bits: &[u8]) -> Vec<(Time, usize, char)> {
+    bits.iter()
+        .enumerate()
+        .map(|(ii, b)| (start + ii as Time * BIT, 0, if *b == 1 { '1' } else { '0' }))
+        .collect()
+}
+
+#[test]
+fn uart_frames() {
+    let mut changes_in = vec![(0, 0, '1')];
+    // 'A' = 0x41, least significant bit first
+    changes_in.extend(uart_frame(10_000, &[0, 1, 0, 0, 0, 0, 0, 1, 0, 1]));
+    // 0x0a with a stop bit that is low
+    changes_in.extend(uart_frame(30_000, &[0, 0, 1, 0, 1, 0, 0, 0, 0, 0, 1]));
+    let mut wave = trace(&["tx"], changes_in);
+    let ids = load(&mut wave, &["tx"]);
+    // 1 MBd is 1000 ticks of 1ns per bit
+    let uart = Uart::new(1_000_000);
+    let timescale = wave.hierarchy().timescale();
+    let out = uart
+        .decode(
+            output_id(&wave),
+            wave.time_table(),
+            timescale,
+            wave.get_signal(ids[0]).unwrap(),
+        )
+        .unwrap();
+    assert_eq!(out.signal_ref(), output_id(&wave));
+    assert_eq!(
+        changes(&wave, &out),
+        [
+            (0, "".to_string()),
+            (10_000, "0x41 'A'".to_string()),
+            (30_000, "0x0a framing error".to_string()),
+            // first time step after the end of the frame
+            (40_000, "".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn uart_parity() {
+    let mut changes_in = vec![(0, 0, '1')];
+    // 7 bits of 0x41 with even parity
+    changes_in.extend(uart_frame(10_000, &[0, 1, 0, 0, 0, 0, 0, 1, 0, 1]));
+    // wrong parity bit
+    changes_in.extend(uart_frame(25_000, &[0, 1, 0, 0, 0, 0, 0, 1, 1, 1]));
+    // time steps after the end of each frame
+    changes_in.extend([(0, 1, '0'), (22_000, 1, '1'), (40_000, 1, '0')]);
+    let mut wave = trace(&["tx", "other"], changes_in);
+    let ids = load(&mut wave, &["tx"]);
+    let uart = Uart {
+        data_bits: 7,
+        parity: Parity::Even,
+        ..Uart::new(1_000_000)
+    };
+    let out = uart
+        .decode(
+            output_id(&wave),
+            wave.time_table(),
+            wave.hierarchy().timescale(),
+            wave.get_signal(ids[0]).unwrap(),
+        )
+        .unwrap();
+    assert_eq!(
+        values(&out),
+        ["", "0x41 'A'", "", "0x41 'A' parity error", ""]
+    );
+    let odd = Uart {
+        parity: Parity::Odd,
+        ..uart
+    };
+    let out = odd
+        .decode(
+            output_id(&wave),
+            wave.time_table(),
+            wave.hierarchy().timescale(),
+            wave.get_signal(ids[0]).unwrap(),
+        )
+        .unwrap();
+    assert_eq!(
+        values(&out),
+        ["", "0x41 'A' parity error", "", "0x41 'A'", ""]
+    );
+}
+
+#[test]
+fn uart_errors() {
+    let ns = Some(Timescale::new(1, TimescaleUnit::NanoSeconds));
+    assert_eq!(Uart::new(1_000_000).bit_period(ns), Ok(1000.0));
+    assert_eq!(
+        Uart::new(1_000_000).bit_period(None),
+        Err(SerialError::UnknownTimescale)
+    );
+    assert_eq!(
+        Uart::new(1_000_000_000).bit_period(ns),
+        Err(SerialError::BaudRateTooHigh(1_000_000_000))
+    );
+    let mut wave = trace(&["tx"], vec![(0, 0, '1')]);
+    let ids = load(&mut wave, &["tx"]);
+    let uart = Uart {
+        data_bits: 4,
+        ..Uart::new(9600)
+    };
+    let err = uart
+        .decode(
+            output_id(&wave),
+            wave.time_table(),
+            ns,
+            wave.get_signal(ids[0]).unwrap(),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "[serial] invalid configuration: 4 data bits, expected 5 to 9"
+    );
+}
+
+const SCLK: usize = 0;
+const CS: usize = 1;
+const DATA: usize = 2;
+
+/// Sends `words` in the SPI `mode`, the chip select is deasserted after `bits` bits in total.
+fn spi_trace(cpol: bool, cpha: bool, words: &[u8], bits: usize) -> Waveform {
+    let idle = if cpol { '1' } else { '0' };
+    let active = if cpol { '0' } else { '1' };
+    let mut changes = vec![(0, SCLK, idle), (0, CS, '1'), (0, DATA, '0'), (5, CS, '0')];
+    let all_bits = words
+        .iter()
+        .flat_map(|w| (0..8).rev().map(move |ii| (w >> ii) & 1));
+    let mut time = 20;
+    for bit in all_bits.take(bits) {
+        let value = if bit == 1 { '1' } else { '0' };
+        // data changes on the edge that it is not sampled at
+        let data_time = if cpha { time + 2 } else { time - 3 };
+        changes.push((data_time, DATA, value));
+        changes.push((time, SCLK, active));
+        changes.push((time + 5, SCLK, idle));
+        time += 10;
+    }
+    changes.push((time + 5, CS, '1'));
+    trace(&["sclk", "cs_n", "mosi"], changes)
+}
+
+fn decode_spi(spi: &Spi, wave: &mut Waveform, with_cs: bool) -> Signal {
+    let ids = load(wave, &["sclk", "cs_n", "mosi"]);
+    let signal = |ii: usize| wave.get_signal(ids[ii]).unwrap();
+    spi.decode(
+        output_id(wave),
+        wave.time_table(),
+        signal(SCLK),
+        with_cs.then(|| signal(CS)),
+        signal(DATA),
+    )
+    .unwrap()
+}
+
+#[test]
+fn spi_modes() {
+    for (cpol, cpha) in [(false, false), (false, true), (true, false), (true, true)] {
+        let spi = Spi {
+            cpol,
+            cpha,
+            ..Default::default()
+        };
+        let mut wave = spi_trace(cpol, cpha, &[0xa5, 0x3c], 16);
+        let out = decode_spi(&spi, &mut wave, true);
+        assert_eq!(values(&out), ["", "0xa5", "0x3c", ""], "{spi:?}");
+        // the first bit is sampled at the leading edge in mode 0 and 3
+        let first_sample = if cpha { 25 } else { 20 };
+        assert_eq!(changes(&wave, &out)[1].0, first_sample, "{spi:?}");
+    }
+}
+
+#[test]
+fn spi_word_size_and_bit_order() {
+    let spi = Spi {
+        bits_per_word: 4,
+        msb_first: false,
+        ..Default::default()
+    };
+    let mut wave = spi_trace(false, false, &[0xa5], 8);
+    let out = decode_spi(&spi, &mut wave, false);
+    // without chip select, the annotation is never cleared
+    assert_eq!(values(&out), ["", "0x05", "0x0a"]);
+    let spi = Spi {
+        bits_per_word: 65,
+        ..Default::default()
+    };
+    let ids = load(&mut wave, &["sclk", "mosi"]);
+    let err = spi
+        .decode(
+            output_id(&wave),
+            wave.time_table(),
+            wave.get_signal(ids[0]).unwrap(),
+            None,
+            wave.get_signal(ids[1]).unwrap(),
+        )
+        .unwrap_err();
+    assert!(matches!(err, SerialError::InvalidConfig(_)));
+}
+
+#[test]
+fn spi_incomplete_word() {
+    let mut wave = spi_trace(false, false, &[0xa5, 0xff], 11);
+    let out = decode_spi(&Spi::default(), &mut wave, true);
+    assert_eq!(
+        changes(&wave, &out),
+        [
+            (0, "".to_string()),
+            (20, "0xa5".to_string()),
+            (100, "incomplete word after 3 bits".to_string()),
+            (135, "".to_string()),
+        ]
+    );
+}
+
+const SCL: usize = 0;
+const SDA: usize = 1;
+
+enum I2cOp {
+    Start,
+    Stop,
+    /// a byte and whether it is acknowledged
+    Byte(u8, bool),
+    /// the first bits of a byte
+    Bits(u8, usize),
+}
+
+fn i2c_trace(ops: &[I2cOp]) -> Waveform {
+    let mut changes = vec![(0, SCL, '1'), (0, SDA, '1')];
+    let mut time = 10;
+    let bit = |changes: &mut Vec<_>, time: &mut Time, value: bool| {
+        changes.push((*time, SDA, if value { '1' } else { '0' }));
+        changes.push((*time + 10, SCL, '1'));
+        changes.push((*time + 20, SCL, '0'));
+        *time += 30;
+    };
+    for op in ops {
+        match op {
+            I2cOp::Start => {
+                changes.push((time, SDA, '1'));
+                changes.push((time + 10, SCL, '1'));
+                changes.push((time + 20, SDA, '0'));
+                changes.push((time + 30, SCL, '0'));
+                time += 40;
+            }
+            I2cOp::Stop => {
+                changes.push((time, SDA, '0'));
+                changes.push((time + 10, SCL, '1'));
+                changes.push((time + 20, SDA, '1'));
+                time += 30;
+            }
+            I2cOp::Byte(byte, ack) => {
+                for ii in (0..8).rev() {
+                    bit(&mut changes, &mut time, (byte >> ii) & 1 == 1);
+                }
+                bit(&mut changes, &mut time, !ack);
+            }
+            I2cOp::Bits(byte, count) => {
+                for ii in (8 - count..8).rev() {
+                    bit(&mut changes, &mut time, (byte >> ii) & 1 == 1);
+                }
+            }
+        }
+    }
+    // one more time step after the stop condition
+    changes.push((time + 10, SCL, '0'));
+    trace(&["scl", "sda"], changes)
+}
+
+fn decode_i2c(i2c: &I2c, ops: &[I2cOp]) -> Vec<String> {
+    let mut wave = i2c_trace(ops);
+    let ids = load(&mut wave, &["scl", "sda"]);
+    let out = i2c
+        .decode(
+            output_id(&wave),
+            wave.time_table(),
+            wave.get_signal(ids[SCL]).unwrap(),
+            wave.get_signal(ids[SDA]).unwrap(),
+        )
+        .unwrap();
+    values(&out)
+}
+
+#[test]
+fn i2c_seven_bit_address() {
+    use I2cOp::*;
+    let ops = [
+        Start,
+        Byte(0x50 << 1, true),
+        Byte(0x12, true),
+        Start,
+        Byte((0x50 << 1) | 1, true),
+        Byte(0x34, false),
+        Stop,
+    ];
+    let expected = [
+        "",
+        "S",
+        "W 0x50 ACK",
+        "0x12 ACK",
+        "Sr",
+        "R 0x50 ACK",
+        "0x34 NACK",
+        "P",
+        "",
+    ];
+    assert_eq!(decode_i2c(&I2c::default(), &ops), expected);
+}
+
+#[test]
+fn i2c_ten_bit_address() {
+    use I2cOp::*;
+    // 0x3ab is sent as `11110_11_w` followed by `0xab`
+    let ops = [
+        Start,
+        Byte(0b1111_0110, true),
+        Byte(0xab, true),
+        Byte(0x01, true),
+        Start,
+        Byte(0b1111_0111, true),
+        Byte(0x55, false),
+        Stop,
+    ];
+    let ten_bit = I2c {
+        ten_bit_addresses: true,
+    };
+    let expected = [
+        "",
+        "S",
+        "W 0x3ab ACK",
+        "0x01 ACK",
+        "Sr",
+        "R 0x3ab ACK",
+        "0x55 NACK",
+        "P",
+        "",
+    ];
+    assert_eq!(decode_i2c(&ten_bit, &ops), expected);
+    // without 10-bit addressing, the header is a reserved 7-bit address
+    let seven_bit = decode_i2c(&I2c::default(), &ops);
+    assert_eq!(seven_bit[2], "W 0x7b ACK");
+    assert_eq!(seven_bit[3], "0xab ACK");
+}
+
+#[test]
+fn i2c_incomplete_byte() {
+    use I2cOp::*;
+    let ops = [Start, Bits(0xa0, 4), Stop];
+    assert_eq!(
+        decode_i2c(&I2c::default(), &ops),
+        ["", "S", "incomplete byte", "P", ""]
+    );
+}
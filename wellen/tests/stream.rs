@@ -0,0 +1,197 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// test ingesting waveforms while they are still being written
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+use wellen::stream::*;
+use wellen::*;
+
+fn load(filename: &str) -> (Hierarchy, SignalSource, TimeTable) {
+    let options = LoadOptions::default();
+    let header = viewers::read_header_from_file(filename, &options).expect("failed to load header");
+    let body =
+        viewers::read_body(header.body, &header.hierarchy, None).expect("failed to load body");
+    (header.hierarchy, body.source, body.time_table)
+}
+
+fn all_signals(hierarchy: &Hierarchy) -> Vec<SignalRef> {
+    hierarchy
+        .get_unique_signals_vars()
+        .iter()
+        .flatten()
+        .map(|v| v.signal_ref())
+        .collect()
+}
+
+fn changes(hierarchy: &Hierarchy, source: &mut SignalSource) -> Vec<Vec<(TimeTableIdx, String)>> {
+    let ids = all_signals(hierarchy);
+    source
+        .load_signals(&ids, hierarchy, false)
+        .unwrap()
+        .iter()
+        .map(|(_, signal)| {
+            signal
+                .iter_changes()
+                .map(|(t, v)| (t, v.to_string()))
+                .collect()
+        })
+        .collect()
+}
+
+/// Checks that a snapshot has a strictly increasing time table and only refers to time steps that
+/// are part of it.
+fn check_snapshot(hierarchy: &Hierarchy, handle: &StreamHandle) {
+    let (mut source, time_table) = handle.snapshot();
+    assert!(time_table.windows(2).all(|w| w[0] < w[1]));
+    let ids = all_signals(hierarchy);
+    for (_, signal) in source.load_signals(&ids, hierarchy, false).unwrap() {
+        if let Some(last) = signal.time_indices().last() {
+            assert!((*last as usize) < time_table.len());
+        }
+    }
+}
+
+fn tmp_file(test_name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wellen_stream_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(format!("{test_name}.vcd"))
+}
+
+#[test]
+fn follow_growing_vcd() {
+    let filename = "inputs/icarus/CPU.vcd";
+    let (expected_hierarchy, mut expected_source, expected_time_table) = load(filename);
+    let expected = changes(&expected_hierarchy, &mut expected_source);
+
+    // the header needs to be complete before we can start following
+    let content = std::fs::read(filename).unwrap();
+    let header_end = content
+        .windows(b"$enddefinitions".len())
+        .position(|w| w == b"$enddefinitions")
+        .unwrap()
+        + 100;
+    let path = tmp_file("follow_growing_vcd");
+    let mut out = std::fs::File::create(&path).unwrap();
+    out.write_all(&content[..header_end]).unwrap();
+    out.flush().unwrap();
+
+    let (hierarchy, mut follower) = stream::follow_vcd(&path, &LoadOptions::default()).unwrap();
+    // publish on every poll, in order to end blocks as often as possible
+    follower.set_publish_interval(Duration::ZERO);
+    let handle = follower.handle();
+    let updates = handle.subscribe();
+
+    // chunks are cut at arbitrary positions in order to split tokens
+    let writer = std::thread::spawn(move || {
+        for chunk in content[header_end..].chunks(4999) {
+            out.write_all(chunk).unwrap();
+            out.flush().unwrap();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    });
+
+    let mut last_time = None;
+    while !writer.is_finished() {
+        if follower.poll().unwrap() {
+            check_snapshot(&hierarchy, &handle);
+        }
+        while let Ok(time) = updates.try_recv() {
+            assert!(last_time <= Some(time));
+            last_time = Some(time);
+        }
+    }
+    writer.join().unwrap();
+    follower.finish().unwrap();
+
+    assert_eq!(handle.last_time(), expected_time_table.last().copied());
+    check_snapshot(&hierarchy, &handle);
+    let (mut source, time_table) = handle.snapshot();
+    assert_eq!(time_table, expected_time_table);
+    assert_eq!(changes(&hierarchy, &mut source), expected);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn push_value_changes_from_another_thread() {
+    // contains delta cycles
+    let filename = "inputs/my-hdl/top.vcd";
+    let (hierarchy, mut expected_source, expected_time_table) = load(filename);
+    let ids = all_signals(&hierarchy);
+    let signals = expected_source
+        .load_signals(&ids, &hierarchy, false)
+        .unwrap();
+    let expected = changes(&hierarchy, &mut expected_source);
+    let final_time = *expected_time_table.last().unwrap();
+
+    let mut wave = StreamingWaveform::new(&hierarchy);
+    wave.set_publish_interval(Duration::ZERO);
+    let handle = wave.handle();
+    let updates = handle.subscribe();
+    assert!(handle.last_time().is_none());
+
+    let time_table = expected_time_table.clone();
+    let writer = std::thread::spawn(move || {
+        for (idx, time) in time_table.iter().enumerate() {
+            wave.time_change(*time);
+            for (id, signal) in signals.iter() {
+                if let Some(offset) = signal.get_offset(idx as TimeTableIdx) {
+                    if signal.get_time_idx_at(&offset) == idx as TimeTableIdx {
+                        // includes changes in delta cycles
+                        for delta in 0..offset.elements {
+                            wave.value_change(*id, signal.get_value_at(&offset, delta))
+                                .unwrap();
+                        }
+                    }
+                }
+            }
+            wave.flush();
+        }
+        wave.finish();
+    });
+
+    // updates arrive in order until the last time step was published
+    let mut prev = None;
+    while prev != Some(final_time) {
+        let time = updates.recv().unwrap();
+        assert!(prev < Some(time));
+        assert!(handle.last_time() >= Some(time));
+        check_snapshot(&hierarchy, &handle);
+        prev = Some(time);
+    }
+    writer.join().unwrap();
+
+    let (mut source, time_table) = handle.snapshot();
+    assert_eq!(time_table, expected_time_table);
+    assert_eq!(changes(&hierarchy, &mut source), expected);
+}
+
+#[test]
+fn reject_invalid_value_changes() {
+    let (hierarchy, _, _) = load("inputs/my-hdl/top.vcd");
+    let var = hierarchy
+        .iter_vars()
+        .find(|v| v.signal_encoding() == SignalEncoding::bit_vec_of_len(1))
+        .unwrap();
+    let id = var.signal_ref();
+    let mut wave = StreamingWaveform::new(&hierarchy);
+    let one = SignalValue::Binary(&[1], 1);
+    assert_eq!(wave.value_change(id, one), Err(StreamError::NoTimeStep));
+    wave.time_change(0);
+    assert_eq!(wave.value_change(id, one), Ok(()));
+    let out_of_range = SignalRef::from_index(hierarchy.num_unique_signals()).unwrap();
+    assert_eq!(
+        wave.value_change(out_of_range, one),
+        Err(StreamError::UnknownSignal(out_of_range))
+    );
+    assert!(matches!(
+        wave.value_change(id, SignalValue::Binary(&[1], 2)),
+        Err(StreamError::InvalidValue { .. })
+    ));
+    assert!(matches!(
+        wave.value_change(id, SignalValue::Real(1.0)),
+        Err(StreamError::InvalidValue { .. })
+    ));
+}
@@ -12,6 +12,7 @@ fn surfer_verilator_empty_scope_flatten_scope_with_empty_name() {
     let opts = LoadOptions {
         multi_thread: true,
         remove_scopes_with_empty_name: true,
+        ..Default::default()
     };
     let waves = read_with_options(filename, &opts).expect("failed to parse");
     let h = waves.hierarchy();
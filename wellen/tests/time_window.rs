@@ -0,0 +1,101 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// test loading signals for a time window
+
+use wellen::*;
+
+fn load(filename: &str) -> (Hierarchy, SignalSource, TimeTable) {
+    let options = LoadOptions::default();
+    let header = viewers::read_header_from_file(filename, &options).expect("failed to load header");
+    let body =
+        viewers::read_body(header.body, &header.hierarchy, None).expect("failed to load body");
+    (header.hierarchy, body.source, body.time_table)
+}
+
+/// All changes inside the window, plus the last change at or before its start.
+fn expected_changes(
+    signal: &Signal,
+    time_table: &[Time],
+    start: Time,
+    end: Time,
+) -> Vec<(TimeTableIdx, String)> {
+    let changes: Vec<_> = signal
+        .iter_changes()
+        .map(|(t, v)| (t, v.to_string()))
+        .collect();
+    let before = changes
+        .iter()
+        .rev()
+        .map(|(t, _)| *t)
+        .find(|t| time_table[*t as usize] <= start);
+    changes
+        .into_iter()
+        .filter(|(t, _)| before.is_none_or(|b| *t >= b) && time_table[*t as usize] <= end)
+        .collect()
+}
+
+fn run_time_window_test(filename: &str) {
+    let (hierarchy, mut source, time_table) = load(filename);
+    let ids: Vec<_> = hierarchy
+        .get_unique_signals_vars()
+        .iter()
+        .flatten()
+        .map(|v| v.signal_ref())
+        .collect();
+    let full = source.load_signals(&ids, &hierarchy, true).unwrap();
+
+    let len = time_table.len();
+    let first = time_table[0];
+    let last = time_table[len - 1];
+    let windows = [
+        (first, first),
+        (first, last),
+        (time_table[len / 4], time_table[len / 2]),
+        (time_table[len / 2] + 1, time_table[3 * len / 4]),
+        (time_table[len / 3] + 1, time_table[len / 3] + 1),
+        (last, last + 100),
+        (last + 1, last + 100),
+    ];
+    for (start, end) in windows {
+        let windowed = source
+            .load_signals_in_range(&ids, &hierarchy, start, end, true)
+            .unwrap();
+        assert_eq!(windowed.len(), full.len());
+        for ((full_ref, full_signal), (window_ref, window_signal)) in
+            full.iter().zip(windowed.iter())
+        {
+            assert_eq!(full_ref, window_ref);
+            let actual: Vec<_> = window_signal
+                .iter_changes()
+                .map(|(t, v)| (t, v.to_string()))
+                .collect();
+            assert_eq!(
+                expected_changes(full_signal, &time_table, start, end),
+                actual,
+                "{full_ref:?} in {start}..={end}"
+            );
+        }
+    }
+}
+
+#[test]
+fn time_window_vcd_single_block() {
+    run_time_window_test("inputs/icarus/CPU.vcd");
+}
+
+#[test]
+fn time_window_vcd_multiple_blocks() {
+    // large enough to be parsed by multiple threads
+    run_time_window_test("inputs/github_issues/issue28.vcd");
+}
+
+#[test]
+fn time_window_fst() {
+    run_time_window_test("inputs/nvc/xwb_fofb_shaper_filt_tb_arrays.fst");
+}
+
+#[test]
+fn time_window_ghw() {
+    run_time_window_test("inputs/ghdl/tb_recv.ghw");
+}
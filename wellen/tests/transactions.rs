@@ -0,0 +1,314 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// tests for the bus protocol transaction decoders
+
+use wellen::simple::*;
+use wellen::transactions::*;
+
+/// Creates a VCD with a clock that rises at `10 * cycle + 5` and variables in scope `tb` that
+/// take the values in `rows[cycle]` at `10 * cycle`.
+fn trace(clock: &str, vars: &[(&str, u32)], rows: &[&[u64]]) -> Waveform {
+    let id = |ii: usize| char::from(b'"' + ii as u8);
+    let mut out =
+        format!("$timescale 1ns $end\n$scope module tb $end\n$var wire 1 ! {clock} $end\n");
+    for (ii, (name, width)) in vars.iter().enumerate() {
+        out += &format!("$var wire {width} {} {name} $end\n", id(ii));
+    }
+    out += "$upscope $end\n$enddefinitions $end\n";
+    for (cycle, row) in rows.iter().enumerate() {
+        assert_eq!(row.len(), vars.len());
+        out += &format!("#{}\n0!\n", cycle * 10);
+        for (ii, ((_, width), value)) in vars.iter().zip(row.iter()).enumerate() {
+            out += &format!("b{value:0w$b} {}\n", id(ii), w = *width as usize);
+        }
+        out += &format!("#{}\n1!\n", cycle * 10 + 5);
+    }
+    out += &format!("#{}\n0!\n", rows.len() * 10);
+    read_from_reader(std::io::Cursor::new(out.into_bytes())).unwrap()
+}
+
+fn run(wave: &mut Waveform, prefix: &str, decoder: &dyn Decoder) -> Result<Vec<Transaction>> {
+    let scope = wave.hierarchy().lookup_scope(&["tb"]).unwrap();
+    decode(wave, scope, prefix, decoder)
+}
+
+fn data(t: &Transaction) -> Vec<&str> {
+    t.beats.iter().map(|b| b.field("data").unwrap()).collect()
+}
+
+#[test]
+fn handshake_with_backpressure() {
+    let mut wave = trace(
+        "clk",
+        &[("in_valid", 1), ("in_ready", 1), ("in_data", 4)],
+        &[
+            &[0, 1, 0],
+            &[1, 0, 1], // waits for ready
+            &[1, 0, 1],
+            &[1, 1, 1], // first transfer
+            &[1, 1, 2], // back to back
+            &[0, 1, 0],
+        ],
+    );
+    let ts = run(&mut wave, "in_", &Handshake).unwrap();
+    assert_eq!(ts.len(), 2);
+    assert_eq!((ts[0].kind, ts[0].start, ts[0].end), ("transfer", 15, 35));
+    assert_eq!(data(&ts[0]), ["0001"]);
+    assert_eq!((ts[1].start, ts[1].end), (45, 45));
+    assert_eq!(data(&ts[1]), ["0010"]);
+    let time_table = wave.time_table();
+    assert_eq!(time_table[ts[1].beats[0].time_idx as usize], 45);
+}
+
+#[test]
+fn handshake_without_ready() {
+    let mut wave = trace("clk", &[("valid", 1)], &[&[1], &[0], &[1]]);
+    let ts = run(&mut wave, "", &Handshake).unwrap();
+    let times: Vec<_> = ts.iter().map(|t| (t.start, t.end)).collect();
+    assert_eq!(times, [(5, 5), (25, 25)]);
+    assert!(ts[0].beats[0].fields.is_empty());
+}
+
+#[test]
+fn bind_errors() {
+    let mut wave = trace(
+        "clk",
+        &[("a_valid", 1), ("b_valid", 1), ("a_data", 1)],
+        &[&[0, 0, 0]],
+    );
+    let err = run(&mut wave, "", &Handshake).unwrap_err();
+    assert!(matches!(
+        err,
+        TransactionError::AmbiguousPort { scope, port: "valid", candidates }
+            if scope == "tb" && candidates == ["a_valid", "b_valid"]
+    ));
+    // the prefix picks one of the interfaces, the clock is shared
+    assert!(run(&mut wave, "b_", &Handshake).is_ok());
+    let mut wave = trace("clk", &[("a_ready", 1)], &[&[0]]);
+    let err = run(&mut wave, "a_", &Handshake).unwrap_err();
+    assert!(matches!(
+        err,
+        TransactionError::MissingPort { port: "valid", .. }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "[transactions] no variable in scope tb matches the required port `valid`"
+    );
+}
+
+#[test]
+fn decode_rejects_unbound_ports() {
+    let mut wave = trace("clk", &[("valid", 1), ("ready", 1)], &[&[1, 1]]);
+    let ids: Vec<_> = wave
+        .hierarchy()
+        .iter_vars()
+        .map(|v| v.signal_ref())
+        .collect();
+    wave.load_signals(&ids).unwrap();
+    // clk, valid and ready, data is not bound
+    let mut signals: Vec<_> = ids.iter().map(|id| wave.get_signal(*id)).collect();
+    signals.push(None);
+    let time_table = wave.time_table();
+    assert_eq!(Handshake.decode(time_table, &signals).unwrap().len(), 1);
+    assert!(matches!(
+        Handshake.decode(time_table, &[None, signals[1], signals[2], None]),
+        Err(TransactionError::UnboundPort { port: "clk" })
+    ));
+    assert!(matches!(
+        Handshake.decode(time_table, &[signals[0], None, signals[2], None]),
+        Err(TransactionError::UnboundPort { port: "valid" })
+    ));
+    assert!(matches!(
+        Handshake.decode(time_table, &signals[..3]),
+        Err(TransactionError::PortCount {
+            expected: 4,
+            actual: 3
+        })
+    ));
+}
+
+#[test]
+fn bind_prefers_exact_match() {
+    let wave = trace("clk", &[("valid", 1), ("out_valid", 1)], &[&[1, 0]]);
+    let scope = wave.hierarchy().lookup_scope(&["tb"]).unwrap();
+    let h = wave.hierarchy();
+    let bound = bind(h, scope, "", Handshake.ports()).unwrap();
+    let names: Vec<_> = bound
+        .iter()
+        .map(|v| v.map(|v| h[v].name(h).to_string()))
+        .collect();
+    assert_eq!(
+        names,
+        [Some("clk".into()), Some("valid".into()), None, None]
+    );
+}
+
+const AXI4_VARS: &[(&str, u32)] = &[
+    ("m_axi_awvalid", 1),
+    ("m_axi_awready", 1),
+    ("m_axi_awid", 2),
+    ("m_axi_awaddr", 8),
+    ("m_axi_awlen", 8),
+    ("m_axi_wvalid", 1),
+    ("m_axi_wready", 1),
+    ("m_axi_wdata", 8),
+    ("m_axi_wlast", 1),
+    ("m_axi_bvalid", 1),
+    ("m_axi_bready", 1),
+    ("m_axi_bid", 2),
+    ("m_axi_bresp", 2),
+    ("m_axi_arvalid", 1),
+    ("m_axi_arready", 1),
+    ("m_axi_arid", 2),
+    ("m_axi_araddr", 8),
+    ("m_axi_arlen", 8),
+    ("m_axi_rvalid", 1),
+    ("m_axi_rready", 1),
+    ("m_axi_rid", 2),
+    ("m_axi_rdata", 8),
+    ("m_axi_rlast", 1),
+];
+
+#[test]
+fn axi4_out_of_order() {
+    #[rustfmt::skip]
+    let rows: &[&[u64]] = &[
+        //aw: v  r  id addr  len  w: v  r  data  last b: v  r  id resp  ar: v  r  id addr  len  r: v  r  id data  last
+        &[    0, 1, 0, 0,    0,      0, 1, 0,    0,      0, 1, 0, 0,       0, 1, 0, 0,    0,      0, 1, 0, 0,    0],
+        // write with id 1 and two beats, read with id 2
+        &[    1, 1, 1, 0x10, 1,      1, 1, 0xa1, 0,      0, 1, 0, 0,       1, 1, 2, 0x40, 0,      0, 1, 0, 0,    0],
+        // write with id 3, data arrives before the address
+        &[    0, 1, 0, 0,    0,      1, 1, 0xa2, 1,      0, 1, 0, 0,       1, 1, 1, 0x50, 1,      0, 1, 0, 0,    0],
+        &[    0, 1, 0, 0,    0,      1, 1, 0xb1, 1,      0, 1, 0, 0,       0, 1, 0, 0,    0,      1, 1, 1, 0xc1, 0],
+        // read responses are interleaved
+        &[    1, 1, 3, 0x20, 0,      0, 1, 0,    0,      0, 1, 0, 0,       0, 1, 0, 0,    0,      1, 1, 2, 0xd1, 1],
+        // write responses come back out of order
+        &[    0, 1, 0, 0,    0,      0, 1, 0,    0,      1, 1, 3, 2,       0, 1, 0, 0,    0,      1, 1, 1, 0xc2, 1],
+        &[    0, 1, 0, 0,    0,      0, 1, 0,    0,      1, 1, 1, 0,       0, 1, 0, 0,    0,      0, 1, 0, 0,    0],
+        &[    0, 1, 0, 0,    0,      0, 1, 0,    0,      0, 1, 0, 0,       0, 1, 0, 0,    0,      0, 1, 0, 0,    0],
+    ];
+    let mut wave = trace("aclk", AXI4_VARS, rows);
+    let ts = run(&mut wave, "m_axi_", &Axi4).unwrap();
+    let summary: Vec<_> = ts
+        .iter()
+        .map(|t| {
+            (
+                t.kind,
+                t.start,
+                t.end,
+                t.field("id").unwrap(),
+                t.field("addr").unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("read", 15, 45, "10", "01000000"),
+            ("write", 15, 65, "01", "00010000"),
+            ("read", 25, 55, "01", "01010000"),
+            ("write", 35, 55, "11", "00100000"),
+        ]
+    );
+    assert_eq!(data(&ts[0]), ["11010001"]);
+    assert_eq!(data(&ts[1]), ["10100001", "10100010"]);
+    assert_eq!(ts[1].field("resp"), Some("00"));
+    assert_eq!(ts[1].field("len"), Some("00000001"));
+    // optional ports that are not bound are left out
+    assert_eq!(ts[1].field("prot"), None);
+    assert_eq!(data(&ts[2]), ["11000001", "11000010"]);
+    assert_eq!(data(&ts[3]), ["10110001"]);
+    assert_eq!(ts[3].field("resp"), Some("10"));
+}
+
+#[test]
+fn axi4_reset_drops_outstanding() {
+    let mut vars = AXI4_VARS.to_vec();
+    vars.push(("m_axi_aresetn", 1));
+    #[rustfmt::skip]
+    let rows: &[&[u64]] = &[
+        // read request with id 0 which is aborted by the reset
+        &[0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 1, 1, 0, 0x40, 0, 0, 1, 0, 0, 0, 1],
+        &[0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0],
+        &[0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 1, 1, 0, 0x80, 0, 0, 1, 0, 0, 0, 1],
+        &[0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 1, 0, 0x33, 1, 1],
+    ];
+    let mut wave = trace("aclk", &vars, rows);
+    let ts = run(&mut wave, "m_axi_", &Axi4).unwrap();
+    assert_eq!(ts.len(), 1);
+    assert_eq!((ts[0].start, ts[0].end), (25, 35));
+    assert_eq!(ts[0].field("addr"), Some("10000000"));
+}
+
+#[test]
+fn axi4_stream_packets() {
+    #[rustfmt::skip]
+    let rows: &[&[u64]] = &[
+        //tvalid tready tdata tlast tdest
+        &[1,     1,     1,    0,    0],
+        &[1,     0,     2,    0,    1], // stalled
+        &[1,     1,     2,    0,    1], // packet for dest 1 is interleaved
+        &[1,     1,     3,    1,    0],
+        &[1,     1,     4,    1,    1],
+        &[1,     1,     5,    0,    0], // incomplete
+    ];
+    let mut wave = trace(
+        "clk",
+        &[
+            ("s_tvalid", 1),
+            ("s_tready", 1),
+            ("s_tdata", 8),
+            ("s_tlast", 1),
+            ("s_tdest", 1),
+        ],
+        rows,
+    );
+    let ts = run(&mut wave, "", &Axi4Stream).unwrap();
+    assert_eq!(ts.len(), 2);
+    assert_eq!((ts[0].kind, ts[0].start, ts[0].end), ("packet", 5, 35));
+    assert_eq!(ts[0].field("dest"), Some("0"));
+    assert_eq!(data(&ts[0]), ["00000001", "00000011"]);
+    assert_eq!((ts[1].start, ts[1].end), (15, 45));
+    assert_eq!(ts[1].field("dest"), Some("1"));
+    assert_eq!(data(&ts[1]), ["00000010", "00000100"]);
+    assert_eq!(ts[1].field("id"), None);
+}
+
+#[test]
+fn apb_read_and_write() {
+    #[rustfmt::skip]
+    let rows: &[&[u64]] = &[
+        //psel penable pwrite paddr pwdata prdata pready pslverr
+        &[0,   0,      0,     0,    0,     0,     0,     0],
+        &[1,   0,      1,     0x4,  0x55,  0,     0,     0], // setup
+        &[1,   1,      1,     0x4,  0x55,  0,     0,     0], // wait state
+        &[1,   1,      1,     0x4,  0x55,  0,     1,     0],
+        &[1,   0,      0,     0x8,  0,     0,     0,     0], // back to back
+        &[1,   1,      0,     0x8,  0,     0x66,  1,     1],
+        &[0,   0,      0,     0,    0,     0,     0,     0],
+    ];
+    let mut wave = trace(
+        "pclk",
+        &[
+            ("psel", 1),
+            ("penable", 1),
+            ("pwrite", 1),
+            ("paddr", 4),
+            ("pwdata", 8),
+            ("prdata", 8),
+            ("pready", 1),
+            ("pslverr", 1),
+        ],
+        rows,
+    );
+    let ts = run(&mut wave, "", &Apb).unwrap();
+    assert_eq!(ts.len(), 2);
+    assert_eq!((ts[0].kind, ts[0].start, ts[0].end), ("write", 15, 35));
+    assert_eq!(ts[0].field("addr"), Some("0100"));
+    assert_eq!(ts[0].field("slverr"), Some("0"));
+    assert_eq!(data(&ts[0]), ["01010101"]);
+    assert_eq!((ts[1].kind, ts[1].start, ts[1].end), ("read", 45, 55));
+    assert_eq!(ts[1].field("slverr"), Some("1"));
+    assert_eq!(data(&ts[1]), ["01100110"]);
+}
@@ -49,7 +49,9 @@ fn test_vcd_not_starting_at_zero() {
     };
 
     // querying a signal before it has a value should return none
-    waves.load_signals(&[pc.signal_ref(), sp.signal_ref()]);
+    waves
+        .load_signals(&[pc.signal_ref(), sp.signal_ref()])
+        .unwrap();
 
     // pc is fine since it changes at 4 which is time_table idx 0
     let pc_signal = waves.get_signal(pc.signal_ref()).unwrap();
@@ -85,7 +87,7 @@ fn check_no_fake_changes(mut waves: Waveform) {
     };
     assert_eq!(data.full_name(waves.hierarchy()), "logic.data");
 
-    waves.load_signals(&[data.signal_ref()]);
+    waves.load_signals(&[data.signal_ref()]).unwrap();
     let signal = waves.get_signal(data.signal_ref()).unwrap();
 
     assert_eq!(
@@ -149,6 +151,7 @@ fn load_github_issue_18() {
     let opts = LoadOptions {
         multi_thread: false,
         remove_scopes_with_empty_name: false,
+        ..Default::default()
     };
     let r = read_with_options(filename, &opts);
     assert!(r.is_err());
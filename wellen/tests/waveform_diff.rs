@@ -0,0 +1,210 @@
+// Copyright 2025 Cornell University
+// released under BSD 3-Clause License
+//
+// tests for the public waveform diff api
+
+use wellen::diff::*;
+use wellen::simple::*;
+use wellen::*;
+
+fn read_vcd(content: &str) -> Waveform {
+    read_from_reader(std::io::Cursor::new(content.as_bytes().to_vec())).unwrap()
+}
+
+fn full_names(wave: &Waveform, vars: &[VarRef]) -> Vec<String> {
+    vars.iter()
+        .map(|v| wave.hierarchy()[*v].full_name(wave.hierarchy()))
+        .collect()
+}
+
+#[test]
+fn diff_vcd_against_fst() {
+    for (vcd, fst) in [
+        ("inputs/ghdl/idea.vcd", "inputs/ghdl/idea.vcd.fst"),
+        ("inputs/icarus/CPU.vcd", "inputs/icarus/CPU.vcd.fst"),
+    ] {
+        let mut a = read(vcd).unwrap();
+        let mut b = read(fst).unwrap();
+        for normalize_timescale in [false, true] {
+            let options = DiffOptions {
+                normalize_timescale,
+                ..Default::default()
+            };
+            let report = diff::diff(&mut a, &mut b, &options).unwrap();
+            assert!(report.is_empty(), "{vcd}: {report:?}");
+        }
+    }
+}
+
+const RTL: &str = r#"
+$timescale 1ns $end
+$scope module tb $end
+$scope module dut $end
+$var wire 1 ! clk $end
+$var wire 4 " count $end
+$var wire 8 # data $end
+$var wire 1 $ only_rtl $end
+$upscope $end
+$upscope $end
+$enddefinitions $end
+#0
+0!
+b0000 "
+b00000000 #
+0$
+#10
+1!
+b0001 "
+#20
+0!
+#30
+1!
+b0010 "
+#40
+0!
+"#;
+
+/// Same design, but with a different top-level name, a finer timescale, a bug in
+/// the counter and a narrower data bus.
+const GATE_LEVEL: &str = r#"
+$timescale 100ps $end
+$scope module top $end
+$var wire 1 ! clk $end
+$var wire 4 " count $end
+$var wire 4 # data $end
+$var wire 1 % only_gate_level $end
+$upscope $end
+$enddefinitions $end
+#0
+0!
+b0000 "
+b0000 #
+0%
+#100
+1!
+#150
+b0001 "
+#200
+0!
+#300
+1!
+b0011 "
+#400
+0!
+b0010 "
+"#;
+
+#[test]
+fn diff_rtl_against_gate_level() {
+    let mut rtl = read_vcd(RTL);
+    let mut gate = read_vcd(GATE_LEVEL);
+    let options = DiffOptions {
+        remap_a: vec![("tb.dut".to_string(), "top".to_string())],
+        normalize_timescale: true,
+        ..Default::default()
+    };
+    let report = diff::diff(&mut rtl, &mut gate, &options).unwrap();
+    assert_eq!(
+        report.timescale,
+        Some(Timescale::new(1, TimescaleUnit::PicoSeconds))
+    );
+    assert_eq!(full_names(&rtl, &report.only_in_a), ["tb.dut.only_rtl"]);
+    assert_eq!(
+        full_names(&gate, &report.only_in_b),
+        ["top.only_gate_level"]
+    );
+
+    assert_eq!(report.type_mismatches.len(), 1);
+    let data = &report.type_mismatches[0];
+    assert_eq!(data.name, "top.data");
+    assert_eq!(data.a_encoding, SignalEncoding::bit_vec_of_len(8));
+    assert_eq!(data.b_encoding, SignalEncoding::bit_vec_of_len(4));
+
+    // the clock is the same, the counter is late by 5ns and then has the wrong value for 10ns
+    assert_eq!(report.mismatches.len(), 1);
+    let count = &report.mismatches[0];
+    assert_eq!(count.name, "top.count");
+    assert_eq!(count.first_mismatch(), 10_000);
+    assert_eq!(
+        count.intervals,
+        [
+            MismatchInterval {
+                start: 10_000,
+                end: Some(15_000)
+            },
+            MismatchInterval {
+                start: 30_000,
+                end: Some(40_000)
+            },
+        ]
+    );
+
+    // signals needed for the comparison are not kept in memory
+    assert_eq!(rtl.signal_memory_usage(), 0);
+    assert_eq!(gate.signal_memory_usage(), 0);
+}
+
+#[test]
+fn diff_without_timescale_normalization() {
+    let mut rtl = read_vcd(RTL);
+    let mut gate = read_vcd(GATE_LEVEL);
+    let options = DiffOptions {
+        remap_a: vec![("tb.dut".to_string(), "top".to_string())],
+        ..Default::default()
+    };
+    let report = diff::diff(&mut rtl, &mut gate, &options).unwrap();
+    assert_eq!(report.timescale, None);
+    // raw times do not line up, thus the clock differs as well
+    let clk = report
+        .mismatches
+        .iter()
+        .find(|m| m.name == "top.clk")
+        .unwrap();
+    assert_eq!(clk.first_mismatch(), 10);
+    // the counter ends up with the same value
+    let count = report
+        .mismatches
+        .iter()
+        .find(|m| m.name == "top.count")
+        .unwrap();
+    assert_eq!(count.intervals.last().unwrap().end, Some(400));
+}
+
+#[test]
+fn diff_reports_timescales_that_cannot_be_normalized() {
+    let with_timescale = |timescale: &str, last: u64| {
+        read_vcd(&format!(
+            "$timescale {timescale} $end\n$var wire 1 ! a $end\n$enddefinitions $end\n#0\n0!\n#{last}\n1!\n"
+        ))
+    };
+    let normalize = DiffOptions {
+        normalize_timescale: true,
+        ..Default::default()
+    };
+    // 1s expressed in zs does not fit into 64 bits
+    let mut a = with_timescale("1s", 1);
+    let mut b = with_timescale("1zs", 1);
+    let err = diff::diff(&mut a, &mut b, &normalize).unwrap_err();
+    assert!(matches!(
+        err,
+        WellenError::Malformed {
+            section: "timescale",
+            ..
+        }
+    ));
+    let options = DiffOptions::default();
+    assert!(diff::diff(&mut a, &mut b, &options).unwrap().is_empty());
+    // 1s fits into 64 bits when expressed in fs, but 100000s does not
+    let mut a = with_timescale("1s", 100_000);
+    let mut b = with_timescale("1fs", 1);
+    let err = diff::diff(&mut a, &mut b, &normalize).unwrap_err();
+    assert!(matches!(
+        err,
+        WellenError::Malformed {
+            section: "timescale",
+            ..
+        }
+    ));
+    let mut a = with_timescale("1s", 10_000);
+    assert!(diff::diff(&mut a, &mut b, &normalize).is_ok());
+}